@@ -0,0 +1,80 @@
+use std::env;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Embeds the commit hash, build date and enabled Cargo features for `--version`.
+fn main() {
+    println!(
+        "cargo:rustc-env=GIT_CHAI_COMMIT={}",
+        git_commit().unwrap_or_else(|| "unknown".to_string())
+    );
+    println!("cargo:rustc-env=GIT_CHAI_BUILD_DATE={}", build_date());
+    println!("cargo:rustc-env=GIT_CHAI_FEATURES={}", features().join(","));
+
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
+
+/// Short hash of HEAD, or `None` when building outside a git checkout (e.g. from
+/// the installer's downloaded sources).
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=9", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let mut commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let dirty = Command::new("git")
+        .args(["status", "--porcelain=v1", "--untracked-files=no"])
+        .output()
+        .is_ok_and(|output| !output.stdout.is_empty());
+    if dirty {
+        commit.push_str("-dirty");
+    }
+    Some(commit)
+}
+
+/// UTC build date as YYYY-MM-DD, honouring SOURCE_DATE_EPOCH for reproducible builds.
+fn build_date() -> String {
+    let secs = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+        });
+
+    // Days since the epoch to a civil date (Howard Hinnant's algorithm)
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn features() -> Vec<String> {
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|name| name.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+    features
+}
@@ -55,15 +55,97 @@ install_rust() {
 
 download_source() {
     local files=(
+        "src/git/attributes.rs"
+        "src/git/audit.rs"
+        "src/git/command.rs"
         "src/git/commit.rs"
+        "src/git/diff.rs"
+        "src/git/graft.rs"
         "src/git/grouping.rs"
+        "src/git/horizon.rs"
+        "src/git/in_progress.rs"
+        "src/git/index_flags.rs"
+        "src/git/isolated.rs"
+        "src/git/limits.rs"
+        "src/git/log.rs"
+        "src/git/merge.rs"
+        "src/git/message_style.rs"
+        "src/git/mirror.rs"
+        "src/git/notes.rs"
         "src/git/mod.rs"
         "src/git/operations.rs"
+        "src/git/patch.rs"
+        "src/git/rename.rs"
+        "src/git/repository.rs"
+        "src/git/retract.rs"
+        "src/git/side_branch.rs"
+        "src/git/simulate.rs"
+        "src/git/snapshot.rs"
         "src/git/status.rs"
+        "src/git/subject.rs"
+        "src/git/symlink.rs"
+        "src/git/unpushed.rs"
+        "src/git/worktree.rs"
+        "src/ai.rs"
+        "src/approval.rs"
+        "src/artifacts.rs"
+        "src/bisect.rs"
+        "src/capabilities.rs"
+        "src/chaiignore.rs"
+        "src/check.rs"
+        "src/cli.rs"
+        "src/codeowners.rs"
         "src/config.rs"
+        "src/conflict_markers.rs"
+        "src/control.rs"
+        "src/crypt.rs"
+        "src/dbus.rs"
+        "src/doctor.rs"
+        "src/encoding.rs"
         "src/error.rs"
+        "src/export.rs"
+        "src/failed.rs"
+        "src/gitignore.rs"
+        "src/graft.rs"
+        "src/headless.rs"
+        "src/health.rs"
+        "src/http.rs"
+        "src/init.rs"
+        "src/lib.rs"
+        "src/log_dedup.rs"
         "src/main.rs"
+        "src/notebook.rs"
+        "src/notify.rs"
+        "src/observe.rs"
+        "src/outbox.rs"
+        "src/pause.rs"
+        "src/plan.rs"
+        "src/policy.rs"
+        "src/provenance.rs"
+        "src/push.rs"
+        "src/rate_limit.rs"
+        "src/repeat.rs"
+        "src/repo_access.rs"
+        "src/report.rs"
+        "src/retract.rs"
+        "src/scheduler.rs"
+        "src/session.rs"
+        "src/session_merge.rs"
+        "src/skipped.rs"
+        "src/snapshot.rs"
+        "src/summary.rs"
+        "src/syntax_check.rs"
+        "src/team_policy.rs"
+        "src/template.rs"
+        "src/test_repo.rs"
+        "src/time_tracking.rs"
+        "src/title.rs"
+        "src/tray.rs"
         "src/types.rs"
+        "src/vault.rs"
+        "src/version.rs"
+        "src/watch.rs"
+        "build.rs"
         "Cargo.toml"
         "Cargo.lock"
     )
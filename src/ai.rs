@@ -0,0 +1,164 @@
+use anyhow::{Context, Result, bail};
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::http::HttpClient;
+
+/// Longest diff sent to the model; longer ones are cut, keeping the start.
+const MAX_DIFF_BYTES: usize = 16 * 1024;
+
+/// Longest subject taken from the model's answer.
+const MAX_SUBJECT_CHARS: usize = 72;
+
+const SYSTEM_PROMPT: &str = "You write git commit messages. Reply with only the message: \
+an imperative subject line of at most 72 characters, optionally followed by a blank line and \
+a short body. No quotes, no Markdown.";
+
+/// Where `--ai` sends staged diffs to be summarized into commit messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AiSettings {
+    /// Base URL of an OpenAI-compatible API, e.g. `https://api.openai.com/v1`
+    pub endpoint: String,
+    pub model: String,
+    pub api_key: Option<String>,
+    pub timeout: Duration,
+}
+
+impl AiSettings {
+    /// The settings of `config` if AI messages are on. The API key is read from
+    /// the environment variable named by `ai_api_key_env`; local servers work
+    /// without one.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        config.ai_messages.then(|| AiSettings {
+            endpoint: config.ai_endpoint.clone(),
+            model: config.ai_model.clone(),
+            api_key: std::env::var(&config.ai_api_key_env)
+                .ok()
+                .filter(|key| !key.is_empty()),
+            timeout: Duration::from_secs(config.ai_timeout_secs),
+        })
+    }
+
+    /// Asks the model to describe `diff`, the staged changes of the commit
+    /// git-chai would have titled `generated`.
+    pub fn commit_message(&self, diff: &str, generated: &str) -> Result<String> {
+        let mut end = diff.len().min(MAX_DIFF_BYTES);
+        while !diff.is_char_boundary(end) {
+            end -= 1;
+        }
+        let truncated = if end < diff.len() {
+            "\n[diff truncated]"
+        } else {
+            ""
+        };
+        let body = serde_json::json!({
+            "model": self.model,
+            "temperature": 0.2,
+            "messages": [
+                { "role": "system", "content": SYSTEM_PROMPT },
+                {
+                    "role": "user",
+                    "content": format!(
+                        "Default message: {}\n\nStaged diff:\n{}{}",
+                        generated,
+                        &diff[..end],
+                        truncated
+                    ),
+                },
+            ],
+        });
+        let headers: Vec<String> = self
+            .api_key
+            .iter()
+            .map(|key| format!("Authorization: Bearer {}", key))
+            .collect();
+        let client = HttpClient {
+            timeout: self.timeout,
+            retries: 1,
+            ..Default::default()
+        };
+        let response =
+            client.send_json("POST", &completions_url(&self.endpoint), &headers, &body)?;
+        let response: serde_json::Value =
+            serde_json::from_str(&response.body).context("The model's answer is not JSON")?;
+        let content = response["choices"][0]["message"]["content"]
+            .as_str()
+            .context("The model's answer has no message")?;
+        clean_message(content)
+    }
+}
+
+/// `<endpoint>/chat/completions`, unless the endpoint already names it.
+fn completions_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with("/chat/completions") {
+        endpoint.to_string()
+    } else {
+        format!("{}/chat/completions", endpoint)
+    }
+}
+
+/// Strips the code fences and quotes models like to add, and cuts a long
+/// subject line.
+fn clean_message(content: &str) -> Result<String> {
+    let mut content = content.trim();
+    if let Some(fenced) = content.strip_prefix("```") {
+        // Drop the fence line, which may name a language
+        content = fenced
+            .split_once('\n')
+            .map_or("", |(_, rest)| rest)
+            .trim_end()
+            .trim_end_matches("```");
+    }
+    let content = content.trim().trim_matches('"').trim();
+    let (subject, body) = match content.split_once('\n') {
+        Some((subject, body)) => (subject.trim(), body.trim()),
+        None => (content, ""),
+    };
+    if subject.is_empty() {
+        bail!("The model returned an empty message");
+    }
+
+    let subject = if subject.chars().count() > MAX_SUBJECT_CHARS {
+        let cut: String = subject.chars().take(MAX_SUBJECT_CHARS).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        subject.to_string()
+    };
+    Ok(if body.is_empty() {
+        subject
+    } else {
+        format!("{}\n\n{}", subject, body)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_answers_are_cleaned_up() {
+        assert_eq!(
+            clean_message("```text\nAdd retry to the sync loop\n\nRetries twice.\n```").unwrap(),
+            "Add retry to the sync loop\n\nRetries twice."
+        );
+        assert_eq!(
+            clean_message("\"Fix typo in README\"").unwrap(),
+            "Fix typo in README"
+        );
+        assert!(clean_message("  ").is_err());
+        assert_eq!(
+            clean_message(&"a".repeat(80)).unwrap().chars().count(),
+            MAX_SUBJECT_CHARS + 1
+        );
+
+        assert_eq!(
+            completions_url("http://localhost:11434/v1/"),
+            "http://localhost:11434/v1/chat/completions"
+        );
+        assert_eq!(
+            completions_url("https://llm.example.com/v1/chat/completions"),
+            "https://llm.example.com/v1/chat/completions"
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+use crate::git::{diff_against_head, get_git_dir};
+
+/// File in the git directory holding changes that wait for `git-chai review`.
+pub const APPROVAL_QUEUE_FILE: &str = "chai-approvals.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Pending,
+    Approved,
+    Denied,
+}
+
+impl std::fmt::Display for Decision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Decision::Pending => write!(f, "pending"),
+            Decision::Approved => write!(f, "approved"),
+            Decision::Denied => write!(f, "denied"),
+        }
+    }
+}
+
+/// A change to a `require-approval` path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedChange {
+    pub change_type: String,
+    pub decision: Decision,
+    /// Seconds since the epoch when the change was first queued
+    pub queued_at: u64,
+    /// Size and modification times of the path when it was queued or decided
+    /// on, so a decision only covers the version that was reviewed
+    stamp: String,
+}
+
+/// Changes to `require-approval` paths, persisted across cycles. Headless mode
+/// queues them and only commits those approved in `git-chai review`; an
+/// approval is used up by the cycle that acts on it. A path is forgotten once
+/// it is no longer dirty, and goes back to pending when it changes after a
+/// decision.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ApprovalQueue {
+    #[serde(default)]
+    paths: BTreeMap<String, QueuedChange>,
+    #[serde(skip)]
+    file: Option<PathBuf>,
+}
+
+impl ApprovalQueue {
+    /// Loads the queue of a repository. A missing or unreadable file gives an
+    /// empty queue.
+    pub fn load(repo_path: &Path) -> ApprovalQueue {
+        let Some(file) = get_git_dir(repo_path).map(|dir| dir.join(APPROVAL_QUEUE_FILE)) else {
+            return ApprovalQueue::default();
+        };
+
+        let mut queue = match std::fs::read_to_string(&file) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Ignoring unreadable {}: {}", file.display(), e);
+                ApprovalQueue::default()
+            }),
+            Err(_) => ApprovalQueue::default(),
+        };
+        queue.file = Some(file);
+        queue
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if self.paths.is_empty() {
+            if file.exists() {
+                std::fs::remove_file(file)
+                    .with_context(|| format!("Failed to remove {}", file.display()))?;
+            }
+            return Ok(());
+        }
+
+        let content = toml::to_string(self).context("Failed to serialize the approval queue")?;
+        std::fs::write(file, content).with_context(|| format!("Failed to write {}", file.display()))
+    }
+
+    /// Returns the decision for `path` as it is now, queueing it if it is new.
+    /// An approval is used up: the path has to be approved again after this.
+    pub fn check(&mut self, repo_path: &Path, path: &str, change_type: &str) -> Decision {
+        self.check_stamped(path, change_type, path_stamp(repo_path, path))
+    }
+
+    fn check_stamped(&mut self, path: &str, change_type: &str, stamp: String) -> Decision {
+        let Some(entry) = self.paths.get_mut(path) else {
+            self.paths.insert(
+                path.to_string(),
+                QueuedChange {
+                    change_type: change_type.to_string(),
+                    decision: Decision::Pending,
+                    queued_at: now(),
+                    stamp,
+                },
+            );
+            return Decision::Pending;
+        };
+
+        if entry.stamp != stamp {
+            if entry.decision != Decision::Pending {
+                log::info!(
+                    "{} changed after it was {}; waiting for approval again",
+                    path,
+                    entry.decision
+                );
+            }
+            entry.change_type = change_type.to_string();
+            entry.decision = Decision::Pending;
+            entry.stamp = stamp;
+        }
+
+        let decision = entry.decision;
+        if decision == Decision::Approved {
+            self.paths.remove(path);
+        }
+        decision
+    }
+
+    /// Records a decision for a queued path, covering the path as it is now.
+    /// Returns false if the path is not in the queue.
+    pub fn decide(&mut self, repo_path: &Path, path: &str, decision: Decision) -> bool {
+        let Some(entry) = self.paths.get_mut(path) else {
+            return false;
+        };
+        entry.decision = decision;
+        entry.stamp = path_stamp(repo_path, path);
+        true
+    }
+
+    /// Removes a path, e.g. once it was approved on the terminal.
+    pub fn forget(&mut self, path: &str) {
+        self.paths.remove(path);
+    }
+
+    /// Forgets paths that are no longer dirty.
+    pub fn retain_dirty(&mut self, remaining: &[String]) {
+        self.paths.retain(|path, _| remaining.contains(path));
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &QueuedChange)> {
+        self.paths
+            .iter()
+            .map(|(path, entry)| (path.as_str(), entry))
+    }
+
+    /// Changes whenever a path is queued or decided on, so a decision made in
+    /// `git-chai review` is acted on even if no file changed.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for (path, entry) in &self.paths {
+            path.hash(&mut hasher);
+            entry.decision.hash(&mut hasher);
+            entry.stamp.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+/// Runs `git-chai review`: records the decisions given on the command line, or
+/// else lists the queue and, in a terminal, asks about each pending change.
+pub fn run_review(config: &Config, approve: &[String], deny: &[String]) -> Result<()> {
+    let repo_path = config.repo_path.as_path();
+    let mut queue = ApprovalQueue::load(repo_path);
+
+    if !approve.is_empty() || !deny.is_empty() {
+        let decisions = approve
+            .iter()
+            .map(|path| (path, Decision::Approved))
+            .chain(deny.iter().map(|path| (path, Decision::Denied)));
+        for (path, decision) in decisions {
+            if !queue.decide(repo_path, path, decision) {
+                bail!("{} is not waiting for approval", path);
+            }
+            println!("{}: {}", decision, path);
+        }
+        return queue.save();
+    }
+
+    if queue.paths.is_empty() {
+        println!("No changes are waiting for approval");
+        return Ok(());
+    }
+
+    for (path, entry) in queue.entries() {
+        println!(
+            "  [{}] {}: {} (queued {})",
+            entry.decision,
+            entry.change_type,
+            path,
+            describe_age(now().saturating_sub(entry.queued_at))
+        );
+    }
+    if !std::io::stdin().is_terminal() {
+        println!("Run `git-chai review --approve <path>` or `--deny <path>` to decide.");
+        return Ok(());
+    }
+
+    let pending: Vec<String> = queue
+        .entries()
+        .filter(|(_, entry)| entry.decision == Decision::Pending)
+        .map(|(path, _)| path.to_string())
+        .collect();
+    let color = std::io::stdout().is_terminal();
+    for path in pending {
+        println!();
+        print!("{}", diff_against_head(repo_path, &path, color)?);
+        let decision = loop {
+            print!("{}: [a]pprove, [d]eny, [s]kip, [q]uit? ", path);
+            std::io::stdout().flush()?;
+            let mut answer = String::new();
+            if std::io::stdin().lock().read_line(&mut answer)? == 0 {
+                break None;
+            }
+            match answer.trim().to_lowercase().as_str() {
+                "a" | "approve" => break Some(Decision::Approved),
+                "d" | "deny" => break Some(Decision::Denied),
+                "s" | "skip" | "" => break Some(Decision::Pending),
+                "q" | "quit" => break None,
+                _ => continue,
+            }
+        };
+        let Some(decision) = decision else {
+            break;
+        };
+        queue.decide(repo_path, &path, decision);
+        // Saved after every answer, so quitting halfway keeps what was decided
+        queue.save()?;
+    }
+
+    Ok(())
+}
+
+/// Hashes the size and modification time of `path`, and of everything below it
+/// if it is a directory.
+fn path_stamp(repo_path: &Path, path: &str) -> String {
+    fn visit(path: &Path, hasher: &mut DefaultHasher) {
+        let metadata = std::fs::symlink_metadata(path).ok();
+        metadata.as_ref().map(|m| m.len()).hash(hasher);
+        metadata
+            .as_ref()
+            .and_then(|m| m.modified().ok())
+            .hash(hasher);
+        if metadata.is_some_and(|m| m.is_dir())
+            && let Ok(entries) = std::fs::read_dir(path)
+        {
+            let mut children: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+            children.sort();
+            for child in children {
+                child.file_name().hash(hasher);
+                visit(&child, hasher);
+            }
+        }
+    }
+
+    let mut hasher = DefaultHasher::new();
+    visit(&repo_path.join(path), &mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn describe_age(secs: u64) -> String {
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} minute(s) ago", secs / 60),
+        3600..86400 => format!("{} hour(s) ago", secs / 3600),
+        _ => format!("{} day(s) ago", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decisions_cover_the_reviewed_version_only() {
+        let mut queue = ApprovalQueue::default();
+        let stamp = |s: &str| s.to_string();
+
+        assert_eq!(
+            queue.check_stamped("migrations/1.sql", "add", stamp("v1")),
+            Decision::Pending
+        );
+        assert_eq!(
+            queue.check_stamped("migrations/1.sql", "add", stamp("v1")),
+            Decision::Pending
+        );
+
+        queue.paths.get_mut("migrations/1.sql").unwrap().decision = Decision::Denied;
+        assert_eq!(
+            queue.check_stamped("migrations/1.sql", "add", stamp("v1")),
+            Decision::Denied
+        );
+        // Editing a denied change asks again
+        assert_eq!(
+            queue.check_stamped("migrations/1.sql", "add", stamp("v2")),
+            Decision::Pending
+        );
+
+        // An approval is used once
+        queue.paths.get_mut("migrations/1.sql").unwrap().decision = Decision::Approved;
+        assert_eq!(
+            queue.check_stamped("migrations/1.sql", "add", stamp("v2")),
+            Decision::Approved
+        );
+        assert_eq!(
+            queue.check_stamped("migrations/1.sql", "mod", stamp("v3")),
+            Decision::Pending
+        );
+
+        queue.retain_dirty(&[]);
+        assert_eq!(queue.entries().count(), 0);
+    }
+}
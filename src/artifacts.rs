@@ -0,0 +1,70 @@
+use crate::git::status::GitChange;
+use crate::types::GitStatus;
+
+/// Directory names that almost always hold build output or local environments
+/// rather than source.
+const ARTIFACT_DIRECTORIES: &[&str] = &[
+    "target",
+    "build",
+    "dist",
+    "out",
+    "node_modules",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".gradle",
+    ".next",
+    ".tox",
+];
+
+/// Returns true if `change` is an untracked directory that looks like build
+/// output and hasn't been opted in via `commit_artifact_dirs`.
+///
+/// Only whole untracked directories (`?? target/`) qualify: a directory that
+/// already has tracked files is treated as source.
+pub fn is_unwanted_artifact(change: &GitChange, commit_artifact_dirs: &[String]) -> bool {
+    if change.status != GitStatus::Untracked || !change.filename.ends_with('/') {
+        return false;
+    }
+
+    let path = change.filename.trim_end_matches('/');
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if !ARTIFACT_DIRECTORIES.contains(&name) {
+        return false;
+    }
+
+    !commit_artifact_dirs
+        .iter()
+        .map(|dir| dir.trim_end_matches('/'))
+        .any(|dir| dir == path || dir == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ChangeType;
+
+    fn untracked(filename: &str) -> GitChange {
+        GitChange {
+            status: GitStatus::Untracked,
+            change_type: ChangeType::Add,
+            filename: filename.to_string(),
+            renamed_from: None,
+            symlink_target: None,
+        }
+    }
+
+    #[test]
+    fn test_is_unwanted_artifact() {
+        assert!(is_unwanted_artifact(&untracked("target/"), &[]));
+        assert!(is_unwanted_artifact(&untracked("web/node_modules/"), &[]));
+        assert!(!is_unwanted_artifact(&untracked("build.rs"), &[]));
+        assert!(!is_unwanted_artifact(&untracked("build/out.txt"), &[]));
+        assert!(!is_unwanted_artifact(&untracked("src/"), &[]));
+
+        let opted_in = vec!["dist".to_string(), "web/build/".to_string()];
+        assert!(!is_unwanted_artifact(&untracked("dist/"), &opted_in));
+        assert!(!is_unwanted_artifact(&untracked("web/build/"), &opted_in));
+        assert!(is_unwanted_artifact(&untracked("build/"), &opted_in));
+    }
+}
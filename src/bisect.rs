@@ -0,0 +1,51 @@
+use anyhow::Result;
+
+use crate::check::CheckStatus;
+use crate::config::Config;
+use crate::git::{CHECK_NOTES_REF, get_commits_since, get_notes_since};
+use crate::summary::is_chai_commit;
+
+/// Runs `git-chai bisect-prepare`: prints a `git bisect skip` command for the
+/// git-chai commits since `since` that are not known to pass `check_command`,
+/// so a bisect only stops on autosave commits that built. Commits made by hand
+/// are never skipped; unchecked autosave commits are skipped unless
+/// `keep_unchecked` is set.
+pub fn run_bisect_prepare(config: &Config, since: &str, keep_unchecked: bool) -> Result<()> {
+    let notes = get_notes_since(&config.repo_path, CHECK_NOTES_REF, since)?;
+    let commits = get_commits_since(&config.repo_path, since)?;
+
+    let (mut passed, mut failed, mut unchecked) = (0, 0, 0);
+    let mut skip = Vec::new();
+    for commit in commits
+        .iter()
+        .filter(|c| is_chai_commit(c.generated_subject()))
+    {
+        match notes
+            .get(&commit.sha)
+            .and_then(|n| CheckStatus::from_note(n))
+        {
+            Some(CheckStatus::Pass) => passed += 1,
+            Some(CheckStatus::Fail) => {
+                failed += 1;
+                skip.push(commit.sha.as_str());
+            }
+            None => {
+                unchecked += 1;
+                if !keep_unchecked {
+                    skip.push(commit.sha.as_str());
+                }
+            }
+        }
+    }
+
+    println!(
+        "# git-chai commits since {}: {} passed check, {} failed, {} unchecked",
+        since, passed, failed, unchecked
+    );
+    if skip.is_empty() {
+        println!("# nothing to skip");
+    } else {
+        println!("git bisect skip {}", skip.join(" "));
+    }
+    Ok(())
+}
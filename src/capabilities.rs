@@ -0,0 +1,71 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::config::{CONFIG_SCHEMA_VERSION, Config};
+use crate::version::{VersionInfo, version_info};
+
+/// What this build of git-chai supports, for editor plugins and scripts that
+/// need to adapt to the installed version.
+#[derive(Debug, Serialize)]
+pub struct Capabilities {
+    pub version: VersionInfo,
+    pub config_schema_version: u32,
+    /// Whether commit messages can be generated by a language model
+    pub llm: bool,
+    /// How git is driven: `cli` runs the `git` executable
+    pub git_backend: &'static str,
+    /// How changes are detected: `polling` rescans every `scan_interval_secs`
+    pub watcher: &'static str,
+    /// Code forges git-chai can talk to directly
+    pub forge_integrations: Vec<&'static str>,
+    pub commands: Vec<String>,
+    pub config_keys: Vec<String>,
+}
+
+pub fn capabilities(commands: Vec<String>) -> Capabilities {
+    Capabilities {
+        version: version_info(),
+        config_schema_version: CONFIG_SCHEMA_VERSION,
+        llm: false,
+        git_backend: "cli",
+        watcher: "polling",
+        forge_integrations: Vec::new(),
+        commands,
+        config_keys: Config::supported_keys(),
+    }
+}
+
+/// Runs `git-chai capabilities`. `commands` are the subcommand names known to the CLI.
+pub fn run_capabilities(json: bool, commands: Vec<String>) -> Result<()> {
+    let capabilities = capabilities(commands);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&capabilities)?);
+        return Ok(());
+    }
+
+    println!("{}", capabilities.version.long_version());
+    println!(
+        "config schema version: {}",
+        capabilities.config_schema_version
+    );
+    println!("llm: {}", if capabilities.llm { "yes" } else { "no" });
+    println!("git backend: {}", capabilities.git_backend);
+    println!("watcher: {}", capabilities.watcher);
+    println!(
+        "forge integrations: {}",
+        list_or_none(&capabilities.forge_integrations)
+    );
+    println!("commands: {}", capabilities.commands.join(", "));
+    println!("config keys: {}", capabilities.config_keys.join(", "));
+
+    Ok(())
+}
+
+fn list_or_none(items: &[&str]) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use glob::{MatchOptions, Pattern};
+
+/// File at the repository root listing paths git-chai leaves alone, even if
+/// they are tracked.
+pub const CHAIIGNORE_FILE: &str = ".chaiignore";
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// The patterns of a `.chaiignore` file, in gitignore syntax. As in
+/// `.gitignore`, the last pattern matching a path decides, `!` re-includes
+/// what an earlier pattern excluded, and nothing inside an excluded directory
+/// can be re-included.
+#[derive(Debug, Default)]
+pub struct ChaiIgnore {
+    rules: Vec<IgnoreRule>,
+}
+
+#[derive(Debug)]
+struct IgnoreRule {
+    pattern: Pattern,
+    negated: bool,
+    /// Written with a trailing slash, so only directories match
+    dir_only: bool,
+}
+
+impl ChaiIgnore {
+    /// Reads the repository's `.chaiignore`, or returns `None` if it has none.
+    pub fn load(repo_path: &Path) -> Option<Self> {
+        let path = repo_path.join(CHAIIGNORE_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Some(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!("Failed to read {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim_end();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(line) => (true, line),
+                    None => (false, line.strip_prefix('\\').unwrap_or(line)),
+                };
+                let dir_only = line.ends_with('/');
+                let body = line.trim_end_matches('/');
+                // A slash anywhere but at the end anchors the pattern to the root
+                let anchored = body.contains('/');
+                let body = body.trim_start_matches('/');
+                let glob = if anchored || body.starts_with("**") {
+                    body.to_string()
+                } else {
+                    format!("**/{}", body)
+                };
+                match Pattern::new(&glob) {
+                    Ok(pattern) => Some(IgnoreRule {
+                        pattern,
+                        negated,
+                        dir_only,
+                    }),
+                    Err(e) => {
+                        log::warn!("Ignoring {} pattern '{}': {}", CHAIIGNORE_FILE, line, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        ChaiIgnore { rules }
+    }
+
+    /// Returns true if `path` (ending in `/` for an untracked directory) or a
+    /// directory containing it is excluded.
+    pub fn is_ignored(&self, path: &str) -> bool {
+        let is_dir = path.ends_with('/');
+        let path = path.trim_end_matches('/');
+        let mut ends = path
+            .match_indices('/')
+            .map(|(end, _)| end)
+            .collect::<Vec<_>>();
+        ends.push(path.len());
+        ends.iter().any(|&end| {
+            let is_dir = end < path.len() || is_dir;
+            self.rules
+                .iter()
+                .rev()
+                .find(|rule| {
+                    (is_dir || !rule.dir_only)
+                        && rule.pattern.matches_with(&path[..end], MATCH_OPTIONS)
+                })
+                .is_some_and(|rule| !rule.negated)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_syntax() {
+        let ignore = ChaiIgnore::parse(
+            "# scratch files\n*.log\n!keep.log\ntarget/\n/drafts\ndocs/*.tmp\n.*.swp\n\\#notes#\n",
+        );
+        for ignored in [
+            "server.log",
+            "logs/today.log",
+            "target/",
+            "target/debug/app",
+            "crates/cli/target/",
+            "drafts/post.md",
+            "docs/a.tmp",
+            "src/.main.rs.swp",
+            "#notes#",
+        ] {
+            assert!(ignore.is_ignored(ignored), "{}", ignored);
+        }
+        for kept in [
+            "keep.log",
+            "logs/keep.log",
+            "target",
+            "src/drafts/post.md",
+            "docs/api/a.tmp",
+            "src/main.rs",
+            "log",
+        ] {
+            assert!(!ignore.is_ignored(kept), "{}", kept);
+        }
+
+        // An excluded directory's contents can't be re-included
+        let ignore = ChaiIgnore::parse("build/\n!build/keep.txt\n");
+        assert!(ignore.is_ignored("build/keep.txt"));
+    }
+}
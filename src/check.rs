@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::process::{Command, Stdio};
+
+use crate::config::Config;
+use crate::git::{CHECK_NOTES_REF, add_note, get_head_sha, short_sha};
+
+/// Outcome of `check_command` on a commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+}
+
+impl CheckStatus {
+    /// Reads the status back from a note written by [`check_head`].
+    pub fn from_note(note: &str) -> Option<CheckStatus> {
+        note.lines()
+            .find_map(|line| line.strip_prefix("check: "))
+            .and_then(|status| match status.trim() {
+                "pass" => Some(CheckStatus::Pass),
+                "fail" => Some(CheckStatus::Fail),
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckStatus::Pass => write!(f, "pass"),
+            CheckStatus::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+/// Runs `check_command` on the working tree and records the outcome as a note on
+/// HEAD under `refs/notes/chai-check`, so autosave commits that didn't build can
+/// be told apart later. Only HEAD is annotated: commits made earlier in the
+/// same run were never checked on their own. Returns `None` if no check is
+/// configured.
+pub fn check_head(config: &Config) -> Result<Option<CheckStatus>> {
+    let Some(check) = &config.check_command else {
+        return Ok(None);
+    };
+
+    let head = get_head_sha(&config.repo_path)?;
+    log::info!("Running check on {}: {}", short_sha(&head), check);
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(check)
+        .current_dir(&config.repo_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("Failed to run check command '{}'", check))?;
+    let status = if status.success() {
+        CheckStatus::Pass
+    } else {
+        CheckStatus::Fail
+    };
+
+    add_note(
+        &config.repo_path,
+        CHECK_NOTES_REF,
+        &head,
+        &note_message(status, check),
+    )?;
+    Ok(Some(status))
+}
+
+fn note_message(status: CheckStatus, check: &str) -> String {
+    format!("check: {}\ncommand: {}\n", status, check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_message() {
+        assert_eq!(
+            note_message(CheckStatus::Fail, "cargo check"),
+            "check: fail\ncommand: cargo check\n"
+        );
+        assert_eq!(
+            CheckStatus::from_note(&note_message(CheckStatus::Pass, "make")),
+            Some(CheckStatus::Pass)
+        );
+        assert_eq!(CheckStatus::from_note("reviewed"), None);
+    }
+}
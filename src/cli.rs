@@ -0,0 +1,848 @@
+use crate::approval::run_review;
+use crate::bisect::run_bisect_prepare;
+use crate::codeowners::CodeOwners;
+use crate::config::{
+    CONFIG_FILE_NAME, Config, JjMode, global_config_path, parse_author, parse_interval,
+};
+use crate::doctor::run_doctor;
+use crate::error::error_class;
+use crate::export::{ExportFormat, run_export};
+use crate::failed::{FAILURE_LIMIT, FailedPaths};
+use crate::git::{bridge_kind, get_promisor_remotes, git_command, is_jj_colocated, is_shallow};
+use crate::graft::run_graft;
+use crate::headless::{HeadlessOptions, run_sessions};
+use crate::health::run_healthz;
+use crate::init::{InitTemplate, run_init};
+use crate::repo_access::RepoAccess;
+use crate::report::{ReportFormat, run_heatmap};
+use crate::retract::run_retract;
+use crate::session::{ChaiSession, DryRunMode, JJ_REFUSAL};
+use crate::session_merge::merge_session;
+use crate::summary::run_summary;
+use crate::team_policy::{TEAM_POLICY_FILE, enforce_team_policy};
+use crate::time_tracking::run_time_report;
+use crate::{capabilities, git, version};
+use anyhow::Result;
+use clap::{CommandFactory, Parser, Subcommand};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(about, long_about = None, disable_version_flag = true)]
+struct Args {
+    /// Path to git repository (repeat to watch several repositories)
+    #[arg(short = 'r', long = "repo-path", default_value = ".")]
+    repo_paths: Vec<PathBuf>,
+
+    /// Number of worker threads shared by all repositories in headless mode
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Push changes to remote after committing
+    #[arg(short, long, default_value_t = false)]
+    push: bool,
+
+    /// Dry run - show what would be committed without actually committing;
+    /// `--dry-run=deep` also stages each group into a temporary index and runs
+    /// the commit hooks to report which groups would fail
+    #[arg(
+        short,
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "plan"
+    )]
+    dry_run: Option<DryRunMode>,
+
+    /// Observe only: scan as usual but append what would be committed to
+    /// .git/chai-observe.jsonl, never staging, committing or pushing (implies --dry-run)
+    #[arg(long, default_value_t = false)]
+    observe: bool,
+
+    /// Verbose output
+    #[arg(short, long, default_value_t = false)]
+    verbose: bool,
+
+    /// Headless mode - run continuously until interrupted
+    #[arg(short = '!', long, default_value_t = false)]
+    headless: bool,
+
+    /// Headless mode: offer status, pause/resume and scan-now on the D-Bus session bus (Linux)
+    #[arg(long, default_value_t = false)]
+    dbus: bool,
+
+    /// Headless mode with a tray icon showing its state, pause and push toggles and the session summary (Linux)
+    #[arg(long, default_value_t = false)]
+    tray: bool,
+
+    /// Headless mode that scans as soon as files change instead of every scan_interval_secs
+    #[arg(long, default_value_t = false)]
+    watch: bool,
+
+    /// Headless mode: seconds between scans, e.g. 0.5 or 3600 (overrides scan_interval_secs)
+    #[arg(long, value_name = "SECONDS", value_parser = parse_interval_arg)]
+    interval: Option<f64>,
+
+    /// Author and commit autosaves as this identity, e.g. "Chai Bot <bot@example.com>"
+    /// (overrides commit_author)
+    #[arg(long, value_name = "IDENTITY", value_parser = parse_author_arg)]
+    author: Option<String>,
+
+    /// Named profile from .chai.toml to apply
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Override a config key for this run (repeatable), e.g. --set key=value
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<String>,
+
+    /// Also commit files matched by .gitignore
+    #[arg(long, default_value_t = false)]
+    include_ignored: bool,
+
+    /// Have a model describe each commit's staged diff (see ai_endpoint), falling
+    /// back to the generated message
+    #[arg(long, default_value_t = false)]
+    ai: bool,
+
+    /// In a shallow clone, fetch full history before pushing
+    #[arg(long, default_value_t = false)]
+    unshallow_before_push: bool,
+
+    /// Append every git command run to this audit file
+    #[arg(long, value_name = "PATH", global = true)]
+    audit_log: Option<PathBuf>,
+
+    /// Commit even in repositories outside the global allowlist or on the denylist
+    #[arg(long = "i-know-what-im-doing", default_value_t = false)]
+    ignore_repo_access: bool,
+
+    /// Never commit; only push local commits that the remote doesn't have yet
+    #[arg(long, default_value_t = false)]
+    push_only: bool,
+
+    /// Skip the first-run safe mode when no .chai.toml exists
+    #[arg(long, default_value_t = false)]
+    no_safe_mode: bool,
+
+    /// Show version information
+    #[arg(short = '?', long = "version")]
+    version: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Write a .chai.toml, optionally tailored to a common kind of repository
+    Init {
+        /// Start from the settings for this kind of repository instead of the defaults
+        #[arg(long, value_enum)]
+        template: Option<InitTemplate>,
+        /// Replace an existing .chai.toml
+        #[arg(long, default_value_t = false)]
+        force: bool,
+    },
+    /// Report repository settings that affect what git-chai commits
+    Doctor,
+    /// Work with the .chai.toml configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Report on git-chai's activity, read from the audit log
+    Report {
+        /// Show which directories and files git-chai stages most often
+        #[arg(long, required = true)]
+        heatmap: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+        format: ReportFormat,
+    },
+    /// Summarize recent work, grouped by top-level directory
+    Summary {
+        /// Start of the period, in any form git understands (e.g. "yesterday")
+        #[arg(long, default_value = "yesterday")]
+        since: String,
+        /// Include commits not made by git-chai
+        #[arg(long, default_value_t = false)]
+        all_commits: bool,
+        /// Post the summary to summary_webhook instead of printing it
+        #[arg(long, default_value_t = false)]
+        post: bool,
+    },
+    /// Print a `git bisect skip` list of autosave commits not known to pass check_command
+    BisectPrepare {
+        /// Start of the period, in any form git understands (e.g. "1 week ago")
+        #[arg(long, default_value = "1 week ago")]
+        since: String,
+        /// Only skip commits that failed the check, not unchecked ones
+        #[arg(long, default_value_t = false)]
+        keep_unchecked: bool,
+    },
+    /// Write recent autosave commits as mailable patches
+    Export {
+        /// Start of the period, in any form git understands (e.g. "3 hours ago")
+        #[arg(long)]
+        since: String,
+        /// One mbox file or a directory of numbered .patch files
+        #[arg(long, value_enum, default_value_t = ExportFormat::Mbox)]
+        format: ExportFormat,
+        /// File or directory to write (default: chai-<repo>.mbox or chai-<repo>-patches)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Copy recent autosave commits onto another branch
+    Graft {
+        /// Start of the period, in any form git understands (e.g. "2 hours ago")
+        #[arg(long)]
+        since: String,
+        /// Branch to copy the commits onto
+        #[arg(long)]
+        onto: String,
+        /// Apply the commits as a single commit
+        #[arg(long, default_value_t = false)]
+        squash: bool,
+    },
+    /// Merge the checked-out autosave branch into another branch as one merge commit
+    MergeSession {
+        /// Branch to merge into (default: session_merge_into)
+        #[arg(long)]
+        into: Option<String>,
+    },
+    /// Remove a file from the unpushed git-chai commits it is in and stop committing it
+    Retract {
+        /// File or directory to take out of the commits
+        path: String,
+    },
+    /// Approve or deny changes to require-approval paths; approved ones are committed on the next run
+    Review {
+        /// Approve the queued change to this path (repeatable)
+        #[arg(long, value_name = "PATH")]
+        approve: Vec<String>,
+        /// Deny the queued change to this path (repeatable)
+        #[arg(long, value_name = "PATH")]
+        deny: Vec<String>,
+    },
+    /// Exit non-zero if a headless watcher looks wedged, for container health checks
+    Healthz {
+        /// Seconds since the last finished cycle after which the watcher is stale
+        /// (default: three scan intervals plus a minute)
+        #[arg(long)]
+        max_age: Option<u64>,
+        /// Failed cycles in a row after which the watcher is unhealthy
+        #[arg(long, default_value_t = 3)]
+        max_failures: u32,
+    },
+    /// Show paths git-chai has stopped retrying after repeated failures
+    Status {
+        /// Forget the failing paths so the next cycle retries them
+        #[arg(long, default_value_t = false)]
+        retry_failed: bool,
+    },
+    /// Estimate active work time per day from the gaps between commits
+    Time {
+        /// Start of the period, in any form git understands (e.g. "1 week ago")
+        #[arg(long, default_value = "1 week ago")]
+        since: String,
+        /// Gap between commits, in minutes, that ends a work session
+        #[arg(long)]
+        idle_minutes: Option<u64>,
+        /// Report every local branch instead of the current one
+        #[arg(long, default_value_t = false)]
+        all_branches: bool,
+    },
+    /// List the features, commands and config keys supported by this build
+    Capabilities {
+        /// Print machine-readable JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+impl Args {
+    fn dry_run(&self) -> bool {
+        self.dry_run.is_some()
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print a JSON Schema for .chai.toml, for editor completion and validation
+    Schema,
+}
+
+/// Runs `git-chai status`: lists the paths that are no longer retried, or with
+/// `retry_failed` forgets them.
+fn run_status(config: &Config, retry_failed: bool) -> Result<()> {
+    let mut failed = FailedPaths::load(&config.repo_path);
+
+    if retry_failed {
+        let cleared = failed.clear();
+        failed.save()?;
+        println!(
+            "Cleared {} failing path(s); they will be retried on the next run",
+            cleared
+        );
+        return Ok(());
+    }
+
+    let parked: Vec<_> = failed.parked().collect();
+    if parked.is_empty() {
+        println!("No paths are being skipped after repeated failures");
+        return Ok(());
+    }
+    println!(
+        "Not retrying {} path(s) that failed {} times in a row:",
+        parked.len(),
+        FAILURE_LIMIT
+    );
+    for (path, entry) in parked {
+        println!("  {}: {}", path, entry.last_error);
+    }
+    println!("Run `git-chai status --retry-failed` to retry them.");
+    Ok(())
+}
+
+/// Parses `--interval`, which has the same bounds as `scan_interval_secs`.
+fn parse_interval_arg(value: &str) -> Result<f64, String> {
+    let secs: f64 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number of seconds", value))?;
+    parse_interval("--interval", secs).map_err(|e| e.to_string())?;
+    Ok(secs)
+}
+
+/// Checks `--author`, which has the same form as `commit_author`.
+fn parse_author_arg(value: &str) -> Result<String, String> {
+    parse_author("--author", value).map_err(|e| e.to_string())?;
+    Ok(value.trim().to_string())
+}
+
+fn resolve_repo_toplevel(path: &Path) -> anyhow::Result<PathBuf> {
+    let output = git_command(path)
+        .arg("rev-parse")
+        .arg("--show-toplevel")
+        .output()
+        .map_err(|e| anyhow::anyhow!("Failed to run git rev-parse: {}", e))?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git rev-parse --show-toplevel failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let toplevel = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(PathBuf::from(toplevel))
+}
+
+/// Resolves the repository root and loads its config with the command-line
+/// overrides applied. Exits the process if either step fails.
+fn load_repo_config(path: &Path, args: &Args) -> Config {
+    let repo_root = match resolve_repo_toplevel(path) {
+        Ok(p) => p,
+        Err(e) => {
+            log::error!("Failed to resolve git repo top-level for {:?}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut config = match Config::load(&repo_root, args.profile.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("Failed to load config: {}", e);
+            std::process::exit(1);
+        }
+    };
+    config.push_by_default |= args.push;
+    config.include_ignored |= args.include_ignored;
+    config.ai_messages |= args.ai;
+    config.unshallow_before_push |= args.unshallow_before_push;
+    config.push_only |= args.push_only;
+    if let Some(interval) = args.interval {
+        config.scan_interval_secs = interval;
+    }
+    if let Some(author) = &args.author {
+        config.commit_author = Some(author.clone());
+    }
+
+    if let Err(e) = config.apply_overrides(&args.overrides) {
+        log::error!("Invalid --set override: {}", e);
+        std::process::exit(1);
+    }
+
+    config
+}
+
+/// Enforces each repository's `.chai-policy.toml` for subcommands, which don't
+/// build the session that would otherwise. Exits if a policy can't be loaded.
+fn enforce_team_policies(configs: &mut [Config]) {
+    for config in configs {
+        // Applied last, so that neither .chai.toml nor --set can loosen it
+        if let Err(e) = enforce_team_policy(config) {
+            log::error!("Failed to load {}: {}", TEAM_POLICY_FILE, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// A session for `config`, held to the global allow/denylist.
+fn new_session(config: Config) -> Result<ChaiSession> {
+    let mut session = ChaiSession::new(config)?;
+    if let Some(path) = global_config_path() {
+        session.load_repo_access(&path)?;
+    }
+    Ok(session)
+}
+
+/// Exits if any repository is excluded by the global allow/denylist, before
+/// anything is committed.
+fn check_repo_access(configs: &[Config]) {
+    let access = match RepoAccess::load() {
+        Ok(access) => access,
+        Err(e) => {
+            log::error!("Failed to load global config: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    for config in configs {
+        if let Some(reason) = access.refusal_reason(&config.repo_path) {
+            log::error!(
+                "Refusing to run in {}: {}",
+                config.repo_path.display(),
+                reason
+            );
+            log::error!("Pass --i-know-what-im-doing to run there anyway.");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Exits with guidance if any repository is colocated with Jujutsu and
+/// `jj_mode = "refuse"`; sessions switch the others to a temporary index.
+fn check_jj_repos(configs: &[Config], dry_run: bool) {
+    let mut refused = false;
+    for config in configs
+        .iter()
+        .filter(|config| config.jj_mode == JjMode::Refuse && is_jj_colocated(&config.repo_path))
+    {
+        log::error!(
+            "Refusing to run in {}: {}",
+            config.repo_path.display(),
+            JJ_REFUSAL
+        );
+        refused = true;
+    }
+    if refused && !dry_run {
+        std::process::exit(1);
+    }
+}
+
+/// Warns once at startup about repository layouts that limit what git-chai can do.
+fn warn_about_repo(config: &Config) {
+    let promisor_remotes = get_promisor_remotes(&config.repo_path);
+    if !promisor_remotes.is_empty() {
+        log::warn!(
+            "Partial clone (promisor remote: {}): move detection is disabled so scans never fetch missing objects",
+            promisor_remotes.join(", ")
+        );
+    }
+
+    if !config.owned_by.is_empty() && CodeOwners::load(&config.repo_path).is_none() {
+        log::warn!(
+            "owned_by is set but the repository has no CODEOWNERS file: no path is owned, so nothing will be committed"
+        );
+    }
+
+    if config.push_by_default
+        && !config.allow_bridge_push
+        && let Some(bridge) = bridge_kind(&config.repo_path)
+    {
+        log::warn!(
+            "{} repository: commits won't be pushed to origin; {}",
+            bridge,
+            bridge.push_hint()
+        );
+    }
+
+    if config.push_by_default && !config.unshallow_before_push && is_shallow(&config.repo_path) {
+        log::warn!(
+            "Shallow clone: pushes may be rejected; pass --unshallow-before-push to fetch full history first"
+        );
+    }
+}
+
+/// Runs `sessions` in headless mode until Ctrl+C. The first Ctrl+C stops
+/// scheduling new cycles and lets in-flight git commands finish; a second one
+/// exits immediately.
+fn run_headless(sessions: Vec<ChaiSession>, options: HeadlessOptions) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        log::info!("git-chai: Starting in headless mode. Press Ctrl+C to stop.");
+        let interrupted = async {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return std::future::pending().await;
+            }
+            println!(
+                "\nReceived interrupt signal, finishing in-flight operations (press Ctrl+C again to exit immediately)..."
+            );
+            tokio::spawn(async {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    println!("\nReceived second interrupt signal, exiting immediately");
+                    std::process::exit(130);
+                }
+            });
+        };
+        if let Err(e) = run_sessions(sessions, options, interrupted).await {
+            log::warn!("{}; exiting", e);
+            std::process::exit(1);
+        }
+        Ok(())
+    })
+}
+
+/// Runs a dry run in a repo with no `.chai.toml` and offers to write one, so that
+/// trying the binary out never creates commits by surprise.
+fn run_safe_mode(config: Config, verbose: bool) -> Result<()> {
+    log::warn!(
+        "No {} found in {}: running in safe mode (dry run only)",
+        CONFIG_FILE_NAME,
+        config.repo_path.display()
+    );
+
+    let mut session = ChaiSession::new(config)?;
+    session.dry_run = Some(DryRunMode::Plan);
+    session.verbose = verbose;
+    session.process_once()?;
+    let config = session.config();
+
+    println!();
+    println!("git-chai did not create any commits because this repository has no config file.");
+
+    if !std::io::stdin().is_terminal() {
+        println!(
+            "Create {} in the repository root (or pass --no-safe-mode) to start committing.",
+            CONFIG_FILE_NAME
+        );
+        return Ok(());
+    }
+
+    print!(
+        "Write a default {} to enable committing? [y/N] ",
+        CONFIG_FILE_NAME
+    );
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+
+    if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        let path = Config::write_default(&config.repo_path)?;
+        println!(
+            "Wrote {}. Run git-chai again to commit changes.",
+            path.display()
+        );
+    } else {
+        println!("No config written.");
+    }
+
+    Ok(())
+}
+
+/// Runs the `git-chai` command with the process's arguments.
+pub fn run() -> Result<()> {
+    let mut args = Args::parse();
+    if args.observe {
+        args.dry_run.get_or_insert(DryRunMode::Plan);
+    }
+
+    unsafe {
+        if args.verbose {
+            std::env::set_var("RUST_LOG", "debug");
+        } else {
+            std::env::set_var("RUST_LOG", "info");
+        }
+    }
+    env_logger::init();
+
+    if args.version {
+        println!("{}", version::version_info().long_version());
+        return Ok(());
+    }
+
+    if let Some(Commands::Config {
+        action: ConfigAction::Schema,
+    }) = args.command
+    {
+        println!("{}", serde_json::to_string_pretty(&Config::json_schema())?);
+        return Ok(());
+    }
+
+    if let Some(Commands::Capabilities { json }) = args.command {
+        let commands = Args::command()
+            .get_subcommands()
+            .map(|command| command.get_name().to_string())
+            .collect();
+        return capabilities::run_capabilities(json, commands);
+    }
+
+    // Before loading the configuration, which may be the broken file to replace
+    if let Some(Commands::Init { template, force }) = args.command {
+        for path in &args.repo_paths {
+            run_init(&resolve_repo_toplevel(path)?, template, force)?;
+        }
+        return Ok(());
+    }
+
+    let mut configs: Vec<Config> = args
+        .repo_paths
+        .iter()
+        .map(|path| load_repo_config(path, &args))
+        .collect();
+    if args.command.is_some() {
+        enforce_team_policies(&mut configs);
+    }
+
+    // Everything that commits or rewrites history is held to the same checks
+    // as an autosave run
+    let writes = match &args.command {
+        None => true,
+        Some(Commands::Graft { .. } | Commands::Retract { .. } | Commands::MergeSession { .. }) => {
+            true
+        }
+        Some(Commands::Review { approve, .. }) => !approve.is_empty(),
+        Some(_) => false,
+    };
+    if writes {
+        if !args.dry_run() && !args.ignore_repo_access {
+            check_repo_access(&configs);
+        }
+        check_jj_repos(&configs, args.dry_run());
+    }
+
+    if let Some(Commands::Doctor) = args.command {
+        for config in &configs {
+            run_doctor(config)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::Report { format, .. }) = args.command {
+        for config in &configs {
+            let Some(audit_log) = args.audit_log.clone().or_else(|| {
+                config
+                    .audit_log
+                    .as_ref()
+                    .map(|path| config.repo_path.join(path))
+            }) else {
+                log::error!(
+                    "No audit log for {}: set audit_log in {} or pass --audit-log",
+                    config.repo_path.display(),
+                    CONFIG_FILE_NAME
+                );
+                std::process::exit(1);
+            };
+            run_heatmap(&config.repo_path, &audit_log, format)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::Summary {
+        since,
+        all_commits,
+        post,
+    }) = &args.command
+    {
+        for config in &configs {
+            run_summary(config, since, *all_commits, *post)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::BisectPrepare {
+        since,
+        keep_unchecked,
+    }) = &args.command
+    {
+        for config in &configs {
+            run_bisect_prepare(config, since, *keep_unchecked)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::Export {
+        since,
+        format,
+        output,
+    }) = &args.command
+    {
+        for config in &configs {
+            run_export(config, since, *format, output.as_deref(), args.dry_run())?;
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::Graft {
+        since,
+        onto,
+        squash,
+    }) = &args.command
+    {
+        for config in &configs {
+            run_graft(config, since, onto, *squash, args.dry_run())?;
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::Retract { path }) = &args.command {
+        for config in &configs {
+            run_retract(config, path, args.dry_run())?;
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::MergeSession { into }) = &args.command {
+        for config in &configs {
+            let Some(into) = into.as_ref().or(config.session_merge_into.as_ref()) else {
+                anyhow::bail!("merge-session needs --into or session_merge_into");
+            };
+            merge_session(config, into, args.dry_run())?;
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::Review { approve, deny }) = &args.command {
+        for config in &configs {
+            run_review(config, approve, deny)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::Healthz {
+        max_age,
+        max_failures,
+    }) = args.command
+    {
+        let mut healthy = true;
+        for config in &configs {
+            healthy &= run_healthz(config, max_age, max_failures);
+        }
+        if !healthy {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::Status { retry_failed }) = &args.command {
+        for config in &configs {
+            run_status(config, *retry_failed)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::Time {
+        since,
+        idle_minutes,
+        all_branches,
+    }) = &args.command
+    {
+        for config in &configs {
+            run_time_report(config, since, *idle_minutes, *all_branches)?;
+        }
+        return Ok(());
+    }
+
+    for config in &configs {
+        warn_about_repo(config);
+    }
+
+    // Audit logging is process-wide; relative config paths are resolved against
+    // the repository that sets them
+    let audit_log = args.audit_log.clone().or_else(|| {
+        configs.iter().find_map(|config| {
+            config
+                .audit_log
+                .as_ref()
+                .map(|path| config.repo_path.join(path))
+        })
+    });
+    if let Some(path) = audit_log
+        && let Err(e) = git::audit::configure(&path)
+    {
+        log::error!("Failed to open audit log {}: {}", path.display(), e);
+        std::process::exit(1);
+    }
+
+    // git process limits are process-wide, so the strictest setting wins
+    git::limits::configure(
+        configs.iter().filter_map(|c| c.max_concurrent_git).min(),
+        configs.iter().filter_map(|c| c.max_git_per_minute).min(),
+    );
+
+    // Safe mode only guards against surprise commits, which push-only and
+    // observe never make
+    let safe_mode = !args.no_safe_mode && !args.push_only && !args.observe;
+    if let [config] = configs.as_slice()
+        && safe_mode
+        && !Config::file_exists(&config.repo_path)
+    {
+        return run_safe_mode(configs.remove(0), args.verbose);
+    }
+
+    configs.retain(|config| {
+        let enabled = !safe_mode || Config::file_exists(&config.repo_path);
+        if !enabled {
+            log::warn!(
+                "Skipping {}: no {} found (run git-chai there first, or pass --no-safe-mode)",
+                config.repo_path.display(),
+                CONFIG_FILE_NAME
+            );
+        }
+        enabled
+    });
+    if configs.is_empty() {
+        log::warn!("No repositories to process");
+        return Ok(());
+    }
+
+    if args.headless || args.tray || args.watch {
+        let sessions = configs
+            .into_iter()
+            .map(|config| {
+                let mut session = new_session(config)?;
+                session.dry_run = args.dry_run;
+                session.verbose = args.verbose;
+                session.observe = args.observe;
+                session.ignore_repo_access = args.ignore_repo_access;
+                Ok(session)
+            })
+            .collect::<Result<_>>()?;
+        let options = HeadlessOptions {
+            jobs: args.jobs,
+            watch: args.watch,
+            dbus: args.dbus,
+            tray: args.tray,
+        };
+        run_headless(sessions, options)
+    } else {
+        log::info!("git-chai: Running once");
+        for config in configs {
+            let mut session = new_session(config)?;
+            session.dry_run = args.dry_run;
+            session.verbose = args.verbose;
+            session.ignore_repo_access = args.ignore_repo_access;
+            session.interactive = std::io::stdin().is_terminal();
+            session.observe = args.observe;
+            if let Err(e) = session.process_once() {
+                let class = error_class(&e);
+                log::error!("Error processing changes ({}): {}", class, e);
+                std::process::exit(class.exit_code());
+            }
+        }
+        Ok(())
+    }
+}
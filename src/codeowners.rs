@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use glob::{MatchOptions, Pattern};
+
+/// Where GitHub looks for the CODEOWNERS file, in the order it checks them.
+const CODEOWNERS_PATHS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// The rules of a CODEOWNERS file. As on GitHub, the last rule matching a path
+/// decides its owners.
+#[derive(Debug, Default)]
+pub struct CodeOwners {
+    rules: Vec<OwnerRule>,
+}
+
+#[derive(Debug)]
+struct OwnerRule {
+    patterns: Vec<Pattern>,
+    owners: Vec<String>,
+}
+
+impl CodeOwners {
+    /// Reads the repository's CODEOWNERS file, or returns `None` if it has none.
+    pub fn load(repo_path: &Path) -> Option<Self> {
+        CODEOWNERS_PATHS.iter().find_map(|path| {
+            let path = repo_path.join(path);
+            match std::fs::read_to_string(&path) {
+                Ok(text) => Some(Self::parse(&text)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    log::warn!("Failed to read {}: {}", path.display(), e);
+                    None
+                }
+            }
+        })
+    }
+
+    fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .filter_map(|line| {
+                let mut tokens = line
+                    .split_whitespace()
+                    .take_while(|token| !token.starts_with('#'));
+                let pattern = tokens.next()?;
+                let patterns = owner_patterns(pattern);
+                if patterns.is_empty() {
+                    log::warn!("Ignoring CODEOWNERS pattern '{}'", pattern);
+                    return None;
+                }
+                Some(OwnerRule {
+                    patterns,
+                    owners: tokens.map(str::to_string).collect(),
+                })
+            })
+            .collect();
+        CodeOwners { rules }
+    }
+
+    /// Returns the owners of `path`: empty if no rule matches it, or if the last
+    /// matching rule lists none.
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        let path = path.trim_end_matches('/');
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                rule.patterns
+                    .iter()
+                    .any(|pattern| pattern.matches_with(path, MATCH_OPTIONS))
+            })
+            .map(|rule| rule.owners.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns true if one of `identities` (GitHub handles, teams or emails,
+    /// compared case-insensitively) owns `path`.
+    pub fn is_owned_by(&self, path: &str, identities: &[String]) -> bool {
+        self.owners_of(path).iter().any(|owner| {
+            identities
+                .iter()
+                .any(|identity| identity.eq_ignore_ascii_case(owner))
+        })
+    }
+}
+
+/// Translates a CODEOWNERS pattern (gitignore syntax) into globs: a pattern
+/// without a slash before its end matches at any depth, and a pattern naming a
+/// directory also covers everything below it. `docs/*` only covers the files
+/// directly in `docs`, as on GitHub.
+fn owner_patterns(pattern: &str) -> Vec<Pattern> {
+    let body = pattern.trim_end_matches('/');
+    let anchored = body.starts_with('/') || body.contains('/');
+    let body = body.trim_start_matches('/');
+    let base = if anchored || body.starts_with("**") {
+        body.to_string()
+    } else {
+        format!("**/{}", body)
+    };
+
+    let mut globs = vec![base.clone()];
+    if !base.ends_with('*') {
+        globs.push(format!("{}/**", base));
+    }
+    globs
+        .iter()
+        .filter_map(|glob| Pattern::new(glob).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_matching_rule_decides_the_owners() {
+        let owners = CodeOwners::parse(
+            "# default owners\n\
+             *       @org/everyone\n\
+             *.js    @alice # frontend\n\
+             /api/   @org/backend @bob\n\
+             docs/*  docs@example.com\n\
+             /api/generated/\n",
+        );
+        let mine = vec!["@Bob".to_string()];
+
+        assert_eq!(owners.owners_of("README.md"), ["@org/everyone"]);
+        assert_eq!(owners.owners_of("web/app.js"), ["@alice"]);
+        assert!(owners.is_owned_by("api/routes.rs", &mine));
+        assert!(owners.is_owned_by("api/", &mine));
+        assert!(owners.is_owned_by("api/handlers/user.rs", &mine));
+        assert!(!owners.is_owned_by("api/generated/client.rs", &mine));
+        assert!(!owners.is_owned_by("web/api/routes.rs", &mine));
+        assert_eq!(owners.owners_of("docs/intro.md"), ["docs@example.com"]);
+        assert_eq!(owners.owners_of("docs/guide/intro.md"), ["@org/everyone"]);
+    }
+}
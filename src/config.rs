@@ -1,21 +1,1139 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use serde_yaml::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::error::GitChaiError;
+use crate::team_policy::TeamPolicy;
+
+pub const CONFIG_FILE_NAME: &str = ".chai.toml";
+
+/// Location of the per-user config file: `$XDG_CONFIG_HOME/git-chai/config.toml`,
+/// falling back to `~/.config/git-chai/config.toml`.
+pub fn global_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("git-chai").join("config.toml"))
+}
+
+/// Shortest scan interval accepted; shorter ones would keep a core busy
+/// running `git status`.
+pub const MIN_SCAN_INTERVAL_SECS: f64 = 0.1;
+
+/// Version of the `.chai.toml` format, bumped when keys are renamed or removed.
+pub const CONFIG_SCHEMA_VERSION: u32 = 1;
+
+const DEFAULT_CONFIG_FILE: &str = r#"# git-chai configuration
+# Subject of file and directory commits; placeholders: {change_type}, {name}, {path}, {dir},
+# {file_count}, {timestamp}, {branch}, plus {hostname}, {user}, {os} and {env:VAR}
+commit_message_template = "{change_type}: {name}"
+min_files_for_directory_commit = 2
+# For note vaults (Obsidian and the like): everything changed in a day goes into one commit,
+# "notes: 2024-06-01 (14 files)" or the note's title when it is the only one, amended until
+# it is pushed; editor swap, lock and backup files are never committed
+notes_mode = false
+# Seconds between scans in headless mode (also --interval); fractions like 0.5 work
+scan_interval_secs = 5
+# After a few scans in a row find nothing new, double the wait before each next one, up
+# to this many seconds; it drops back to scan_interval_secs once something changes
+# scan_backoff_max_secs = 300
+# With --watch, scan once files have stopped changing for watch_debounce_ms, and in any
+# case every watch_rescan_secs to catch missed events and retry pushes
+# watch_debounce_ms = 500
+# watch_rescan_secs = 600
+push_by_default = false
+# Never commit; only push commits made by hand that the remote doesn't have yet
+push_only = false
+# Never push to remotes whose push URL doesn't match / matches these globs, even with --push
+# push_allowed_remotes = ["git@git.corp.example:*"]
+# push_denied_remotes = ["*github.com*"]
+# Copy every branch git-chai commits to into this local repository (created bare if missing)
+# mirror_repo = "/mnt/backup/notes.git"
+# Write an encrypted patch of new commits here after each run (age or gpg), optionally
+# copied to an rclone remote
+# snapshot_dir = "/mnt/backup/notes-snapshots"
+# snapshot_recipient = "age1..."
+# snapshot_encryption = "age"
+# snapshot_rclone_remote = "backup:notes"
+# Incoming webhook (Slack/Mattermost style) for `git-chai summary --post`
+# summary_webhook = "https://hooks.example.com/services/..."
+# `git-chai time`: a gap between commits longer than this ends a work session
+# idle_threshold_minutes = 15
+# After committing, run this quick check and record pass/fail as a note on the new HEAD
+# (under refs/notes/chai-check)
+# check_command = "cargo check --quiet"
+# In a shallow clone, fetch full history before pushing
+unshallow_before_push = false
+# In a git-svn or hg-bridged repository, push HEAD to origin anyway instead of leaving
+# the commits for `git svn dcommit` or the bridge
+allow_bridge_push = false
+skip_symlinks = false
+# Commit through a temporary index so the real index is never used (skips commit hooks)
+isolated_index = false
+# In a repository colocated with Jujutsu (a .jj directory next to .git): "refuse" (don't
+# commit; jj already records the working copy) or "git-only" (commit to git through a
+# temporary index, as with isolated_index, leaving the index jj manages alone)
+jj_mode = "refuse"
+# Also commit files matched by .gitignore
+include_ignored = false
+# Trailer appended to every commit message, e.g. to record which machine autosaved;
+# {hostname}, {user}, {os} and {env:VAR} are replaced
+# commit_trailer = "Autosave-Host: {hostname} ({user}, {os})"
+# Author and commit autosaves as a bot instead of your own git identity
+# commit_author = "Chai Bot <bot@example.com>"
+# Write commit subjects in the style of the project's own history (Conventional Commits,
+# [scope] prefixes, tense, capitalization), learned from its recent hand-written commits;
+# the generated subject is kept in a Git-Chai trailer
+learn_message_style = false
+# Clean-up of generated subjects: "keep", "lower" or "sentence" case for the first word of
+# the description, dropping file extensions, and an ellipsis past max_subject_length
+subject_case = "keep"
+strip_extensions = false
+# Name single-file commits ({name}) after the file's title instead of its path: the first
+# Markdown heading, a Rust file's //! summary or the front matter title, if it has one
+content_titles = false
+# Jupyter notebooks: commit .ipynb files without cell outputs and execution counts (your
+# copy keeps them), skip saves that only re-ran cells, and list the changed cells in the message
+strip_notebook_outputs = false
+# max_subject_length = 72
+# Fix misspellings in generated subjects from a codespell-style list (typo->fix per line)
+# spelling_wordlist = ".chai-words.txt"
+# Record which settings produced each autosave (git-chai version, a hash of this config,
+# the grouping strategy and a hash of the scan's plan): "note" attaches it as a git note
+# under refs/notes/chai-provenance, "trailer" adds a Chai-Provenance trailer
+# provenance = "note"
+# Commit timestamps: "real" (default), "batch" (all commits of a scan share its start
+# time) or "offset" (real time plus commit_date_offset_secs)
+# commit_date = "batch"
+# commit_date_offset_secs = 0
+# Append a token to commit messages so autosaves don't trigger CI, optionally only on
+# some branches
+# skip_ci = true
+# skip_ci_token = "[skip ci]"
+# skip_ci_branches = ["wip/*"]
+# Pause while a rebase, merge, cherry-pick, revert or bisect is in progress
+pause_on_in_progress = true
+# Pause while any of these processes is running (e.g. a merge tool)
+# blocking_processes = ["meld", "kdiff3"]
+# Skip a cycle while another program (e.g. an IDE's git integration) holds index.lock or
+# wrote the index within this many seconds, instead of racing it
+# external_git_quiet_secs = 10
+# Untracked build output directories (target/, build/, dist/, .venv/, ...) are never
+# committed unless listed here; prefer adding them to .gitignore
+# commit_artifact_dirs = ["dist"]
+# Headless mode: when such a directory keeps appearing, add it to .gitignore and
+# commit that as "chore: ignore build artifacts" (otherwise only suggested)
+auto_gitignore = false
+# Files that add unresolved merge conflict markers (<<<<<<<, >>>>>>>) are left
+# uncommitted until the conflict is resolved, unless this is set
+allow_conflict_markers = false
+# Leave modified files uncommitted for review when they turn from text into UTF-16 or
+# binary, or gain a UTF-8 byte order mark (usually an editor setting, not an edit)
+encoding_guard = false
+# Leave files uncommitted while they fail a quick syntax check: python (python3),
+# javascript (node --check) or rust (cargo check in the file's package)
+# syntax_check = ["python", "javascript"]
+# In a monorepo, only commit paths that CODEOWNERS assigns to one of these handles, teams
+# or emails; other teams' directories are left alone even when your tooling touched them
+# owned_by = ["@me", "@my-org/my-team"]
+# Directories whose changes are always committed as a single directory-level commit
+# opaque_directories = ["vendor", "third_party"]
+# max_commits_per_hour = 30
+# Limit git subprocesses across all watched repositories (useful on NFS/SMB)
+# max_concurrent_git = 2
+# max_git_per_minute = 120
+# Append every git command run (argv, cwd, duration, exit code, stderr) to this file
+# audit_log = ".git/chai-audit.log"
+# Defer pushing while the unpushed commits exceed these sizes
+# max_push_bytes = 52428800
+# max_push_lines = 20000
+# When headless mode stops, merge the checked-out (autosave) branch into this branch as
+# one merge commit summarizing the session
+# session_merge_into = "main"
+# Push options sent with every push (`git push -o`), e.g. to skip CI or open a GitLab MR
+# push_options = ["ci.skip"]
+# Warn when the branch falls this many commits behind its upstream (as of the last fetch),
+# and optionally stop pushing until it has caught up
+# max_behind_commits = 20
+# pause_push_when_behind = false
+# Headless mode: `git fetch --prune` this often (independent of pushing)
+# fetch_interval_secs = 600
+# Email problems that need attention (persistent push failures, paths that keep failing
+# to commit, errors that stop headless mode) through an SMTP server using curl; the
+# password is read from the environment variable named by notify_smtp_password_env
+# notify_email_to = ["me@example.com"]
+# notify_email_from = "git-chai <chai@example.com>"
+# notify_smtp_url = "smtps://smtp.example.com:465"
+# notify_smtp_user = "chai@example.com"
+# notify_smtp_password_env = "CHAI_SMTP_PASSWORD"
+# Least severe events emailed: info, warning or critical
+# notify_email_min_severity = "critical"
+# Collect notifications and send one digest email this often instead of one email each
+# notify_email_digest_minutes = 60
+# Also send notifications to a Matrix room (as the user whose access token is in
+# $CHAI_MATRIX_TOKEN) and/or a Telegram chat (from the bot whose token is in
+# $CHAI_TELEGRAM_TOKEN)
+# notify_matrix_homeserver = "https://matrix.example.org"
+# notify_matrix_room = "!roomid:example.org"
+# notify_matrix_token_env = "CHAI_MATRIX_TOKEN"
+# notify_matrix_min_severity = "warning"
+# notify_telegram_chat_id = "123456789"
+# notify_telegram_token_env = "CHAI_TELEGRAM_TOKEN"
+# notify_telegram_min_severity = "warning"
+# Notifications that can't be sent (e.g. while offline) are kept and retried on later
+# scans for this long; 0 drops them right away
+# notify_queue_max_age_minutes = 1440
+
+# Have a model describe each commit's staged diff (also turned on by --ai), through an
+# OpenAI-compatible API; the diff leaves the machine, so point it at a server you trust.
+# The API key is read from the environment variable named by ai_api_key_env. If the
+# model can't be reached in ai_timeout_secs, the generated message is used.
+ai_messages = false
+# ai_endpoint = "http://localhost:11434/v1"
+# ai_model = "llama3.2"
+# ai_api_key_env = "OPENAI_API_KEY"
+# ai_timeout_secs = 20
+
+# How to handle files that change on many consecutive scans (commit, amend or hold)
+# [[repeat_change_rules]]
+# pattern = "notes/**"
+# policy = "hold"
+# quiet_minutes = 5
+
+# Per-path commit policies; the first matching pattern wins and unmatched paths are
+# auto-committed. require-approval asks in an interactive run, and otherwise queues
+# the change until it is approved with `git-chai review`; separate-branch commits to
+# `branch` without checking it out
+# [[path_policies]]
+# pattern = "migrations/**"
+# policy = "require-approval"
+# [[path_policies]]
+# pattern = "secrets/**"
+# policy = "never-commit"
+# [[path_policies]]
+# pattern = "journal/**"
+# policy = "separate-branch"
+# branch = "journal"
+
+# Stacked branches: changes under `pattern` are committed to `branch` (first match
+# wins) from a temporary worktree, so that branch's commit hooks run; changes that
+# match no pattern go to the checked-out branch
+# [[branch_map]]
+# pattern = "api/**"
+# branch = "stack/api"
+# [[branch_map]]
+# pattern = "web/**"
+# branch = "stack/web"
+
+# Named profiles, selected with --profile <name>
+# [profile.stream]
+# push_by_default = true
+# scan_interval_secs = 1
+"#;
+
+/// What to do when a file keeps changing across consecutive scans.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatPolicy {
+    /// Create a new commit every time the file changes
+    #[default]
+    Commit,
+    /// Amend the previous git-chai commit for the same file
+    Amend,
+    /// Wait until the file has been quiet for `quiet_minutes`
+    Hold,
+}
+
+/// How files matching a `path_policies` pattern are committed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathPolicy {
+    /// Commit like any other change
+    #[default]
+    AutoCommit,
+    /// Ask before committing in an interactive run; leave uncommitted otherwise
+    RequireApproval,
+    /// Never commit
+    NeverCommit,
+    /// Commit to `branch` instead of the checked-out branch
+    SeparateBranch,
+}
+
+/// A language whose changed files must pass a syntax check to be committed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SyntaxCheck {
+    /// `.py` files, compiled by `python3`
+    Python,
+    /// `.js`, `.mjs` and `.cjs` files, checked by `node --check`
+    JavaScript,
+    /// `.rs` files, by `cargo check` in the package containing them
+    Rust,
+}
+
+impl std::fmt::Display for SyntaxCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyntaxCheck::Python => write!(f, "python"),
+            SyntaxCheck::JavaScript => write!(f, "javascript"),
+            SyntaxCheck::Rust => write!(f, "rust"),
+        }
+    }
+}
+
+/// What to do in a git repository colocated with Jujutsu (`jj`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum JjMode {
+    /// Don't commit, and explain why
+    #[default]
+    Refuse,
+    /// Commit to git through a temporary index, never touching the index jj manages
+    GitOnly,
+}
+
+/// How the first word of a commit subject's description is cased.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SubjectCase {
+    /// Leave it as generated
+    #[default]
+    Keep,
+    /// `update notes`
+    Lower,
+    /// `Update notes`
+    Sentence,
+}
+
+/// Where a commit's provenance is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ProvenanceMode {
+    /// A git note under `refs/notes/chai-provenance`
+    Note,
+    /// A `Chai-Provenance` trailer in the message
+    Trailer,
+}
+
+/// How commit timestamps are chosen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitDateStrategy {
+    /// The time each commit is made
+    #[default]
+    Real,
+    /// Every commit of a scan shares the time the scan started
+    Batch,
+    /// The real time shifted by `commit_date_offset_secs`
+    Offset,
+}
+
+/// How urgent a notification is; channels only send those at or above their minimum.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifySeverity {
+    Info,
+    /// Something git-chai works around, such as a path it stopped retrying
+    Warning,
+    /// Something that needs a person, such as pushes that keep failing
+    #[default]
+    Critical,
+}
+
+impl std::fmt::Display for NotifySeverity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotifySeverity::Info => write!(f, "info"),
+            NotifySeverity::Warning => write!(f, "warning"),
+            NotifySeverity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// Tool used to encrypt snapshots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotEncryption {
+    /// `age`, with an age or SSH public key as recipient
+    #[default]
+    Age,
+    /// `gpg`, with a key ID or email address as recipient
+    Gpg,
+}
+
+impl SnapshotEncryption {
+    pub fn program(self) -> &'static str {
+        match self {
+            SnapshotEncryption::Age => "age",
+            SnapshotEncryption::Gpg => "gpg",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RepeatChangeRule {
+    /// Glob matched against repository-relative paths
+    pub pattern: String,
+    pub policy: RepeatPolicy,
+    /// Quiet period before held changes are committed
+    #[serde(default)]
+    pub quiet_minutes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PathPolicyRule {
+    /// Glob matched against repository-relative paths
+    pub pattern: String,
+    pub policy: PathPolicy,
+    /// Branch the `separate-branch` policy commits to
+    #[serde(default)]
+    pub branch: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BranchMapRule {
+    /// Glob matched against repository-relative paths
+    pub pattern: String,
+    /// Branch that matching changes are committed to
+    pub branch: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct Config {
+    /// Set to the repository root on load; not read from the file
+    #[schemars(skip)]
     pub repo_path: PathBuf,
+    /// The repository's committed `.chai-policy.toml`; not read from the file
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub team_policy: Option<TeamPolicy>,
+    /// Push after committing
     pub push_by_default: bool,
+    /// Subject of file and directory commits, with `{change_type}`, `{name}`, `{path}`,
+    /// `{dir}`, `{file_count}`, `{timestamp}` and `{branch}` placeholders
     pub commit_message_template: String,
+    /// Changed files needed before a directory gets one combined commit
     pub min_files_for_directory_commit: usize,
+    /// Commit a day's changes as one amended `notes:` commit and skip editor temporary files
+    pub notes_mode: bool,
+    /// Seconds between scans in headless mode, at least 0.1
+    pub scan_interval_secs: f64,
+    /// Slow scanning down to this many seconds between scans while nothing changes
+    pub scan_backoff_max_secs: Option<f64>,
+    /// `--watch`: quiet time after a change before scanning
+    pub watch_debounce_ms: u64,
+    /// `--watch`: seconds between scans when no change is seen
+    pub watch_rescan_secs: u64,
+    /// Headless mode: hold changes and commit them as one batch when exceeded
+    pub max_commits_per_hour: Option<usize>,
+    /// Policies for files that change on many consecutive scans
+    pub repeat_change_rules: Vec<RepeatChangeRule>,
+    /// Per-path commit policies; the first matching pattern wins
+    pub path_policies: Vec<PathPolicyRule>,
+    /// Branches that changes to matching paths are committed to; the first matching pattern wins
+    pub branch_map: Vec<BranchMapRule>,
+    /// Leave symlink changes uncommitted
+    pub skip_symlinks: bool,
+    /// Directories always committed as one unit, without a completeness check
+    pub opaque_directories: Vec<String>,
+    /// Commit via a temporary index, leaving the user's staging area alone
+    pub isolated_index: bool,
+    /// What to do in a repository colocated with Jujutsu
+    pub jj_mode: JjMode,
+    /// Also commit files matched by `.gitignore`
+    pub include_ignored: bool,
+    /// In a shallow clone, fetch full history before pushing
+    pub unshallow_before_push: bool,
+    /// Push even when the repository is bridged to Subversion or Mercurial
+    pub allow_bridge_push: bool,
+    /// Trailer appended to every commit message; `{hostname}`, `{user}`, `{os}` and
+    /// `{env:VAR}` are replaced
+    pub commit_trailer: Option<String>,
+    /// Identity autosaves are authored and committed as, e.g. `Chai Bot <bot@example.com>`
+    pub commit_author: Option<String>,
+    /// Rewrite commit subjects in the style learned from the project's history
+    pub learn_message_style: bool,
+    /// Case of the first word of a subject's description
+    pub subject_case: SubjectCase,
+    /// Drop file extensions from paths in commit subjects
+    pub strip_extensions: bool,
+    /// Use a file's heading, `//!` summary or front matter title as `{name}` in
+    /// single-file commits
+    pub content_titles: bool,
+    /// Commit notebooks without their outputs and summarize their changed cells
+    pub strip_notebook_outputs: bool,
+    /// Truncate longer subjects with an ellipsis
+    pub max_subject_length: Option<usize>,
+    /// Codespell-style wordlist of misspellings fixed in commit subjects, relative to the repo
+    pub spelling_wordlist: Option<PathBuf>,
+    /// Where each commit records the settings that produced it: note or trailer
+    pub provenance: Option<ProvenanceMode>,
+    /// How commit timestamps are chosen: real, batch or offset
+    pub commit_date: CommitDateStrategy,
+    /// Seconds added to commit timestamps with `commit_date = "offset"` (may be negative)
+    pub commit_date_offset_secs: i64,
+    /// Append `skip_ci_token` to commit messages so autosaves don't trigger CI
+    pub skip_ci: bool,
+    /// Token appended to commit messages when `skip_ci` is set
+    pub skip_ci_token: String,
+    /// Only append `skip_ci_token` on branches matching these globs (all branches if empty)
+    pub skip_ci_branches: Vec<String>,
+    /// Don't commit during a rebase, merge, cherry-pick, revert or bisect
+    pub pause_on_in_progress: bool,
+    /// Don't commit while any of these processes is running
+    pub blocking_processes: Vec<String>,
+    /// Don't commit while another git client wrote the index within this many seconds
+    pub external_git_quiet_secs: Option<u64>,
+    /// Limit on git subprocesses running at once, across all repositories
+    pub max_concurrent_git: Option<usize>,
+    /// Limit on git subprocesses started per minute, across all repositories
+    pub max_git_per_minute: Option<usize>,
+    /// File recording every git command run, relative to the repository root
+    pub audit_log: Option<PathBuf>,
+    /// Defer pushing while the unpushed commits take up more than this many bytes
+    pub max_push_bytes: Option<u64>,
+    /// Defer pushing while the unpushed commits change more than this many lines
+    pub max_push_lines: Option<u64>,
+    /// Headless mode: when the session ends, merge the checked-out branch into this one
+    pub session_merge_into: Option<String>,
+    /// Push options (`git push -o`) sent with every push, e.g. `ci.skip`
+    pub push_options: Vec<String>,
+    /// Warn when the branch falls more than this many commits behind its upstream
+    pub max_behind_commits: Option<usize>,
+    /// Headless mode: run `git fetch --prune` this often, so remote state stays fresh
+    pub fetch_interval_secs: Option<u64>,
+    /// Defer pushing while the branch is more than `max_behind_commits` behind its upstream
+    pub pause_push_when_behind: bool,
+    /// Untracked build output directories (e.g. `dist`) to commit anyway
+    pub commit_artifact_dirs: Vec<String>,
+    /// Append build output that keeps appearing to `.gitignore` and commit it
+    pub auto_gitignore: bool,
+    /// Commit files even if they add merge conflict markers
+    pub allow_conflict_markers: bool,
+    /// Leave files uncommitted whose encoding changed from text to UTF-16, binary or UTF-8 with a BOM
+    pub encoding_guard: bool,
+    /// Languages whose changed files must pass a syntax check to be committed
+    pub syntax_check: Vec<SyntaxCheck>,
+    /// CODEOWNERS identities whose paths are committed; empty commits every path
+    pub owned_by: Vec<String>,
+    /// Never commit; only push local commits the remote doesn't have yet
+    pub push_only: bool,
+    /// Glob patterns for push URLs git-chai may push to; empty allows any
+    pub push_allowed_remotes: Vec<String>,
+    /// Glob patterns for push URLs git-chai must never push to
+    pub push_denied_remotes: Vec<String>,
+    /// Local repository that every new commit is copied into, relative to the repository root
+    pub mirror_repo: Option<PathBuf>,
+    /// Directory for encrypted patches of new commits, relative to the repository root
+    pub snapshot_dir: Option<PathBuf>,
+    /// Recipient the snapshots are encrypted to; snapshots are off without one
+    pub snapshot_recipient: Option<String>,
+    pub snapshot_encryption: SnapshotEncryption,
+    /// rclone destination (`remote:path`) each snapshot is also copied to
+    pub snapshot_rclone_remote: Option<String>,
+    /// Incoming webhook that `git-chai summary --post` sends the summary to
+    pub summary_webhook: Option<String>,
+    /// `git-chai time`: a gap between commits longer than this ends a work session
+    pub idle_threshold_minutes: u64,
+    /// Quick check (run with `sh -c`) whose pass/fail is recorded as a git note on each new HEAD
+    pub check_command: Option<String>,
+    /// Addresses notification emails are sent to; email notifications are off without any
+    pub notify_email_to: Vec<String>,
+    /// Sender of notification emails (defaults to `notify_smtp_user`)
+    pub notify_email_from: Option<String>,
+    /// SMTP server for notification emails, e.g. `smtps://smtp.example.com:465`
+    pub notify_smtp_url: Option<String>,
+    /// SMTP login; TLS is required when set
+    pub notify_smtp_user: Option<String>,
+    /// Environment variable holding the SMTP password
+    pub notify_smtp_password_env: String,
+    /// Least severe notifications that are emailed: info, warning or critical
+    pub notify_email_min_severity: NotifySeverity,
+    /// Send one digest email this often instead of an email per notification
+    pub notify_email_digest_minutes: Option<u64>,
+    /// Matrix homeserver for notifications, e.g. `https://matrix.example.org`
+    pub notify_matrix_homeserver: Option<String>,
+    /// Matrix room ID (`!id:server`) notifications are posted to
+    pub notify_matrix_room: Option<String>,
+    /// Environment variable holding the Matrix access token
+    pub notify_matrix_token_env: String,
+    /// Least severe notifications that are posted to Matrix
+    pub notify_matrix_min_severity: NotifySeverity,
+    /// Telegram chat the notification bot writes to
+    pub notify_telegram_chat_id: Option<String>,
+    /// Environment variable holding the Telegram bot token
+    pub notify_telegram_token_env: String,
+    /// Least severe notifications that are sent to Telegram
+    pub notify_telegram_min_severity: NotifySeverity,
+    /// How long undelivered notifications are kept for retrying; 0 disables
+    /// the queue
+    pub notify_queue_max_age_minutes: u64,
+    /// Describe each commit's staged diff with a model instead of the generated message
+    pub ai_messages: bool,
+    /// Base URL of the OpenAI-compatible API
+    pub ai_endpoint: String,
+    pub ai_model: String,
+    /// Environment variable holding the API key
+    pub ai_api_key_env: String,
+    /// Seconds to wait for the model before using the generated message
+    pub ai_timeout_secs: u64,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             repo_path: PathBuf::from("."),
-            push_by_default: true,
+            team_policy: None,
+            push_by_default: false,
             commit_message_template: "{change_type}: {name}".to_string(),
             min_files_for_directory_commit: 2,
+            notes_mode: false,
+            scan_interval_secs: 5.0,
+            scan_backoff_max_secs: None,
+            watch_debounce_ms: 500,
+            watch_rescan_secs: 600,
+            max_commits_per_hour: None,
+            repeat_change_rules: Vec::new(),
+            path_policies: Vec::new(),
+            branch_map: Vec::new(),
+            skip_symlinks: false,
+            opaque_directories: Vec::new(),
+            isolated_index: false,
+            jj_mode: JjMode::Refuse,
+            include_ignored: false,
+            unshallow_before_push: false,
+            allow_bridge_push: false,
+            commit_trailer: None,
+            commit_author: None,
+            learn_message_style: false,
+            subject_case: SubjectCase::Keep,
+            strip_extensions: false,
+            content_titles: false,
+            strip_notebook_outputs: false,
+            max_subject_length: None,
+            spelling_wordlist: None,
+            provenance: None,
+            commit_date: CommitDateStrategy::Real,
+            commit_date_offset_secs: 0,
+            skip_ci: false,
+            skip_ci_token: "[skip ci]".to_string(),
+            skip_ci_branches: Vec::new(),
+            pause_on_in_progress: true,
+            blocking_processes: Vec::new(),
+            external_git_quiet_secs: None,
+            max_concurrent_git: None,
+            max_git_per_minute: None,
+            audit_log: None,
+            max_push_bytes: None,
+            max_push_lines: None,
+            session_merge_into: None,
+            push_options: Vec::new(),
+            max_behind_commits: None,
+            fetch_interval_secs: None,
+            pause_push_when_behind: false,
+            commit_artifact_dirs: Vec::new(),
+            auto_gitignore: false,
+            allow_conflict_markers: false,
+            encoding_guard: false,
+            syntax_check: Vec::new(),
+            owned_by: Vec::new(),
+            push_only: false,
+            push_allowed_remotes: Vec::new(),
+            push_denied_remotes: Vec::new(),
+            mirror_repo: None,
+            snapshot_dir: None,
+            snapshot_recipient: None,
+            snapshot_encryption: SnapshotEncryption::Age,
+            snapshot_rclone_remote: None,
+            summary_webhook: None,
+            idle_threshold_minutes: 15,
+            check_command: None,
+            notify_email_to: Vec::new(),
+            notify_email_from: None,
+            notify_smtp_url: None,
+            notify_smtp_user: None,
+            notify_smtp_password_env: "CHAI_SMTP_PASSWORD".to_string(),
+            notify_email_min_severity: NotifySeverity::Critical,
+            notify_email_digest_minutes: None,
+            notify_matrix_homeserver: None,
+            notify_matrix_room: None,
+            notify_matrix_token_env: "CHAI_MATRIX_TOKEN".to_string(),
+            notify_matrix_min_severity: NotifySeverity::Critical,
+            notify_telegram_chat_id: None,
+            notify_telegram_token_env: "CHAI_TELEGRAM_TOKEN".to_string(),
+            notify_telegram_min_severity: NotifySeverity::Critical,
+            notify_queue_max_age_minutes: 24 * 60,
+            ai_messages: false,
+            ai_endpoint: "https://api.openai.com/v1".to_string(),
+            ai_model: "gpt-4o-mini".to_string(),
+            ai_api_key_env: "OPENAI_API_KEY".to_string(),
+            ai_timeout_secs: 20,
+        }
+    }
+}
+
+impl Config {
+    /// Returns true if the repo root already has a `.chai.toml`.
+    pub fn file_exists(repo_root: &Path) -> bool {
+        repo_root.join(CONFIG_FILE_NAME).is_file()
+    }
+
+    /// Writes a commented default `.chai.toml` to the repo root.
+    pub fn write_default(repo_root: &Path) -> Result<PathBuf, GitChaiError> {
+        let config_path = repo_root.join(CONFIG_FILE_NAME);
+        std::fs::write(&config_path, DEFAULT_CONFIG_FILE)?;
+        log::debug!("Wrote default config to {:?}", config_path);
+        Ok(config_path)
+    }
+
+    /// Appends a `[[path_policies]]` entry to the repo root's `.chai.toml`,
+    /// creating the file if needed and leaving the rest of it as it is.
+    pub fn append_path_policy(
+        repo_root: &Path,
+        rule: &PathPolicyRule,
+    ) -> Result<PathBuf, GitChaiError> {
+        let config_path = repo_root.join(CONFIG_FILE_NAME);
+        let mut contents = if config_path.is_file() {
+            std::fs::read_to_string(&config_path)?
+        } else {
+            String::new()
+        };
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        let entry = toml::to_string(rule).map_err(|e| {
+            GitChaiError::ConfigError(format!("Failed to serialize path policy: {}", e))
+        })?;
+        contents.push_str(&format!("\n[[path_policies]]\n{}", entry));
+
+        // An inline `path_policies = [...]` can't be extended with a table
+        contents.parse::<toml::Table>().map_err(|_| {
+            GitChaiError::ConfigError(format!(
+                "Can't add a path policy for {} to {:?}; add it to path_policies by hand",
+                rule.pattern, config_path
+            ))
+        })?;
+        std::fs::write(&config_path, contents)?;
+        Ok(config_path)
+    }
+
+    /// Loads `.chai.toml` from the repo root (if present) on top of the settings
+    /// in the global config file, then applies the named `[profile.<name>]`
+    /// table.
+    pub fn load(repo_root: &Path, profile: Option<&str>) -> Result<Self, GitChaiError> {
+        Self::load_layered(global_config_path().as_deref(), repo_root, profile)
+    }
+
+    fn load_layered(
+        global_path: Option<&Path>,
+        repo_root: &Path,
+        profile: Option<&str>,
+    ) -> Result<Self, GitChaiError> {
+        let config_path = repo_root.join(CONFIG_FILE_NAME);
+
+        let mut table = match global_path {
+            Some(path) => read_table(path)?,
+            None => toml::Table::new(),
+        };
+        // The repository access lists only apply globally, see `RepoAccess`
+        table.remove("allowed_repos");
+        table.remove("denied_repos");
+        for (key, value) in read_table(&config_path)? {
+            match (table.get_mut(&key), value) {
+                // Profiles of the same name are merged key by key as well
+                (Some(toml::Value::Table(global)), toml::Value::Table(local))
+                    if key == "profile" =>
+                {
+                    for (name, settings) in local {
+                        match (global.get_mut(&name), settings) {
+                            (Some(toml::Value::Table(global)), toml::Value::Table(local)) => {
+                                global.extend(local)
+                            }
+                            (_, settings) => {
+                                global.insert(name, settings);
+                            }
+                        }
+                    }
+                }
+                (_, value) => {
+                    table.insert(key, value);
+                }
+            }
+        }
+
+        let profiles = match table.remove("profile") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => {
+                return Err(GitChaiError::ConfigError(
+                    "'profile' must be a table of named profiles".to_string(),
+                ));
+            }
+            None => toml::Table::new(),
+        };
+
+        if let Some(name) = profile {
+            match profiles.get(name) {
+                Some(toml::Value::Table(settings)) => {
+                    log::debug!("Applying config profile '{}'", name);
+                    for (key, value) in settings {
+                        table.insert(key.clone(), value.clone());
+                    }
+                }
+                Some(_) => {
+                    return Err(GitChaiError::ConfigError(format!(
+                        "Profile '{}' must be a table",
+                        name
+                    )));
+                }
+                None => {
+                    return Err(GitChaiError::ConfigError(format!(
+                        "Profile '{}' not found in {:?}",
+                        name, config_path
+                    )));
+                }
+            }
+        }
+
+        let mut config: Config = table.try_into().map_err(|e| {
+            GitChaiError::ConfigError(format!("Invalid config in {:?}: {}", config_path, e))
+        })?;
+        config.repo_path = repo_root.to_path_buf();
+        config.check_values()?;
+
+        Ok(config)
+    }
+
+    /// Time between scans in headless mode.
+    pub fn scan_interval(&self) -> Duration {
+        Duration::from_secs_f64(self.scan_interval_secs)
+    }
+
+    /// Longest time between scans when `scan_backoff_max_secs` is set.
+    pub fn scan_backoff_max(&self) -> Option<Duration> {
+        self.scan_backoff_max_secs.map(Duration::from_secs_f64)
+    }
+
+    fn check_values(&self) -> Result<(), GitChaiError> {
+        parse_interval("scan_interval_secs", self.scan_interval_secs)?;
+        if let Some(max) = self.scan_backoff_max_secs {
+            parse_interval("scan_backoff_max_secs", max)?;
+        }
+        if let Some(author) = &self.commit_author {
+            parse_author("commit_author", author)?;
+        }
+        Ok(())
+    }
+
+    /// Names of all top-level keys accepted in `.chai.toml`, sorted.
+    pub fn supported_keys() -> Vec<String> {
+        let value = serde_yaml::to_value(Config::default()).unwrap_or(Value::Null);
+        let mut keys: Vec<String> = value
+            .as_mapping()
+            .into_iter()
+            .flat_map(|mapping| mapping.keys())
+            .filter_map(|key| key.as_str().map(str::to_string))
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// JSON Schema for `.chai.toml`, including `[profile.<name>]` tables.
+    pub fn json_schema() -> serde_json::Value {
+        let mut schema =
+            serde_json::to_value(schemars::schema_for!(Config)).unwrap_or(serde_json::Value::Null);
+        schema["title"] = "git-chai configuration (.chai.toml)".into();
+        schema["x-schema-version"] = CONFIG_SCHEMA_VERSION.into();
+        schema["properties"]["profile"] = serde_json::json!({
+            "description": "Named profiles overriding the top-level settings, selected with --profile",
+            "type": "object",
+            "additionalProperties": { "$ref": "#" },
+        });
+        schema
+    }
+
+    /// Applies `key=value` overrides (from `--set`) on top of the current settings.
+    pub fn apply_overrides(&mut self, overrides: &[String]) -> Result<(), GitChaiError> {
+        if overrides.is_empty() {
+            return Ok(());
+        }
+
+        let mut value = serde_yaml::to_value(&*self)
+            .map_err(|e| GitChaiError::ConfigError(format!("Failed to serialize config: {}", e)))?;
+
+        let mapping = value
+            .as_mapping_mut()
+            .ok_or_else(|| GitChaiError::ConfigError("Config is not a mapping".to_string()))?;
+
+        for entry in overrides {
+            let (key, raw) = entry.split_once('=').ok_or_else(|| {
+                GitChaiError::ConfigError(format!("Expected key=value, got '{}'", entry))
+            })?;
+            let key = key.trim();
+            if key == "repo_path" {
+                return Err(GitChaiError::ConfigError(
+                    "repo_path can't be overridden; pass the repository path instead".to_string(),
+                ));
+            }
+
+            let current = mapping.get(key).ok_or_else(|| {
+                GitChaiError::ConfigError(format!("Unknown config key '{}'", key))
+            })?;
+
+            let parsed = parse_override_value(key, current, raw)?;
+            log::debug!("Overriding config key {} = {:?}", key, parsed);
+            mapping.insert(Value::String(key.to_string()), parsed);
+        }
+
+        *self = serde_yaml::from_value(value)
+            .map_err(|e| GitChaiError::ConfigError(format!("Invalid config override: {}", e)))?;
+
+        self.check_values()
+    }
+}
+
+/// Checks a scan interval of `secs` seconds given as `name`.
+pub fn parse_interval(name: &str, secs: f64) -> Result<Duration, GitChaiError> {
+    if secs.is_nan() || secs < MIN_SCAN_INTERVAL_SECS {
+        return Err(GitChaiError::ConfigError(format!(
+            "{} must be at least {} seconds, got {}",
+            name, MIN_SCAN_INTERVAL_SECS, secs
+        )));
+    }
+    Duration::try_from_secs_f64(secs)
+        .map_err(|_| GitChaiError::ConfigError(format!("{} is too long: {}", name, secs)))
+}
+
+/// Splits an identity `author` given as `name`, in git's `Name <email>` form,
+/// into its name and email.
+pub fn parse_author(name: &str, author: &str) -> Result<(String, String), GitChaiError> {
+    author
+        .trim()
+        .strip_suffix('>')
+        .and_then(|author| author.split_once('<'))
+        .map(|(user, email)| (user.trim(), email.trim()))
+        .filter(|(user, email)| {
+            !user.is_empty() && !email.is_empty() && !email.contains(['<', '>'])
+        })
+        .map(|(user, email)| (user.to_string(), email.to_string()))
+        .ok_or_else(|| {
+            GitChaiError::ConfigError(format!(
+                "{} must look like 'Name <email>', got '{}'",
+                name, author
+            ))
+        })
+}
+
+/// Parses a config file into a table; a missing file is an empty one.
+fn read_table(path: &Path) -> Result<toml::Table, GitChaiError> {
+    if !path.is_file() {
+        return Ok(toml::Table::new());
+    }
+    log::debug!("Loading config from {:?}", path);
+    let contents = std::fs::read_to_string(path)?;
+    contents
+        .parse::<toml::Table>()
+        .map_err(|e| GitChaiError::ConfigError(format!("Failed to parse {:?}: {}", path, e)))
+}
+
+/// Interprets a raw override string according to the type of the key it replaces,
+/// so that e.g. `commit_message_template=wip: {name}` stays a plain string.
+fn parse_override_value(key: &str, current: &Value, raw: &str) -> Result<Value, GitChaiError> {
+    let invalid = |expected: &str| {
+        GitChaiError::ConfigError(format!(
+            "Invalid value '{}' for '{}': expected {}",
+            raw, key, expected
+        ))
+    };
+
+    match current {
+        Value::Bool(_) => raw
+            .trim()
+            .parse::<bool>()
+            .map(Value::Bool)
+            .map_err(|_| invalid("true or false")),
+        Value::Number(_) => serde_yaml::from_str::<serde_yaml::Number>(raw.trim())
+            .map(Value::Number)
+            .map_err(|_| invalid("a number")),
+        Value::String(_) => Ok(Value::String(raw.to_string())),
+        Value::Null => match serde_yaml::from_str::<Value>(raw) {
+            Ok(value @ (Value::Bool(_) | Value::Number(_))) => Ok(value),
+            _ => Ok(Value::String(raw.to_string())),
+        },
+        _ => serde_yaml::from_str(raw).map_err(|_| invalid("a valid value")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_load_with_profile() {
+        let dir = TempRepo::dir("config");
+        dir.write(
+            CONFIG_FILE_NAME,
+            r#"
+commit_message_template = "chore: {name}"
+push_by_default = false
+
+[profile.stream]
+push_by_default = true
+scan_interval_secs = 1
+"#,
+        );
+
+        let base = Config::load(&dir, None).unwrap();
+        assert_eq!(base.commit_message_template, "chore: {name}");
+        assert!(!base.push_by_default);
+        assert_eq!(base.scan_interval_secs, 5.0);
+
+        let stream = Config::load(&dir, Some("stream")).unwrap();
+        assert_eq!(stream.commit_message_template, "chore: {name}");
+        assert!(stream.push_by_default);
+        assert_eq!(stream.scan_interval_secs, 1.0);
+
+        assert!(Config::load(&dir, Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_repo_config_layers_over_the_global_one() {
+        let dir = TempRepo::dir("global");
+        let global = dir.join("config.toml");
+        dir.write(
+            "config.toml",
+            r#"
+allowed_repos = ["~/notes"]
+scan_interval_secs = 30
+min_files_for_directory_commit = 4
+
+[profile.stream]
+push_by_default = true
+scan_interval_secs = 2
+"#,
+        );
+        dir.write(
+            CONFIG_FILE_NAME,
+            r#"
+scan_interval_secs = 10
+
+[profile.stream]
+scan_interval_secs = 1
+"#,
+        );
+
+        let base = Config::load_layered(Some(&global), &dir, None).unwrap();
+        assert_eq!(base.scan_interval_secs, 10.0);
+        assert_eq!(base.min_files_for_directory_commit, 4);
+
+        let stream = Config::load_layered(Some(&global), &dir, Some("stream")).unwrap();
+        assert!(stream.push_by_default);
+        assert_eq!(stream.scan_interval_secs, 1.0);
+    }
+
+    #[test]
+    fn test_default_config_file_parses() {
+        let config: Config = toml::from_str(DEFAULT_CONFIG_FILE).unwrap();
+        let defaults = Config::default();
+        assert_eq!(
+            config.commit_message_template,
+            defaults.commit_message_template
+        );
+        assert_eq!(config.scan_interval_secs, defaults.scan_interval_secs);
+        assert_eq!(config.push_by_default, defaults.push_by_default);
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut config = Config::default();
+        config
+            .apply_overrides(&[
+                "commit_message_template=wip: {name}".to_string(),
+                "min_files_for_directory_commit=5".to_string(),
+                "push_by_default=false".to_string(),
+                "max_commits_per_hour=20".to_string(),
+                "scan_interval_secs=0.5".to_string(),
+                "scan_backoff_max_secs=300".to_string(),
+            ])
+            .unwrap();
+
+        assert_eq!(config.commit_message_template, "wip: {name}");
+        assert_eq!(config.min_files_for_directory_commit, 5);
+        assert!(!config.push_by_default);
+        assert_eq!(config.max_commits_per_hour, Some(20));
+        assert_eq!(config.scan_interval(), Duration::from_millis(500));
+        assert_eq!(config.scan_backoff_max(), Some(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn test_apply_overrides_rejects_bad_input() {
+        let mut config = Config::default();
+        assert!(
+            config
+                .apply_overrides(&["no_such_key=1".to_string()])
+                .is_err()
+        );
+        assert!(
+            config
+                .apply_overrides(&["push_by_default".to_string()])
+                .is_err()
+        );
+        assert!(
+            config
+                .apply_overrides(&["min_files_for_directory_commit=many".to_string()])
+                .is_err()
+        );
+        assert!(
+            config
+                .apply_overrides(&["scan_interval_secs=0".to_string()])
+                .is_err()
+        );
+        assert!(
+            config
+                .apply_overrides(&["repo_path=/etc".to_string()])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_author() {
+        assert_eq!(
+            parse_author("--author", " Chai Bot <bot@example.com> ").unwrap(),
+            ("Chai Bot".to_string(), "bot@example.com".to_string())
+        );
+        for bad in ["Chai Bot", "<bot@example.com>", "Chai Bot <>", "Bot <a<b>"] {
+            assert!(parse_author("--author", bad).is_err(), "{}", bad);
+        }
+
+        let mut config = Config::default();
+        assert!(
+            config
+                .apply_overrides(&["commit_author=bot@example.com".to_string()])
+                .is_err()
+        );
+        config
+            .apply_overrides(&["commit_author=Chai Bot <bot@example.com>".to_string()])
+            .unwrap();
+        assert_eq!(
+            config.commit_author.as_deref(),
+            Some("Chai Bot <bot@example.com>")
+        );
+    }
+
+    #[test]
+    fn test_json_schema_covers_config_keys() {
+        let schema = Config::json_schema();
+        let properties = schema["properties"].as_object().unwrap();
+
+        for key in Config::supported_keys() {
+            assert_eq!(properties.contains_key(&key), key != "repo_path", "{}", key);
         }
+        assert!(properties.contains_key("profile"));
     }
 }
@@ -0,0 +1,104 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::diff_against_head;
+use crate::git::status::GitChange;
+use crate::types::ChangeType;
+
+/// Returns where a change adds a line that looks like an unresolved merge
+/// conflict, as `file:line`, or `None` if it adds none.
+pub fn find_conflict_marker(
+    repo_path: &Path,
+    change: &GitChange,
+) -> Result<Option<String>, GitChaiError> {
+    if change.change_type == ChangeType::Delete {
+        return Ok(None);
+    }
+    let diff = diff_against_head(repo_path, &change.filename, false)?;
+    Ok(first_marker(&diff))
+}
+
+/// Scans the added lines of a unified diff for `<<<<<<<`, `>>>>>>>` and diff3's
+/// `|||||||`. A `=======` line alone is not enough, since it also underlines
+/// Markdown and reStructuredText headings.
+fn first_marker(diff: &str) -> Option<String> {
+    let mut file = "";
+    let mut line = 0usize;
+
+    for text in diff.lines() {
+        if let Some(name) = text.strip_prefix("+++ ") {
+            file = name.strip_prefix("b/").unwrap_or(name);
+            continue;
+        }
+        if let Some(hunk) = text.strip_prefix("@@ ") {
+            // "@@ -a,b +c,d @@": the next line of the new file is c
+            line = hunk
+                .split_whitespace()
+                .find_map(|range| range.strip_prefix('+'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|start| start.parse().ok())
+                .unwrap_or(1);
+            continue;
+        }
+
+        let Some(added) = text.strip_prefix('+') else {
+            if !text.starts_with('-') && !text.starts_with('\\') {
+                line += 1;
+            }
+            continue;
+        };
+        if is_marker(added, "<<<<<<<") || is_marker(added, ">>>>>>>") || is_marker(added, "|||||||")
+        {
+            return Some(format!("{}:{}", file, line));
+        }
+        line += 1;
+    }
+    None
+}
+
+/// A marker is seven characters at the start of a line, alone or followed by a
+/// space and the name of the side.
+fn is_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_marker_finds_conflicts_but_not_headings() {
+        let conflict = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,3 +10,7 @@ fn main() {
+ let a = 1;
++<<<<<<< HEAD
+ let b = 2;
++=======
++let b = 3;
++>>>>>>> feature
+";
+        assert_eq!(first_marker(conflict), Some("src/lib.rs:11".to_string()));
+
+        let heading = "\
+--- /dev/null
++++ b/README.md
+@@ -0,0 +1,3 @@
++Title
++=======
++<<<<<<<< not a marker
+";
+        assert_eq!(first_marker(heading), None);
+
+        let removed = "\
++++ b/notes.txt
+@@ -1,2 +1,1 @@
+-<<<<<<< HEAD
+ kept
+";
+        assert_eq!(first_marker(removed), None);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Notify;
+
+/// What a watched repository is doing right now.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Activity {
+    #[default]
+    Idle,
+    Scanning,
+    /// The last cycle failed
+    Error,
+}
+
+impl fmt::Display for Activity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Activity::Idle => write!(f, "idle"),
+            Activity::Scanning => write!(f, "scanning"),
+            Activity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RepoStatus {
+    pub activity: Activity,
+    pub last_error: Option<String>,
+}
+
+/// Headless-mode state shared with outside controllers such as the D-Bus
+/// service and the tray icon: pause and push switches, requests for an
+/// immediate scan or to stop, and the status of each watched repository.
+#[derive(Debug, Default)]
+pub struct Control {
+    paused: AtomicBool,
+    /// Overrides `push_by_default` of every repository when set
+    push: Mutex<Option<bool>>,
+    wake: Arc<Notify>,
+    stop: Arc<Notify>,
+    repos: Mutex<BTreeMap<PathBuf, RepoStatus>>,
+    last_error: Mutex<Option<String>>,
+}
+
+impl Control {
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+        if !paused {
+            self.wake.notify_waiters();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn set_push(&self, push: bool) {
+        *self.push.lock().unwrap_or_else(|e| e.into_inner()) = Some(push);
+    }
+
+    /// Whether a repository configured with `push_by_default = configured`
+    /// should push right now.
+    pub fn push_enabled(&self, configured: bool) -> bool {
+        self.push
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .unwrap_or(configured)
+    }
+
+    /// Stops headless mode as if Ctrl+C was pressed.
+    pub fn request_stop(&self) {
+        self.stop.notify_one();
+    }
+
+    pub fn stop_handle(&self) -> Arc<Notify> {
+        self.stop.clone()
+    }
+
+    /// Starts the next scan of every repository that is waiting for one now.
+    pub fn request_scan(&self) {
+        self.wake.notify_waiters();
+    }
+
+    /// Woken by `request_scan` (and by resuming), for the scheduler.
+    pub fn wake_handle(&self) -> Arc<Notify> {
+        self.wake.clone()
+    }
+
+    pub fn set_activity(&self, repo: &Path, activity: Activity) {
+        let mut repos = self.repos.lock().unwrap_or_else(|e| e.into_inner());
+        let status = repos.entry(repo.to_path_buf()).or_default();
+        status.activity = activity;
+        if activity == Activity::Idle {
+            status.last_error = None;
+        }
+    }
+
+    pub fn record_error(&self, repo: &Path, error: String) {
+        let mut repos = self.repos.lock().unwrap_or_else(|e| e.into_inner());
+        let status = repos.entry(repo.to_path_buf()).or_default();
+        status.activity = Activity::Error;
+        status.last_error = Some(error.clone());
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error);
+    }
+
+    pub fn repos(&self) -> Vec<(PathBuf, RepoStatus)> {
+        let repos = self.repos.lock().unwrap_or_else(|e| e.into_inner());
+        repos
+            .iter()
+            .map(|(path, status)| (path.clone(), status.clone()))
+            .collect()
+    }
+
+    /// One word for the whole session: `paused`, `error` if any repository's
+    /// last cycle failed, `scanning` if any is being scanned, otherwise `idle`.
+    pub fn state(&self) -> String {
+        if self.is_paused() {
+            return "paused".to_string();
+        }
+        let repos = self.repos();
+        [Activity::Error, Activity::Scanning]
+            .into_iter()
+            .find(|activity| repos.iter().any(|(_, status)| status.activity == *activity))
+            .unwrap_or(Activity::Idle)
+            .to_string()
+    }
+
+    /// The most recent error of any repository, even if it has since recovered.
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_prefers_paused_then_error_then_scanning() {
+        let control = Control::default();
+        assert_eq!(control.state(), "idle");
+
+        control.set_activity(Path::new("/a"), Activity::Scanning);
+        assert_eq!(control.state(), "scanning");
+        control.record_error(Path::new("/b"), "push failed".to_string());
+        assert_eq!(control.state(), "error");
+        control.set_paused(true);
+        assert_eq!(control.state(), "paused");
+
+        control.set_paused(false);
+        control.set_activity(Path::new("/a"), Activity::Idle);
+        control.set_activity(Path::new("/b"), Activity::Idle);
+        assert_eq!(control.state(), "idle");
+        assert_eq!(control.last_error().as_deref(), Some("push failed"));
+
+        assert!(control.push_enabled(true));
+        control.set_push(false);
+        assert!(!control.push_enabled(true));
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::git::status::{GitChange, untracked_files};
+use crate::types::ChangeType;
+
+/// Filter driver of git-crypt's default key; `git-crypt-<name>` uses key `<name>`.
+const GIT_CRYPT_FILTER: &str = "git-crypt";
+
+/// Why the clean filter of `driver` can't run, or `None` if it can. Without
+/// it, git stages the working copy as it is: plaintext where the repository
+/// expects ciphertext.
+fn unusable_reason(repo_path: &Path, driver: &str) -> Option<String> {
+    let configured = ["clean", "process"].iter().any(|command| {
+        git_command(repo_path)
+            .arg("config")
+            .arg("--get")
+            .arg(format!("filter.{}.{}", driver, command))
+            .output()
+            .is_ok_and(|output| output.status.success())
+    });
+
+    let key = match driver.strip_prefix(GIT_CRYPT_FILTER) {
+        Some("") => Some("default"),
+        Some(name) => name.strip_prefix('-'),
+        None => None,
+    };
+    match key {
+        Some(key) if !configured || !has_git_crypt_key(repo_path, key) => {
+            Some(if key == "default" {
+                "git-crypt is locked; run `git-crypt unlock` to commit it".to_string()
+            } else {
+                format!(
+                    "git-crypt key '{}' is locked; run `git-crypt unlock` to commit it",
+                    key
+                )
+            })
+        }
+        Some(_) => None,
+        None if !configured => Some(format!(
+            "filter '{}' from .gitattributes is not configured",
+            driver
+        )),
+        None => None,
+    }
+}
+
+/// Returns true if git-crypt's `key` is unlocked in the repository, in its own
+/// git directory or, for a linked worktree, the main one.
+fn has_git_crypt_key(repo_path: &Path, key: &str) -> bool {
+    ["--absolute-git-dir", "--git-common-dir"]
+        .iter()
+        .any(|arg| {
+            git_command(repo_path)
+                .arg("rev-parse")
+                .arg(arg)
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .is_some_and(|output| {
+                    let dir = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                    repo_path
+                        .join(dir)
+                        .join("git-crypt/keys")
+                        .join(key)
+                        .is_file()
+                })
+        })
+}
+
+/// Parses `git check-attr -z filter` output into the filter driver of each
+/// path that has one.
+fn parse_filters(output: &str) -> Vec<(&str, &str)> {
+    let fields: Vec<&str> = output.split('\0').collect();
+    fields
+        .chunks_exact(3)
+        .filter(|entry| !matches!(entry[2], "unspecified" | "unset" | "set"))
+        .map(|entry| (entry[0], entry[2]))
+        .collect()
+}
+
+/// Finds the changes whose content goes through a `.gitattributes` filter
+/// that can't run, such as git-crypt while the repository is locked, and
+/// returns why for each. An untracked directory is included if any file in it
+/// is affected. Deletions have no content to protect and are never included.
+pub fn locked_paths(
+    repo_path: &Path,
+    changes: &[GitChange],
+) -> Result<HashMap<String, String>, GitChaiError> {
+    // File checked -> the change it belongs to
+    let mut files: BTreeMap<String, &str> = BTreeMap::new();
+    for change in changes {
+        if change.change_type == ChangeType::Delete {
+            continue;
+        }
+        if change.filename.ends_with('/') {
+            for file in untracked_files(repo_path, &change.filename)? {
+                files.insert(file, &change.filename);
+            }
+        } else {
+            files.insert(change.filename.clone(), &change.filename);
+        }
+    }
+    if files.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut command = git_command(repo_path);
+    command
+        .arg("check-attr")
+        .arg("-z")
+        .arg("filter")
+        .arg("--")
+        .args(files.keys());
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let mut reasons: HashMap<&str, Option<String>> = HashMap::new();
+    let mut locked = HashMap::new();
+    for (file, driver) in parse_filters(&String::from_utf8_lossy(&output.stdout)) {
+        let reason = reasons
+            .entry(driver)
+            .or_insert_with(|| unusable_reason(repo_path, driver));
+        if let (Some(reason), Some(change)) = (reason, files.get(file)) {
+            locked.insert(change.to_string(), reason.clone());
+        }
+    }
+    Ok(locked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_locked_git_crypt_paths() {
+        let repo = TempRepo::new("crypt");
+        repo.write(
+            ".gitattributes",
+            "secrets/** filter=git-crypt diff=git-crypt\n",
+        );
+        repo.write("secrets/api.key", "hunter2");
+        repo.write("secrets/new/db.key", "hunter3");
+        repo.write("README.md", "hi");
+        let change = |filename: &str, change_type| GitChange {
+            status: crate::types::GitStatus::Untracked,
+            change_type,
+            filename: filename.to_string(),
+            renamed_from: None,
+            symlink_target: None,
+        };
+        let changes = [
+            change("secrets/api.key", ChangeType::Add),
+            change("secrets/new/", ChangeType::Add),
+            change("secrets/old.key", ChangeType::Delete),
+            change("README.md", ChangeType::Add),
+        ];
+
+        let locked = locked_paths(&repo, &changes).unwrap();
+        let mut paths: Vec<&str> = locked.keys().map(String::as_str).collect();
+        paths.sort();
+        assert_eq!(paths, ["secrets/api.key", "secrets/new/"]);
+        assert!(locked["secrets/api.key"].contains("git-crypt is locked"));
+
+        // Unlocked: the filter is configured and the key is in place
+        repo.git(&["config", "filter.git-crypt.clean", "cat"]);
+        repo.git(&["config", "filter.git-crypt.smudge", "cat"]);
+        repo.write(".git/git-crypt/keys/default", "key");
+        assert!(locked_paths(&repo, &changes).unwrap().is_empty());
+    }
+}
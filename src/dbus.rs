@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+
+use crate::control::Control;
+
+pub const BUS_NAME: &str = "io.github.chyna_gvng.GitChai";
+pub const OBJECT_PATH: &str = "/io/github/chyna_gvng/GitChai";
+
+/// The `io.github.chyna_gvng.GitChai1` interface, for desktop widgets that want
+/// to show or steer a headless session without parsing its log.
+struct GitChaiService {
+    control: Arc<Control>,
+}
+
+#[zbus::interface(name = "io.github.chyna_gvng.GitChai1")]
+impl GitChaiService {
+    /// `idle`, `scanning`, `error` or `paused`
+    fn status(&self) -> String {
+        self.control.state()
+    }
+
+    /// Each watched repository as (path, activity, last error or "")
+    fn repositories(&self) -> Vec<(String, String, String)> {
+        self.control
+            .repos()
+            .into_iter()
+            .map(|(path, status)| {
+                (
+                    path.display().to_string(),
+                    status.activity.to_string(),
+                    status.last_error.unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    /// Stop committing until `Resume` is called
+    fn pause(&self) {
+        log::info!("Paused over D-Bus");
+        self.control.set_paused(true);
+    }
+
+    fn resume(&self) {
+        log::info!("Resumed over D-Bus");
+        self.control.set_paused(false);
+    }
+
+    /// Scan every repository now instead of waiting for the next interval
+    fn scan(&self) {
+        log::info!("Scan requested over D-Bus");
+        self.control.request_scan();
+    }
+
+    /// The most recent error, or "" if there was none
+    fn last_error(&self) -> String {
+        self.control.last_error().unwrap_or_default()
+    }
+}
+
+/// Registers the service on the session bus. It is served for as long as the
+/// returned connection is kept.
+pub async fn serve(control: Arc<Control>) -> Result<zbus::Connection> {
+    let connection = zbus::connection::Builder::session()
+        .context("Failed to connect to the D-Bus session bus")?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, GitChaiService { control })?
+        .build()
+        .await
+        .with_context(|| format!("Failed to register {} on the session bus", BUS_NAME))?;
+    log::info!("D-Bus service available as {}", BUS_NAME);
+    Ok(connection)
+}
@@ -0,0 +1,85 @@
+use anyhow::Result;
+
+use crate::config::{Config, JjMode};
+use crate::git::{bridge_kind, get_flagged_files, get_promisor_remotes, is_jj_colocated};
+use crate::team_policy::TEAM_POLICY_FILE;
+
+/// Runs `git-chai doctor`: reports repository settings that change what git-chai
+/// will or won't commit.
+pub fn run_doctor(config: &Config) -> Result<()> {
+    println!("git-chai doctor: {}", config.repo_path.display());
+
+    check_flagged_files(config)?;
+    check_partial_clone(config);
+    check_jj(config);
+    check_bridge(config);
+    check_team_policy(config);
+
+    Ok(())
+}
+
+fn check_jj(config: &Config) {
+    if !is_jj_colocated(&config.repo_path) {
+        println!("ok: not colocated with jj");
+    } else if config.jj_mode == JjMode::GitOnly {
+        println!(
+            "note: colocated with jj; committing through a temporary index (jj_mode = \"git-only\")"
+        );
+    } else {
+        println!(
+            "note: colocated with jj; git-chai won't commit here unless jj_mode = \"git-only\" (jj already records the working copy)"
+        );
+    }
+}
+
+fn check_bridge(config: &Config) {
+    match bridge_kind(&config.repo_path) {
+        None => println!("ok: not bridged to another version control system"),
+        Some(bridge) if config.allow_bridge_push => println!(
+            "note: {} repository; pushing to origin anyway (allow_bridge_push = true)",
+            bridge
+        ),
+        Some(bridge) => println!(
+            "note: {} repository; git-chai commits but never pushes here: {}",
+            bridge,
+            bridge.push_hint()
+        ),
+    }
+}
+
+fn check_team_policy(config: &Config) {
+    match &config.team_policy {
+        None => println!("ok: no team policy ({})", TEAM_POLICY_FILE),
+        Some(policy) => println!("note: {}", policy.describe()),
+    }
+}
+
+fn check_partial_clone(config: &Config) {
+    let remotes = get_promisor_remotes(&config.repo_path);
+    if remotes.is_empty() {
+        println!("ok: not a partial clone");
+    } else {
+        println!(
+            "note: partial clone (promisor remote: {}); move detection is disabled to avoid fetching missing objects",
+            remotes.join(", ")
+        );
+    }
+}
+
+fn check_flagged_files(config: &Config) -> Result<()> {
+    let flagged = get_flagged_files(&config.repo_path)?;
+    if flagged.is_empty() {
+        println!("ok: no files are marked skip-worktree or assume-unchanged");
+        return Ok(());
+    }
+
+    println!(
+        "note: {} file(s) are marked skip-worktree or assume-unchanged and will never be committed:",
+        flagged.len()
+    );
+    for (filename, flag) in &flagged {
+        println!("  {} ({})", filename, flag);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::io::Read;
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::git::isolated::has_head;
+use crate::git::status::GitChange;
+use crate::types::ChangeType;
+
+/// How many leading bytes are inspected, as in git's own binary detection.
+const SNIFF_LEN: usize = 8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    /// No NUL bytes and no byte order mark: UTF-8 or another 8-bit encoding
+    Text,
+    Utf8Bom,
+    Utf16,
+    Binary,
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Encoding::Text => write!(f, "text"),
+            Encoding::Utf8Bom => write!(f, "UTF-8 with a byte order mark"),
+            Encoding::Utf16 => write!(f, "UTF-16"),
+            Encoding::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+/// Returns how a modified file's encoding changed since HEAD, if it went from
+/// text to UTF-16 or binary, or gained a byte order mark: usually an editor
+/// setting rather than an intended edit.
+pub fn encoding_change(
+    repo_path: &Path,
+    change: &GitChange,
+) -> Result<Option<String>, GitChaiError> {
+    if change.change_type != ChangeType::Modify
+        || change.filename.ends_with('/')
+        || !has_head(repo_path)
+    {
+        return Ok(None);
+    }
+
+    let mut command = git_command(repo_path);
+    command
+        .arg("cat-file")
+        .arg("blob")
+        .arg(format!("HEAD:{}", change.filename));
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    let before = detect(&output.stdout[..output.stdout.len().min(SNIFF_LEN)]);
+
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    std::fs::File::open(repo_path.join(&change.filename))
+        .and_then(|file| file.take(SNIFF_LEN as u64).read_to_end(&mut head))
+        .map_err(GitChaiError::IoError)?;
+    let after = detect(&head);
+
+    Ok(is_suspicious(before, after).then(|| format!("changed from {} to {}", before, after)))
+}
+
+fn is_suspicious(before: Encoding, after: Encoding) -> bool {
+    matches!(
+        (before, after),
+        (Encoding::Text, Encoding::Utf8Bom)
+            | (
+                Encoding::Text | Encoding::Utf8Bom,
+                Encoding::Utf16 | Encoding::Binary
+            )
+    )
+}
+
+fn detect(bytes: &[u8]) -> Encoding {
+    if bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]) {
+        return Encoding::Utf16;
+    }
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Encoding::Utf8Bom;
+    }
+    if !bytes.contains(&0) {
+        return Encoding::Text;
+    }
+
+    // UTF-16 without a byte order mark: mostly ASCII, so every other byte is NUL
+    let pairs = bytes.len() / 2;
+    let even = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    if pairs > 0 && (even.max(odd) * 10 >= pairs * 9) && even.min(odd) == 0 {
+        Encoding::Utf16
+    } else {
+        Encoding::Binary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_detect_and_suspicious_changes() {
+        assert_eq!(detect("héllo\n".as_bytes()), Encoding::Text);
+        assert_eq!(detect(b"\xEF\xBB\xBFhello"), Encoding::Utf8Bom);
+        assert_eq!(
+            detect(&[&[0xFF, 0xFE][..], &utf16le("hi")].concat()),
+            Encoding::Utf16
+        );
+        assert_eq!(detect(&utf16le("no byte order mark")), Encoding::Utf16);
+        assert_eq!(detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Encoding::Binary);
+
+        assert!(is_suspicious(Encoding::Text, Encoding::Utf16));
+        assert!(is_suspicious(Encoding::Text, Encoding::Utf8Bom));
+        assert!(is_suspicious(Encoding::Utf8Bom, Encoding::Binary));
+        assert!(!is_suspicious(Encoding::Utf8Bom, Encoding::Text));
+        assert!(!is_suspicious(Encoding::Binary, Encoding::Binary));
+    }
+}
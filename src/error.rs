@@ -1,3 +1,7 @@
+use std::fmt;
+use std::io;
+use std::process::Output;
+
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -5,7 +9,13 @@ use thiserror::Error;
 pub enum GitChaiError {
     #[error("Git command failed: {command}: {stderr}")]
     GitCommandError {
+        /// The command line as one string, for display
         command: String,
+        /// The full argument vector, starting with `git`
+        argv: Vec<String>,
+        /// `None` if git was killed by a signal or the failure was detected by git-chai
+        exit_code: Option<i32>,
+        stdout: String,
         stderr: String,
         #[source]
         source: Option<std::io::Error>,
@@ -16,6 +26,160 @@ pub enum GitChaiError {
 
     #[error("Parse error: {0}")]
     ParseError(String),
+
+    #[error("Config error: {0}")]
+    ConfigError(String),
+
+    #[error("Index changed since staging: {0}")]
+    IndexMismatchError(String),
+}
+
+/// Broad category of a failure, so callers can decide whether to retry, which
+/// exit code to use and how loudly to notify without inspecting stderr themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Network trouble talking to a remote; retrying later may succeed
+    TransientNetwork,
+    /// Another process holds a git lock or changed the index concurrently
+    LockContention,
+    /// The remote rejected our credentials
+    Auth,
+    /// A git hook refused the commit or push
+    HookRejection,
+    /// The repository or configuration is in a state git-chai cannot work with
+    InvalidState,
+    /// Unexpected output or behaviour; likely a git-chai bug
+    Bug,
+}
+
+impl ErrorClass {
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorClass::TransientNetwork | ErrorClass::LockContention
+        )
+    }
+
+    /// Process exit code for a run that failed with this class of error.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorClass::TransientNetwork | ErrorClass::LockContention => 75,
+            ErrorClass::Auth => 77,
+            ErrorClass::HookRejection => 65,
+            ErrorClass::InvalidState => 1,
+            ErrorClass::Bug => 70,
+        }
+    }
+}
+
+impl fmt::Display for ErrorClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorClass::TransientNetwork => write!(f, "transient-network"),
+            ErrorClass::LockContention => write!(f, "lock-contention"),
+            ErrorClass::Auth => write!(f, "auth"),
+            ErrorClass::HookRejection => write!(f, "hook-rejection"),
+            ErrorClass::InvalidState => write!(f, "invalid-state"),
+            ErrorClass::Bug => write!(f, "bug"),
+        }
+    }
+}
+
+const LOCK_MARKERS: &[&str] = &[".lock': file exists", "another git process"];
+const AUTH_MARKERS: &[&str] = &[
+    "authentication failed",
+    "permission denied (publickey",
+    "could not read username",
+    "could not read password",
+    "invalid username or password",
+    "the requested url returned error: 403",
+];
+const NETWORK_MARKERS: &[&str] = &[
+    "could not resolve host",
+    "could not resolve hostname",
+    "connection timed out",
+    "operation timed out",
+    "connection refused",
+    "connection reset",
+    "network is unreachable",
+    "failed to connect",
+    "the remote end hung up unexpectedly",
+    "early eof",
+    "temporary failure",
+];
+const HOOK_MARKERS: &[&str] = &[
+    "hook declined",
+    "hook failed",
+    "pre-commit hook",
+    "commit-msg hook",
+];
+
+impl GitChaiError {
+    /// Classifies the error. This is the only place that interprets git's stderr.
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            GitChaiError::GitCommandError { stderr, .. } => classify_git_stderr(stderr),
+            GitChaiError::IoError(e) => match e.kind() {
+                io::ErrorKind::TimedOut | io::ErrorKind::Interrupted => {
+                    ErrorClass::TransientNetwork
+                }
+                _ => ErrorClass::InvalidState,
+            },
+            GitChaiError::ParseError(_) => ErrorClass::Bug,
+            GitChaiError::ConfigError(_) => ErrorClass::InvalidState,
+            GitChaiError::IndexMismatchError(_) => ErrorClass::LockContention,
+        }
+    }
+
+    /// Builds the error for a git command that exited unsuccessfully.
+    pub fn git_failed(argv: &[String], output: &Output) -> Self {
+        GitChaiError::GitCommandError {
+            command: argv.join(" "),
+            argv: argv.to_vec(),
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            source: None,
+        }
+    }
+
+    /// Builds the error for a git command that succeeded but whose result git-chai
+    /// cannot use.
+    pub fn git_rejected(argv: &[&str], reason: impl Into<String>) -> Self {
+        GitChaiError::GitCommandError {
+            command: argv.join(" "),
+            argv: argv.iter().map(|arg| arg.to_string()).collect(),
+            exit_code: None,
+            stdout: String::new(),
+            stderr: reason.into(),
+            source: None,
+        }
+    }
+}
+
+/// Classifies an error bubbled up from a cycle. Anything that is not a
+/// `GitChaiError` was unexpected and counts as a bug.
+pub fn error_class(error: &anyhow::Error) -> ErrorClass {
+    error
+        .downcast_ref::<GitChaiError>()
+        .map_or(ErrorClass::Bug, GitChaiError::class)
+}
+
+fn classify_git_stderr(stderr: &str) -> ErrorClass {
+    let stderr = stderr.to_lowercase();
+    let matches_any = |markers: &[&str]| markers.iter().any(|marker| stderr.contains(marker));
+
+    if matches_any(LOCK_MARKERS) {
+        ErrorClass::LockContention
+    } else if matches_any(AUTH_MARKERS) {
+        ErrorClass::Auth
+    } else if matches_any(NETWORK_MARKERS) {
+        ErrorClass::TransientNetwork
+    } else if matches_any(HOOK_MARKERS) {
+        ErrorClass::HookRejection
+    } else {
+        ErrorClass::InvalidState
+    }
 }
 
 #[cfg(test)]
@@ -24,18 +188,54 @@ mod tests {
 
     #[test]
     fn test_error_display() {
-        let git_error = GitChaiError::GitCommandError {
-            command: "test".to_string(),
-            stderr: "error".to_string(),
-            source: None,
-        };
-        assert!(git_error.to_string().contains("Git command failed"));
+        let git_error = GitChaiError::git_rejected(&["git", "write-tree"], "nothing to commit");
+        assert_eq!(
+            git_error.to_string(),
+            "Git command failed: git write-tree: nothing to commit"
+        );
 
-        let io_error =
-            GitChaiError::IoError(std::io::Error::new(std::io::ErrorKind::Other, "test"));
+        let io_error = GitChaiError::IoError(std::io::Error::other("test"));
         assert!(io_error.to_string().contains("IO error"));
 
         let parse_error = GitChaiError::ParseError("test".to_string());
         assert!(parse_error.to_string().contains("Parse error"));
+
+        let config_error = GitChaiError::ConfigError("test".to_string());
+        assert!(config_error.to_string().contains("Config error"));
+
+        let index_error = GitChaiError::IndexMismatchError("test".to_string());
+        assert!(index_error.to_string().contains("Index changed"));
+    }
+
+    #[test]
+    fn test_error_class() {
+        let git_error = |stderr: &str| GitChaiError::git_rejected(&["git", "push"], stderr);
+
+        assert_eq!(
+            git_error("fatal: Unable to create '/repo/.git/index.lock': File exists.").class(),
+            ErrorClass::LockContention
+        );
+        assert_eq!(
+            git_error("fatal: Authentication failed for 'https://example.com/'").class(),
+            ErrorClass::Auth
+        );
+        assert_eq!(
+            git_error("ssh: Could not resolve hostname example.com").class(),
+            ErrorClass::TransientNetwork
+        );
+        assert_eq!(
+            git_error("! [remote rejected] HEAD -> main (pre-receive hook declined)").class(),
+            ErrorClass::HookRejection
+        );
+        assert_eq!(
+            git_error("fatal: not a git repository").class(),
+            ErrorClass::InvalidState
+        );
+        assert!(ErrorClass::LockContention.is_retryable());
+        assert!(!ErrorClass::Auth.is_retryable());
+        assert_eq!(
+            GitChaiError::ParseError("bad".to_string()).class(),
+            ErrorClass::Bug
+        );
     }
 }
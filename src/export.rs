@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::git::{format_patch, get_commits_since, patch_file_name, short_sha};
+use crate::summary::is_chai_commit;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One mbox file, for `git send-email` or `git am`
+    Mbox,
+    /// A directory with one numbered `.patch` file per commit
+    PatchDir,
+}
+
+/// Runs `git-chai export`: writes the git-chai commits made since `since` as
+/// patches, oldest first. Without `output`, they go to `chai-<repo>.mbox` or
+/// `chai-<repo>-patches/` in the current directory.
+pub fn run_export(
+    config: &Config,
+    since: &str,
+    format: ExportFormat,
+    output: Option<&Path>,
+    dry_run: bool,
+) -> Result<()> {
+    let repo = &config.repo_path;
+    let mut commits = get_commits_since(repo, since)?;
+    let total_commits = commits.len();
+    commits.retain(|commit| is_chai_commit(commit.generated_subject()));
+    commits.reverse();
+    if commits.is_empty() {
+        log::info!("No git-chai commits since {}", since);
+        return Ok(());
+    }
+    if commits.len() < total_commits {
+        log::warn!(
+            "Leaving out {} commit(s) not made by git-chai; the patches may not apply without them",
+            total_commits - commits.len()
+        );
+    }
+
+    let name = repo
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "repo".to_string());
+    let output = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| match format {
+            ExportFormat::Mbox => PathBuf::from(format!("chai-{}.mbox", name)),
+            ExportFormat::PatchDir => PathBuf::from(format!("chai-{}-patches", name)),
+        });
+
+    log::info!(
+        "{} {} commit(s) to {}",
+        if dry_run {
+            "DRY RUN: Would export"
+        } else {
+            "Exporting"
+        },
+        commits.len(),
+        output.display()
+    );
+    for commit in &commits {
+        log::info!("  {} {}", short_sha(&commit.sha), commit.subject);
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    let total = commits.len();
+    match format {
+        ExportFormat::Mbox => {
+            let mut mbox = String::new();
+            for (i, commit) in commits.iter().enumerate() {
+                mbox.push_str(&format_patch(repo, &commit.sha, i + 1, total)?);
+            }
+            std::fs::write(&output, mbox)
+                .with_context(|| format!("Failed to write {}", output.display()))?;
+        }
+        ExportFormat::PatchDir => {
+            std::fs::create_dir_all(&output)
+                .with_context(|| format!("Failed to create {}", output.display()))?;
+            for (i, commit) in commits.iter().enumerate() {
+                let file = output.join(patch_file_name(i + 1, &commit.subject));
+                std::fs::write(&file, format_patch(repo, &commit.sha, i + 1, total)?)
+                    .with_context(|| format!("Failed to write {}", file.display()))?;
+            }
+        }
+    }
+
+    log::info!("Exported {} patch(es) to {}", total, output.display());
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::git::get_git_dir;
+
+/// File in the git directory that remembers paths which keep failing to commit.
+pub const FAILED_PATHS_FILE: &str = "chai-failed.toml";
+
+/// Consecutive failed runs after which a path is no longer retried.
+pub const FAILURE_LIMIT: u32 = 3;
+
+/// A path that failed to stage or commit, e.g. because a hook rejected it or it
+/// can't be read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedPath {
+    pub failures: u32,
+    pub last_error: String,
+}
+
+/// Paths that failed to commit in consecutive runs, persisted across runs so a
+/// path that can never be committed stops being retried (and logged) every
+/// cycle. A path is retried again once it is no longer dirty, or after
+/// `git-chai status --retry-failed`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FailedPaths {
+    #[serde(default)]
+    paths: BTreeMap<String, FailedPath>,
+    #[serde(skip)]
+    file: Option<PathBuf>,
+}
+
+impl FailedPaths {
+    /// Loads the list for a repository. A missing or unreadable file gives an
+    /// empty list.
+    pub fn load(repo_path: &Path) -> FailedPaths {
+        let Some(file) = get_git_dir(repo_path).map(|dir| dir.join(FAILED_PATHS_FILE)) else {
+            return FailedPaths::default();
+        };
+
+        let mut failed = match std::fs::read_to_string(&file) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Ignoring unreadable {}: {}", file.display(), e);
+                FailedPaths::default()
+            }),
+            Err(_) => FailedPaths::default(),
+        };
+        failed.file = Some(file);
+        failed
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if self.paths.is_empty() {
+            if file.exists() {
+                std::fs::remove_file(file)
+                    .with_context(|| format!("Failed to remove {}", file.display()))?;
+            }
+            return Ok(());
+        }
+
+        let content = toml::to_string(self).context("Failed to serialize failed paths")?;
+        std::fs::write(file, content).with_context(|| format!("Failed to write {}", file.display()))
+    }
+
+    /// Changes whenever a path stops or starts being retried, so that clearing
+    /// the list replans a scan that is otherwise unchanged.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for (path, _) in self.parked() {
+            path.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// The paths that are no longer retried.
+    pub fn parked(&self) -> impl Iterator<Item = (&str, &FailedPath)> {
+        self.paths
+            .iter()
+            .filter(|(_, failed)| failed.failures >= FAILURE_LIMIT)
+            .map(|(path, failed)| (path.as_str(), failed))
+    }
+
+    /// Returns the entry that stops `path` from being retried, if any. Entries
+    /// ending in `/` cover a whole directory.
+    pub fn parked_entry(&self, path: &str) -> Option<&FailedPath> {
+        self.parked()
+            .find(|(parked, _)| {
+                *parked == path || (parked.ends_with('/') && path.starts_with(parked))
+            })
+            .map(|(_, failed)| failed)
+    }
+
+    /// Updates the list after a run: `failures` maps the paths that failed in
+    /// this run to their error, and `remaining` lists the paths still dirty
+    /// afterwards. Paths that are no longer dirty, or were retried without
+    /// failing, are forgotten. Returns the paths parked by this run.
+    pub fn record_run(
+        &mut self,
+        failures: &HashMap<String, String>,
+        remaining: &[String],
+    ) -> Vec<String> {
+        let still_dirty = |path: &str| {
+            remaining
+                .iter()
+                .any(|dirty| dirty == path || (path.ends_with('/') && dirty.starts_with(path)))
+        };
+        self.paths.retain(|path, failed| {
+            still_dirty(path) && (failed.failures >= FAILURE_LIMIT || failures.contains_key(path))
+        });
+
+        let mut parked = Vec::new();
+        for (path, error) in failures {
+            let entry = self.paths.entry(path.clone()).or_insert(FailedPath {
+                failures: 0,
+                last_error: String::new(),
+            });
+            entry.failures += 1;
+            entry.last_error = error.clone();
+            if entry.failures == FAILURE_LIMIT {
+                log::warn!(
+                    "{} failed to commit {} times in a row and will no longer be retried; run `git-chai status --retry-failed` to retry",
+                    path,
+                    FAILURE_LIMIT
+                );
+                parked.push(path.clone());
+            }
+        }
+        parked
+    }
+
+    /// Forgets every path, so all of them are retried. Returns how many were
+    /// parked.
+    pub fn clear(&mut self) -> usize {
+        let parked = self.parked().count();
+        self.paths.clear();
+        parked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_are_parked_after_repeated_failures() {
+        let mut failed = FailedPaths::default();
+        let failures = HashMap::from([("secret.key".to_string(), "hook rejected".to_string())]);
+        let remaining = vec!["secret.key".to_string(), "notes.md".to_string()];
+
+        for run in 1..=FAILURE_LIMIT {
+            assert!(failed.parked_entry("secret.key").is_none());
+            let parked = failed.record_run(&failures, &remaining);
+            assert_eq!(parked.is_empty(), run < FAILURE_LIMIT);
+        }
+        assert_eq!(failed.parked_entry("secret.key").unwrap().failures, 3);
+
+        // Parked paths aren't retried, so they stay parked while dirty...
+        failed.record_run(&HashMap::new(), &remaining);
+        assert!(failed.parked_entry("secret.key").is_some());
+
+        // ...and are forgotten once they're committed or reverted
+        failed.record_run(&HashMap::new(), &["notes.md".to_string()]);
+        assert_eq!(failed.parked().count(), 0);
+    }
+}
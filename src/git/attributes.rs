@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::isolated::{TempIndex, run_git};
+
+/// Message of the commit restaging files normalized differently after a
+/// `.gitattributes` change.
+pub const RENORMALIZE_COMMIT_MESSAGE: &str = "chore: renormalize for .gitattributes";
+
+/// Returns true if `path` is a `.gitattributes` file, at the root or deeper.
+pub fn is_attributes_file(path: &str) -> bool {
+    path == ".gitattributes" || path.ends_with("/.gitattributes")
+}
+
+/// Path -> staged blob, from `git ls-files --stage -z`.
+fn index_entries(
+    repo_path: &Path,
+    index: Option<&TempIndex>,
+) -> Result<HashMap<String, String>, GitChaiError> {
+    let output = run_git(repo_path, index, &["ls-files", "--stage", "-z"])?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter_map(|entry| {
+            let (info, path) = entry.split_once('\t')?;
+            let sha = info.split_whitespace().nth(1)?;
+            Some((path.to_string(), sha.to_string()))
+        })
+        .collect())
+}
+
+/// Lists the tracked files that `git add --renormalize` would restage under
+/// the working tree's `.gitattributes`, leaving out `exclude` and any file
+/// whose working-tree content differs from the index, so that only the
+/// normalization of committed content changes. The files are renormalized in
+/// a copy of the index, so the real one is untouched.
+pub fn renormalized_files(repo_path: &Path, exclude: &[&str]) -> Result<Vec<String>, GitChaiError> {
+    let temp_index = TempIndex::copy_of_index(repo_path)?;
+    let excludes: Vec<String> = exclude
+        .iter()
+        .map(|path| format!(":(exclude,literal){}", path.trim_end_matches('/')))
+        .collect();
+    let mut args = vec!["add", "--renormalize", "--", "."];
+    args.extend(excludes.iter().map(String::as_str));
+    run_git(repo_path, Some(&temp_index), &args)?;
+
+    let before = index_entries(repo_path, None)?;
+    let mut changed: Vec<String> = index_entries(repo_path, Some(&temp_index))?
+        .into_iter()
+        .filter(|(path, sha)| before.get(path) != Some(sha))
+        .map(|(path, _)| path)
+        .collect();
+    changed.sort();
+    unmodified(repo_path, changed, &before)
+}
+
+/// Keeps the `paths` whose working-tree bytes, unfiltered, are the blob `index`
+/// has for them.
+fn unmodified(
+    repo_path: &Path,
+    paths: Vec<String>,
+    index: &HashMap<String, String>,
+) -> Result<Vec<String>, GitChaiError> {
+    let paths: Vec<String> = paths
+        .into_iter()
+        .filter(|path| repo_path.join(path).is_file())
+        .collect();
+    if paths.is_empty() {
+        return Ok(paths);
+    }
+    let mut args = vec!["hash-object", "--no-filters", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    let output = run_git(repo_path, None, &args)?;
+    let hashes = String::from_utf8_lossy(&output.stdout).into_owned();
+    Ok(paths
+        .into_iter()
+        .zip(hashes.lines())
+        .filter(|(path, sha)| index.get(path).is_some_and(|indexed| indexed == sha))
+        .map(|(path, _)| path)
+        .collect())
+}
+
+/// Restages `paths` through the filters of the current `.gitattributes`.
+pub fn stage_renormalized(repo_path: &Path, paths: &[String]) -> Result<(), GitChaiError> {
+    log::debug!("Renormalizing {} file(s)", paths.len());
+    let mut args = vec!["add", "--renormalize", "--"];
+    args.extend(paths.iter().map(String::as_str));
+    run_git(repo_path, None, &args)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_renormalized_files_skip_excluded_and_unaffected() {
+        let repo = TempRepo::new("renormalize");
+        repo.git(&["config", "core.autocrlf", "false"]);
+        repo.write("windows.txt", "a\r\nb\r\n");
+        repo.write("edited.txt", "c\r\n");
+        repo.write("unscanned.txt", "f\r\n");
+        repo.write("unix.txt", "d\n");
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "-q", "-m", "init"]);
+
+        repo.write(".gitattributes", "* text=auto\n");
+        repo.write("edited.txt", "c\r\ne\r\n");
+        // Modified, but not among the excluded paths
+        repo.write("unscanned.txt", "f\r\ng\r\n");
+        assert_eq!(
+            renormalized_files(&repo, &["edited.txt", ".gitattributes"]).unwrap(),
+            ["windows.txt"]
+        );
+        // The real index is left alone
+        assert_eq!(
+            crate::git::get_staged_files(&repo).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+}
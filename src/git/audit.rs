@@ -0,0 +1,204 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Longest stderr excerpt written per command.
+const MAX_STDERR_CHARS: usize = 200;
+
+static AUDIT_LOG: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Starts appending a line for every git command git-chai runs to `path`.
+pub fn configure(path: &Path) -> std::io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    if AUDIT_LOG.set(Mutex::new(file)).is_err() {
+        log::warn!("Audit log was already configured");
+    }
+    Ok(())
+}
+
+/// One executed git command, as written to the audit log.
+pub struct AuditEntry<'a> {
+    pub cwd: &'a Path,
+    pub argv: &'a [String],
+    pub duration: Duration,
+    /// `None` if the process was killed by a signal or could not be started
+    pub exit_code: Option<i32>,
+    pub stderr: &'a str,
+}
+
+/// Appends an entry to the audit log, if one is configured.
+pub fn record(entry: &AuditEntry) {
+    let Some(file) = AUDIT_LOG.get() else {
+        return;
+    };
+
+    let line = format_entry(entry, SystemTime::now());
+    let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+    if let Err(e) = writeln!(file, "{}", line) {
+        log::warn!("Failed to write audit log: {}", e);
+    }
+}
+
+fn format_entry(entry: &AuditEntry, now: SystemTime) -> String {
+    let timestamp = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let exit_code = entry
+        .exit_code
+        .map_or_else(|| "none".to_string(), |code| code.to_string());
+
+    let mut stderr: String = entry.stderr.trim().chars().take(MAX_STDERR_CHARS).collect();
+    if entry.stderr.trim().chars().count() > MAX_STDERR_CHARS {
+        stderr.push_str("...");
+    }
+
+    format!(
+        "{}.{:03} cwd={} exit={} duration={}ms argv={} stderr={:?}",
+        timestamp.as_secs(),
+        timestamp.subsec_millis(),
+        entry.cwd.display(),
+        exit_code,
+        entry.duration.as_millis(),
+        entry
+            .argv
+            .iter()
+            .map(|arg| quote(arg))
+            .collect::<Vec<_>>()
+            .join(" "),
+        stderr
+    )
+}
+
+/// Quotes an argument shell-style when it would otherwise be ambiguous.
+fn quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c))
+    {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// One line read back from the audit log.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub cwd: PathBuf,
+    pub exit_code: Option<i32>,
+    pub argv: Vec<String>,
+}
+
+/// Reads every well-formed entry from an audit log; other lines are skipped.
+pub fn read_log(path: &Path) -> std::io::Result<Vec<AuditRecord>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(contents.lines().filter_map(parse_entry).collect())
+}
+
+/// Parses a line written by `format_entry`.
+fn parse_entry(line: &str) -> Option<AuditRecord> {
+    let (_, rest) = line.split_once(" cwd=")?;
+    let (cwd, rest) = rest.split_once(" exit=")?;
+    let (exit_code, rest) = rest.split_once(' ')?;
+    let (_, rest) = rest.split_once(" argv=")?;
+    // stderr is written with `{:?}`, so its own quotes are escaped and the last
+    // ` stderr="` is always the field separator
+    let argv = &rest[..rest.rfind(" stderr=\"")?];
+
+    Some(AuditRecord {
+        cwd: PathBuf::from(cwd),
+        exit_code: exit_code.parse().ok(),
+        argv: unquote_argv(argv),
+    })
+}
+
+/// Splits an argv joined from `quote`d arguments.
+fn unquote_argv(argv: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut chars = argv.chars().peekable();
+
+    loop {
+        while chars.next_if_eq(&' ').is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut arg = String::new();
+        while let Some(&c) = chars.peek() {
+            match c {
+                ' ' => break,
+                '\'' => {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if c == '\'' {
+                            break;
+                        }
+                        arg.push(c);
+                    }
+                }
+                '\\' => {
+                    chars.next();
+                    arg.extend(chars.next());
+                }
+                _ => {
+                    arg.push(c);
+                    chars.next();
+                }
+            }
+        }
+        args.push(arg);
+    }
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_entry() {
+        let argv = vec![
+            "git".to_string(),
+            "commit".to_string(),
+            "-m".to_string(),
+            "mod: it's here".to_string(),
+        ];
+        let entry = AuditEntry {
+            cwd: Path::new("/repo"),
+            argv: &argv,
+            duration: Duration::from_millis(12),
+            exit_code: Some(1),
+            stderr: "error: line one\nline two\n",
+        };
+
+        assert_eq!(
+            format_entry(&entry, UNIX_EPOCH + Duration::from_millis(1_500)),
+            r#"1.500 cwd=/repo exit=1 duration=12ms argv=git commit -m 'mod: it'\''s here' stderr="error: line one\nline two""#
+        );
+    }
+
+    #[test]
+    fn test_parse_entry_round_trips() {
+        let argv = vec![
+            "git".to_string(),
+            "commit".to_string(),
+            "-m".to_string(),
+            "mod: it's here".to_string(),
+            "stderr=\"x\"".to_string(),
+        ];
+        let entry = AuditEntry {
+            cwd: Path::new("/my repo"),
+            argv: &argv,
+            duration: Duration::from_millis(3),
+            exit_code: Some(0),
+            stderr: "",
+        };
+
+        let record = parse_entry(&format_entry(&entry, UNIX_EPOCH)).unwrap();
+        assert_eq!(record.cwd, PathBuf::from("/my repo"));
+        assert_eq!(record.exit_code, Some(0));
+        assert_eq!(record.argv, argv);
+    }
+}
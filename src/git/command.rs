@@ -0,0 +1,148 @@
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::Instant;
+
+use crate::git::audit::{self, AuditEntry};
+use crate::git::limits;
+
+/// A `git` invocation. Every git subprocess git-chai spawns goes through here, so
+/// process limits, latency metrics and the audit log apply to all of them.
+pub struct GitCommand {
+    command: Command,
+    cwd: PathBuf,
+    /// Full argument vector, starting with `git`
+    argv: Vec<String>,
+}
+
+/// Builds a `git` command that runs in `repo_path`.
+///
+/// On Unix the child gets its own process group, so a Ctrl+C in the terminal only
+/// reaches git-chai, which then lets in-flight git commands finish instead of
+/// having them killed halfway through a commit or push.
+///
+/// Git runs in the C locale: error classification reads git's messages, which
+/// are translated under other locales.
+pub fn git_command(repo_path: &Path) -> GitCommand {
+    command_for("git", repo_path)
+}
+
+fn command_for(program: impl AsRef<OsStr>, repo_path: &Path) -> GitCommand {
+    let mut command = Command::new(program);
+    command
+        .current_dir(repo_path)
+        .env("LC_ALL", "C")
+        .env("LANG", "C");
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    GitCommand {
+        command,
+        cwd: repo_path.to_path_buf(),
+        argv: vec!["git".to_string()],
+    }
+}
+
+impl GitCommand {
+    pub fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Self {
+        self.argv.push(arg.as_ref().to_string_lossy().to_string());
+        self.command.arg(arg);
+        self
+    }
+
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        for arg in args {
+            self.arg(arg);
+        }
+        self
+    }
+
+    pub fn env(&mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> &mut Self {
+        self.command.env(key, value);
+        self
+    }
+
+    /// The full argument vector, starting with `git`.
+    pub fn argv(&self) -> &[String] {
+        &self.argv
+    }
+
+    /// Runs the command to completion, waiting for a slot first if git process
+    /// limits are configured.
+    pub fn output(&mut self) -> io::Result<Output> {
+        let subcommand = self.argv.get(1).map_or("git", String::as_str);
+
+        let _slot = limits::acquire();
+        let started = Instant::now();
+        let output = self.command.output();
+        let duration = started.elapsed();
+        limits::record_latency(subcommand, duration);
+
+        let (exit_code, stderr) = match &output {
+            Ok(output) => (
+                output.status.code(),
+                String::from_utf8_lossy(&output.stderr).to_string(),
+            ),
+            Err(e) => (None, e.to_string()),
+        };
+        audit::record(&AuditEntry {
+            cwd: &self.cwd,
+            argv: &self.argv,
+            duration,
+            exit_code,
+            stderr: &stderr,
+        });
+
+        output
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorClass, GitChaiError};
+    use crate::test_repo::TempRepo;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Stands in for a git whose messages are translated unless the C locale is set.
+    const LOCALIZED_GIT: &str = r#"#!/bin/sh
+if [ "$LC_ALL" = "C" ]; then
+    echo "fatal: Unable to create '/repo/.git/index.lock': File exists." >&2
+else
+    echo "Schwerwiegend: Konnte '/repo/.git/index.lock' nicht erstellen: Datei existiert bereits." >&2
+fi
+exit 128
+"#;
+
+    #[test]
+    fn test_git_runs_in_c_locale() {
+        let dir = TempRepo::dir("locale");
+        dir.write("git", LOCALIZED_GIT);
+        let script = dir.join("git");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let localized = Command::new(&script)
+            .env("LC_ALL", "de_DE.UTF-8")
+            .output()
+            .unwrap();
+        let mut command = command_for(&script, &dir);
+        command.arg("commit");
+        let output = command.output().unwrap();
+
+        let argv = command.argv();
+        assert_eq!(
+            GitChaiError::git_failed(argv, &localized).class(),
+            ErrorClass::InvalidState
+        );
+        assert_eq!(
+            GitChaiError::git_failed(argv, &output).class(),
+            ErrorClass::LockContention
+        );
+    }
+}
@@ -1,98 +1,675 @@
 use std::path::Path;
-use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::ai::AiSettings;
 use crate::error::GitChaiError;
+use crate::git::command::GitCommand;
+use crate::git::diff::staged_diff;
+use crate::git::git_command;
+use crate::git::horizon::ensure_rewritable;
+use crate::git::isolated::TempIndex;
+use crate::git::message_style::{CHAI_TRAILER, MessageStyle};
+use crate::git::rename::is_case_only_rename;
+use crate::git::subject::SubjectRules;
+use crate::notebook::{is_notebook, notebook_cell_summary, strip_staged_notebooks};
+use crate::summary::is_chai_commit;
+use crate::template::{environment_value, expand, format_utc};
+use crate::title::file_title;
+
+/// How the commits git-chai writes are dated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommitDate {
+    /// The time of the commit
+    #[default]
+    Now,
+    /// A fixed Unix timestamp
+    At(u64),
+    /// The time of the commit shifted by this many seconds
+    Shifted(i64),
+}
+
+/// Per-repository settings applied to every commit git-chai writes.
+#[derive(Debug, Clone, Default)]
+pub struct CommitSettings {
+    /// Text appended to the subject line, e.g. `[skip ci]`
+    pub message_suffix: Option<String>,
+    /// Trailer lines appended to the message, e.g. `Autosave-Host: laptop`
+    pub trailer: Option<String>,
+    pub date: CommitDate,
+    /// Name and email commits are authored and committed as, instead of the
+    /// user's own identity
+    pub author: Option<(String, String)>,
+    /// The project's own subject style, which generated subjects are rewritten in
+    pub style: Option<MessageStyle>,
+    pub subject_rules: SubjectRules,
+    /// Trailer recording the settings and plan that produced this cycle's commits
+    pub provenance: Option<String>,
+    /// `commit_message_template`, filled in for file and directory commits
+    pub template: Option<String>,
+    /// `{name}` of a file commit is the file's title, when it has one
+    pub content_titles: bool,
+    /// Commit Jupyter notebooks without their outputs, listing the changed cells
+    pub strip_notebook_outputs: bool,
+    /// The checked-out branch, for `{branch}`
+    pub branch: Option<String>,
+    /// With `--ai`, where staged diffs are sent to be described
+    pub ai: Option<AiSettings>,
+}
+
+/// What a file or directory commit covers, for the placeholders of
+/// `commit_message_template`.
+struct MessageFields<'a> {
+    change_type: &'a str,
+    /// The file's path (or title, with `content_titles`), or the directory's name
+    name: &'a str,
+    path: &'a str,
+    /// Directory containing the file, or the directory itself
+    dir: &'a str,
+    file_count: usize,
+    /// Branch the commit goes to, if not the checked-out one
+    branch: Option<&'a str>,
+}
+
+/// Returns `message` with its subject line rewritten in the repository's learned
+/// style and cleaned up by its subject rules, the repository's suffix appended
+/// to the subject and its trailer appended after the body. A subject that no
+/// longer looks like git-chai's is recorded as generated in a `Git-Chai`
+/// trailer.
+pub fn with_suffix(settings: &CommitSettings, message: &str) -> String {
+    // A templated message keeps the subject git-chai would have written in a
+    // trailer of its own
+    let (message, chai_subject) = match message.rsplit_once(&format!("\n\n{}: ", CHAI_TRAILER)) {
+        Some((message, subject)) if !subject.contains('\n') => (message, Some(subject)),
+        _ => (message, None),
+    };
+    let (generated, body) = match message.split_once('\n') {
+        Some((subject, body)) => (subject, Some(body)),
+        None => (message, None),
+    };
+    let restyled = settings.style.and_then(|style| style.restyle(generated));
+    let reserved = settings
+        .message_suffix
+        .as_ref()
+        .map_or(0, |suffix| suffix.chars().count() + 1);
+    let mut subject = settings
+        .subject_rules
+        .apply(restyled.as_deref().unwrap_or(generated), reserved);
+    let mut trailers = Vec::new();
+    let chai_subject = chai_subject.or(is_chai_commit(generated).then_some(generated));
+    if let Some(chai_subject) = chai_subject
+        && subject != chai_subject
+        && !is_chai_commit(&subject)
+    {
+        trailers.push(format!("{}: {}", CHAI_TRAILER, chai_subject));
+    }
+    if let Some(suffix) = &settings.message_suffix {
+        subject = format!("{} {}", subject, suffix);
+    }
+    let mut message = match body {
+        Some(body) => format!("{}\n{}", subject, body),
+        None => subject,
+    };
+    trailers.extend(settings.trailer.clone());
+    trailers.extend(settings.provenance.clone());
+    if !trailers.is_empty() {
+        message = format!("{}\n\n{}", message.trim_end(), trailers.join("\n"));
+    }
+    message
+}
+
+/// The Unix timestamp a commit made now gets, or `None` for the real time.
+fn commit_timestamp(date: CommitDate) -> Option<i64> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    match date {
+        CommitDate::Now => None,
+        CommitDate::At(timestamp) => Some(timestamp as i64),
+        CommitDate::Shifted(offset) => Some(now + offset),
+    }
+}
+
+/// Sets the author and committer dates of a commit command according to the
+/// repository's date setting.
+pub fn apply_commit_date(settings: &CommitSettings, command: &mut GitCommand) {
+    let Some(timestamp) = commit_timestamp(settings.date) else {
+        return;
+    };
+    let date = format!("@{}", timestamp);
+    command
+        .env("GIT_AUTHOR_DATE", &date)
+        .env("GIT_COMMITTER_DATE", &date);
+}
+
+/// Sets the author and committer of a commit command to the repository's
+/// `commit_author`, if it has one.
+pub fn apply_commit_author(settings: &CommitSettings, command: &mut GitCommand) {
+    let Some((name, email)) = &settings.author else {
+        return;
+    };
+    command
+        .env("GIT_AUTHOR_NAME", name)
+        .env("GIT_AUTHOR_EMAIL", email)
+        .env("GIT_COMMITTER_NAME", name)
+        .env("GIT_COMMITTER_EMAIL", email);
+}
+
+/// With `--ai`, replaces `message` with the model's description of the staged
+/// changes, keeping the subject git-chai generated in a `Git-Chai` trailer.
+/// Falls back to `message` if the model can't be reached.
+fn ai_message(repo_path: &Path, settings: &CommitSettings, message: &str) -> String {
+    let Some(ai) = &settings.ai else {
+        return message.to_string();
+    };
+    let generated = match message.rsplit_once(&format!("\n\n{}: ", CHAI_TRAILER)) {
+        Some((_, subject)) => subject,
+        None => message.lines().next().unwrap_or_default(),
+    };
+
+    match staged_diff(repo_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|diff| ai.commit_message(&diff, generated))
+    {
+        Ok(described) if is_chai_commit(generated) => {
+            format!("{}\n\n{}: {}", described, CHAI_TRAILER, generated)
+        }
+        Ok(described) => described,
+        Err(e) => {
+            log::warn!(
+                "Failed to get a commit message from {}: {:#}; using '{}'",
+                ai.endpoint,
+                e,
+                generated
+            );
+            message.to_string()
+        }
+    }
+}
+
+/// With `strip_notebook_outputs`, strips the outputs of the notebooks staged in
+/// the index (or in `index`) before they are committed. On failure they are
+/// committed as they are.
+pub(crate) fn strip_notebooks_if_enabled(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    index: Option<&TempIndex>,
+) {
+    if !settings.strip_notebook_outputs {
+        return;
+    }
+    if let Err(e) = strip_staged_notebooks(repo_path, index) {
+        log::warn!("Failed to strip notebook outputs, committing them: {}", e);
+    }
+}
+
+/// Builds `git commit -m <message>` with the repository's commit settings applied.
+fn commit_command(repo_path: &Path, settings: &CommitSettings, message: &str) -> GitCommand {
+    strip_notebooks_if_enabled(repo_path, settings, None);
+    let message = ai_message(repo_path, settings, message);
+    let mut command = git_command(repo_path);
+    command
+        .arg("commit")
+        .arg("-m")
+        .arg(with_suffix(settings, &message));
+    apply_commit_date(settings, &mut command);
+    apply_commit_author(settings, &mut command);
+    command
+}
+
+/// Fills in the repository's `commit_message_template` for a file or directory
+/// commit. The subject git-chai generates itself is kept in a `Git-Chai`
+/// trailer when the template changes it.
+fn templated_message(
+    settings: &CommitSettings,
+    generated: String,
+    fields: MessageFields,
+) -> String {
+    let Some(template) = &settings.template else {
+        return generated;
+    };
+    let subject = expand(template, |name| match name {
+        "change_type" => Some(fields.change_type.to_string()),
+        "name" => Some(fields.name.to_string()),
+        "path" => Some(fields.path.to_string()),
+        "dir" => Some(fields.dir.to_string()),
+        "file_count" => Some(fields.file_count.to_string()),
+        "timestamp" => Some(format_utc(commit_timestamp(settings.date).unwrap_or_else(
+            || {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs() as i64
+            },
+        ))),
+        "branch" => Some(
+            fields
+                .branch
+                .or(settings.branch.as_deref())
+                .unwrap_or("HEAD")
+                .to_string(),
+        ),
+        _ => environment_value(name),
+    });
+
+    if subject.trim().is_empty() || subject == generated {
+        generated
+    } else {
+        format!("{}\n\n{}: {}", subject.trim(), CHAI_TRAILER, generated)
+    }
+}
+
+pub fn file_commit_message(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    change_type: &str,
+    filename: &str,
+) -> String {
+    branch_commit_message(repo_path, settings, None, change_type, filename)
+}
+
+/// The message of a commit of one file to `branch`, or to the checked-out
+/// branch if `None`. With `strip_notebook_outputs`, a notebook's message lists
+/// its changed cells.
+pub fn branch_commit_message(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    branch: Option<&str>,
+    change_type: &str,
+    filename: &str,
+) -> String {
+    let title = (settings.content_titles && change_type != "del")
+        .then(|| file_title(repo_path, filename))
+        .flatten();
+    let cells = (settings.strip_notebook_outputs && change_type != "del" && is_notebook(filename))
+        .then(|| notebook_cell_summary(repo_path, filename))
+        .flatten();
+    let dir = Path::new(filename)
+        .parent()
+        .and_then(|dir| dir.to_str())
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or(".");
+    let message = templated_message(
+        settings,
+        format!("{}: {}", change_type, filename),
+        MessageFields {
+            change_type,
+            name: title.as_deref().unwrap_or(filename),
+            path: filename,
+            dir,
+            file_count: 1,
+            branch,
+        },
+    );
+    // The cell summary goes in the body, ahead of any Git-Chai trailer
+    match (cells, message.split_once("\n\n")) {
+        (Some(cells), Some((subject, trailer))) => {
+            format!("{}\n\n{}\n\n{}", subject, cells, trailer)
+        }
+        (Some(cells), None) => format!("{}\n\n{}", message, cells),
+        (None, _) => message,
+    }
+}
+
+pub fn directory_commit_message(
+    settings: &CommitSettings,
+    change_type: &str,
+    directory: &Path,
+    file_count: usize,
+) -> String {
+    let dir_name = directory
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_else(|| directory.to_str().unwrap_or("directory"));
+    let dir = directory.to_string_lossy();
+    let dir = dir.trim_end_matches('/');
+    templated_message(
+        settings,
+        format!("{}: {}", change_type, dir_name),
+        MessageFields {
+            change_type,
+            name: dir_name,
+            path: &format!("{}/", dir),
+            dir,
+            file_count,
+            branch: None,
+        },
+    )
+}
+
+pub fn rename_commit_message(from: &str, to: &str) -> String {
+    format!("rename: {} -> {}", from, to)
+}
+
+/// Abbreviates a commit SHA for log output.
+pub fn short_sha(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+pub fn link_commit_message(filename: &str, target: &str) -> String {
+    format!("link: {} -> {}", filename, target)
+}
 
 pub fn create_commit_for_file(
     repo_path: &Path,
+    settings: &CommitSettings,
     filename: &str,
     change_type: &str,
-) -> Result<(), GitChaiError> {
-    let message = format!("{}: {}", change_type, filename);
+) -> Result<String, GitChaiError> {
+    let message = file_commit_message(repo_path, settings, change_type, filename);
     log::debug!("Creating commit for file: {} - {}", change_type, filename);
 
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .arg("commit")
-        .arg("-m")
-        .arg(&message)
-        .output()
-        .map_err(GitChaiError::IoError)?;
+    let mut command = commit_command(repo_path, settings, &message);
+    let output = command.output().map_err(GitChaiError::IoError)?;
 
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
         log::error!("Failed to commit file {}: {}", filename, error_msg);
-        return Err(GitChaiError::GitCommandError {
-            command: format!("git commit -m '{}'", message),
-            stderr: error_msg.to_string(),
-            source: None,
-        });
+        return Err(GitChaiError::git_failed(command.argv(), &output));
     }
 
-    log::debug!("Successfully committed file: {}", filename);
-    Ok(())
+    let sha = get_head_sha(repo_path)?;
+    log::debug!("Successfully committed file: {} ({})", filename, sha);
+    Ok(sha)
+}
+
+/// Commits a staged rename. Only the two paths are committed, so that other
+/// renames already staged with `git mv` get commits of their own.
+pub fn create_commit_for_rename(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    from: &str,
+    to: &str,
+) -> Result<String, GitChaiError> {
+    let message = rename_commit_message(from, to);
+    log::debug!("Creating commit for rename: {} -> {}", from, to);
+
+    let mut command = commit_command(repo_path, settings, &message);
+    // On a case-insensitive filesystem both names are the same path
+    if !is_case_only_rename(from, to) {
+        command.arg("--").arg(from).arg(to);
+    }
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to commit rename {} -> {}: {}", from, to, error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let sha = get_head_sha(repo_path)?;
+    log::debug!(
+        "Successfully committed rename: {} -> {} ({})",
+        from,
+        to,
+        sha
+    );
+    Ok(sha)
+}
+
+pub fn create_commit_for_link(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    filename: &str,
+    target: &str,
+) -> Result<String, GitChaiError> {
+    let message = link_commit_message(filename, target);
+    log::debug!("Creating commit for symlink: {} -> {}", filename, target);
+
+    let mut command = commit_command(repo_path, settings, &message);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to commit symlink {}: {}", filename, error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let sha = get_head_sha(repo_path)?;
+    log::debug!("Successfully committed symlink: {} ({})", filename, sha);
+    Ok(sha)
+}
+
+pub fn amend_commit_for_file(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    filename: &str,
+    change_type: &str,
+) -> Result<String, GitChaiError> {
+    let message = file_commit_message(repo_path, settings, change_type, filename);
+    log::debug!("Amending commit for file: {} - {}", change_type, filename);
+    ensure_rewritable(repo_path, &["HEAD"])?;
+
+    let mut command = commit_command(repo_path, settings, &message);
+    command.arg("--amend");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!(
+            "Failed to amend commit for file {}: {}",
+            filename,
+            error_msg
+        );
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let sha = get_head_sha(repo_path)?;
+    log::debug!(
+        "Successfully amended commit for file: {} ({})",
+        filename,
+        sha
+    );
+    Ok(sha)
+}
+
+pub fn get_head_sha(repo_path: &Path) -> Result<String, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command.arg("rev-parse").arg("HEAD");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
 pub fn create_commit_for_directory(
     repo_path: &Path,
+    settings: &CommitSettings,
     directory: &Path,
     change_type: &str,
-) -> Result<(), GitChaiError> {
-    let dir_name = directory
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or_else(|| directory.to_str().unwrap_or("directory"));
-
-    let message = format!("{}: {}", change_type, dir_name);
+    file_count: usize,
+) -> Result<String, GitChaiError> {
+    let message = directory_commit_message(settings, change_type, directory, file_count);
     log::debug!(
-        "Creating commit for directory: {} - {}",
+        "Creating commit for directory: {} - {:?}",
         change_type,
-        dir_name
+        directory
     );
 
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .arg("commit")
-        .arg("-m")
-        .arg(&message)
-        .output()
-        .map_err(GitChaiError::IoError)?;
+    let mut command = commit_command(repo_path, settings, &message);
+    let output = command.output().map_err(GitChaiError::IoError)?;
 
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
         log::error!("Failed to commit directory {:?}: {}", directory, error_msg);
-        return Err(GitChaiError::GitCommandError {
-            command: format!("git commit -m '{}'", message),
-            stderr: error_msg.to_string(),
-            source: None,
-        });
+        return Err(GitChaiError::git_failed(command.argv(), &output));
     }
 
-    log::debug!("Successfully committed directory: {:?}", directory);
-    Ok(())
+    let sha = get_head_sha(repo_path)?;
+    log::debug!(
+        "Successfully committed directory: {:?} ({})",
+        directory,
+        sha
+    );
+    Ok(sha)
 }
 
-pub fn push_changes(repo_path: &Path) -> Result<(), GitChaiError> {
+/// Commits the staged changes with a fixed message.
+pub fn create_commit(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    message: &str,
+) -> Result<String, GitChaiError> {
+    log::debug!("Creating commit: {}", message);
+
+    let mut command = commit_command(repo_path, settings, message);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to create commit '{}': {}", message, error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    get_head_sha(repo_path)
+}
+
+/// Folds the staged changes into HEAD and gives it `message`.
+pub fn amend_commit(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    message: &str,
+) -> Result<String, GitChaiError> {
+    log::debug!("Amending commit: {}", message);
+    ensure_rewritable(repo_path, &["HEAD"])?;
+
+    let mut command = commit_command(repo_path, settings, message);
+    command.arg("--amend");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to amend commit '{}': {}", message, error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    get_head_sha(repo_path)
+}
+
+pub fn batch_commit_message(file_count: usize) -> String {
+    format!("batch: {} files", file_count)
+}
+
+pub fn create_batch_commit(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    file_count: usize,
+) -> Result<String, GitChaiError> {
+    let message = batch_commit_message(file_count);
+    log::debug!("Creating batch commit for {} files", file_count);
+
+    let mut command = commit_command(repo_path, settings, &message);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to create batch commit: {}", error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let sha = get_head_sha(repo_path)?;
+    log::debug!("Successfully created batch commit ({})", sha);
+    Ok(sha)
+}
+
+/// Pushes HEAD to `origin`, passing each of `push_options` with `-o` for the
+/// server side (e.g. `ci.skip` or GitLab merge request options).
+pub fn push_changes(repo_path: &Path, push_options: &[String]) -> Result<(), GitChaiError> {
     log::debug!("Pushing changes to remote");
 
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .arg("push")
-        .arg("origin")
-        .arg("HEAD")
-        .output()
-        .map_err(GitChaiError::IoError)?;
+    let mut command = git_command(repo_path);
+    command.arg("push");
+    for option in push_options {
+        command.arg("-o").arg(option);
+    }
+    command.arg("origin").arg("HEAD");
+    let output = command.output().map_err(GitChaiError::IoError)?;
 
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
-        log::error!("Failed to push changes: {}", error_msg);
-        return Err(GitChaiError::GitCommandError {
-            command: "git push origin HEAD".to_string(),
-            stderr: error_msg.to_string(),
-            source: None,
-        });
+        log::debug!("Failed to push changes: {}", error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
     }
 
     log::debug!("Successfully pushed changes to remote");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::message_style::{Convention, Mood};
+
+    #[test]
+    fn test_message_suffix_goes_on_the_subject_line() {
+        let settings = CommitSettings::default();
+        assert_eq!(with_suffix(&settings, "mod: a.txt"), "mod: a.txt");
+
+        let settings = CommitSettings {
+            message_suffix: Some("[skip ci]".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(with_suffix(&settings, "mod: a.txt"), "mod: a.txt [skip ci]");
+        assert_eq!(
+            with_suffix(&settings, "batch: 2 files\n\na.txt\nb.txt"),
+            "batch: 2 files [skip ci]\n\na.txt\nb.txt"
+        );
+
+        let settings = CommitSettings {
+            trailer: Some("Autosave-Host: laptop".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            with_suffix(&settings, "mod: a.txt"),
+            "mod: a.txt\n\nAutosave-Host: laptop"
+        );
+
+        let settings = CommitSettings {
+            message_suffix: Some("[skip ci]".to_string()),
+            style: Some(MessageStyle {
+                convention: Convention::Plain,
+                mood: Mood::Imperative,
+                capitalized: true,
+                period: false,
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            with_suffix(&settings, "mod: a.txt"),
+            "Update a.txt [skip ci]\n\nGit-Chai: mod: a.txt"
+        );
+    }
+
+    #[test]
+    fn test_commit_message_template() {
+        let repo = Path::new("/nonexistent/template-test");
+        let settings = CommitSettings {
+            template: Some("{change_type}: {name}".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            file_commit_message(repo, &settings, "mod", "src/a.rs"),
+            "mod: src/a.rs"
+        );
+
+        let settings = CommitSettings {
+            message_suffix: Some("[skip ci]".to_string()),
+            template: Some("wip({dir}) {file_count} on {branch} at {timestamp}".to_string()),
+            date: CommitDate::At(0),
+            branch: Some("main".to_string()),
+            ..Default::default()
+        };
+        let message = directory_commit_message(&settings, "add", Path::new("docs/api"), 3);
+        assert_eq!(
+            with_suffix(&settings, &message),
+            "wip(docs/api) 3 on main at 1970-01-01T00:00:00Z [skip ci]\n\nGit-Chai: add: api"
+        );
+        assert_eq!(
+            branch_commit_message(repo, &settings, Some("stack/api"), "del", "README.md"),
+            "wip(.) 1 on stack/api at 1970-01-01T00:00:00Z\n\nGit-Chai: del: README.md"
+        );
+    }
+}
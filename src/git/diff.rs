@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::git::isolated::has_head;
+
+/// git's empty tree, diffed against in a repository without commits.
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Returns the staged changes, as the next commit would record them.
+pub fn staged_diff(repo_path: &Path) -> Result<String, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command.args(["diff", "--cached", "--no-color", "--no-ext-diff"]);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Returns the diff of `path` (a file or directory) against HEAD, with untracked
+/// files shown as new files, for `git-chai review`.
+pub fn diff_against_head(
+    repo_path: &Path,
+    path: &str,
+    color: bool,
+) -> Result<String, GitChaiError> {
+    let color = if color {
+        "--color=always"
+    } else {
+        "--color=never"
+    };
+    let base = if has_head(repo_path) {
+        "HEAD"
+    } else {
+        EMPTY_TREE
+    };
+
+    let mut command = git_command(repo_path);
+    command.args(["diff", color, base, "--", path]);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    let mut diff = String::from_utf8_lossy(&output.stdout).to_string();
+
+    let mut command = git_command(repo_path);
+    command.args([
+        "ls-files",
+        "--others",
+        "--exclude-standard",
+        "-z",
+        "--",
+        path,
+    ]);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    let untracked = String::from_utf8_lossy(&output.stdout).to_string();
+
+    for file in untracked.split('\0').filter(|file| !file.is_empty()) {
+        let mut command = git_command(repo_path);
+        command.args(["diff", "--no-index", color, "--", "/dev/null", file]);
+        let output = command.output().map_err(GitChaiError::IoError)?;
+        // `--no-index` exits with 1 when the files differ
+        if output.status.code() != Some(1) && !output.status.success() {
+            return Err(GitChaiError::git_failed(command.argv(), &output));
+        }
+        diff.push_str(&String::from_utf8_lossy(&output.stdout));
+    }
+
+    Ok(diff)
+}
@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+
+/// Switches the working tree to `branch`.
+pub fn checkout_branch(repo_path: &Path, branch: &str) -> Result<(), GitChaiError> {
+    let mut command = git_command(repo_path);
+    command.arg("checkout").arg("--quiet").arg(branch).arg("--");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        log::error!(
+            "Failed to check out {}: {}",
+            branch,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(())
+}
+
+/// Applies `shas` (oldest first) on top of HEAD. With `no_commit`, the changes
+/// are only staged so they can be committed as one. On a conflict the
+/// cherry-pick is aborted, leaving HEAD and the index as they were.
+pub fn cherry_pick(repo_path: &Path, shas: &[String], no_commit: bool) -> Result<(), GitChaiError> {
+    let mut command = git_command(repo_path);
+    command.arg("cherry-pick").arg("--allow-empty");
+    if no_commit {
+        command.arg("--no-commit");
+    }
+    command.args(shas);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        log::error!(
+            "Failed to cherry-pick: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let error = GitChaiError::git_failed(command.argv(), &output);
+
+        let mut abort = git_command(repo_path);
+        abort.arg("cherry-pick").arg("--abort");
+        if let Ok(output) = abort.output()
+            && !output.status.success()
+        {
+            log::warn!(
+                "Failed to abort cherry-pick: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        return Err(error);
+    }
+
+    Ok(())
+}
@@ -1,17 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 use crate::error::GitChaiError;
+use crate::git::git_command;
 use crate::git::status::GitChange;
+use crate::plan::{ChangeGroup, GroupKind, PlannedFile};
+use crate::types::ChangeType;
 
-#[derive(Debug)]
-pub struct ChangeGroup {
-    pub path: PathBuf,
-    pub change_type: String,
-    pub files: Vec<String>,
-    pub file_change_types: Option<Vec<String>>,
-}
-
+/// Lists every file under `directory` that a directory-level commit would stage:
+/// tracked files (including deleted ones still in the index) and untracked files
+/// that aren't ignored.
 pub fn get_all_files_in_directory(
     repo_path: &Path,
     directory: &Path,
@@ -24,12 +22,15 @@ pub fn get_all_files_in_directory(
         directory.to_str().unwrap_or(".")
     };
 
-    let output = std::process::Command::new("git")
-        .current_dir(repo_path)
+    let mut command = git_command(repo_path);
+    command
         .arg("ls-files")
-        .arg(dir_arg)
-        .output()
-        .map_err(GitChaiError::IoError)?;
+        .arg("--cached")
+        .arg("--others")
+        .arg("--exclude-standard")
+        .arg("--")
+        .arg(dir_arg);
+    let output = command.output().map_err(GitChaiError::IoError)?;
 
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
@@ -38,62 +39,133 @@ pub fn get_all_files_in_directory(
             directory,
             error_msg
         );
-        return Err(GitChaiError::GitCommandError {
-            command: format!("git ls-files {}", dir_arg),
-            stderr: error_msg.to_string(),
-            source: None,
-        });
+        return Err(GitChaiError::git_failed(command.argv(), &output));
     }
 
     let output_str = String::from_utf8_lossy(&output.stdout);
-    let files: Vec<String> = output_str.lines().map(|s| s.to_string()).collect();
+    let mut files: Vec<String> = output_str.lines().map(|s| s.to_string()).collect();
+    // A file deleted from the working tree but still in the index can be listed twice
+    files.sort_unstable();
+    files.dedup();
 
     log::debug!("Found {} files in directory: {:?}", files.len(), directory);
     Ok(files)
 }
 
+/// The change type shared by all files, or `None` if they changed in different
+/// ways.
+fn uniform_change_type(files: &[PlannedFile]) -> Option<ChangeType> {
+    let first = files.first()?.change_type;
+    files
+        .iter()
+        .all(|file| file.change_type == first)
+        .then_some(first)
+}
+
+/// Whether the changed files are exactly the files in the directory, so that one
+/// directory-level commit stages nothing else. Counts alone aren't enough: a
+/// deleted file and an unrelated untracked one give equal counts of different
+/// paths.
+fn covers_directory(changed_files: &[String], all_files: &[String]) -> bool {
+    let changed: HashSet<&str> = changed_files.iter().map(String::as_str).collect();
+    let all: HashSet<&str> = all_files.iter().map(String::as_str).collect();
+    changed == all
+}
+
+/// Returns the opaque directory containing `filename`, if any.
+pub fn find_opaque_directory<'a>(
+    filename: &str,
+    opaque_directories: &'a [String],
+) -> Option<&'a str> {
+    opaque_directories
+        .iter()
+        .map(|dir| dir.trim_end_matches('/'))
+        .find(|dir| {
+            filename == *dir
+                || filename
+                    .strip_prefix(dir)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+}
+
+/// Plans one commit per change, keeping each change's type. This is the degraded
+/// mode used for any directory whose files can't be listed.
+pub fn individual_groups(changes: &[GitChange]) -> Vec<ChangeGroup> {
+    changes
+        .iter()
+        .map(|change| ChangeGroup::individual(vec![planned_file(change)]))
+        .collect()
+}
+
+fn planned_file(change: &GitChange) -> PlannedFile {
+    PlannedFile {
+        path: change.filename.clone(),
+        change_type: change.change_type,
+    }
+}
+
+/// Drops files already planned by an earlier group, so no file is staged and
+/// committed twice. Groups left without files are dropped.
+pub fn dedup_plan(groups: Vec<ChangeGroup>) -> Vec<ChangeGroup> {
+    let mut planned = HashSet::new();
+    groups
+        .into_iter()
+        .filter_map(|mut group| {
+            group.files.retain(|file| {
+                let new = planned.insert(file.path.clone());
+                if !new {
+                    log::debug!("{} is already planned; not committing it twice", file.path);
+                }
+                new
+            });
+            (!group.files.is_empty()).then_some(group)
+        })
+        .collect()
+}
+
+/// Groups changes into directory-level commits where every file of a directory
+/// changed the same way, and individual commits otherwise. A directory whose
+/// files can't be listed falls back to individual commits, so grouping never
+/// fails.
 pub fn group_changes_by_directory(
     repo_path: &Path,
     changes: &[GitChange],
-) -> Result<Vec<ChangeGroup>, GitChaiError> {
-    let mut directory_groups: HashMap<PathBuf, (String, Vec<String>)> = HashMap::new();
+    opaque_directories: &[String],
+) -> Vec<ChangeGroup> {
+    let mut directory_groups: HashMap<PathBuf, Vec<PlannedFile>> = HashMap::new();
+    let mut opaque_groups: HashMap<PathBuf, Vec<PlannedFile>> = HashMap::new();
     let mut untracked_directories = Vec::new();
 
     for change in changes {
         let path = PathBuf::from(&change.filename);
 
+        // Opaque directories always become a single directory-level commit
+        if let Some(opaque_dir) = find_opaque_directory(&change.filename, opaque_directories) {
+            opaque_groups
+                .entry(PathBuf::from(opaque_dir))
+                .or_default()
+                .push(planned_file(change));
+            continue;
+        }
+
         // Special case: if the filename ends with "/", it's a directory itself
         if change.filename.ends_with('/') && change.status == crate::types::GitStatus::Untracked {
             untracked_directories.push(ChangeGroup {
-                path: path.clone(),
-                change_type: "add".to_string(),
-                files: vec![change.filename.clone()],
-                file_change_types: Some(vec!["add".to_string()]),
+                path,
+                kind: GroupKind::Directory(ChangeType::Add),
+                files: vec![planned_file(change)],
             });
             continue;
         }
 
-        let parent_dir = if let Some(parent) = path.parent() {
-            if parent == Path::new("") {
-                PathBuf::from(".")
-            } else {
-                parent.to_path_buf()
-            }
-        } else {
-            PathBuf::from(".")
+        let parent_dir = match path.parent() {
+            Some(parent) if parent != Path::new("") => parent.to_path_buf(),
+            _ => PathBuf::from("."),
         };
-
-        let change_type_str = change.change_type.to_string();
-
         directory_groups
-            .entry(parent_dir.clone())
-            .and_modify(|(existing_type, files)| {
-                if existing_type != &change_type_str {
-                    *existing_type = "mixed".to_string();
-                }
-                files.push(change.filename.clone());
-            })
-            .or_insert_with(|| (change_type_str, vec![change.filename.clone()]));
+            .entry(parent_dir)
+            .or_default()
+            .push(planned_file(change));
     }
 
     let mut result = Vec::new();
@@ -101,26 +173,33 @@ pub fn group_changes_by_directory(
     // Add untracked directories first
     result.extend(untracked_directories);
 
-    for (path, (change_type, changed_files)) in directory_groups {
-        if change_type != "mixed" {
+    for (path, files) in opaque_groups {
+        result.push(ChangeGroup {
+            path,
+            kind: GroupKind::Directory(uniform_change_type(&files).unwrap_or(ChangeType::Modify)),
+            files,
+        });
+    }
+
+    for (path, files) in directory_groups {
+        if let Some(change_type) = uniform_change_type(&files) {
             // Check if ALL files in this directory are changed
             match get_all_files_in_directory(repo_path, &path) {
                 Ok(all_files) => {
-                    if changed_files.len() == all_files.len() {
-                        // All files in directory are changed with uniform type
+                    let changed_files: Vec<String> =
+                        files.iter().map(|file| file.path.clone()).collect();
+                    if covers_directory(&changed_files, &all_files) {
                         result.push(ChangeGroup {
                             path,
-                            change_type,
-                            files: changed_files,
-                            file_change_types: None,
+                            kind: GroupKind::Directory(change_type),
+                            files,
                         });
                         continue;
                     }
                 }
                 Err(e) => {
-                    // Continue with individual processing
-                    eprintln!(
-                        "Warning: Failed to get files for directory {}: {}",
+                    log::warn!(
+                        "Failed to list files in {}, committing its changes individually: {}",
                         path.display(),
                         e
                     );
@@ -129,23 +208,96 @@ pub fn group_changes_by_directory(
         }
 
         // Mixed changes or not all files changed - treat as individual files
-        let mut individual_files = Vec::new();
-        let mut individual_change_types = Vec::new();
+        result.push(ChangeGroup::individual(files));
+    }
 
-        for change in changes {
-            if changed_files.contains(&change.filename) {
-                individual_files.push(change.filename.clone());
-                individual_change_types.push(change.change_type.to_string());
-            }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::GitStatus;
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    fn change(filename: &str, status: GitStatus) -> GitChange {
+        GitChange {
+            change_type: status.clone().into(),
+            status,
+            filename: filename.to_string(),
+            renamed_from: None,
+            symlink_target: None,
         }
+    }
 
-        result.push(ChangeGroup {
-            path: PathBuf::from("."),
-            change_type: "individual".to_string(),
-            files: individual_files,
-            file_change_types: Some(individual_change_types),
-        });
+    #[test]
+    fn test_grouping_degrades_to_individual_commits() {
+        let changes = vec![
+            change("docs/a.md", GitStatus::ModifiedUnstaged),
+            change("docs/b.md", GitStatus::ModifiedUnstaged),
+            change("docs/c.md", GitStatus::DeletedUnstaged),
+        ];
+
+        // Listing the directory fails outside a repository
+        let groups = group_changes_by_directory(Path::new("/nonexistent/repo"), &changes[..2], &[]);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].kind, GroupKind::Individual);
+        assert_eq!(
+            groups[0].files,
+            vec![
+                PlannedFile {
+                    path: "docs/a.md".to_string(),
+                    change_type: ChangeType::Modify
+                },
+                PlannedFile {
+                    path: "docs/b.md".to_string(),
+                    change_type: ChangeType::Modify
+                },
+            ]
+        );
+
+        let groups = individual_groups(&changes);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[2].files[0].path, "docs/c.md");
+        assert_eq!(groups[2].files[0].change_type, ChangeType::Delete);
     }
 
-    Ok(result)
+    #[test]
+    fn test_dedup_plan_commits_each_file_once() {
+        let changes = vec![
+            change("docs/a.md", GitStatus::ModifiedUnstaged),
+            change("docs/a.md", GitStatus::ModifiedUnstaged),
+            change("notes.md", GitStatus::Untracked),
+        ];
+        let mut groups = group_changes_by_directory(Path::new("/nonexistent/repo"), &changes, &[]);
+        groups.extend(individual_groups(&changes[2..]));
+
+        let plan = dedup_plan(groups);
+        let mut files: Vec<&str> = plan
+            .iter()
+            .flat_map(|group| &group.files)
+            .map(|file| file.path.as_str())
+            .collect();
+        assert_eq!(files.len(), 2);
+        files.sort_unstable();
+        files.dedup();
+        assert_eq!(files, vec!["docs/a.md", "notes.md"]);
+    }
+
+    #[test]
+    fn test_covers_directory_compares_paths_not_counts() {
+        let all = paths(&["docs/a.md", "docs/b.md"]);
+
+        assert!(covers_directory(&paths(&["docs/b.md", "docs/a.md"]), &all));
+        assert!(!covers_directory(&paths(&["docs/a.md"]), &all));
+        // Same count, different files: b.md untouched, c.md untracked
+        assert!(!covers_directory(
+            &paths(&["docs/a.md", "docs/c.md"]),
+            &paths(&["docs/a.md", "docs/b.md", "docs/c.md"])
+        ));
+        assert!(!covers_directory(&paths(&["docs/a.md", "docs/c.md"]), &all));
+    }
 }
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+
+/// Returns the remote-tracking ref that already has `commit`, if any. Commits
+/// behind this rewrite horizon have been published (to any remote, not only
+/// `origin`), so amending or otherwise rewriting them would make the branch
+/// diverge from what others may have fetched.
+pub fn published_on(repo_path: &Path, commit: &str) -> Result<Option<String>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("for-each-ref")
+        .arg("--count=1")
+        .arg("--format=%(refname:short)")
+        .arg(format!("--contains={}", commit))
+        .arg("refs/remotes");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let reference = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok((!reference.is_empty()).then_some(reference))
+}
+
+/// Fails unless `commits` are all in front of the rewrite horizon. Every
+/// history-rewriting operation (amending, retracting) checks this before it
+/// touches a ref. Checking the oldest commit of a linear range covers the
+/// rest, since a remote that has a later commit has its ancestors too.
+pub fn ensure_rewritable(repo_path: &Path, commits: &[&str]) -> Result<(), GitChaiError> {
+    for commit in commits {
+        if let Some(reference) = published_on(repo_path, commit)? {
+            return Err(GitChaiError::git_rejected(
+                &["git", "update-ref"],
+                format!(
+                    "{} is already on {}; published commits are never rewritten",
+                    commit, reference
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_commits_on_any_remote_are_behind_the_horizon() {
+        let repo = TempRepo::new("horizon");
+        let git = |args: &[&str]| repo.git(args);
+        git(&["commit", "--quiet", "--allow-empty", "-m", "published"]);
+        let published = git(&["rev-parse", "HEAD"]);
+        git(&["update-ref", "refs/remotes/backup/main", &published]);
+        git(&["commit", "--quiet", "--allow-empty", "-m", "local"]);
+        let local = git(&["rev-parse", "HEAD"]);
+
+        assert_eq!(
+            published_on(&repo, &published).unwrap().as_deref(),
+            Some("backup/main")
+        );
+        assert_eq!(published_on(&repo, &local).unwrap(), None);
+        assert!(ensure_rewritable(&repo, &[&local]).is_ok());
+        assert!(ensure_rewritable(&repo, &[&published]).is_err());
+    }
+}
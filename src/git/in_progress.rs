@@ -0,0 +1,44 @@
+use std::path::{Path, PathBuf};
+
+use crate::git::git_command;
+
+/// Marker files in the git directory that mean a multi-step operation is waiting
+/// on the user, paired with a description for logging.
+const IN_PROGRESS_MARKERS: &[(&str, &str)] = &[
+    ("rebase-merge", "rebase"),
+    ("rebase-apply", "rebase or am"),
+    ("MERGE_HEAD", "merge"),
+    ("CHERRY_PICK_HEAD", "cherry-pick"),
+    ("REVERT_HEAD", "revert"),
+    ("BISECT_LOG", "bisect"),
+];
+
+/// Returns the operation in progress in the repository (rebase, merge, ...), if any.
+pub fn in_progress_operation(repo_path: &Path) -> Option<&'static str> {
+    let git_dir = get_git_dir(repo_path)?;
+    IN_PROGRESS_MARKERS
+        .iter()
+        .find(|(marker, _)| git_dir.join(marker).exists())
+        .map(|(_, operation)| *operation)
+}
+
+/// Absolute path of the repository's git directory.
+pub fn get_git_dir(repo_path: &Path) -> Option<PathBuf> {
+    let output = git_command(repo_path)
+        .arg("rev-parse")
+        .arg("--absolute-git-dir")
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        log::debug!(
+            "Failed to locate git directory: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return None;
+    }
+
+    Some(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim(),
+    ))
+}
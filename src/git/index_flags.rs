@@ -0,0 +1,75 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+
+/// Index bits that tell git to leave a tracked file alone in the working tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexFlag {
+    AssumeUnchanged,
+    SkipWorktree,
+}
+
+impl fmt::Display for IndexFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexFlag::AssumeUnchanged => write!(f, "assume-unchanged"),
+            IndexFlag::SkipWorktree => write!(f, "skip-worktree"),
+        }
+    }
+}
+
+/// Lists tracked files marked skip-worktree or assume-unchanged. git-chai never
+/// stages these, since they usually hold local edits to config templates.
+pub fn get_flagged_files(repo_path: &Path) -> Result<Vec<(String, IndexFlag)>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command.arg("ls-files").arg("-v").arg("-z");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to list index flags: {}", error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(parse_flagged_files(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Parses `git ls-files -v -z` output. `S` marks skip-worktree and a lowercase tag
+/// marks assume-unchanged; skip-worktree wins when both are set.
+fn parse_flagged_files(output: &str) -> Vec<(String, IndexFlag)> {
+    output
+        .split('\0')
+        .filter_map(|entry| {
+            let (tag, filename) = entry.split_once(' ')?;
+            let flag = match tag {
+                "S" | "s" => IndexFlag::SkipWorktree,
+                _ if tag.chars().all(|c| c.is_ascii_lowercase()) => IndexFlag::AssumeUnchanged,
+                _ => return None,
+            };
+            Some((filename.to_string(), flag))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flagged_files() {
+        let output = "H src/main.rs\0h config.local.toml\0S docs/big.bin\0s both.txt\0";
+
+        assert_eq!(
+            parse_flagged_files(output),
+            vec![
+                ("config.local.toml".to_string(), IndexFlag::AssumeUnchanged),
+                ("docs/big.bin".to_string(), IndexFlag::SkipWorktree),
+                ("both.txt".to_string(), IndexFlag::SkipWorktree),
+            ]
+        );
+    }
+}
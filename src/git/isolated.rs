@@ -0,0 +1,211 @@
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::GitChaiError;
+use crate::git::commit::{
+    CommitSettings, apply_commit_author, apply_commit_date, strip_notebooks_if_enabled, with_suffix,
+};
+use crate::git::git_command;
+use crate::git::horizon::ensure_rewritable;
+
+static TEMP_INDEX_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Commits `paths` (after dropping `removals` from the index) without touching the
+/// user's index: the paths are staged into a temporary index built from HEAD, the
+/// tree is written with `write-tree` and committed with `commit-tree`, and HEAD is
+/// moved with a compare-and-swap `update-ref`. The real index is left as it was,
+/// apart from refreshing its stat info. With `force`, paths matched by
+/// `.gitignore` are staged as well.
+///
+/// Commits created this way do not run the pre-commit or commit-msg hooks.
+pub fn commit_isolated(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    paths: &[String],
+    removals: &[String],
+    message: &str,
+    amend: bool,
+    force: bool,
+) -> Result<String, GitChaiError> {
+    log::debug!("Committing {:?} through a temporary index", paths);
+
+    let temp_index = TempIndex::new();
+    let old_head = get_head(repo_path)?;
+
+    if old_head.is_some() {
+        run_git(repo_path, Some(&temp_index), &["read-tree", "HEAD"])?;
+    }
+
+    if !removals.is_empty() {
+        let mut args = vec!["rm", "--cached", "-r", "--quiet", "--ignore-unmatch", "--"];
+        args.extend(removals.iter().map(String::as_str));
+        run_git(repo_path, Some(&temp_index), &args)?;
+    }
+
+    // `git add --all` without a pathspec would stage the whole tree
+    if !paths.is_empty() {
+        let mut args = vec!["add", "--all"];
+        if force {
+            args.push("--force");
+        }
+        args.push("--");
+        args.extend(paths.iter().map(String::as_str));
+        run_git(repo_path, Some(&temp_index), &args)?;
+    }
+
+    strip_notebooks_if_enabled(repo_path, settings, Some(&temp_index));
+    let tree = stdout_of(run_git(repo_path, Some(&temp_index), &["write-tree"])?);
+
+    if amend && let Some(head) = &old_head {
+        ensure_rewritable(repo_path, &[head])?;
+    }
+    let parents: Vec<String> = match (&old_head, amend) {
+        (Some(_), true) => stdout_of(run_git(
+            repo_path,
+            None,
+            &["log", "-1", "--format=%P", "HEAD"],
+        )?)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect(),
+        (Some(head), false) => vec![head.clone()],
+        (None, _) => Vec::new(),
+    };
+
+    if !amend
+        && let Some(head) = &old_head
+        && stdout_of(run_git(
+            repo_path,
+            None,
+            &["rev-parse", &format!("{}^{{tree}}", head)],
+        )?) == tree
+    {
+        return Err(GitChaiError::git_rejected(
+            &["git", "write-tree"],
+            "nothing to commit",
+        ));
+    }
+
+    let message = with_suffix(settings, message);
+    let mut args = vec!["commit-tree", tree.as_str(), "-m", message.as_str()];
+    for parent in &parents {
+        args.push("-p");
+        args.push(parent);
+    }
+    let mut command = git_command(repo_path);
+    command.args(&args);
+    apply_commit_date(settings, &mut command);
+    apply_commit_author(settings, &mut command);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        log::error!(
+            "git commit-tree failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    let commit = stdout_of(output);
+
+    let reflog_message = format!("git-chai: {}", message);
+    run_git(
+        repo_path,
+        None,
+        &[
+            "update-ref",
+            "-m",
+            &reflog_message,
+            "HEAD",
+            &commit,
+            old_head.as_deref().unwrap_or(""),
+        ],
+    )?;
+
+    // Only stat info is refreshed; the entries themselves are the user's
+    let _ = run_git(repo_path, None, &["update-index", "-q", "--refresh"]);
+
+    log::debug!("Created commit {} through a temporary index", commit);
+    Ok(commit)
+}
+
+pub(crate) fn has_head(repo_path: &Path) -> bool {
+    git_command(repo_path)
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg("HEAD")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+fn get_head(repo_path: &Path) -> Result<Option<String>, GitChaiError> {
+    if !has_head(repo_path) {
+        return Ok(None);
+    }
+    Ok(Some(stdout_of(run_git(
+        repo_path,
+        None,
+        &["rev-parse", "HEAD"],
+    )?)))
+}
+
+pub(crate) fn run_git(
+    repo_path: &Path,
+    index: Option<&TempIndex>,
+    args: &[&str],
+) -> Result<Output, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command.args(args);
+    if let Some(index) = index {
+        command.env("GIT_INDEX_FILE", &index.path);
+    }
+
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("git {} failed: {}", args.join(" "), error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(output)
+}
+
+pub(crate) fn stdout_of(output: Output) -> String {
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// A temporary index file that is removed when dropped.
+pub(crate) struct TempIndex {
+    pub(crate) path: PathBuf,
+}
+
+impl TempIndex {
+    pub(crate) fn new() -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "git-chai-index-{}-{}",
+            std::process::id(),
+            TEMP_INDEX_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        Self { path }
+    }
+
+    /// Creates a temporary copy of the repository's current index.
+    pub(crate) fn copy_of_index(repo_path: &Path) -> Result<Self, GitChaiError> {
+        let output = run_git(repo_path, None, &["rev-parse", "--git-path", "index"])?;
+        let index_path = repo_path.join(stdout_of(output));
+        let temp_index = Self::new();
+
+        if index_path.is_file() {
+            std::fs::copy(&index_path, &temp_index.path)?;
+        }
+
+        Ok(temp_index)
+    }
+}
+
+impl Drop for TempIndex {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Upper bound on a single wait, so waiters re-check the limits regularly.
+const MAX_WAIT_STEP: Duration = Duration::from_millis(100);
+
+/// Waits longer than this are logged, since they mean git-chai is throttling itself.
+const SLOW_WAIT: Duration = Duration::from_secs(1);
+
+/// Token bucket allowing bursts of up to `per_minute` git processes, refilled
+/// continuously at `per_minute` tokens per minute.
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(per_minute: usize, now: Instant) -> Self {
+        let capacity = per_minute.max(1) as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / 60.0,
+            last_refill: now,
+        }
+    }
+
+    /// Takes a token, or returns how long until the next one is available.
+    pub fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    pub fn average(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.count as u32
+        }
+    }
+}
+
+struct Limiter {
+    max_concurrent: Option<usize>,
+    bucket: Option<TokenBucket>,
+    running: usize,
+}
+
+static LIMITER: OnceLock<(Mutex<Limiter>, Condvar)> = OnceLock::new();
+static LATENCY: Mutex<BTreeMap<String, LatencyStats>> = Mutex::new(BTreeMap::new());
+
+/// Sets process-wide limits on git subprocesses, shared by every watched
+/// repository. Without a call to this, git commands are never throttled.
+pub fn configure(max_concurrent: Option<usize>, per_minute: Option<usize>) {
+    if max_concurrent.is_none() && per_minute.is_none() {
+        return;
+    }
+
+    let limiter = Limiter {
+        max_concurrent: max_concurrent.map(|max| max.max(1)),
+        bucket: per_minute.map(|per_minute| TokenBucket::new(per_minute, Instant::now())),
+        running: 0,
+    };
+    if LIMITER.set((Mutex::new(limiter), Condvar::new())).is_err() {
+        log::warn!("git process limits were already configured");
+    }
+}
+
+/// Held while a git process runs; releases the concurrency slot when dropped.
+pub struct Slot {
+    _private: (),
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Some((limiter, released)) = LIMITER.get() {
+            lock(limiter).running -= 1;
+            released.notify_one();
+        }
+    }
+}
+
+/// Waits until the configured limits allow another git process to start.
+/// Returns `None` when no limits are configured.
+pub fn acquire() -> Option<Slot> {
+    let (limiter, released) = LIMITER.get()?;
+    let started = Instant::now();
+    let mut state = lock(limiter);
+
+    loop {
+        let wait = if state.max_concurrent.is_some_and(|max| state.running >= max) {
+            MAX_WAIT_STEP
+        } else {
+            match state.bucket.as_mut().map(|b| b.try_take(Instant::now())) {
+                Some(Err(wait)) => wait.min(MAX_WAIT_STEP),
+                Some(Ok(())) | None => break,
+            }
+        };
+
+        state = released
+            .wait_timeout(state, wait)
+            .unwrap_or_else(|e| e.into_inner())
+            .0;
+    }
+
+    state.running += 1;
+
+    let waited = started.elapsed();
+    if waited >= SLOW_WAIT {
+        log::debug!("Waited {:?} for a git process slot", waited);
+    }
+
+    Some(Slot { _private: () })
+}
+
+/// Records how long a git subcommand took.
+pub fn record_latency(subcommand: &str, elapsed: Duration) {
+    log::trace!("git {} took {:?}", subcommand, elapsed);
+
+    let mut latency = LATENCY.lock().unwrap_or_else(|e| e.into_inner());
+    let stats = latency.entry(subcommand.to_string()).or_default();
+    stats.count += 1;
+    stats.total += elapsed;
+    stats.max = stats.max.max(elapsed);
+}
+
+/// Returns latency statistics per git subcommand, sorted by name.
+pub fn latency_stats() -> Vec<(String, LatencyStats)> {
+    LATENCY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(subcommand, stats)| (subcommand.clone(), *stats))
+        .collect()
+}
+
+fn lock(limiter: &Mutex<Limiter>) -> MutexGuard<'_, Limiter> {
+    limiter.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_bursts_then_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, start);
+
+        assert!(bucket.try_take(start).is_ok());
+        assert!(bucket.try_take(start).is_ok());
+
+        let wait = bucket.try_take(start).unwrap_err();
+        assert_eq!(wait.as_secs(), 30);
+
+        assert!(bucket.try_take(start + Duration::from_secs(31)).is_ok());
+        assert!(bucket.try_take(start + Duration::from_secs(32)).is_err());
+    }
+}
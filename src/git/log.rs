@@ -0,0 +1,238 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::git::message_style::CHAI_TRAILER;
+use crate::template::format_utc;
+
+/// A commit on HEAD's history, with the paths it touched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogCommit {
+    pub sha: String,
+    pub subject: String,
+    /// The subject git-chai generated, kept in a `Git-Chai` trailer when the
+    /// message was rewritten in the project's learned style
+    pub chai_subject: Option<String>,
+    pub files: Vec<String>,
+}
+
+impl LogCommit {
+    /// The subject as git-chai generated it, before any restyling.
+    pub fn generated_subject(&self) -> &str {
+        self.chai_subject.as_deref().unwrap_or(&self.subject)
+    }
+}
+
+/// Lists the commits on HEAD made since `since` (anything `git log --since`
+/// accepts, e.g. `yesterday` or `2 days ago`), newest first.
+pub fn get_commits_since(repo_path: &Path, since: &str) -> Result<Vec<LogCommit>, GitChaiError> {
+    run_log(repo_path, &[&format!("--since={}", since), "HEAD"])
+}
+
+/// Lists the commits in `range` (e.g. `main..wip`), newest first.
+pub fn get_commits_in_range(repo_path: &Path, range: &str) -> Result<Vec<LogCommit>, GitChaiError> {
+    run_log(repo_path, &[range, "--"])
+}
+
+/// Lists the commits on HEAD that no remote-tracking ref has yet (those in
+/// front of the rewrite horizon), newest first; with `path`, only those that
+/// touched it.
+pub fn get_unpublished_commits(
+    repo_path: &Path,
+    path: Option<&str>,
+) -> Result<Vec<LogCommit>, GitChaiError> {
+    let mut args = vec!["HEAD", "--not", "--remotes", "--"];
+    args.extend(path);
+    run_log(repo_path, &args)
+}
+
+/// The commit HEAD points to, if any.
+pub fn get_head_commit(repo_path: &Path) -> Result<Option<LogCommit>, GitChaiError> {
+    Ok(run_log(repo_path, &["-1", "HEAD", "--"])?.pop())
+}
+
+/// The most recent commit on HEAD that touched `path`, if any.
+pub fn get_last_commit_touching(
+    repo_path: &Path,
+    path: &str,
+) -> Result<Option<LogCommit>, GitChaiError> {
+    Ok(run_log(repo_path, &["-1", "HEAD", "--", path])?.pop())
+}
+
+fn run_log(repo_path: &Path, args: &[&str]) -> Result<Vec<LogCommit>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("-c")
+        .arg("core.quotePath=false")
+        .arg("log")
+        .arg(format!(
+            "--format=%x1e%H%x1f%s%x1f%(trailers:key={},valueonly,separator=%x1d)",
+            CHAI_TRAILER
+        ))
+        .arg("--name-only")
+        .args(args);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(parse_log(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Commit timestamps (seconds since the epoch) on `rev` since `since`, each with
+/// its local calendar day, oldest first.
+pub fn get_commit_times(
+    repo_path: &Path,
+    rev: &str,
+    since: &str,
+) -> Result<Vec<(i64, String)>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("log")
+        .arg("--reverse")
+        .arg(format!("--since={}", since))
+        .arg("--date=format-local:%Y-%m-%d")
+        .arg("--format=%ct %ad")
+        .arg(rev)
+        .arg("--");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| {
+            line.split_once(' ')
+                .and_then(|(time, day)| Some((time.parse().ok()?, day.to_string())))
+                .ok_or_else(|| GitChaiError::ParseError(format!("Unexpected log line: {}", line)))
+        })
+        .collect()
+}
+
+/// The local calendar day (`2024-06-01`) `rev` was committed on.
+pub fn get_commit_day(repo_path: &Path, rev: &str) -> Result<String, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("log")
+        .arg("-1")
+        .arg("--date=format-local:%Y-%m-%d")
+        .arg("--format=%cd")
+        .arg(rev)
+        .arg("--");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Today's local calendar day, as a commit made now would be dated.
+pub fn get_local_day(repo_path: &Path) -> Result<String, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command.arg("var").arg("GIT_COMMITTER_IDENT");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let ident = String::from_utf8_lossy(&output.stdout);
+    parse_ident_day(ident.trim())
+        .ok_or_else(|| GitChaiError::ParseError(format!("Unexpected identity: {}", ident.trim())))
+}
+
+/// The local day of the `<timestamp> <+hhmm>` that ends an identity line.
+fn parse_ident_day(ident: &str) -> Option<String> {
+    let mut fields = ident.rsplitn(3, ' ');
+    let offset = fields.next()?;
+    let timestamp: i64 = fields.next()?.parse().ok()?;
+    let (sign, offset) = match offset.split_at_checked(1)? {
+        ("+", offset) => (1, offset),
+        ("-", offset) => (-1, offset),
+        _ => return None,
+    };
+    let hours: i64 = offset.get(..2)?.parse().ok()?;
+    let minutes: i64 = offset.get(2..)?.parse().ok()?;
+    let local = timestamp + sign * (hours * 3_600 + minutes * 60);
+    Some(format_utc(local)[..10].to_string())
+}
+
+/// Names of the local branches.
+pub fn get_local_branches(repo_path: &Path) -> Result<Vec<String>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("for-each-ref")
+        .arg("--format=%(refname:short)")
+        .arg("refs/heads");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+fn parse_log(output: &str) -> Vec<LogCommit> {
+    output
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut lines = record.lines();
+            let mut fields = lines.next()?.split('\x1f');
+            let (sha, subject) = (fields.next()?, fields.next()?);
+            let chai_subject = fields
+                .next()
+                .and_then(|trailer| trailer.split('\x1d').next())
+                .filter(|trailer| !trailer.is_empty());
+            Some(LogCommit {
+                sha: sha.to_string(),
+                subject: subject.to_string(),
+                chai_subject: chai_subject.map(str::to_string),
+                files: lines
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let output = "\x1eabc\x1fUpdate today.md\x1fmod: today.md\n\nnotes/today.md\n\x1edef\x1fInitial commit\x1f\n\nREADME.md\nsrc/main.rs\n";
+        let commits = parse_log(output);
+
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].subject, "Update today.md");
+        assert_eq!(commits[0].generated_subject(), "mod: today.md");
+        assert_eq!(commits[1].chai_subject, None);
+        assert_eq!(commits[0].files, vec!["notes/today.md"]);
+        assert_eq!(commits[1].files, vec!["README.md", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_parse_ident_day() {
+        let ident = "Chai <chai@example.com> 1717282800";
+        assert_eq!(
+            parse_ident_day(&format!("{} +0000", ident)).as_deref(),
+            Some("2024-06-01")
+        );
+        assert_eq!(
+            parse_ident_day(&format!("{} +0530", ident)).as_deref(),
+            Some("2024-06-02")
+        );
+        assert_eq!(parse_ident_day(ident), None);
+    }
+}
@@ -0,0 +1,91 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+
+/// Creates a merge commit of `branch` into `into` without touching the working
+/// tree or the index, and moves `into` to it. Fails without changing anything if
+/// the branches conflict. Returns the new commit.
+pub fn merge_branch_into(
+    repo_path: &Path,
+    branch: &str,
+    into: &str,
+    message: &str,
+) -> Result<String, GitChaiError> {
+    let into_ref = format!("refs/heads/{}", into);
+    let old_tip = run(repo_path, &["rev-parse", "--verify", &into_ref])?;
+    let branch_tip = run(repo_path, &["rev-parse", "--verify", branch])?;
+
+    let mut command = git_command(repo_path);
+    command
+        .arg("merge-tree")
+        .arg("--write-tree")
+        .arg("--name-only")
+        .arg("--no-messages")
+        .arg(&old_tip)
+        .arg(&branch_tip);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Exit code 1 means conflicts: the tree is followed by the conflicted paths
+    if output.status.code() == Some(1) {
+        let conflicts: Vec<&str> = stdout.lines().skip(1).filter(|l| !l.is_empty()).collect();
+        return Err(GitChaiError::git_rejected(
+            &["git", "merge-tree"],
+            format!(
+                "{} conflicts with {} in {}",
+                branch,
+                into,
+                conflicts.join(", ")
+            ),
+        ));
+    }
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    let tree = stdout.trim().to_string();
+
+    let commit = run(
+        repo_path,
+        &[
+            "commit-tree",
+            &tree,
+            "-p",
+            &old_tip,
+            "-p",
+            &branch_tip,
+            "-m",
+            message,
+        ],
+    )?;
+    let reflog_message = format!("git-chai: merge {}", branch);
+    run(
+        repo_path,
+        &[
+            "update-ref",
+            "-m",
+            &reflog_message,
+            &into_ref,
+            &commit,
+            &old_tip,
+        ],
+    )?;
+
+    Ok(commit)
+}
+
+fn run(repo_path: &Path, args: &[&str]) -> Result<String, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command.args(args);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        log::error!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
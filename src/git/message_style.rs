@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::summary::is_chai_commit;
+
+/// Trailer that keeps the subject git-chai would have written on a commit whose
+/// message was restyled, so `summary`, `export` and `retract` still recognize it.
+pub const CHAI_TRAILER: &str = "Git-Chai";
+
+/// How many recent commits are read to learn the style.
+const SAMPLE_COMMITS: &str = "200";
+
+/// Fewer hand-written subjects than this are too few to learn from.
+const MIN_SAMPLES: usize = 10;
+
+static LEARNED_STYLES: OnceLock<Mutex<HashMap<PathBuf, Option<MessageStyle>>>> = OnceLock::new();
+
+/// How a project's hand-written commit subjects are formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageStyle {
+    pub convention: Convention,
+    pub mood: Mood,
+    /// The description starts with a capital letter
+    pub capitalized: bool,
+    /// The subject ends with a period
+    pub period: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Convention {
+    /// Conventional Commits, `type(scope): description`
+    Conventional {
+        scoped: bool,
+    },
+    /// `[scope] description`
+    Bracketed,
+    Plain,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mood {
+    /// "Add", "Fix"
+    Imperative,
+    /// "Added", "Fixed"
+    Past,
+    /// "Adds", "Fixes"
+    ThirdPerson,
+}
+
+impl MessageStyle {
+    /// Rewrites a subject git-chai generated (`mod: src/main.rs`) in this style,
+    /// e.g. `chore(src): update src/main.rs` or `Updated src/main.rs.`. Returns
+    /// `None` for subjects that aren't git-chai's own.
+    pub fn restyle(&self, subject: &str) -> Option<String> {
+        let (kind, rest) = subject.split_once(": ")?;
+        let (verb, rest) = match kind {
+            "add" => (["add", "added", "adds"], rest.to_string()),
+            "mod" | "batch" => (["update", "updated", "updates"], rest.to_string()),
+            "del" => (["remove", "removed", "removes"], rest.to_string()),
+            "rename" => (
+                ["rename", "renamed", "renames"],
+                rest.replacen(" -> ", " to ", 1),
+            ),
+            "copy" => (
+                ["copy", "copied", "copies"],
+                rest.replacen(" -> ", " to ", 1),
+            ),
+            "link" => (["link", "linked", "links"], rest.to_string()),
+            _ => return None,
+        };
+        let verb = match self.mood {
+            Mood::Imperative => verb[0],
+            Mood::Past => verb[1],
+            Mood::ThirdPerson => verb[2],
+        };
+
+        let mut description = format!("{} {}", verb, rest);
+        if self.capitalized {
+            description = capitalize(&description);
+        }
+        if self.period {
+            description.push('.');
+        }
+        let scope = rest
+            .split_once('/')
+            .map(|(dir, _)| dir)
+            .filter(|dir| !dir.contains(' '));
+
+        Some(match (self.convention, scope) {
+            (Convention::Conventional { scoped: true }, Some(scope)) => {
+                format!("chore({}): {}", scope, description)
+            }
+            (Convention::Conventional { .. }, _) => format!("chore: {}", description),
+            (Convention::Bracketed, Some(scope)) => format!("[{}] {}", scope, description),
+            _ => description,
+        })
+    }
+}
+
+/// Returns the style of the repository's hand-written commit subjects, learned
+/// once per session from its recent history. `None` if there are too few of
+/// them, in which case git-chai keeps its own format.
+pub fn learned_style(repo_path: &Path) -> Option<MessageStyle> {
+    let mut styles = LEARNED_STYLES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    *styles
+        .entry(repo_path.to_path_buf())
+        .or_insert_with(|| match hand_written_subjects(repo_path) {
+            Ok(subjects) => {
+                let style = learn(&subjects);
+                match &style {
+                    Some(style) => log::info!(
+                        "Learned the commit message style of {} commits; autosaves read like \"{}\"",
+                        subjects.len(),
+                        style.restyle("mod: src/main.rs").unwrap_or_default()
+                    ),
+                    None => log::info!(
+                        "Only {} hand-written commits to learn a message style from; keeping git-chai's format",
+                        subjects.len()
+                    ),
+                }
+                style
+            }
+            Err(e) => {
+                log::warn!("Failed to read commit messages to learn their style: {}", e);
+                None
+            }
+        })
+}
+
+/// Subjects of recent commits on HEAD that git-chai didn't write.
+fn hand_written_subjects(repo_path: &Path) -> Result<Vec<String>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("log")
+        .arg("-n")
+        .arg(SAMPLE_COMMITS)
+        .arg("--no-merges")
+        .arg(format!(
+            "--format=%s%x1f%(trailers:key={},valueonly,separator=%x1d)",
+            CHAI_TRAILER
+        ))
+        .arg("HEAD")
+        .arg("--");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        // No commits yet
+        return Ok(Vec::new());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('\x1f'))
+        .filter(|(subject, trailer)| {
+            trailer.is_empty()
+                && !is_chai_commit(subject)
+                && !subject.starts_with("Revert ")
+                && !subject.starts_with("fixup!")
+                && !subject.starts_with("squash!")
+        })
+        .map(|(subject, _)| subject.to_string())
+        .collect())
+}
+
+/// Picks the majority convention, mood, capitalization and punctuation of
+/// `subjects`.
+fn learn(subjects: &[String]) -> Option<MessageStyle> {
+    if subjects.len() < MIN_SAMPLES {
+        return None;
+    }
+
+    let mut conventional = 0;
+    let mut scoped = 0;
+    let mut bracketed = 0;
+    let mut capitalized = 0;
+    let mut period = 0;
+    let mut moods = [0; 3];
+    for subject in subjects {
+        let description = if let Some((head, description)) = subject.split_once(": ")
+            && is_conventional_type(head)
+        {
+            conventional += 1;
+            if head.contains('(') {
+                scoped += 1;
+            }
+            description
+        } else if let Some(description) = subject
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once("] "))
+            .map(|(_, description)| description)
+        {
+            bracketed += 1;
+            description
+        } else {
+            subject.as_str()
+        };
+
+        if description.starts_with(|c: char| c.is_uppercase()) {
+            capitalized += 1;
+        }
+        if description.ends_with('.') {
+            period += 1;
+        }
+        if let Some(mood) = description.split_whitespace().next().and_then(mood_of) {
+            moods[mood as usize] += 1;
+        }
+    }
+
+    let majority = |count: usize| count * 2 > subjects.len();
+    let convention = if majority(conventional) {
+        Convention::Conventional {
+            scoped: scoped * 2 > conventional,
+        }
+    } else if majority(bracketed) {
+        Convention::Bracketed
+    } else {
+        Convention::Plain
+    };
+    let mood = [Mood::Imperative, Mood::Past, Mood::ThirdPerson]
+        .into_iter()
+        .max_by_key(|mood| (moods[*mood as usize], *mood == Mood::Imperative))
+        .unwrap_or(Mood::Imperative);
+
+    Some(MessageStyle {
+        convention,
+        mood,
+        capitalized: majority(capitalized),
+        period: majority(period),
+    })
+}
+
+/// `feat`, `fix(api)` or `refactor!`: a lowercase word, optionally scoped.
+fn is_conventional_type(head: &str) -> bool {
+    let head = head.strip_suffix('!').unwrap_or(head);
+    let kind = match head.split_once('(') {
+        Some((kind, scope)) if scope.ends_with(')') => kind,
+        Some(_) => return false,
+        None => head,
+    };
+    !kind.is_empty() && kind.chars().all(|c| c.is_ascii_lowercase())
+}
+
+/// Guesses the mood of a subject's first word from its ending.
+fn mood_of(word: &str) -> Option<Mood> {
+    let word = word.to_ascii_lowercase();
+    if !word.chars().all(|c| c.is_ascii_alphabetic()) || word.len() < 3 {
+        return None;
+    }
+    Some(if word.ends_with("ed") {
+        Mood::Past
+    } else if word.ends_with('s') && !word.ends_with("ss") {
+        Mood::ThirdPerson
+    } else {
+        Mood::Imperative
+    })
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subjects(subjects: &[&str]) -> Vec<String> {
+        subjects.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_learns_and_mimics_the_project_style() {
+        let conventional = subjects(&[
+            "feat(api): add user routes",
+            "fix(api): handle empty body",
+            "docs: explain setup",
+            "feat(web): add login form",
+            "refactor(core)!: split config loading",
+            "chore(deps): bump serde",
+            "fix(web): escape titles",
+            "test(api): cover pagination",
+            "feat(cli): add --json",
+            "mod: notes.md",
+            "fix: typo in readme",
+        ]);
+        let style = learn(&conventional).unwrap();
+        assert_eq!(style.convention, Convention::Conventional { scoped: true });
+        assert_eq!(style.mood, Mood::Imperative);
+        assert!(!style.capitalized && !style.period);
+        assert_eq!(
+            style.restyle("mod: src/main.rs").as_deref(),
+            Some("chore(src): update src/main.rs")
+        );
+        assert_eq!(
+            style.restyle("rename: a.txt -> b.txt").as_deref(),
+            Some("chore: rename a.txt to b.txt")
+        );
+
+        let plain = subjects(&[
+            "Added a user page.",
+            "Fixed the login redirect.",
+            "Updated dependencies.",
+            "Removed dead code.",
+            "Improved error messages.",
+            "Added tests for the parser.",
+            "Tweaked the CI config.",
+            "Merged settings screens.",
+            "Renamed helpers.",
+            "Release 1.2",
+        ]);
+        let style = learn(&plain).unwrap();
+        assert_eq!(style.convention, Convention::Plain);
+        assert_eq!(style.mood, Mood::Past);
+        assert_eq!(
+            style.restyle("del: old/notes.md").as_deref(),
+            Some("Removed old/notes.md.")
+        );
+        assert_eq!(style.restyle("Fix typo"), None);
+
+        assert_eq!(learn(&plain[..5]), None);
+    }
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+
+/// Returns the checked-out branch, or `None` on a detached HEAD.
+pub fn get_current_branch(repo_path: &Path) -> Result<Option<String>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("symbolic-ref")
+        .arg("--quiet")
+        .arg("--short")
+        .arg("HEAD");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    // Exit code 1 without output means HEAD is detached
+    if output.status.code() == Some(1) {
+        return Ok(None);
+    }
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(Some(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+/// Copies `branch` from `repo_path` into the mirror repository, creating a bare
+/// mirror first if `mirror_path` doesn't exist. The mirror's branch is
+/// overwritten, since amended autosave commits rewrite history.
+pub fn mirror_branch(
+    repo_path: &Path,
+    mirror_path: &Path,
+    branch: &str,
+) -> Result<(), GitChaiError> {
+    if !mirror_path.exists() {
+        log::info!(
+            "Creating bare mirror repository at {}",
+            mirror_path.display()
+        );
+        std::fs::create_dir_all(mirror_path)?;
+        let mut command = git_command(mirror_path);
+        command.arg("init").arg("--bare").arg("--quiet");
+        let output = command.output().map_err(GitChaiError::IoError)?;
+        if !output.status.success() {
+            return Err(GitChaiError::git_failed(command.argv(), &output));
+        }
+    }
+
+    let refspec = format!("+refs/heads/{0}:refs/heads/{0}", branch);
+    let mut command = git_command(mirror_path);
+    command
+        .arg("fetch")
+        .arg("--quiet")
+        .arg("--no-tags")
+        .arg(repo_path)
+        .arg(&refspec);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to mirror {}: {}", branch, error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(())
+}
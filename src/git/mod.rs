@@ -1,9 +1,79 @@
+pub mod attributes;
+pub mod audit;
+pub mod command;
 pub mod commit;
+pub mod diff;
+pub mod graft;
 pub mod grouping;
+pub mod horizon;
+pub mod in_progress;
+pub mod index_flags;
+pub mod isolated;
+pub mod limits;
+pub mod log;
+pub mod merge;
+pub mod message_style;
+pub mod mirror;
+pub mod notes;
 pub mod operations;
+pub mod patch;
+pub mod rename;
+pub mod repository;
+pub mod retract;
+pub mod side_branch;
+pub mod simulate;
+pub mod snapshot;
 pub mod status;
+pub mod subject;
+pub mod symlink;
+pub mod unpushed;
+pub mod worktree;
 
-pub use commit::{create_commit_for_directory, create_commit_for_file, push_changes};
-pub use grouping::{ChangeGroup, group_changes_by_directory};
-pub use operations::{stage_directory, stage_file};
-pub use status::get_changed_files;
+pub use attributes::{
+    RENORMALIZE_COMMIT_MESSAGE, is_attributes_file, renormalized_files, stage_renormalized,
+};
+pub use command::git_command;
+pub use commit::{
+    CommitDate, CommitSettings, amend_commit, amend_commit_for_file, batch_commit_message,
+    branch_commit_message, create_batch_commit, create_commit, create_commit_for_directory,
+    create_commit_for_file, create_commit_for_link, create_commit_for_rename,
+    directory_commit_message, file_commit_message, get_head_sha, link_commit_message, push_changes,
+    rename_commit_message, short_sha,
+};
+pub use diff::diff_against_head;
+pub use graft::{checkout_branch, cherry_pick};
+pub use grouping::{
+    dedup_plan, find_opaque_directory, group_changes_by_directory, individual_groups,
+};
+pub use horizon::published_on;
+pub use in_progress::{get_git_dir, in_progress_operation};
+pub use index_flags::get_flagged_files;
+pub use isolated::commit_isolated;
+pub use log::{
+    LogCommit, get_commit_day, get_commit_times, get_commits_in_range, get_commits_since,
+    get_head_commit, get_last_commit_touching, get_local_branches, get_local_day,
+    get_unpublished_commits,
+};
+pub use merge::merge_branch_into;
+pub use message_style::learned_style;
+pub use mirror::{get_current_branch, mirror_branch};
+pub use notes::{CHECK_NOTES_REF, add_note, get_notes_since};
+pub use operations::{
+    get_staged_files, stage_case_rename, stage_deletion, stage_deletions, stage_file, stage_files,
+    stage_ignored_file, stage_rename, unstage_planned, verify_staged_files,
+};
+pub use patch::{format_patch, patch_file_name};
+pub use rename::{detect_case_renames, detect_moved_files, is_case_only_rename};
+pub use repository::{
+    bridge_kind, fetch_prune, get_promisor_remotes, get_push_url, is_jj_colocated,
+    is_partial_clone, is_shallow, unshallow,
+};
+pub use retract::retract_path;
+pub use side_branch::commit_to_branch;
+pub use simulate::StagingSimulation;
+pub use snapshot::{last_snapshot, record_snapshot, snapshot_patch};
+pub use status::{drop_index_only_changes, get_changed_files, scan_fingerprint};
+pub use subject::{SubjectRules, load_wordlist};
+pub use symlink::detect_symlinks;
+pub use unpushed::{count_unpushed_commits, get_unpushed_size, get_upstream_divergence};
+pub use worktree::commit_in_worktree;
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+
+/// Notes ref holding the outcome of `check_command` for each checked commit.
+pub const CHECK_NOTES_REF: &str = "refs/notes/chai-check";
+
+/// Attaches `message` to `sha` under `notes_ref`, replacing any existing note.
+pub fn add_note(
+    repo_path: &Path,
+    notes_ref: &str,
+    sha: &str,
+    message: &str,
+) -> Result<(), GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("notes")
+        .arg(format!("--ref={}", notes_ref))
+        .arg("add")
+        .arg("--force")
+        .arg("--message")
+        .arg(message)
+        .arg(sha);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        log::error!(
+            "Failed to add note to {}: {}",
+            sha,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(())
+}
+
+/// Notes under `notes_ref` on the commits of HEAD's history since `since`, keyed
+/// by commit SHA. Commits without a note are left out.
+pub fn get_notes_since(
+    repo_path: &Path,
+    notes_ref: &str,
+    since: &str,
+) -> Result<HashMap<String, String>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("log")
+        .arg(format!("--since={}", since))
+        .arg(format!("--notes={}", notes_ref))
+        .arg("--format=%x1e%H%x1f%N")
+        .arg("HEAD");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\x1e')
+        .filter_map(|record| record.split_once('\x1f'))
+        .filter(|(_, note)| !note.trim().is_empty())
+        .map(|(sha, note)| (sha.to_string(), note.trim().to_string()))
+        .collect())
+}
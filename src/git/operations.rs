@@ -1,59 +1,314 @@
 use std::path::Path;
-use std::process::Command;
 
 use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::git::isolated::has_head;
 
 pub fn stage_file(repo_path: &Path, filename: &str) -> Result<(), GitChaiError> {
     log::debug!("Staging file: {}", filename);
 
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .arg("add")
-        .arg(filename)
-        .output()
-        .map_err(GitChaiError::IoError)?;
+    let mut command = git_command(repo_path);
+    command.arg("add").arg("--").arg(filename);
+    let output = command.output().map_err(GitChaiError::IoError)?;
 
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
         log::error!("Failed to stage file {}: {}", filename, error_msg);
-        return Err(GitChaiError::GitCommandError {
-            command: format!("git add {}", filename),
-            stderr: error_msg.to_string(),
-            source: None,
-        });
+        return Err(GitChaiError::git_failed(command.argv(), &output));
     }
 
     log::debug!("Successfully staged file: {}", filename);
     Ok(())
 }
 
-pub fn stage_directory(repo_path: &Path, directory: &Path) -> Result<(), GitChaiError> {
-    log::debug!("Staging directory: {:?}", directory);
+/// Stages a file that is matched by `.gitignore`; only used with `--include-ignored`.
+pub fn stage_ignored_file(repo_path: &Path, filename: &str) -> Result<(), GitChaiError> {
+    log::debug!("Staging ignored file: {}", filename);
 
-    let dir_arg = if directory == Path::new(".") {
-        "."
-    } else {
-        directory.to_str().unwrap_or(".")
-    };
+    let mut command = git_command(repo_path);
+    command.arg("add").arg("--force").arg("--").arg(filename);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to stage ignored file {}: {}", filename, error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    log::debug!("Successfully staged ignored file: {}", filename);
+    Ok(())
+}
+
+/// Stages a rename. The source may already be gone from the index, when the
+/// rename was staged with `git mv`.
+pub fn stage_rename(repo_path: &Path, from: &str, to: &str) -> Result<(), GitChaiError> {
+    log::debug!("Staging rename: {} -> {}", from, to);
+
+    let mut command = git_command(repo_path);
+    command
+        .arg("rm")
+        .arg("--cached")
+        .arg("--ignore-unmatch")
+        .arg("--quiet")
+        .arg("--")
+        .arg(from);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to stage rename {} -> {}: {}", from, to, error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let mut command = git_command(repo_path);
+    command.arg("add").arg("--all").arg("--").arg(to);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to stage rename {} -> {}: {}", from, to, error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    log::debug!("Successfully staged rename: {} -> {}", from, to);
+    Ok(())
+}
+
+/// Stages a case-only rename in two steps (drop the old index entry, then add the
+/// new name), which is what `git mv` does on case-insensitive filesystems.
+pub fn stage_case_rename(repo_path: &Path, from: &str, to: &str) -> Result<(), GitChaiError> {
+    log::debug!("Staging case-only rename: {} -> {}", from, to);
+
+    let mut command = git_command(repo_path);
+    command
+        .arg("rm")
+        .arg("--cached")
+        .arg("--quiet")
+        .arg("--")
+        .arg(from);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to unstage {}: {}", from, error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    stage_file(repo_path, to)?;
+
+    log::debug!("Successfully staged case-only rename: {} -> {}", from, to);
+    Ok(())
+}
+
+/// Stages exactly `paths`, including the removal of any that were deleted.
+pub fn stage_files(repo_path: &Path, paths: &[String]) -> Result<(), GitChaiError> {
+    log::debug!("Staging {} file(s)", paths.len());
+
+    let mut command = git_command(repo_path);
+    command.arg("add").arg("--all").arg("--").args(paths);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to stage files: {}", error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    log::debug!("Successfully staged {} file(s)", paths.len());
+    Ok(())
+}
+
+/// Stages the removal of a deleted file or directory. `git add -u` only looks at
+/// tracked paths, so untracked files that have since appeared at or below `path`
+/// are left alone.
+pub fn stage_deletion(repo_path: &Path, path: &str) -> Result<(), GitChaiError> {
+    stage_deletions(repo_path, &[path.to_string()])
+}
+
+/// Stages the removal of several deleted paths at once, like [`stage_deletion`].
+pub fn stage_deletions(repo_path: &Path, paths: &[String]) -> Result<(), GitChaiError> {
+    log::debug!("Staging deletion: {}", paths.join(", "));
 
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .arg("add")
-        .arg("--all")
-        .arg(dir_arg)
-        .output()
-        .map_err(GitChaiError::IoError)?;
+    let mut command = git_command(repo_path);
+    command.arg("add").arg("--update").arg("--").args(paths);
+    let output = command.output().map_err(GitChaiError::IoError)?;
 
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
-        log::error!("Failed to stage directory {:?}: {}", directory, error_msg);
-        return Err(GitChaiError::GitCommandError {
-            command: format!("git add --all {}", dir_arg),
-            stderr: error_msg.to_string(),
-            source: None,
-        });
+        log::error!(
+            "Failed to stage deletion of {}: {}",
+            paths.join(", "),
+            error_msg
+        );
+        return Err(GitChaiError::git_failed(command.argv(), &output));
     }
 
-    log::debug!("Successfully staged directory: {:?}", directory);
+    log::debug!("Successfully staged deletion: {}", paths.join(", "));
     Ok(())
 }
+
+pub fn get_staged_files(repo_path: &Path) -> Result<Vec<String>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("diff")
+        .arg("--cached")
+        .arg("--name-only")
+        .arg("-z");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to list staged files: {}", error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| path.to_string())
+        .collect())
+}
+
+/// Checks that the index holds exactly the planned paths, so a commit never
+/// picks up changes staged by another process or before planning. Planned
+/// entries ending in `/` (untracked directories) match everything below them.
+/// Returns the staged paths that weren't planned as the error.
+pub fn verify_staged_files(repo_path: &Path, planned: &[String]) -> Result<(), GitChaiError> {
+    let staged = get_staged_files(repo_path)?;
+
+    let unexpected: Vec<&String> = staged
+        .iter()
+        .filter(|path| !is_planned_path(path, planned))
+        .collect();
+
+    if !unexpected.is_empty() {
+        return Err(GitChaiError::IndexMismatchError(format!(
+            "unexpected staged paths: {:?}",
+            unexpected
+        )));
+    }
+
+    Ok(())
+}
+
+fn is_planned_path(path: &str, planned: &[String]) -> bool {
+    planned.iter().any(|planned_path| {
+        path == planned_path
+            || (planned_path.ends_with('/')
+                && planned_path != "./"
+                && path.starts_with(planned_path.as_str()))
+    })
+}
+
+/// Resets the index entries of `paths` to HEAD (or drops them before the first
+/// commit), leaving the working tree alone.
+pub fn unstage_paths(repo_path: &Path, paths: &[String]) -> Result<(), GitChaiError> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    log::debug!("Unstaging {} path(s)", paths.len());
+
+    let mut command = git_command(repo_path);
+    if has_head(repo_path) {
+        command.arg("reset").arg("--quiet");
+    } else {
+        command
+            .arg("rm")
+            .arg("--cached")
+            .arg("-r")
+            .arg("--quiet")
+            .arg("--ignore-unmatch");
+    }
+    command.arg("--").args(paths);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to unstage paths: {}", error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    Ok(())
+}
+
+/// Unstages what staging `planned` added to the index: the staged paths it
+/// covers, except those already staged in `before`. Anything else staged is
+/// left alone.
+pub fn unstage_planned(
+    repo_path: &Path,
+    planned: &[String],
+    before: &[String],
+) -> Result<(), GitChaiError> {
+    let added: Vec<String> = get_staged_files(repo_path)?
+        .into_iter()
+        .filter(|path| is_planned_path(path, planned) && !before.contains(path))
+        .collect();
+    unstage_paths(repo_path, &added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_staging_treats_dash_names_as_paths() {
+        let repo = TempRepo::new("dash");
+
+        // Without `--` these would be read as `git add -n` (dry run) and `--all`
+        for name in ["-n", "--all", "-v/notes.md"] {
+            repo.write(name, "x");
+        }
+        stage_file(&repo, "-n").unwrap();
+        stage_file(&repo, "--all").unwrap();
+        stage_files(&repo, &["-v/".to_string()]).unwrap();
+
+        let output = git_command(&repo)
+            .arg("ls-files")
+            .arg("-z")
+            .output()
+            .unwrap();
+        let mut staged: Vec<String> = String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect();
+        staged.sort();
+        assert_eq!(staged, vec!["--all", "-n", "-v/notes.md"]);
+    }
+
+    #[test]
+    fn test_stage_deletion_leaves_untracked_siblings() {
+        let repo = TempRepo::new("deletion");
+        repo.write("old/a.txt", "x");
+        stage_file(&repo, "old/a.txt").unwrap();
+        std::fs::remove_file(repo.join("old/a.txt")).unwrap();
+        repo.write("old/new.txt", "x");
+
+        stage_deletion(&repo, "old/").unwrap();
+
+        assert_eq!(repo.git(&["ls-files"]), "");
+    }
+
+    #[test]
+    fn test_verify_staged_files_rejects_anything_unplanned() {
+        let repo = TempRepo::new("verify-staged");
+        repo.write("a.txt", "a");
+        repo.write("b.txt", "b");
+        repo.write("new/c.txt", "c");
+        stage_file(&repo, "a.txt").unwrap();
+        stage_file(&repo, "new/").unwrap();
+        let planned = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        assert!(verify_staged_files(&repo, &planned(&["a.txt", "new/"])).is_ok());
+        assert!(verify_staged_files(&repo, &planned(&["a.txt"])).is_err());
+        assert!(verify_staged_files(&repo, &planned(&["./"])).is_err());
+
+        unstage_paths(&repo, &planned(&["new/c.txt"])).unwrap();
+        assert_eq!(get_staged_files(&repo).unwrap(), ["a.txt"]);
+
+        stage_file(&repo, "b.txt").unwrap();
+        stage_file(&repo, "new/").unwrap();
+        unstage_planned(&repo, &planned(&["a.txt", "new/"]), &planned(&["a.txt"])).unwrap();
+        assert_eq!(get_staged_files(&repo).unwrap(), ["a.txt", "b.txt"]);
+    }
+}
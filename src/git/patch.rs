@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+
+/// Formats `sha` as a mailable patch (`git format-patch`), numbered as patch
+/// `number` of `total` in the subject unless it is the only one.
+pub fn format_patch(
+    repo_path: &Path,
+    sha: &str,
+    number: usize,
+    total: usize,
+) -> Result<String, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("format-patch")
+        .arg("-1")
+        .arg("--stdout")
+        .arg("--no-color")
+        .arg(sha);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let patch = String::from_utf8_lossy(&output.stdout).to_string();
+    if total <= 1 {
+        return Ok(patch);
+    }
+    // Each commit is formatted on its own, so format-patch can't number them
+    Ok(patch.replacen(
+        "\nSubject: [PATCH] ",
+        &format!("\nSubject: [PATCH {}/{}] ", number, total),
+        1,
+    ))
+}
+
+/// File name `git format-patch -o` would give patch `number` with `subject`.
+pub fn patch_file_name(number: usize, subject: &str) -> String {
+    let mut slug = String::new();
+    for c in subject.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.trim_matches(['-', '.']).chars().take(52).collect();
+    format!("{:04}-{}.patch", number, slug.trim_end_matches(['-', '.']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patch_file_name() {
+        assert_eq!(
+            patch_file_name(1, "mod: src/main.rs"),
+            "0001-mod-src-main.rs.patch"
+        );
+        assert_eq!(
+            patch_file_name(12, "rename: a.txt -> b.txt"),
+            "0012-rename-a.txt-b.txt.patch"
+        );
+    }
+}
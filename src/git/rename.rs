@@ -0,0 +1,438 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::git::isolated::{TempIndex, has_head};
+use crate::git::status::{GitChange, untracked_files};
+use crate::types::{ChangeType, GitStatus};
+
+/// Pairs deletions with new files that git considers renames of them (e.g. a
+/// file moved between directories), so they are committed as one rename.
+///
+/// New files are untracked ones, including those inside an untracked directory
+/// that status reports as a single `dir/` entry, and files staged as added. The
+/// untracked ones are marked intent-to-add in a throwaway copy of the index and
+/// everything is compared against HEAD with `git diff -M`, leaving the real
+/// index untouched.
+pub fn detect_moved_files(
+    repo_path: &Path,
+    changes: &[GitChange],
+) -> Result<Vec<GitChange>, GitChaiError> {
+    let has_deletions = changes.iter().any(|c| c.change_type == ChangeType::Delete);
+    let has_new_files = changes
+        .iter()
+        .any(|c| matches!(c.status, GitStatus::Untracked | GitStatus::AddedStaged));
+    if !has_deletions || !has_new_files || !has_head(repo_path) {
+        return Ok(changes.to_vec());
+    }
+
+    let mut untracked: Vec<String> = Vec::new();
+    let mut untracked_dirs: HashMap<String, Vec<String>> = HashMap::new();
+    for change in changes.iter().filter(|c| c.status == GitStatus::Untracked) {
+        if change.filename.ends_with('/') {
+            let files = untracked_files(repo_path, &change.filename)?;
+            untracked.extend(files.iter().cloned());
+            untracked_dirs.insert(change.filename.clone(), files);
+        } else {
+            untracked.push(change.filename.clone());
+        }
+    }
+
+    log::debug!("Checking {} untracked files for moves", untracked.len());
+
+    let temp_index = TempIndex::copy_of_index(repo_path)?;
+
+    if !untracked.is_empty() {
+        let mut command = git_command(repo_path);
+        command
+            .env("GIT_INDEX_FILE", &temp_index.path)
+            .arg("add")
+            .arg("--intent-to-add")
+            .arg("--")
+            .args(&untracked);
+        let output = command.output().map_err(GitChaiError::IoError)?;
+
+        if !output.status.success() {
+            return Err(GitChaiError::git_failed(command.argv(), &output));
+        }
+    }
+
+    let mut command = git_command(repo_path);
+    command
+        .env("GIT_INDEX_FILE", &temp_index.path)
+        .arg("diff")
+        .arg("HEAD")
+        .arg("-M")
+        .arg("--name-status")
+        .arg("-z");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let diff_output = String::from_utf8_lossy(&output.stdout);
+    let pairs = parse_renames(&diff_output);
+
+    Ok(apply_renames(changes, &pairs, &untracked_dirs))
+}
+
+/// Detects renames that only change the case of a file name.
+///
+/// On case-sensitive filesystems these show up as a deletion plus an untracked file
+/// and are paired directly. With `core.ignorecase` set, git usually reports only
+/// one side, or nothing at all, so the names of the reported paths are compared
+/// against the names actually on disk and in the index.
+pub fn detect_case_renames(
+    repo_path: &Path,
+    changes: &[GitChange],
+) -> Result<Vec<GitChange>, GitChaiError> {
+    let mut pairs: Vec<(String, String)> = Vec::new();
+
+    for deleted in changes
+        .iter()
+        .filter(|c| c.change_type == ChangeType::Delete)
+    {
+        if let Some(untracked) = changes.iter().find(|c| {
+            c.status == GitStatus::Untracked
+                && c.filename != deleted.filename
+                && c.filename.to_lowercase() == deleted.filename.to_lowercase()
+        }) {
+            pairs.push((deleted.filename.clone(), untracked.filename.clone()));
+        }
+    }
+
+    let mut result = apply_renames(changes, &pairs, &HashMap::new());
+
+    if is_ignorecase(repo_path) {
+        for (old, new) in find_case_mismatches(repo_path, &result)? {
+            if result.iter().any(|c| c.renamed_from.as_ref() == Some(&old)) {
+                continue;
+            }
+            // git may still report the old name as deleted; the rename replaces it
+            result.retain(|c| c.filename != old && c.filename != new);
+            log::debug!("Detected case-only rename: {} -> {}", old, new);
+            result.push(GitChange {
+                status: GitStatus::Renamed,
+                change_type: ChangeType::Rename,
+                filename: new,
+                renamed_from: Some(old),
+                symlink_target: None,
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+/// Returns true if a rename only changes the case of the path.
+pub fn is_case_only_rename(from: &str, to: &str) -> bool {
+    from != to && from.to_lowercase() == to.to_lowercase()
+}
+
+fn is_ignorecase(repo_path: &Path) -> bool {
+    git_command(repo_path)
+        .arg("config")
+        .arg("--bool")
+        .arg("core.ignorecase")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "true")
+}
+
+/// Pairs the deletions and untracked files among `changes` with the name they
+/// have on disk or in the index if it differs only by case: a deleted file
+/// whose directory holds a case variant of it, or an untracked file whose case
+/// variant is tracked but gone from disk. Only the reported paths are checked.
+fn find_case_mismatches(
+    repo_path: &Path,
+    changes: &[GitChange],
+) -> Result<Vec<(String, String)>, GitChaiError> {
+    let mut dir_entries: HashMap<PathBuf, Vec<String>> = HashMap::new();
+    let mut entries_of = |path: &str| -> Option<(PathBuf, String, Vec<String>)> {
+        let path = Path::new(path);
+        let (parent, name) = (path.parent()?, path.file_name()?.to_str()?);
+        let entries = dir_entries.entry(parent.to_path_buf()).or_insert_with(|| {
+            std::fs::read_dir(repo_path.join(parent))
+                .map(|entries| {
+                    entries
+                        .filter_map(|e| e.ok())
+                        .filter_map(|e| e.file_name().into_string().ok())
+                        .collect()
+                })
+                .unwrap_or_default()
+        });
+        Some((parent.to_path_buf(), name.to_string(), entries.clone()))
+    };
+    let mut mismatches = Vec::new();
+
+    for deleted in changes
+        .iter()
+        .filter(|c| c.change_type == ChangeType::Delete && !c.filename.ends_with('/'))
+    {
+        let Some((parent, name, entries)) = entries_of(&deleted.filename) else {
+            continue;
+        };
+        if entries.contains(&name) {
+            continue;
+        }
+        if let Some(actual) = entries
+            .iter()
+            .find(|entry| is_case_only_rename(&name, entry))
+        {
+            let new_path = parent.join(actual).to_string_lossy().to_string();
+            mismatches.push((deleted.filename.clone(), new_path));
+        }
+    }
+
+    let untracked: Vec<String> = changes
+        .iter()
+        .filter(|c| c.status == GitStatus::Untracked && !c.filename.ends_with('/'))
+        .map(|c| format!(":(icase,literal){}", c.filename))
+        .collect();
+    if untracked.is_empty() {
+        return Ok(mismatches);
+    }
+
+    let mut command = git_command(repo_path);
+    command.arg("ls-files").arg("-z").arg("--").args(&untracked);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let tracked = String::from_utf8_lossy(&output.stdout);
+    for old in tracked.split('\0').filter(|p| !p.is_empty()) {
+        let Some(new) = changes
+            .iter()
+            .find(|c| c.status == GitStatus::Untracked && is_case_only_rename(old, &c.filename))
+        else {
+            continue;
+        };
+        let Some((_, name, entries)) = entries_of(old) else {
+            continue;
+        };
+        if !entries.contains(&name) && !mismatches.iter().any(|(from, _)| from == old) {
+            mismatches.push((old.to_string(), new.filename.clone()));
+        }
+    }
+
+    Ok(mismatches)
+}
+
+/// Extracts `(old, new)` pairs from `git diff --name-status -z` output.
+fn parse_renames(output: &str) -> Vec<(String, String)> {
+    let mut fields = output.split('\0').filter(|f| !f.is_empty());
+    let mut pairs = Vec::new();
+
+    while let Some(status) = fields.next() {
+        if status.starts_with('R') || status.starts_with('C') {
+            let (Some(old), Some(new)) = (fields.next(), fields.next()) else {
+                break;
+            };
+            if status.starts_with('R') {
+                pairs.push((old.to_string(), new.to_string()));
+            }
+        } else {
+            fields.next();
+        }
+    }
+
+    pairs
+}
+
+/// Replaces each matched pair of a deletion and a new file with a single
+/// rename change. `untracked_dirs` lists the files of untracked `dir/` entries;
+/// a directory holding a renamed file is replaced by its other files, so the
+/// file isn't committed again as part of it.
+fn apply_renames(
+    changes: &[GitChange],
+    pairs: &[(String, String)],
+    untracked_dirs: &HashMap<String, Vec<String>>,
+) -> Vec<GitChange> {
+    let is_deleted = |path: &str| {
+        changes
+            .iter()
+            .any(|c| c.filename == path && c.change_type == ChangeType::Delete)
+    };
+    let is_new = |path: &str| {
+        changes.iter().any(|c| {
+            c.filename == path && matches!(c.status, GitStatus::Untracked | GitStatus::AddedStaged)
+        }) || untracked_dirs
+            .values()
+            .any(|files| files.iter().any(|file| file == path))
+    };
+
+    let matched: Vec<&(String, String)> = pairs
+        .iter()
+        .filter(|(old, new)| is_deleted(old) && is_new(new))
+        .collect();
+
+    if matched.is_empty() {
+        return changes.to_vec();
+    }
+
+    let is_matched = |path: &str| matched.iter().any(|(old, new)| path == old || path == new);
+    let mut result: Vec<GitChange> = Vec::new();
+    for change in changes {
+        if is_matched(&change.filename) {
+            continue;
+        }
+        match untracked_dirs.get(&change.filename) {
+            Some(files) if files.iter().any(|file| is_matched(file)) => {
+                result.extend(files.iter().filter(|file| !is_matched(file)).map(|file| {
+                    GitChange {
+                        status: GitStatus::Untracked,
+                        change_type: ChangeType::Add,
+                        filename: file.clone(),
+                        renamed_from: None,
+                        symlink_target: None,
+                    }
+                }));
+            }
+            _ => result.push(change.clone()),
+        }
+    }
+
+    for (old, new) in matched {
+        log::debug!("Detected move: {} -> {}", old, new);
+        result.push(GitChange {
+            status: GitStatus::Renamed,
+            change_type: ChangeType::Rename,
+            filename: new.clone(),
+            renamed_from: Some(old.clone()),
+            symlink_target: None,
+        });
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TempRepo;
+
+    fn change(status: GitStatus, filename: &str) -> GitChange {
+        GitChange {
+            change_type: ChangeType::from(status.clone()),
+            status,
+            filename: filename.to_string(),
+            renamed_from: None,
+            symlink_target: None,
+        }
+    }
+
+    #[test]
+    fn test_case_rename_pairs_deletion_with_untracked() {
+        let repo = TempRepo::new("case-rename");
+        repo.git(&["config", "core.ignorecase", "false"]);
+        let changes = vec![
+            change(GitStatus::DeletedUnstaged, "docs/Readme.md"),
+            change(GitStatus::Untracked, "docs/README.md"),
+        ];
+
+        let result = detect_case_renames(&repo, &changes).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].filename, "docs/README.md");
+        assert_eq!(result[0].renamed_from.as_deref(), Some("docs/Readme.md"));
+        assert!(is_case_only_rename("docs/Readme.md", "docs/README.md"));
+        assert!(!is_case_only_rename("a.md", "b.md"));
+    }
+
+    #[test]
+    fn test_ignorecase_rename_found_from_one_reported_side() {
+        let repo = TempRepo::new("ignorecase");
+        repo.write("docs/Readme.md", "hi\n");
+        repo.write("docs/Guide.md", "guide\n");
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "-q", "-m", "init"]);
+        repo.git(&["config", "core.ignorecase", "true"]);
+        std::fs::rename(repo.join("docs/Readme.md"), repo.join("docs/README.md")).unwrap();
+        std::fs::rename(repo.join("docs/Guide.md"), repo.join("docs/guide.md")).unwrap();
+
+        // The deletion alone, as git reports it with core.ignorecase on a
+        // case-sensitive filesystem, and the untracked name alone
+        for changes in [
+            vec![change(GitStatus::DeletedUnstaged, "docs/Readme.md")],
+            vec![change(GitStatus::Untracked, "docs/README.md")],
+        ] {
+            let result = detect_case_renames(&repo, &changes).unwrap();
+            assert_eq!(result.len(), 1);
+            assert_eq!(result[0].filename, "docs/README.md");
+            assert_eq!(result[0].renamed_from.as_deref(), Some("docs/Readme.md"));
+        }
+
+        // Paths git didn't report are left alone
+        assert!(detect_case_renames(&repo, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_moves_into_new_directories_and_staged_adds_are_paired() {
+        let repo = TempRepo::new("moves");
+        repo.write("notes/trip.md", "Packing list\n- tea\n- kettle\n");
+        repo.write("todo.txt", "buy more tea\nand cups\n");
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "-q", "-m", "init"]);
+        std::fs::rename(repo.join("notes/trip.md"), repo.join("fresh.md")).unwrap();
+        std::fs::create_dir_all(repo.join("archive/2024")).unwrap();
+        std::fs::rename(repo.join("fresh.md"), repo.join("archive/2024/trip.md")).unwrap();
+        repo.write("archive/2024/other.md", "unrelated\n");
+        std::fs::rename(repo.join("todo.txt"), repo.join("done.txt")).unwrap();
+        repo.git(&["add", "done.txt"]);
+
+        let changes = crate::git::get_changed_files(&repo, false).unwrap();
+        let mut result: Vec<_> = detect_moved_files(&repo, &changes)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.change_type, c.renamed_from, c.filename))
+            .collect();
+        result.sort_by(|a, b| a.2.cmp(&b.2));
+        assert_eq!(
+            result,
+            [
+                (ChangeType::Add, None, "archive/2024/other.md".to_string()),
+                (
+                    ChangeType::Rename,
+                    Some("notes/trip.md".to_string()),
+                    "archive/2024/trip.md".to_string()
+                ),
+                (
+                    ChangeType::Rename,
+                    Some("todo.txt".to_string()),
+                    "done.txt".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_renames() {
+        let output = "M\0src/lib.rs\0R097\0a/f.txt\0b/g.txt\0C100\0x\0y\0A\0new.txt\0";
+        assert_eq!(
+            parse_renames(output),
+            vec![("a/f.txt".to_string(), "b/g.txt".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_apply_renames_pairs_deletion_with_untracked() {
+        let changes = vec![
+            change(GitStatus::DeletedUnstaged, "a/f.txt"),
+            change(GitStatus::Untracked, "b/g.txt"),
+            change(GitStatus::ModifiedUnstaged, "c.txt"),
+        ];
+        let pairs = vec![
+            ("a/f.txt".to_string(), "b/g.txt".to_string()),
+            ("c.txt".to_string(), "d.txt".to_string()),
+        ];
+
+        let result = apply_renames(&changes, &pairs, &HashMap::new());
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].filename, "c.txt");
+        assert_eq!(result[1].filename, "b/g.txt");
+        assert_eq!(result[1].renamed_from.as_deref(), Some("a/f.txt"));
+        assert_eq!(result[1].change_type, ChangeType::Rename);
+    }
+}
@@ -0,0 +1,199 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+
+/// Returns the names of promisor remotes, i.e. the remotes a partial clone lazily
+/// fetches missing objects from. Empty for a regular clone.
+pub fn get_promisor_remotes(repo_path: &Path) -> Vec<String> {
+    let output = git_command(repo_path)
+        .arg("config")
+        .arg("--get-regexp")
+        .arg(r"^remote\..*\.promisor$")
+        .output();
+
+    let mut remotes = output
+        .map(|output| parse_promisor_remotes(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default();
+
+    if remotes.is_empty()
+        && let Ok(output) = git_command(repo_path)
+            .arg("config")
+            .arg("--get")
+            .arg("extensions.partialclone")
+            .output()
+        && output.status.success()
+    {
+        remotes.push(String::from_utf8_lossy(&output.stdout).trim().to_string());
+    }
+
+    remotes
+}
+
+/// Returns true if the repository is a partial clone. Operations that compare blob
+/// contents (such as rename detection) would trigger on-demand fetches there.
+pub fn is_partial_clone(repo_path: &Path) -> bool {
+    !get_promisor_remotes(repo_path).is_empty()
+}
+
+/// Returns true if a Jujutsu (`jj`) repository is colocated with this one,
+/// sharing its working copy.
+pub fn is_jj_colocated(repo_path: &Path) -> bool {
+    repo_path.join(".jj").is_dir()
+}
+
+/// A bridge that maps this git repository onto another version control system.
+/// Pushing HEAD to a git remote is wrong there: changes have to go back through
+/// the bridge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bridge {
+    /// `git svn`, with `svn-remote.*` in the config
+    GitSvn,
+    /// git-remote-hg or git-cinnabar, with an `hg::` remote URL or their config keys
+    Hg,
+}
+
+impl Bridge {
+    /// How changes are meant to leave a repository behind this bridge.
+    pub fn push_hint(self) -> &'static str {
+        match self {
+            Bridge::GitSvn => "run `git svn dcommit` to send the commits to Subversion",
+            Bridge::Hg => {
+                "push to the hg:: remote through the bridge (e.g. `git push <hg-remote>`)"
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Bridge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Bridge::GitSvn => write!(f, "git-svn"),
+            Bridge::Hg => write!(f, "hg bridge"),
+        }
+    }
+}
+
+/// Returns the bridge to another VCS this repository is set up with, if any.
+pub fn bridge_kind(repo_path: &Path) -> Option<Bridge> {
+    let output = git_command(repo_path)
+        .arg("config")
+        .arg("--get-regexp")
+        .arg(r"^(svn-remote\..*|remote-hg\..*|cinnabar\..*|remote\..*\.url)$")
+        .output()
+        .ok()?;
+    parse_bridge(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Returns true if the repository is a shallow clone, from which pushes can be
+/// rejected because the remote needs history that is missing locally.
+pub fn is_shallow(repo_path: &Path) -> bool {
+    git_command(repo_path)
+        .arg("rev-parse")
+        .arg("--is-shallow-repository")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "true")
+}
+
+/// Fetches the full history from `origin` so that a shallow clone can push.
+pub fn unshallow(repo_path: &Path) -> Result<(), GitChaiError> {
+    log::debug!("Fetching full history to unshallow the repository");
+
+    let mut command = git_command(repo_path);
+    command.arg("fetch").arg("--unshallow").arg("origin");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to unshallow repository: {}", error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(())
+}
+
+/// Fetches from the default remote, pruning remote-tracking branches that no
+/// longer exist there.
+pub fn fetch_prune(repo_path: &Path) -> Result<(), GitChaiError> {
+    let mut command = git_command(repo_path);
+    command.arg("fetch").arg("--prune").arg("--quiet");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!("Failed to fetch: {}", error_msg);
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(())
+}
+
+/// Returns the URL that `git push` uses for `remote`.
+pub fn get_push_url(repo_path: &Path, remote: &str) -> Result<String, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("remote")
+        .arg("get-url")
+        .arg("--push")
+        .arg(remote);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn parse_bridge(config: &str) -> Option<Bridge> {
+    let mut bridge = None;
+    for line in config.lines() {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        if key.starts_with("svn-remote.") {
+            return Some(Bridge::GitSvn);
+        }
+        if key.starts_with("remote-hg.")
+            || key.starts_with("cinnabar.")
+            || (key.ends_with(".url") && value.starts_with("hg::"))
+        {
+            bridge = Some(Bridge::Hg);
+        }
+    }
+    bridge
+}
+
+fn parse_promisor_remotes(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(' ')?;
+            let name = key.strip_prefix("remote.")?.strip_suffix(".promisor")?;
+            (value.trim() == "true").then(|| name.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_promisor_remotes() {
+        let output = "remote.origin.promisor true\nremote.fork.promisor false\nremote.up.stream.promisor true\n";
+        assert_eq!(
+            parse_promisor_remotes(output),
+            vec!["origin".to_string(), "up.stream".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_bridge() {
+        let svn = "svn-remote.svn.url https://svn.example.com/repo\nsvn-remote.svn.fetch trunk:refs/remotes/origin/trunk\n";
+        assert_eq!(parse_bridge(svn), Some(Bridge::GitSvn));
+        let hg = "remote.origin.url hg::https://hg.example.com/repo\n";
+        assert_eq!(parse_bridge(hg), Some(Bridge::Hg));
+        assert_eq!(parse_bridge("cinnabar.check traceback\n"), Some(Bridge::Hg));
+        let plain = "remote.origin.url https://example.com/repo.git\n";
+        assert_eq!(parse_bridge(plain), None);
+    }
+}
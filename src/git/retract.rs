@@ -0,0 +1,217 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::git::horizon::ensure_rewritable;
+use crate::git::isolated::{TempIndex, run_git, stdout_of};
+
+/// git's empty tree, which a root commit with nothing left in it would have.
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// What [`retract_path`] did to the branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retraction {
+    pub new_tip: String,
+    /// Commits recreated without the path
+    pub rewritten: usize,
+    /// Commits that touched nothing but the path and were left out
+    pub dropped: usize,
+}
+
+/// Recreates `commits` (oldest first, the last being the tip of `branch`) with
+/// `path` kept as it was in the parent of the first one, and moves `branch` to
+/// the result. Commits left empty are dropped. Messages, authors and author
+/// dates are kept. Only the user's index entry for `path` is updated, so the
+/// file itself stays in the working tree, now uncommitted.
+///
+/// The commits must be a linear stretch of history in front of the rewrite
+/// horizon; merges and published commits are refused.
+pub fn retract_path(
+    repo_path: &Path,
+    branch: &str,
+    path: &str,
+    commits: &[String],
+) -> Result<Retraction, GitChaiError> {
+    let reference = format!("refs/heads/{}", branch);
+    let (Some(first), Some(old_tip)) = (commits.first(), commits.last()) else {
+        return Err(GitChaiError::git_rejected(
+            &["git", "update-ref", &reference],
+            "no commits to rewrite",
+        ));
+    };
+    if let Some(merge) = commits
+        .iter()
+        .find(|commit| matches!(rev_parse(repo_path, &format!("{}^2", commit)), Ok(Some(_))))
+    {
+        return Err(GitChaiError::git_rejected(
+            &["git", "update-ref", &reference],
+            format!("{} is a merge; only linear history can be rewritten", merge),
+        ));
+    }
+    ensure_rewritable(repo_path, &[first])?;
+    let base = rev_parse(repo_path, &format!("{}^", first))?;
+
+    let index = TempIndex::new();
+    let mut parent = base.clone();
+    let mut rewritten = 0;
+    let mut dropped = 0;
+    for commit in commits {
+        run_git(repo_path, Some(&index), &["read-tree", commit])?;
+        match &base {
+            Some(base) => run_git(repo_path, Some(&index), &["reset", "-q", base, "--", path])?,
+            None => run_git(
+                repo_path,
+                Some(&index),
+                &[
+                    "rm",
+                    "--cached",
+                    "-r",
+                    "--quiet",
+                    "--ignore-unmatch",
+                    "--",
+                    path,
+                ],
+            )?,
+        };
+        let tree = stdout_of(run_git(repo_path, Some(&index), &["write-tree"])?);
+
+        let parent_tree = match &parent {
+            Some(parent) => stdout_of(run_git(
+                repo_path,
+                None,
+                &["rev-parse", &format!("{}^{{tree}}", parent)],
+            )?),
+            None => EMPTY_TREE.to_string(),
+        };
+        if tree == parent_tree {
+            log::debug!("Dropping {}, which only touched {}", commit, path);
+            dropped += 1;
+            continue;
+        }
+
+        parent = Some(recommit(repo_path, commit, &tree, parent.as_deref())?);
+        rewritten += 1;
+    }
+
+    let Some(new_tip) = parent else {
+        return Err(GitChaiError::git_rejected(
+            &["git", "update-ref", &reference],
+            format!("removing {} would leave {} without commits", path, branch),
+        ));
+    };
+    let reflog_message = format!("git-chai retract: {}", path);
+    run_git(
+        repo_path,
+        None,
+        &[
+            "update-ref",
+            "-m",
+            &reflog_message,
+            &reference,
+            &new_tip,
+            old_tip,
+        ],
+    )?;
+    run_git(repo_path, None, &["reset", "-q", "HEAD", "--", path])?;
+
+    Ok(Retraction {
+        new_tip,
+        rewritten,
+        dropped,
+    })
+}
+
+/// Commits `tree` with the message and author of `original`.
+fn recommit(
+    repo_path: &Path,
+    original: &str,
+    tree: &str,
+    parent: Option<&str>,
+) -> Result<String, GitChaiError> {
+    let info = stdout_of(run_git(
+        repo_path,
+        None,
+        &[
+            "log",
+            "-1",
+            "--date=raw",
+            "--format=%an%x00%ae%x00%ad%x00%B",
+            original,
+        ],
+    )?);
+    let mut fields = info.splitn(4, '\0');
+    let (Some(name), Some(email), Some(date), Some(message)) =
+        (fields.next(), fields.next(), fields.next(), fields.next())
+    else {
+        return Err(GitChaiError::ParseError(format!(
+            "Unexpected commit info for {}: {:?}",
+            original, info
+        )));
+    };
+
+    let mut command = git_command(repo_path);
+    command
+        .arg("commit-tree")
+        .arg(tree)
+        .arg("-m")
+        .arg(message)
+        .env("GIT_AUTHOR_NAME", name)
+        .env("GIT_AUTHOR_EMAIL", email)
+        .env("GIT_AUTHOR_DATE", date);
+    if let Some(parent) = parent {
+        command.arg("-p").arg(parent);
+    }
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    Ok(stdout_of(output))
+}
+
+fn rev_parse(repo_path: &Path, rev: &str) -> Result<Option<String>, GitChaiError> {
+    let output = git_command(repo_path)
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg(rev)
+        .output()
+        .map_err(GitChaiError::IoError)?;
+    Ok(output.status.success().then(|| stdout_of(output)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_retract_path_drops_and_rewrites_commits() {
+        let repo = TempRepo::new("retract");
+        let git = |args: &[&str]| repo.git(args);
+        repo.write("a.txt", "a");
+        git(&["add", "a.txt"]);
+        git(&["commit", "--quiet", "-m", "init"]);
+
+        repo.write(".env", "TOKEN=1");
+        git(&["add", ".env"]);
+        git(&["commit", "--quiet", "-m", "add: .env"]);
+        repo.write(".env", "TOKEN=2");
+        repo.write("a.txt", "b");
+        git(&["add", ".env", "a.txt"]);
+        git(&["commit", "--quiet", "-m", "mod: a.txt"]);
+
+        let commits: Vec<String> = git(&["rev-list", "--reverse", "HEAD~2..HEAD"])
+            .lines()
+            .map(str::to_string)
+            .collect();
+        let retraction = retract_path(&repo, "main", ".env", &commits).unwrap();
+        assert_eq!((retraction.rewritten, retraction.dropped), (1, 1));
+        assert_eq!(git(&["log", "--format=%s"]), "mod: a.txt\ninit");
+        assert_eq!(git(&["ls-tree", "--name-only", "HEAD"]), "a.txt");
+        assert_eq!(git(&["status", "--porcelain"]), "?? .env");
+        assert_eq!(
+            std::fs::read_to_string(repo.join(".env")).unwrap(),
+            "TOKEN=2"
+        );
+    }
+}
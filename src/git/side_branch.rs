@@ -0,0 +1,151 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::commit::{CommitSettings, apply_commit_author, apply_commit_date, with_suffix};
+use crate::git::git_command;
+use crate::git::isolated::{TempIndex, has_head, run_git, stdout_of};
+
+/// Commits the working-tree state of `paths` (after dropping `removals`) onto
+/// `branch` without checking it out, for the `separate-branch` path policy. The
+/// branch is created from HEAD if it does not exist yet. Neither HEAD, the
+/// user's index nor the working tree is touched, so the paths stay modified on
+/// the checked-out branch.
+///
+/// Returns `None` if the branch already has this content.
+pub fn commit_to_branch(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    branch: &str,
+    paths: &[String],
+    removals: &[String],
+    message: &str,
+) -> Result<Option<String>, GitChaiError> {
+    let reference = format!("refs/heads/{}", branch);
+    let tip = branch_tip(repo_path, &reference)?;
+    let base = match &tip {
+        Some(tip) => Some(tip.clone()),
+        None if has_head(repo_path) => {
+            Some(stdout_of(run_git(repo_path, None, &["rev-parse", "HEAD"])?))
+        }
+        None => None,
+    };
+
+    let index = TempIndex::new();
+    if let Some(base) = &base {
+        run_git(repo_path, Some(&index), &["read-tree", base])?;
+    }
+    if !removals.is_empty() {
+        let mut args = vec!["rm", "--cached", "-r", "--quiet", "--ignore-unmatch", "--"];
+        args.extend(removals.iter().map(String::as_str));
+        run_git(repo_path, Some(&index), &args)?;
+    }
+    if !paths.is_empty() {
+        let mut args = vec!["add", "--all", "--"];
+        args.extend(paths.iter().map(String::as_str));
+        run_git(repo_path, Some(&index), &args)?;
+    }
+
+    let tree = stdout_of(run_git(repo_path, Some(&index), &["write-tree"])?);
+    if let Some(base) = &base
+        && stdout_of(run_git(
+            repo_path,
+            None,
+            &["rev-parse", &format!("{}^{{tree}}", base)],
+        )?) == tree
+    {
+        return Ok(None);
+    }
+
+    let message = with_suffix(settings, message);
+    let mut command = git_command(repo_path);
+    command
+        .arg("commit-tree")
+        .arg(&tree)
+        .arg("-m")
+        .arg(&message);
+    if let Some(base) = &base {
+        command.arg("-p").arg(base);
+    }
+    apply_commit_date(settings, &mut command);
+    apply_commit_author(settings, &mut command);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    let commit = stdout_of(output);
+
+    let reflog_message = format!("git-chai: {}", message);
+    run_git(
+        repo_path,
+        None,
+        &[
+            "update-ref",
+            "-m",
+            &reflog_message,
+            &reference,
+            &commit,
+            tip.as_deref().unwrap_or(""),
+        ],
+    )?;
+
+    log::debug!("Created commit {} on {}", commit, branch);
+    Ok(Some(commit))
+}
+
+fn branch_tip(repo_path: &Path, reference: &str) -> Result<Option<String>, GitChaiError> {
+    let output = git_command(repo_path)
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg(reference)
+        .output()
+        .map_err(GitChaiError::IoError)?;
+    Ok(output.status.success().then(|| stdout_of(output)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_commit_to_branch_leaves_head_and_index_alone() {
+        let repo = TempRepo::new("side-branch");
+        let git = |args: &[&str]| repo.git(args);
+        repo.write("a.txt", "a");
+        git(&["add", "a.txt"]);
+        git(&["commit", "--quiet", "-m", "init"]);
+        let head = git(&["rev-parse", "HEAD"]);
+
+        repo.write("journal.md", "today");
+        let paths = vec!["journal.md".to_string()];
+        let commit = commit_to_branch(
+            &repo,
+            &CommitSettings::default(),
+            "journal",
+            &paths,
+            &[],
+            "add: journal.md",
+        )
+        .unwrap()
+        .expect("a new commit");
+        assert_eq!(git(&["rev-parse", "journal"]), commit);
+        assert_eq!(git(&["rev-parse", "journal^"]), head);
+        assert_eq!(git(&["rev-parse", "HEAD"]), head);
+        assert_eq!(git(&["status", "--porcelain"]), "?? journal.md");
+
+        // Nothing new to commit the second time
+        assert!(
+            commit_to_branch(
+                &repo,
+                &CommitSettings::default(),
+                "journal",
+                &paths,
+                &[],
+                "add: journal.md"
+            )
+            .unwrap()
+            .is_none()
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::GitChaiError;
+use crate::git::commit::{CommitSettings, with_suffix};
+use crate::git::git_command;
+use crate::git::isolated::{TempIndex, has_head, run_git, stdout_of};
+
+/// Replays a commit plan against a temporary index built from HEAD, for
+/// `--dry-run=deep`. Each group is staged on top of the groups before it, as the
+/// real run would commit them, and the pre-commit and commit-msg hooks run
+/// against the temporary index. Neither the user's index nor HEAD is touched,
+/// though hooks that write to the working tree still do so.
+pub struct StagingSimulation {
+    repo_path: PathBuf,
+    /// Settings the commit messages the hooks see are written with
+    settings: CommitSettings,
+    index: TempIndex,
+    /// Tree of the last group that would have been committed
+    tree: Option<String>,
+}
+
+impl StagingSimulation {
+    pub fn new(repo_path: &Path, settings: &CommitSettings) -> Result<Self, GitChaiError> {
+        let index = TempIndex::new();
+        let tree = if has_head(repo_path) {
+            run_git(repo_path, Some(&index), &["read-tree", "HEAD"])?;
+            Some(stdout_of(run_git(
+                repo_path,
+                None,
+                &["rev-parse", "HEAD^{tree}"],
+            )?))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            repo_path: repo_path.to_path_buf(),
+            settings: settings.clone(),
+            index,
+            tree,
+        })
+    }
+
+    /// Stages one group (after dropping `removals`) and runs the commit hooks
+    /// with `message`. A group that would fail is rolled back, so the groups
+    /// after it are checked against the commits that would actually exist.
+    pub fn check(
+        &mut self,
+        paths: &[String],
+        removals: &[String],
+        message: &str,
+        force: bool,
+    ) -> Result<(), GitChaiError> {
+        let result = self
+            .stage(paths, removals, force)
+            .and_then(|tree| self.run_hooks(message).map(|()| tree));
+
+        match result {
+            Ok(tree) => {
+                self.tree = Some(tree);
+                Ok(())
+            }
+            Err(e) => {
+                self.rollback()?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Stages the paths into the temporary index and returns the resulting tree.
+    fn stage(
+        &self,
+        paths: &[String],
+        removals: &[String],
+        force: bool,
+    ) -> Result<String, GitChaiError> {
+        let repo_path = self.repo_path.as_path();
+        let index = Some(&self.index);
+
+        if !removals.is_empty() {
+            let mut args = vec!["rm", "--cached", "-r", "--quiet", "--ignore-unmatch", "--"];
+            args.extend(removals.iter().map(String::as_str));
+            run_git(repo_path, index, &args)?;
+        }
+        if !paths.is_empty() {
+            let mut args = vec!["add", "--all"];
+            if force {
+                args.push("--force");
+            }
+            args.push("--");
+            args.extend(paths.iter().map(String::as_str));
+            run_git(repo_path, index, &args)?;
+        }
+
+        let tree = stdout_of(run_git(repo_path, index, &["write-tree"])?);
+        if self.tree.as_ref() == Some(&tree) {
+            return Err(GitChaiError::git_rejected(
+                &["git", "write-tree"],
+                "nothing to commit",
+            ));
+        }
+        Ok(tree)
+    }
+
+    /// Runs the pre-commit and commit-msg hooks, if installed, against the
+    /// temporary index.
+    fn run_hooks(&self, message: &str) -> Result<(), GitChaiError> {
+        self.run_hook(&["pre-commit"])?;
+
+        let message_file = self.index.path.with_extension("msg");
+        std::fs::write(&message_file, with_suffix(&self.settings, message))?;
+        let result = self.run_hook(&["commit-msg", "--", &message_file.to_string_lossy()]);
+        let _ = std::fs::remove_file(&message_file);
+        result
+    }
+
+    fn run_hook(&self, args: &[&str]) -> Result<(), GitChaiError> {
+        let mut command = git_command(&self.repo_path);
+        command
+            .arg("hook")
+            .arg("run")
+            .arg("--ignore-missing")
+            .args(args)
+            .env("GIT_INDEX_FILE", &self.index.path);
+        let output = command.output().map_err(GitChaiError::IoError)?;
+
+        if !output.status.success() {
+            log::debug!(
+                "{} hook failed: {}",
+                args[0],
+                String::from_utf8_lossy(&output.stderr)
+            );
+            return Err(GitChaiError::git_failed(command.argv(), &output));
+        }
+
+        Ok(())
+    }
+
+    /// Resets the temporary index to the tree of the last successful group.
+    fn rollback(&self) -> Result<(), GitChaiError> {
+        let index = Some(&self.index);
+        match &self.tree {
+            Some(tree) => run_git(&self.repo_path, index, &["read-tree", tree])?,
+            None => run_git(&self.repo_path, index, &["read-tree", "--empty"])?,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_simulation_reports_hook_and_pathspec_failures() {
+        let repo = TempRepo::new("simulate");
+        repo.write(".git/hooks/commit-msg", "#!/bin/sh\n! grep -q bad \"$1\"\n");
+        let hook = repo.join(".git/hooks/commit-msg");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        repo.write("a.txt", "a");
+        repo.write("b.txt", "b");
+
+        let mut simulation = StagingSimulation::new(&repo, &CommitSettings::default()).unwrap();
+        let paths = |path: &str| vec![path.to_string()];
+        assert!(
+            simulation
+                .check(&paths("a.txt"), &[], "add: a.txt", false)
+                .is_ok()
+        );
+        assert!(
+            simulation
+                .check(&paths("a.txt"), &[], "add: a.txt", false)
+                .is_err()
+        );
+        assert!(
+            simulation
+                .check(&paths("missing.txt"), &[], "add: missing.txt", false)
+                .is_err()
+        );
+        #[cfg(unix)]
+        assert!(
+            simulation
+                .check(&paths("b.txt"), &[], "add: bad", false)
+                .is_err()
+        );
+
+        // The user's index is left alone
+        assert_eq!(repo.git(&["ls-files"]), "");
+    }
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+
+/// Ref pointing at the commit covered by the latest encrypted snapshot.
+const SNAPSHOT_REF: &str = "refs/git-chai/snapshot";
+
+/// Git's empty tree, used as the base of the first snapshot.
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Returns the commit covered by the latest snapshot, if any.
+pub fn last_snapshot(repo_path: &Path) -> Option<String> {
+    let output = git_command(repo_path)
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg(SNAPSHOT_REF)
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Records `sha` as covered by the latest snapshot.
+pub fn record_snapshot(repo_path: &Path, sha: &str) -> Result<(), GitChaiError> {
+    let mut command = git_command(repo_path);
+    command.arg("update-ref").arg(SNAPSHOT_REF).arg(sha);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    Ok(())
+}
+
+/// Returns a binary-safe patch from `base` (or the empty tree) to `head`.
+pub fn snapshot_patch(
+    repo_path: &Path,
+    base: Option<&str>,
+    head: &str,
+) -> Result<Vec<u8>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("diff")
+        .arg("--binary")
+        .arg("--no-color")
+        .arg(base.unwrap_or(EMPTY_TREE))
+        .arg(head);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    Ok(output.stdout)
+}
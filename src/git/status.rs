@@ -1,8 +1,13 @@
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::path::Path;
-use std::process::Command;
 use std::str::FromStr;
 
+use crate::chaiignore::{CHAIIGNORE_FILE, ChaiIgnore};
 use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::git::isolated::has_head;
 use crate::types::{ChangeType, GitStatus};
 
 #[derive(Debug, Clone)]
@@ -10,38 +15,106 @@ pub struct GitChange {
     pub status: GitStatus,
     pub change_type: ChangeType,
     pub filename: String,
+    pub renamed_from: Option<String>,
+    pub symlink_target: Option<String>,
 }
 
-pub fn get_changed_files(repo_path: &Path) -> Result<Vec<GitChange>, GitChaiError> {
+/// Hashes a scan result together with the size and modification time of each
+/// changed path, so that a file edited again without changing its status still
+/// gives a different fingerprint. An untracked directory is hashed by the files
+/// inside it, since editing one leaves the directory itself unchanged.
+pub fn scan_fingerprint(repo_path: &Path, changes: &[GitChange]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for change in changes {
+        change.status.to_string().hash(&mut hasher);
+        change.filename.hash(&mut hasher);
+        change.renamed_from.hash(&mut hasher);
+        hash_metadata(repo_path, &change.filename, &mut hasher);
+
+        if change.status == GitStatus::Untracked && change.filename.ends_with('/') {
+            match untracked_files(repo_path, &change.filename) {
+                Ok(files) => {
+                    for file in files {
+                        file.hash(&mut hasher);
+                        hash_metadata(repo_path, &file, &mut hasher);
+                    }
+                }
+                // Without its contents the scan can't be recognized again
+                Err(_) => std::time::SystemTime::now().hash(&mut hasher),
+            }
+        }
+    }
+    hasher.finish()
+}
+
+fn hash_metadata(repo_path: &Path, path: &str, hasher: &mut DefaultHasher) {
+    let metadata = std::fs::symlink_metadata(repo_path.join(path)).ok();
+    metadata.as_ref().map(|m| m.len()).hash(hasher);
+    metadata.and_then(|m| m.modified().ok()).hash(hasher);
+}
+
+/// The untracked files inside the untracked directory `dir`, which status
+/// reports as a single `dir/` entry.
+pub fn untracked_files(repo_path: &Path, dir: &str) -> Result<Vec<String>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("ls-files")
+        .arg("--others")
+        .arg("--exclude-standard")
+        .arg("-z")
+        .arg("--")
+        .arg(dir);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Lists working tree changes. Ignored files (`!!`) are only reported when
+/// `include_ignored` is set; otherwise they are never processed. Paths matched
+/// by `.chaiignore` are left out, whether tracked or not.
+pub fn get_changed_files(
+    repo_path: &Path,
+    include_ignored: bool,
+) -> Result<Vec<GitChange>, GitChaiError> {
     log::debug!("Getting changed files from {:?}", repo_path);
 
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .arg("status")
-        .arg("--porcelain=v1")
-        .output()
-        .map_err(GitChaiError::IoError)?;
+    let mut command = git_command(repo_path);
+    command.arg("status").arg("--porcelain=v1").arg("-z");
+    if include_ignored {
+        command.arg("--ignored");
+    }
+    let output = command.output().map_err(GitChaiError::IoError)?;
 
     if !output.status.success() {
         let error_msg = String::from_utf8_lossy(&output.stderr);
         log::error!("Git status command failed: {}", error_msg);
-        return Err(GitChaiError::GitCommandError {
-            command: "git status --porcelain=v1".to_string(),
-            stderr: error_msg.to_string(),
-            source: None,
-        });
+        return Err(GitChaiError::git_failed(command.argv(), &output));
     }
 
     let status_output = String::from_utf8_lossy(&output.stdout);
     let mut changes = Vec::new();
 
-    for line in status_output.lines() {
-        if line.len() < 3 {
+    // With -z, paths are unquoted and a rename or copy record is followed by
+    // its source path: `R  new\0old\0`
+    let mut entries = status_output.split('\0');
+    while let Some(entry) = entries.next() {
+        if entry.len() < 3 {
             continue;
         }
 
-        let status_str = &line[0..2];
-        let filename = line[3..].trim();
+        let status_str = &entry[0..2];
+        let filename = &entry[3..];
+        let source = if status_str.starts_with(['R', 'C']) {
+            entries.next()
+        } else {
+            None
+        };
 
         if filename.is_empty() {
             continue;
@@ -50,17 +123,200 @@ pub fn get_changed_files(repo_path: &Path) -> Result<Vec<GitChange>, GitChaiErro
         let status = GitStatus::from_str(status_str)
             .map_err(|e| GitChaiError::ParseError(format!("Failed to parse git status: {}", e)))?;
 
+        if status == GitStatus::Ignored && !include_ignored {
+            log::debug!("Skipping ignored file: {}", filename);
+            continue;
+        }
+
         let change_type = ChangeType::from(status.clone());
 
-        log::debug!("Detected change: {} - {}", status, filename);
+        // A copy keeps its source, so only a rename carries it along
+        let renamed_from = source
+            .filter(|_| status == GitStatus::Renamed)
+            .map(str::to_string);
+
+        match &renamed_from {
+            Some(from) => log::debug!("Detected change: {} - {} -> {}", status, from, filename),
+            None => log::debug!("Detected change: {} - {}", status, filename),
+        }
 
         changes.push(GitChange {
             status,
             change_type,
             filename: filename.to_string(),
+            renamed_from,
+            symlink_target: None,
+        });
+    }
+
+    if let Some(ignore) = ChaiIgnore::load(repo_path) {
+        changes.retain(|change| {
+            let ignored = ignore.is_ignored(&change.filename);
+            if ignored {
+                log::debug!("Skipping file in {}: {}", CHAIIGNORE_FILE, change.filename);
+            }
+            !ignored
         });
     }
 
     log::info!("Found {} changed files", changes.len());
     Ok(changes)
 }
+
+/// Drops changes whose working tree already matches HEAD, which only differ in
+/// the index. An isolated-index commit leaves these behind, as the user's index
+/// keeps the entries it had before the commit.
+pub fn drop_index_only_changes(
+    repo_path: &Path,
+    changes: Vec<GitChange>,
+) -> Result<Vec<GitChange>, GitChaiError> {
+    if !has_head(repo_path) {
+        return Ok(changes);
+    }
+
+    let mut command = git_command(repo_path);
+    command
+        .arg("diff")
+        .arg("HEAD")
+        .arg("--name-only")
+        .arg("--no-renames")
+        .arg("-z");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        let error_msg = String::from_utf8_lossy(&output.stderr);
+        log::error!(
+            "Failed to diff the working tree against HEAD: {}",
+            error_msg
+        );
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let differing: HashSet<&str> = stdout.split('\0').filter(|path| !path.is_empty()).collect();
+
+    Ok(changes
+        .into_iter()
+        .filter(|change| {
+            let kept = matches!(change.status, GitStatus::Untracked | GitStatus::Ignored)
+                || differing.contains(change.filename.as_str())
+                || change
+                    .renamed_from
+                    .as_deref()
+                    .is_some_and(|from| differing.contains(from));
+            if !kept {
+                log::debug!(
+                    "Already committed, only stale in the index: {}",
+                    change.filename
+                );
+            }
+            kept
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_scan_fingerprint_tracks_file_contents() {
+        let dir = TempRepo::dir("fingerprint");
+        dir.write("notes.md", "a");
+        let changes = vec![GitChange {
+            status: GitStatus::ModifiedUnstaged,
+            change_type: ChangeType::Modify,
+            filename: "notes.md".to_string(),
+            renamed_from: None,
+            symlink_target: None,
+        }];
+
+        let first = scan_fingerprint(&dir, &changes);
+        assert_eq!(scan_fingerprint(&dir, &changes), first);
+        assert_ne!(scan_fingerprint(&dir, &[]), first);
+
+        // Still ` M`, but edited again
+        dir.write("notes.md", "ab");
+        assert_ne!(scan_fingerprint(&dir, &changes), first);
+    }
+
+    #[test]
+    fn test_scan_fingerprint_tracks_files_in_untracked_directories() {
+        let repo = TempRepo::new("fingerprint-dir");
+        repo.write("pkg/a.txt", "<<<<<<< ours\n");
+        let changes = get_changed_files(&repo, false).unwrap();
+        assert_eq!(changes[0].filename, "pkg/");
+
+        let first = scan_fingerprint(&repo, &changes);
+        assert_eq!(scan_fingerprint(&repo, &changes), first);
+
+        // Resolved in place: `pkg/` itself is unchanged
+        repo.write("pkg/a.txt", "resolved\n");
+        assert_ne!(scan_fingerprint(&repo, &changes), first);
+    }
+
+    #[test]
+    fn test_staged_renames_carry_their_source() {
+        let repo = TempRepo::new("renames");
+        repo.write("old notes.md", "notes\n");
+        repo.write("todo.md", "todo\n");
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "-q", "-m", "init"]);
+        repo.git(&["mv", "old notes.md", "new notes.md"]);
+        repo.git(&["mv", "todo.md", "done.md"]);
+        repo.write("done.md", "todo\ndone\n");
+
+        let mut changes = get_changed_files(&repo, false).unwrap();
+        changes.sort_by(|a, b| a.filename.cmp(&b.filename));
+        let renames: Vec<_> = changes
+            .iter()
+            .map(|change| {
+                (
+                    change.change_type,
+                    change.renamed_from.as_deref(),
+                    change.filename.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            renames,
+            [
+                (ChangeType::Rename, Some("todo.md"), "done.md"),
+                (ChangeType::Rename, Some("old notes.md"), "new notes.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_isolated_commits_leave_no_changes_behind() {
+        let repo = TempRepo::new("index-only");
+        repo.write("notes.md", "a\n");
+        repo.write("todo.md", "a\n");
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "-q", "-m", "init"]);
+        repo.write("notes.md", "b\n");
+        repo.write("todo.md", "b\n");
+        repo.write("new.md", "new\n");
+        crate::git::commit_isolated(
+            &repo,
+            &Default::default(),
+            &["notes.md".to_string()],
+            &[],
+            "notes",
+            false,
+            false,
+        )
+        .unwrap();
+
+        let changes = get_changed_files(&repo, false).unwrap();
+        assert_eq!(changes.len(), 3);
+        let mut left: Vec<_> = drop_index_only_changes(&repo, changes)
+            .unwrap()
+            .into_iter()
+            .map(|change| change.filename)
+            .collect();
+        left.sort();
+        assert_eq!(left, ["new.md", "todo.md"]);
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::config::SubjectCase;
+
+/// Longest extension `strip_extensions` removes, so names like `notes.backup2026`
+/// stay whole.
+const MAX_EXTENSION_LEN: usize = 5;
+
+/// Misspelled word -> correction.
+pub type Wordlist = Arc<HashMap<String, String>>;
+
+static WORDLISTS: OnceLock<Mutex<HashMap<PathBuf, Wordlist>>> = OnceLock::new();
+
+/// Clean-up applied to every generated subject line, after any restyling.
+#[derive(Debug, Clone, Default)]
+pub struct SubjectRules {
+    pub case: SubjectCase,
+    /// Drop file extensions from the paths in the subject
+    pub strip_extensions: bool,
+    /// Longest subject, including the suffix; longer ones end in an ellipsis
+    pub max_length: Option<usize>,
+    /// Corrections from `spelling_wordlist`
+    pub spelling: Wordlist,
+}
+
+impl SubjectRules {
+    /// Returns `subject` with spelling fixed, extensions stripped, the first
+    /// word of the description cased and the result truncated so that it still
+    /// fits with `reserved` more characters (the suffix) appended.
+    pub fn apply(&self, subject: &str, reserved: usize) -> String {
+        let mut subject = subject
+            .split(' ')
+            .map(|token| {
+                let token = self.spelling.get(token).map_or(token, String::as_str);
+                if self.strip_extensions {
+                    strip_extension(token)
+                } else {
+                    token
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        if self.case != SubjectCase::Keep {
+            subject = recase(&subject, self.case);
+        }
+
+        match self.max_length {
+            Some(max) if subject.chars().count() + reserved > max => {
+                let keep = max.saturating_sub(reserved + 1);
+                let truncated: String = subject.chars().take(keep).collect();
+                format!("{}…", truncated.trim_end())
+            }
+            _ => subject,
+        }
+    }
+}
+
+/// Reads a codespell-style wordlist (`misspelling->correction` per line) once
+/// per session. Entries offering several corrections are skipped, since a
+/// subject can't be fixed without a human choosing.
+pub fn load_wordlist(path: &Path) -> Wordlist {
+    WORDLISTS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(path.to_path_buf())
+        .or_insert_with(|| match std::fs::read_to_string(path) {
+            Ok(text) => Arc::new(parse_wordlist(&text)),
+            Err(e) => {
+                log::warn!("Failed to read spelling wordlist {}: {}", path.display(), e);
+                Arc::default()
+            }
+        })
+        .clone()
+}
+
+fn parse_wordlist(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| {
+            let (typo, correction) = line.split_once("->")?;
+            let correction = correction.trim().trim_end_matches(',');
+            (!line.starts_with('#') && !correction.contains(',') && !correction.is_empty())
+                .then(|| (typo.trim().to_string(), correction.to_string()))
+        })
+        .collect()
+}
+
+/// `src/main.rs` -> `src/main`; dotfiles such as `.gitignore` are left alone.
+fn strip_extension(token: &str) -> &str {
+    let name_start = token.rfind('/').map_or(0, |slash| slash + 1);
+    match token[name_start..].rfind('.') {
+        Some(dot)
+            if dot > 0
+                && token.len() - (name_start + dot) - 1 <= MAX_EXTENSION_LEN
+                && token[name_start + dot + 1..]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric())
+                && token[name_start + dot + 1..]
+                    .chars()
+                    .any(|c| c.is_ascii_alphabetic()) =>
+        {
+            &token[..name_start + dot]
+        }
+        _ => token,
+    }
+}
+
+/// Lower- or upper-cases the first word of the description, after any
+/// `type(scope): ` or `[scope] ` prefix. Paths and identifiers (anything but a
+/// plain lowercase or capitalized word) keep their case.
+fn recase(subject: &str, case: SubjectCase) -> String {
+    let start = description_start(subject);
+    let (prefix, description) = subject.split_at(start);
+    let word = description.split(' ').next().unwrap_or_default();
+    let mut chars = word.chars();
+    let plain = chars.next().is_some_and(char::is_alphabetic)
+        && chars.all(|c| c.is_lowercase() && c.is_alphabetic());
+    if !plain {
+        return subject.to_string();
+    }
+
+    let mut chars = description.chars();
+    let first = chars.next().unwrap_or_default();
+    let first: String = match case {
+        SubjectCase::Lower => first.to_lowercase().collect(),
+        SubjectCase::Sentence => first.to_uppercase().collect(),
+        SubjectCase::Keep => first.to_string(),
+    };
+    format!("{}{}{}", prefix, first, chars.as_str())
+}
+
+fn description_start(subject: &str) -> usize {
+    if let Some(rest) = subject.strip_prefix('[')
+        && let Some(end) = rest.find("] ")
+    {
+        return end + 3;
+    }
+    match subject.split_once(": ") {
+        Some((head, _))
+            if head
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || "()!-_".contains(c) || c.is_ascii_digit()) =>
+        {
+            head.len() + 2
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subject_rules() {
+        let rules = SubjectRules {
+            case: SubjectCase::Sentence,
+            strip_extensions: true,
+            max_length: Some(30),
+            spelling: Arc::new(parse_wordlist(
+                "# codespell\nrecieve->receive\nteh->the, tech,\n",
+            )),
+        };
+        assert_eq!(rules.apply("mod: src/main.rs", 0), "mod: src/main");
+        assert_eq!(
+            rules.apply("chore(api): update a.txt", 0),
+            "chore(api): Update a"
+        );
+        assert_eq!(rules.apply("add: .gitignore", 0), "add: .gitignore");
+        assert_eq!(
+            rules.apply("recieve teh notes/today.md", 0),
+            "Receive teh notes/today"
+        );
+        assert_eq!(
+            rules.apply("rename: docs/getting-started.md -> docs/start.md", 10),
+            "rename: docs/gettin…"
+        );
+
+        let lower = SubjectRules {
+            case: SubjectCase::Lower,
+            ..Default::default()
+        };
+        assert_eq!(
+            lower.apply("[web] Update README.md", 0),
+            "[web] update README.md"
+        );
+        assert_eq!(lower.apply("Update README.md", 0), "update README.md");
+        assert_eq!(lower.apply("README.md", 0), "README.md");
+    }
+}
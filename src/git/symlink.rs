@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::git::status::GitChange;
+use crate::types::ChangeType;
+
+const SYMLINK_MODE: &str = "120000";
+
+/// Marks changes that create or retarget a symlink and records the link target.
+///
+/// A path is a symlink if it is one in the working tree. Only with
+/// `core.symlinks=false`, where git checks links out as plain files containing
+/// the target, is a file the index records with mode 120000 taken for one;
+/// otherwise a plain file there has replaced the link and is a modification.
+pub fn detect_symlinks(
+    repo_path: &Path,
+    changes: &[GitChange],
+) -> Result<Vec<GitChange>, GitChaiError> {
+    let index_modes = if has_symlinks(repo_path) {
+        HashMap::new()
+    } else {
+        get_index_modes(repo_path, changes)?
+    };
+
+    let result = changes
+        .iter()
+        .cloned()
+        .map(|mut change| {
+            if change.change_type == ChangeType::Delete || change.renamed_from.is_some() {
+                return change;
+            }
+
+            let full_path = repo_path.join(&change.filename);
+            let is_worktree_link = std::fs::symlink_metadata(&full_path)
+                .is_ok_and(|metadata| metadata.file_type().is_symlink());
+            let is_index_link = index_modes
+                .get(&change.filename)
+                .is_some_and(|mode| mode == SYMLINK_MODE);
+
+            let target = if is_worktree_link {
+                std::fs::read_link(&full_path)
+                    .ok()
+                    .map(|target| target.to_string_lossy().to_string())
+            } else if is_index_link {
+                std::fs::read_to_string(&full_path).ok()
+            } else {
+                None
+            };
+
+            if let Some(target) = target {
+                log::debug!("Detected symlink: {} -> {}", change.filename, target);
+                change.change_type = ChangeType::Link;
+                change.symlink_target = Some(target);
+            }
+            change
+        })
+        .collect();
+
+    Ok(result)
+}
+
+/// Returns false if the repository checks symlinks out as plain files
+/// (`core.symlinks=false`, as on filesystems without them).
+fn has_symlinks(repo_path: &Path) -> bool {
+    git_command(repo_path)
+        .arg("config")
+        .arg("--bool")
+        .arg("core.symlinks")
+        .output()
+        .map_or(true, |output| {
+            String::from_utf8_lossy(&output.stdout).trim() != "false"
+        })
+}
+
+/// Returns the index mode of each changed path that is already tracked.
+fn get_index_modes(
+    repo_path: &Path,
+    changes: &[GitChange],
+) -> Result<HashMap<String, String>, GitChaiError> {
+    if changes.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut command = git_command(repo_path);
+    command
+        .arg("ls-files")
+        .arg("--stage")
+        .arg("-z")
+        .arg("--")
+        .args(changes.iter().map(|c| c.filename.as_str()));
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let stage_output = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_index_modes(&stage_output))
+}
+
+/// Parses `git ls-files --stage -z` records of the form `<mode> <sha> <stage>\t<path>`.
+fn parse_index_modes(output: &str) -> HashMap<String, String> {
+    output
+        .split('\0')
+        .filter_map(|record| {
+            let (info, path) = record.split_once('\t')?;
+            let mode = info.split_whitespace().next()?;
+            Some((path.to_string(), mode.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::get_changed_files;
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_parse_index_modes() {
+        let output = "100644 e69de29bb2d1d6434b8b29ae775ad8c2e48c5391 0\tsrc/main.rs\0\
+                      120000 2e65efe2a145dda7ee51d1741299f848e5bf752e 0\tlatest\0";
+        let modes = parse_index_modes(output);
+        assert_eq!(modes.get("src/main.rs").map(String::as_str), Some("100644"));
+        assert_eq!(modes.get("latest").map(String::as_str), Some(SYMLINK_MODE));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_link_replaced_by_a_file_is_a_modification() {
+        let repo = TempRepo::new("symlink");
+        repo.write("notes.md", "notes\n");
+        std::os::unix::fs::symlink("notes.md", repo.join("latest")).unwrap();
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "-q", "-m", "init"]);
+        std::fs::remove_file(repo.join("latest")).unwrap();
+        repo.write("latest", "not a target\n");
+
+        let changes = get_changed_files(&repo, false).unwrap();
+        let result = detect_symlinks(&repo, &changes).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].change_type, ChangeType::Modify);
+        assert_eq!(result[0].symlink_target, None);
+
+        // Checked out as a plain file, the link holds its target
+        repo.git(&["config", "core.symlinks", "false"]);
+        repo.write("latest", "drafts.md");
+        let result = detect_symlinks(&repo, &changes).unwrap();
+        assert_eq!(result[0].change_type, ChangeType::Link);
+        assert_eq!(result[0].symlink_target.as_deref(), Some("drafts.md"));
+    }
+}
@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+
+/// Size of the commits on HEAD that `origin` doesn't have yet.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UnpushedSize {
+    /// On-disk size of the objects a push would send
+    pub bytes: u64,
+    /// Lines added plus lines removed across the unpushed commits
+    pub lines: u64,
+}
+
+/// Measures what a push of HEAD to `origin` would send.
+pub fn get_unpushed_size(repo_path: &Path) -> Result<UnpushedSize, GitChaiError> {
+    let bytes = run_unpushed(repo_path, &["rev-list", "--objects", "--disk-usage"])?;
+    let numstat = run_unpushed(repo_path, &["log", "--numstat", "--format="])?;
+
+    Ok(UnpushedSize {
+        bytes: bytes.trim().parse().map_err(|_| {
+            GitChaiError::ParseError(format!("Unexpected disk usage output: {:?}", bytes))
+        })?,
+        lines: count_numstat_lines(&numstat),
+    })
+}
+
+/// Counts the commits on HEAD that `origin` doesn't have yet.
+pub fn count_unpushed_commits(repo_path: &Path) -> Result<usize, GitChaiError> {
+    let count = run_unpushed(repo_path, &["rev-list", "--count"])?;
+    count
+        .trim()
+        .parse()
+        .map_err(|_| GitChaiError::ParseError(format!("Unexpected commit count: {:?}", count)))
+}
+
+/// How far HEAD and its upstream branch have moved apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub upstream: String,
+    /// Commits on HEAD the upstream doesn't have
+    pub ahead: usize,
+    /// Commits on the upstream HEAD doesn't have
+    pub behind: usize,
+}
+
+/// Compares HEAD with its upstream branch, as of the last fetch. Returns `None`
+/// if the current branch has no upstream.
+pub fn get_upstream_divergence(repo_path: &Path) -> Result<Option<Divergence>, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .arg("rev-parse")
+        .arg("--abbrev-ref")
+        .arg("--symbolic-full-name")
+        .arg("@{upstream}");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        log::debug!(
+            "No upstream branch: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return Ok(None);
+    }
+    let upstream = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    let mut command = git_command(repo_path);
+    command
+        .arg("rev-list")
+        .arg("--left-right")
+        .arg("--count")
+        .arg("HEAD...@{upstream}");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let counts = String::from_utf8_lossy(&output.stdout);
+    let parse_error =
+        || GitChaiError::ParseError(format!("Unexpected rev-list counts: {:?}", counts));
+    let (ahead, behind) = counts.trim().split_once('\t').ok_or_else(parse_error)?;
+    Ok(Some(Divergence {
+        upstream,
+        ahead: ahead.parse().map_err(|_| parse_error())?,
+        behind: behind.parse().map_err(|_| parse_error())?,
+    }))
+}
+
+/// Runs `git <args> HEAD --not --remotes=origin` and returns its stdout.
+fn run_unpushed(repo_path: &Path, args: &[&str]) -> Result<String, GitChaiError> {
+    let mut command = git_command(repo_path);
+    command
+        .args(args)
+        .arg("HEAD")
+        .arg("--not")
+        .arg("--remotes=origin");
+    let output = command.output().map_err(GitChaiError::IoError)?;
+
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Sums added and removed lines from `--numstat` output. Binary files (`-`) count
+/// as zero lines; their size shows up in the byte count instead.
+fn count_numstat_lines(numstat: &str) -> u64 {
+    numstat
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let added = fields.next()?.parse::<u64>().ok()?;
+            let removed = fields.next()?.parse::<u64>().ok()?;
+            Some(added + removed)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_numstat_lines() {
+        let numstat = "10\t2\tsrc/main.rs\n-\t-\tlogo.png\n\n3\t0\tREADME.md\n";
+        assert_eq!(count_numstat_lines(numstat), 15);
+    }
+}
@@ -0,0 +1,233 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::GitChaiError;
+use crate::git::commit::{CommitSettings, apply_commit_author, apply_commit_date, with_suffix};
+use crate::git::git_command;
+use crate::git::isolated::{run_git, stdout_of};
+
+static WORKTREE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Commits the working-tree state of `paths` (after dropping `removals`) to
+/// `branch` from a temporary worktree, for `branch_map`. Unlike
+/// [`commit_to_branch`](crate::git::commit_to_branch), the commit is made with
+/// `git commit` on the branch itself, so its commit hooks run. The branch is
+/// created from HEAD if it does not exist yet, and must not be checked out in
+/// another worktree. The worktree is created without a checkout, so only the
+/// committed paths are written to disk, and removed again afterwards.
+///
+/// Returns `None` if the branch already has this content.
+pub fn commit_in_worktree(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    branch: &str,
+    paths: &[String],
+    removals: &[String],
+    message: &str,
+) -> Result<Option<String>, GitChaiError> {
+    let worktree = TempWorktree::add(repo_path, branch)?;
+    let dir = worktree.path.as_path();
+    // The index of a worktree added without a checkout is empty
+    run_git(dir, None, &["reset", "--quiet"])?;
+
+    for path in paths {
+        copy_path(&repo_path.join(path), &dir.join(path)).map_err(GitChaiError::IoError)?;
+    }
+    if !removals.is_empty() {
+        let mut args = vec!["rm", "--cached", "-r", "--quiet", "--ignore-unmatch", "--"];
+        args.extend(removals.iter().map(String::as_str));
+        run_git(dir, None, &args)?;
+    }
+    // Files the branch has under a copied directory are not on disk here, so
+    // removals must not be staged from the worktree
+    if !paths.is_empty() {
+        let mut args = vec!["add", "--ignore-removal", "--"];
+        args.extend(paths.iter().map(String::as_str));
+        run_git(dir, None, &args)?;
+    }
+
+    let staged = git_command(dir)
+        .args(["diff", "--cached", "--quiet"])
+        .output()
+        .map_err(GitChaiError::IoError)?;
+    if staged.status.success() {
+        return Ok(None);
+    }
+
+    let mut command = git_command(dir);
+    command
+        .arg("commit")
+        .arg("--quiet")
+        .arg("-m")
+        .arg(with_suffix(settings, message));
+    apply_commit_date(settings, &mut command);
+    apply_commit_author(settings, &mut command);
+    let output = command.output().map_err(GitChaiError::IoError)?;
+    if !output.status.success() {
+        return Err(GitChaiError::git_failed(command.argv(), &output));
+    }
+
+    let sha = stdout_of(run_git(dir, None, &["rev-parse", "HEAD"])?);
+    log::debug!(
+        "Created commit {} on {} in a temporary worktree",
+        sha,
+        branch
+    );
+    Ok(Some(sha))
+}
+
+/// A worktree of `branch` in the temp directory, removed on drop.
+struct TempWorktree {
+    repo_path: PathBuf,
+    path: PathBuf,
+}
+
+impl TempWorktree {
+    fn add(repo_path: &Path, branch: &str) -> Result<Self, GitChaiError> {
+        let path = std::env::temp_dir().join(format!(
+            "git-chai-worktree-{}-{}",
+            std::process::id(),
+            WORKTREE_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let target = path.to_string_lossy();
+        let exists = git_command(repo_path)
+            .args(["rev-parse", "--verify", "--quiet"])
+            .arg(format!("refs/heads/{}", branch))
+            .output()
+            .map_err(GitChaiError::IoError)?
+            .status
+            .success();
+        if exists {
+            run_git(
+                repo_path,
+                None,
+                &[
+                    "worktree",
+                    "add",
+                    "--quiet",
+                    "--no-checkout",
+                    &target,
+                    branch,
+                ],
+            )?;
+        } else {
+            log::info!("Creating branch {} from HEAD", branch);
+            run_git(
+                repo_path,
+                None,
+                &[
+                    "worktree",
+                    "add",
+                    "--quiet",
+                    "--no-checkout",
+                    "-b",
+                    branch,
+                    &target,
+                    "HEAD",
+                ],
+            )?;
+        }
+
+        Ok(TempWorktree {
+            repo_path: repo_path.to_path_buf(),
+            path,
+        })
+    }
+}
+
+impl Drop for TempWorktree {
+    fn drop(&mut self) {
+        let target = self.path.to_string_lossy();
+        if let Err(e) = run_git(
+            &self.repo_path,
+            None,
+            &["worktree", "remove", "--force", &target],
+        ) {
+            log::warn!("Failed to remove temporary worktree {}: {}", target, e);
+            let _ = std::fs::remove_dir_all(&self.path);
+            let _ = run_git(&self.repo_path, None, &["worktree", "prune"]);
+        }
+    }
+}
+
+/// Copies a file, symlink or (recursively) directory, creating parent
+/// directories as needed.
+fn copy_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let metadata = std::fs::symlink_metadata(from)?;
+    if metadata.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+
+    if to.symlink_metadata().is_ok() {
+        std::fs::remove_file(to)?;
+    }
+    #[cfg(unix)]
+    if metadata.file_type().is_symlink() {
+        return std::os::unix::fs::symlink(std::fs::read_link(from)?, to);
+    }
+    std::fs::copy(from, to).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_commit_in_worktree_commits_to_the_branch_only() {
+        let repo = TempRepo::new("worktree-test");
+        let git = |args: &[&str]| repo.git(args);
+        repo.write("a.txt", "a");
+        git(&["add", "a.txt"]);
+        git(&["commit", "--quiet", "-m", "init"]);
+        git(&["branch", "pr/api"]);
+        let head = git(&["rev-parse", "HEAD"]);
+
+        repo.write("api/routes.rs", "fn routes() {}");
+        let paths = vec!["api/".to_string()];
+        let sha = commit_in_worktree(
+            &repo,
+            &CommitSettings::default(),
+            "pr/api",
+            &paths,
+            &[],
+            "add: api",
+        )
+        .unwrap()
+        .expect("a new commit");
+        assert_eq!(git(&["rev-parse", "pr/api"]), sha);
+        assert_eq!(
+            git(&["ls-tree", "-r", "--name-only", "pr/api"]),
+            "a.txt\napi/routes.rs"
+        );
+        assert_eq!(git(&["rev-parse", "HEAD"]), head);
+        assert_eq!(git(&["status", "--porcelain"]), "?? api/");
+        assert_eq!(
+            git(&["worktree", "list", "--porcelain"])
+                .matches("worktree ")
+                .count(),
+            1
+        );
+
+        assert!(
+            commit_in_worktree(
+                &repo,
+                &CommitSettings::default(),
+                "pr/api",
+                &paths,
+                &[],
+                "add: api"
+            )
+            .unwrap()
+            .is_none()
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::GitChaiError;
+
+/// Commit message used when git-chai appends build output to `.gitignore`.
+pub const IGNORE_COMMIT_MESSAGE: &str = "chore: ignore build artifacts";
+
+/// Consecutive scans a noise path must appear in before `.gitignore` is changed.
+const RECURRING_SCANS: u32 = 3;
+
+/// Counts how many consecutive scans each untracked noise path (such as a build
+/// output directory) has shown up in.
+#[derive(Debug, Default)]
+pub struct NoiseTracker {
+    streaks: HashMap<String, u32>,
+}
+
+/// Noise paths from one scan, split by how long they have been around.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct NoiseReport {
+    /// Seen for the first time
+    pub new: Vec<String>,
+    /// Seen in exactly `RECURRING_SCANS` consecutive scans, so reported only once
+    pub recurring: Vec<String>,
+}
+
+impl NoiseTracker {
+    /// Records the noise paths seen in this scan. Paths missing from a scan start
+    /// over, so something that was deleted and comes back is reported again.
+    pub fn observe(&mut self, paths: &[String]) -> NoiseReport {
+        self.streaks.retain(|path, _| paths.contains(path));
+
+        let mut report = NoiseReport::default();
+        for path in paths {
+            let streak = self.streaks.entry(path.clone()).or_insert(0);
+            *streak += 1;
+            if *streak == 1 {
+                report.new.push(path.clone());
+            }
+            if *streak == RECURRING_SCANS {
+                report.recurring.push(path.clone());
+            }
+        }
+        report
+    }
+
+    /// Whether every noise path has been seen often enough to be reported as
+    /// recurring, so further identical scans would not change anything.
+    pub fn is_settled(&self) -> bool {
+        self.streaks
+            .values()
+            .all(|streak| *streak >= RECURRING_SCANS)
+    }
+}
+
+/// The `.gitignore` line for a repository-relative directory, anchored to the root.
+pub fn ignore_entry(path: &str) -> String {
+    format!("/{}/", path.trim_matches('/'))
+}
+
+/// Appends anchored entries for `paths` to the repository's root `.gitignore`.
+pub fn append_to_gitignore(repo_path: &Path, paths: &[String]) -> Result<(), GitChaiError> {
+    let gitignore = repo_path.join(".gitignore");
+    let existing = std::fs::read_to_string(&gitignore).unwrap_or_default();
+
+    let mut addition = String::new();
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        addition.push('\n');
+    }
+    addition.push_str("# Build output\n");
+    for path in paths {
+        addition.push_str(&ignore_entry(path));
+        addition.push('\n');
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&gitignore)?;
+    file.write_all(addition.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_reports_new_then_recurring_once() {
+        let mut tracker = NoiseTracker::default();
+        let target = vec!["target/".to_string()];
+
+        assert_eq!(tracker.observe(&target).new, target);
+        assert_eq!(tracker.observe(&target), NoiseReport::default());
+        assert_eq!(tracker.observe(&target).recurring, target);
+        assert_eq!(tracker.observe(&target), NoiseReport::default());
+
+        tracker.observe(&[]);
+        assert_eq!(tracker.observe(&target).new, target);
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result, bail};
+
+use crate::config::Config;
+use crate::git::{
+    CommitSettings, checkout_branch, cherry_pick, create_commit, get_changed_files,
+    get_commits_since, get_current_branch, short_sha,
+};
+use crate::summary::is_chai_commit;
+
+/// Runs `git-chai graft`: copies the git-chai commits made since `since` onto
+/// `onto`, one by one or (with `squash`) as a single commit, for when autosave
+/// ran on the wrong branch. The original branch is checked out again afterwards
+/// and left untouched; removing the commits from it is up to the user.
+pub fn run_graft(
+    config: &Config,
+    since: &str,
+    onto: &str,
+    squash: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let repo = &config.repo_path;
+    let Some(original) = get_current_branch(repo)? else {
+        bail!("HEAD is detached; check out the branch the commits were made on first");
+    };
+    if original == onto {
+        bail!("The commits are already on {}", onto);
+    }
+
+    let mut commits = get_commits_since(repo, since)?;
+    commits.retain(|commit| is_chai_commit(commit.generated_subject()));
+    commits.reverse();
+    if commits.is_empty() {
+        log::info!("No git-chai commits on {} since {}", original, since);
+        return Ok(());
+    }
+
+    log::info!(
+        "{} {} commit(s) from {} onto {}{}",
+        if dry_run {
+            "DRY RUN: Would graft"
+        } else {
+            "Grafting"
+        },
+        commits.len(),
+        original,
+        onto,
+        if squash { " as one commit" } else { "" }
+    );
+    for commit in &commits {
+        log::info!("  {} {}", short_sha(&commit.sha), commit.subject);
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    if !get_changed_files(repo, false)?.is_empty() {
+        bail!("The working tree has uncommitted changes; commit or stash them before grafting");
+    }
+
+    let shas: Vec<String> = commits.into_iter().map(|commit| commit.sha).collect();
+    checkout_branch(repo, onto)?;
+    let result = cherry_pick(repo, &shas, squash).and_then(|()| {
+        if squash {
+            let message = format!("graft: {} autosave commits from {}", shas.len(), original);
+            create_commit(repo, &CommitSettings::default(), &message).map(|_| ())
+        } else {
+            Ok(())
+        }
+    });
+    checkout_branch(repo, &original)
+        .with_context(|| format!("Grafted onto {} but failed to return to {}", onto, original))?;
+    result.with_context(|| format!("Failed to graft onto {}; {} is unchanged", onto, onto))?;
+
+    log::info!(
+        "Grafted {} commit(s) onto {}; {} still has them",
+        shas.len(),
+        onto,
+        original
+    );
+    Ok(())
+}
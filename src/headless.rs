@@ -0,0 +1,163 @@
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+use crate::control::Control;
+use crate::git;
+use crate::scheduler::Scheduler;
+use crate::session::ChaiSession;
+use crate::watch::watch;
+
+/// Default cap on headless worker threads when watching several repositories.
+const MAX_DEFAULT_JOBS: usize = 4;
+
+/// How long shutdown waits for in-flight commits and pushes to finish.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How headless mode schedules and triggers its sessions.
+#[derive(Debug, Clone, Default)]
+pub struct HeadlessOptions {
+    /// Worker threads shared by all repositories; one per repository, up to
+    /// four, if unset
+    pub jobs: Option<usize>,
+    /// Scan when files change instead of every `scan_interval_secs`
+    pub watch: bool,
+    /// Offer status, pause/resume and scan-now on the D-Bus session bus (Linux)
+    pub dbus: bool,
+    /// Show a tray icon (Linux)
+    pub tray: bool,
+}
+
+/// Runs the headless engine on the caller's tokio runtime, scanning every
+/// repository on its own interval, until `shutdown` completes or a quit is
+/// asked for over D-Bus or the tray. New cycles then stop being scheduled and
+/// running ones get `SHUTDOWN_TIMEOUT` to finish their git commands; if they
+/// don't, an error is returned.
+pub async fn run_sessions(
+    sessions: Vec<ChaiSession>,
+    options: HeadlessOptions,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    let (stop, shutdown_rx) = tokio::sync::watch::channel(false);
+    let mut stopping = shutdown_rx.clone();
+    let control = Arc::new(Control::default());
+    let quit = control.stop_handle();
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = shutdown => {}
+            _ = quit.notified() => log::info!("Quit requested, finishing in-flight operations..."),
+        }
+        stop.send_replace(true);
+    });
+
+    let jobs = options
+        .jobs
+        .unwrap_or_else(|| sessions.len().min(MAX_DEFAULT_JOBS));
+    let any_push = sessions
+        .iter()
+        .any(|session| session.config.push_by_default);
+    let mut watchers = Vec::new();
+    let scheduler = Scheduler::new(
+        sessions
+            .into_iter()
+            .map(|mut session| {
+                let trigger = Arc::new(Notify::new());
+                if options.watch {
+                    let config = &mut session.config;
+                    let debounce = Duration::from_millis(config.watch_debounce_ms);
+                    match watch(&config.repo_path, debounce, trigger.clone()) {
+                        Ok(watcher) => {
+                            log::info!("Watching {} for changes", config.repo_path.display());
+                            watchers.push(watcher);
+                            session.watching = true;
+                            // Also what healthz expects between cycles
+                            config.scan_interval_secs = config.watch_rescan_secs as f64;
+                        }
+                        Err(e) => log::warn!(
+                            "Failed to watch {} ({}); scanning every {} seconds instead",
+                            config.repo_path.display(),
+                            e,
+                            config.scan_interval_secs
+                        ),
+                    }
+                }
+                (session, trigger)
+            })
+            .collect(),
+    );
+
+    let _dbus = if options.dbus {
+        start_dbus(control.clone()).await
+    } else {
+        None
+    };
+    if options.tray {
+        start_tray(control.clone(), any_push).await;
+    }
+
+    let wake = control.wake_handle();
+    let run = scheduler.run(jobs, shutdown_rx, wake, move |session| {
+        session.headless_cycle(&control)
+    });
+    let mut sessions = tokio::select! {
+        sessions = run => sessions,
+        _ = async {
+            let _ = stopping.wait_for(|stop| *stop).await;
+            tokio::time::sleep(SHUTDOWN_TIMEOUT).await;
+        } => {
+            anyhow::bail!(
+                "In-flight operations did not finish within {} seconds",
+                SHUTDOWN_TIMEOUT.as_secs()
+            );
+        }
+    };
+
+    for session in &mut sessions {
+        session.finish();
+    }
+    log_git_latency();
+
+    log::info!("git-chai stopped");
+    Ok(())
+}
+
+/// Starts the D-Bus service; a failure is logged and headless mode runs without it.
+#[cfg(target_os = "linux")]
+async fn start_dbus(control: Arc<Control>) -> Option<zbus::Connection> {
+    crate::dbus::serve(control)
+        .await
+        .map_err(|e| log::warn!("D-Bus service not available: {:#}", e))
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn start_dbus(_control: Arc<Control>) -> Option<()> {
+    log::warn!("--dbus is only supported on Linux");
+    None
+}
+
+#[cfg(target_os = "linux")]
+async fn start_tray(control: Arc<Control>, push: bool) {
+    if let Err(e) = crate::tray::serve(control, push).await {
+        log::warn!("{:#}", e);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn start_tray(_control: Arc<Control>, _push: bool) {
+    log::warn!("--tray is only supported on Linux");
+}
+
+/// Logs per-subcommand git latency collected during the session.
+fn log_git_latency() {
+    for (subcommand, stats) in git::limits::latency_stats() {
+        log::info!(
+            "git {}: {} calls, avg {:?}, max {:?}",
+            subcommand,
+            stats.count,
+            stats.average(),
+            stats.max
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+use crate::git::get_git_dir;
+
+/// File in the git directory where a headless watcher records each cycle.
+pub const HEALTH_FILE: &str = "chai-health.toml";
+
+/// Extra time, on top of a few scan intervals, before the state counts as stale.
+const STALE_GRACE_SECS: u64 = 60;
+
+/// Scan intervals without a finished cycle after which the watcher is wedged.
+const STALE_INTERVALS: u64 = 3;
+
+/// What the last headless cycle of a repository left behind, for
+/// `git-chai healthz`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Health {
+    /// Process that wrote the file
+    pub pid: u32,
+    /// When the last cycle finished, in seconds since the epoch
+    pub last_cycle: u64,
+    pub interval_secs: u64,
+    /// Cycles that failed in a row
+    pub failed_cycles: u32,
+    pub last_error: Option<String>,
+}
+
+fn health_file(repo_path: &Path) -> Option<PathBuf> {
+    get_git_dir(repo_path).map(|dir| dir.join(HEALTH_FILE))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Records that a headless cycle of `config`'s repository finished, with the
+/// error if it failed. Failures are counted across cycles of this process
+/// only, so a restarted watcher starts out healthy.
+pub fn record_cycle(config: &Config, error: Option<String>) -> Result<()> {
+    let Some(file) = health_file(&config.repo_path) else {
+        return Ok(());
+    };
+    let pid = std::process::id();
+    let previous_failures = std::fs::read_to_string(&file)
+        .ok()
+        .and_then(|content| toml::from_str::<Health>(&content).ok())
+        .filter(|health| health.pid == pid)
+        .map_or(0, |health| health.failed_cycles);
+
+    let health = Health {
+        pid,
+        last_cycle: now(),
+        // The longest a watcher waits between cycles
+        interval_secs: config
+            .scan_backoff_max()
+            .unwrap_or_default()
+            .max(config.scan_interval())
+            .as_secs_f64()
+            .ceil() as u64,
+        failed_cycles: if error.is_some() {
+            previous_failures + 1
+        } else {
+            0
+        },
+        last_error: error,
+    };
+    let content = toml::to_string(&health).context("Failed to serialize health state")?;
+    std::fs::write(&file, content).with_context(|| format!("Failed to write {}", file.display()))
+}
+
+/// Returns why the watcher of a repository looks wedged, if it does: it never
+/// finished a cycle, the last one is older than `max_age_secs` (by default a
+/// few scan intervals), or the last `max_failures` cycles failed.
+fn unhealthy_reason(
+    health: Option<&Health>,
+    now: u64,
+    max_age_secs: Option<u64>,
+    max_failures: u32,
+) -> Option<String> {
+    let Some(health) = health else {
+        return Some("no headless cycle has been recorded".to_string());
+    };
+
+    let age = now.saturating_sub(health.last_cycle);
+    let max_age = max_age_secs.unwrap_or(health.interval_secs * STALE_INTERVALS + STALE_GRACE_SECS);
+    if age > max_age {
+        return Some(format!(
+            "the last cycle finished {}s ago (limit {}s)",
+            age, max_age
+        ));
+    }
+    if health.failed_cycles >= max_failures {
+        return Some(format!(
+            "the last {} cycles failed: {}",
+            health.failed_cycles,
+            health.last_error.as_deref().unwrap_or("unknown error")
+        ));
+    }
+    None
+}
+
+/// Runs `git-chai healthz` for one repository and returns whether its watcher
+/// is healthy, printing a one-line verdict.
+pub fn run_healthz(config: &Config, max_age_secs: Option<u64>, max_failures: u32) -> bool {
+    let health = health_file(&config.repo_path)
+        .and_then(|file| std::fs::read_to_string(file).ok())
+        .and_then(|content| toml::from_str::<Health>(&content).ok());
+
+    let now = now();
+    if let Some(reason) = unhealthy_reason(health.as_ref(), now, max_age_secs, max_failures) {
+        println!("unhealthy: {}: {}", config.repo_path.display(), reason);
+        return false;
+    }
+    println!(
+        "ok: {}: last cycle {}s ago",
+        config.repo_path.display(),
+        health.map_or(0, |health| now.saturating_sub(health.last_cycle))
+    );
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unhealthy_reason() {
+        let health = Health {
+            pid: 1,
+            last_cycle: 1_000,
+            interval_secs: 5,
+            failed_cycles: 0,
+            last_error: None,
+        };
+        assert_eq!(unhealthy_reason(Some(&health), 1_070, None, 3), None);
+        assert!(unhealthy_reason(Some(&health), 1_080, None, 3).is_some());
+        assert!(unhealthy_reason(Some(&health), 1_020, Some(10), 3).is_some());
+        assert!(unhealthy_reason(None, 1_000, None, 3).is_some());
+
+        let failing = Health {
+            failed_cycles: 3,
+            last_error: Some("index.lock exists".to_string()),
+            ..health
+        };
+        assert_eq!(
+            unhealthy_reason(Some(&failing), 1_010, None, 3).as_deref(),
+            Some("the last 3 cycles failed: index.lock exists")
+        );
+    }
+}
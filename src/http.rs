@@ -0,0 +1,290 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// When each host was last sent a request, shared by every client so that
+/// integrations talking to the same API space out their requests together.
+static LAST_REQUEST: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+
+/// Marks the end of the body in curl's output; the HTTP status follows it.
+const STATUS_MARKER: &str = "\n--git-chai-status:";
+
+/// HTTP client for the forge, webhook and notification integrations, built on
+/// curl. Requests time out, are retried with exponential backoff when the
+/// network or server fails (or the server asks to slow down), go through the
+/// proxy named by `HTTPS_PROXY`/`HTTP_PROXY` unless `NO_PROXY` covers the
+/// host, and are spaced at least `min_interval` apart per host.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    pub timeout: Duration,
+    /// Attempts after the first
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each further one
+    pub backoff: Duration,
+    pub min_interval: Duration,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        HttpClient {
+            timeout: Duration::from_secs(10),
+            retries: 2,
+            backoff: Duration::from_secs(1),
+            min_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Outcome of one attempt at a request.
+enum Attempt {
+    Done(Response),
+    /// Worth trying again, with the reason
+    Retry(String),
+}
+
+impl HttpClient {
+    /// Sends `body` as JSON and returns the response if its status is 2xx.
+    pub fn send_json(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[String],
+        body: &serde_json::Value,
+    ) -> Result<Response> {
+        let mut headers = headers.to_vec();
+        headers.push("Content-Type: application/json".to_string());
+        let response = self.send(method, url, &headers, Some(&body.to_string()))?;
+        if !(200..300).contains(&response.status) {
+            bail!(
+                "{} {} returned HTTP {}: {}",
+                method,
+                redact(url),
+                response.status,
+                response.body.trim()
+            );
+        }
+        Ok(response)
+    }
+
+    /// Sends a request, retrying transient failures, and returns the final
+    /// response whatever its status.
+    fn send(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[String],
+        body: Option<&str>,
+    ) -> Result<Response> {
+        let mut delay = self.backoff;
+        for attempt in 0..=self.retries {
+            self.wait_for_turn(url);
+            match self.attempt(method, url, headers, body)? {
+                Attempt::Done(response) => return Ok(response),
+                Attempt::Retry(reason) if attempt < self.retries => {
+                    log::debug!(
+                        "{} {} failed ({}); retrying in {:?}",
+                        method,
+                        redact(url),
+                        reason,
+                        delay
+                    );
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                }
+                Attempt::Retry(reason) => {
+                    bail!(
+                        "{} {} failed after {} attempts: {}",
+                        method,
+                        redact(url),
+                        self.retries + 1,
+                        reason
+                    );
+                }
+            }
+        }
+        unreachable!("the last attempt either returns or fails")
+    }
+
+    /// Sleeps until `min_interval` has passed since the last request to the
+    /// same host.
+    fn wait_for_turn(&self, url: &str) {
+        let host = host_of(url).to_string();
+        let wait = {
+            let mut last = LAST_REQUEST
+                .get_or_init(Default::default)
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let next = last
+                .get(&host)
+                .map_or(now, |previous| (*previous + self.min_interval).max(now));
+            last.insert(host, next);
+            next - now
+        };
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Runs curl once. The URL, headers and body go through its stdin as a
+    /// config file, so tokens never show up in the process list.
+    fn attempt(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &[String],
+        body: Option<&str>,
+    ) -> Result<Attempt> {
+        let mut curl_config = format!(
+            "request = \"{}\"\nurl = \"{}\"\nmax-time = {}\nwrite-out = \"{}%{{http_code}}\"\n",
+            method,
+            escape(url),
+            self.timeout.as_secs().max(1),
+            escape(STATUS_MARKER),
+        );
+        match proxy_for(url, |name| std::env::var(name).ok()) {
+            Some(proxy) => curl_config.push_str(&format!("proxy = \"{}\"\n", escape(&proxy))),
+            None => curl_config.push_str("noproxy = \"*\"\n"),
+        }
+        for header in headers {
+            curl_config.push_str(&format!("header = \"{}\"\n", escape(header)));
+        }
+        if let Some(body) = body {
+            curl_config.push_str(&format!("data-binary = \"{}\"\n", escape(body)));
+        }
+
+        let mut child = Command::new("curl")
+            .arg("--silent")
+            .arg("--show-error")
+            .arg("--config")
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut pipe) = child.stdin.take() {
+            pipe.write_all(curl_config.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (body, status) = stdout
+            .rsplit_once(STATUS_MARKER)
+            .map_or((stdout.as_ref(), ""), |(body, status)| (body, status));
+        let status: u16 = status.trim().parse().unwrap_or(0);
+        if !output.status.success() || status == 0 {
+            // Connection refused, DNS failure, timeout and the like
+            return Ok(Attempt::Retry(format!(
+                "curl failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        if status == 429 || status >= 500 {
+            return Ok(Attempt::Retry(format!("HTTP {}", status)));
+        }
+        Ok(Attempt::Done(Response {
+            status,
+            body: body.to_string(),
+        }))
+    }
+}
+
+/// `api.example.com:8443` of `https://user@api.example.com:8443/path`.
+fn host_of(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host)
+}
+
+/// The proxy to reach `url` through, from `HTTPS_PROXY` or `HTTP_PROXY` (either
+/// case) depending on its scheme, unless `NO_PROXY` lists its host or a domain
+/// it is in.
+fn proxy_for(url: &str, env: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let var = |name: &str| {
+        env(name)
+            .or_else(|| env(&name.to_lowercase()))
+            .filter(|value| !value.is_empty())
+    };
+    let host = host_of(url);
+    let host = host.rsplit_once(':').map_or(host, |(host, _)| host);
+    let bypassed = var("NO_PROXY").is_some_and(|list| {
+        list.split(',').map(str::trim).any(|entry| {
+            let domain = entry.trim_start_matches('.');
+            entry == "*"
+                || (!domain.is_empty()
+                    && (host == domain || host.ends_with(&format!(".{}", domain))))
+        })
+    });
+    if bypassed {
+        return None;
+    }
+    if url.starts_with("https://") {
+        var("HTTPS_PROXY")
+    } else {
+        var("HTTP_PROXY")
+    }
+}
+
+/// Drops the path and query from a URL for messages, since APIs such as
+/// Telegram's carry tokens there.
+fn redact(url: &str) -> String {
+    match url.split_once("://") {
+        Some((scheme, _)) => format!("{}://{}/…", scheme, host_of(url)),
+        None => host_of(url).to_string(),
+    }
+}
+
+/// Escapes a value for a double-quoted curl config entry.
+pub fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_and_host() {
+        let env = |name: &str| match name {
+            "https_proxy" => Some("http://proxy:3128".to_string()),
+            "HTTP_PROXY" => Some("http://plain-proxy:3128".to_string()),
+            "NO_PROXY" => Some("localhost, .internal.example.com".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            proxy_for("https://api.telegram.org/bot1/sendMessage", env).as_deref(),
+            Some("http://proxy:3128")
+        );
+        assert_eq!(
+            proxy_for("http://hooks.example.org/x", env).as_deref(),
+            Some("http://plain-proxy:3128")
+        );
+        assert_eq!(proxy_for("http://localhost:8080/v1", env), None);
+        assert_eq!(proxy_for("https://git.internal.example.com/api", env), None);
+        assert_eq!(proxy_for("https://api.example.com", |_| None), None);
+
+        assert_eq!(
+            host_of("https://user@api.example.com:8443/v1?q=1"),
+            "api.example.com:8443"
+        );
+        assert_eq!(
+            redact("https://api.telegram.org/bot123:secret/sendMessage"),
+            "https://api.telegram.org/…"
+        );
+    }
+}
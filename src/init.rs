@@ -0,0 +1,205 @@
+use anyhow::{Result, bail};
+use std::path::Path;
+
+use crate::config::{CONFIG_FILE_NAME, Config};
+
+/// Starting configurations for the most common kinds of autosaved repository.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum InitTemplate {
+    /// An Obsidian (or other Markdown) note vault, synced between devices
+    Obsidian,
+    /// A dotfiles repository of configuration from the home directory
+    Dotfiles,
+    /// The sources of a static site (Hugo, Jekyll, Eleventy, ...)
+    Blog,
+    /// A software project, where autosaves go alongside hand-written commits
+    Code,
+}
+
+const OBSIDIAN_CONFIG: &str = r#"# git-chai configuration for a note vault (git-chai init --template obsidian)
+# Run `git-chai config schema` for every setting.
+
+# One "notes: 2024-06-01 (14 files)" commit per day, titled after the note's heading
+# when only one changed; editor swap and lock files and the window layout are skipped
+notes_mode = true
+# Notes don't need committing every few seconds; run with --watch to react to saves
+scan_interval_secs = 30
+# Keep other devices in sync
+push_by_default = true
+
+# A note being written changes on every scan; commit it once it has been left alone
+[[repeat_change_rules]]
+pattern = "**/*.md"
+policy = "hold"
+quiet_minutes = 5
+
+# The vault's trash
+[[path_policies]]
+pattern = ".trash/**"
+policy = "never-commit"
+"#;
+
+const DOTFILES_CONFIG: &str = r#"# git-chai configuration for dotfiles (git-chai init --template dotfiles)
+# Run `git-chai config schema` for every setting.
+
+# Paths are more telling than names here ("mod: .config/nvim/init.lua")
+commit_message_template = "{change_type}: {path}"
+# Each configuration file is its own commit
+min_files_for_directory_commit = 10
+scan_interval_secs = 60
+# Review before publishing; configuration tends to pick up tokens and hostnames
+push_by_default = false
+# Record which machine a change was made on
+commit_trailer = "Autosave-Host: {hostname}"
+encoding_guard = true
+
+# Secrets, history and caches that don't belong in a dotfiles repository
+[[path_policies]]
+pattern = ".ssh/id_*"
+policy = "never-commit"
+[[path_policies]]
+pattern = ".gnupg/**"
+policy = "never-commit"
+[[path_policies]]
+pattern = "**/*.pem"
+policy = "never-commit"
+[[path_policies]]
+pattern = ".netrc"
+policy = "never-commit"
+[[path_policies]]
+pattern = "**/.*_history"
+policy = "never-commit"
+[[path_policies]]
+pattern = ".cache/**"
+policy = "never-commit"
+"#;
+
+const BLOG_CONFIG: &str = r#"# git-chai configuration for a static site (git-chai init --template blog)
+# Run `git-chai config schema` for every setting.
+
+# Capitalized subjects without file extensions
+commit_message_template = "{change_type}: {name}"
+strip_extensions = true
+subject_case = "sentence"
+# A post is named after its front matter title or heading ("mod: Hello world")
+content_titles = true
+# A post with its images is one commit
+min_files_for_directory_commit = 2
+scan_interval_secs = 60
+push_by_default = true
+# Publishing is up to hand-written commits; autosaves don't trigger a deploy
+skip_ci = true
+
+# Drafts are saved often while being written
+[[repeat_change_rules]]
+pattern = "**/*.md"
+policy = "hold"
+quiet_minutes = 10
+
+# Generated site and caches of Hugo, Jekyll and Eleventy
+[[path_policies]]
+pattern = "public/**"
+policy = "never-commit"
+[[path_policies]]
+pattern = "_site/**"
+policy = "never-commit"
+[[path_policies]]
+pattern = "resources/_gen/**"
+policy = "never-commit"
+[[path_policies]]
+pattern = ".jekyll-cache/**"
+policy = "never-commit"
+"#;
+
+const CODE_CONFIG: &str = r#"# git-chai configuration for a software project (git-chai init --template code)
+# Run `git-chai config schema` for every setting.
+
+commit_message_template = "{change_type}: {path}"
+# Write subjects in the style of the project's recent hand-written commits
+learn_message_style = true
+min_files_for_directory_commit = 2
+scan_interval_secs = 10
+# Autosaves stay local until you push them (or run with --push)
+push_by_default = false
+# Autosaves don't need a CI run each
+skip_ci = true
+# Don't commit half-resolved merges or files that don't parse
+allow_conflict_markers = false
+syntax_check = ["python", "javascript"]
+
+# Local environment and keys
+[[path_policies]]
+pattern = ".env"
+policy = "never-commit"
+[[path_policies]]
+pattern = ".env.*"
+policy = "never-commit"
+[[path_policies]]
+pattern = "**/*.pem"
+policy = "never-commit"
+[[path_policies]]
+pattern = "**/*.key"
+policy = "never-commit"
+"#;
+
+impl InitTemplate {
+    fn config(self) -> &'static str {
+        match self {
+            InitTemplate::Obsidian => OBSIDIAN_CONFIG,
+            InitTemplate::Dotfiles => DOTFILES_CONFIG,
+            InitTemplate::Blog => BLOG_CONFIG,
+            InitTemplate::Code => CODE_CONFIG,
+        }
+    }
+}
+
+/// Runs `git-chai init`: writes `.chai.toml` to the repository root, from
+/// `template` or the commented defaults. An existing file is only replaced
+/// with `force`.
+pub fn run_init(repo_root: &Path, template: Option<InitTemplate>, force: bool) -> Result<()> {
+    if Config::file_exists(repo_root) && !force {
+        bail!(
+            "{} already exists in {}; pass --force to replace it",
+            CONFIG_FILE_NAME,
+            repo_root.display()
+        );
+    }
+
+    let path = match template {
+        Some(template) => {
+            let path = repo_root.join(CONFIG_FILE_NAME);
+            std::fs::write(&path, template.config())?;
+            path
+        }
+        None => Config::write_default(repo_root)?,
+    };
+    println!(
+        "Wrote {}. Review it, then run git-chai to start committing.",
+        path.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+
+    #[test]
+    fn test_templates_are_valid_configs() {
+        let supported = Config::supported_keys();
+        for template in InitTemplate::value_variants() {
+            let table: toml::Table = toml::from_str(template.config()).unwrap();
+            for key in table.keys() {
+                assert!(
+                    supported.contains(key),
+                    "{:?}: unknown key {}",
+                    template,
+                    key
+                );
+            }
+            let config: Config = toml::from_str(template.config()).unwrap();
+            assert!(!config.path_policies.is_empty(), "{:?}", template);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! The git-chai auto-commit engine, for tools that want to commit a
+//! repository's changes the way the `git-chai` command does without running
+//! it.
+//!
+//! ```no_run
+//! use git_chai::{ChaiSession, Config};
+//!
+//! let config = Config::load(std::path::Path::new("/home/me/notes"), None)?;
+//! let mut session = ChaiSession::new(config)?;
+//! let commits = session.process_once()?;
+//! println!("{} commit(s)", commits);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! [`ChaiSession::run`] keeps committing on the caller's tokio runtime until a
+//! shutdown future completes, as `git-chai --headless` does until Ctrl+C.
+
+mod ai;
+mod approval;
+mod artifacts;
+mod bisect;
+mod capabilities;
+mod chaiignore;
+mod check;
+mod cli;
+mod codeowners;
+mod config;
+mod conflict_markers;
+mod control;
+mod crypt;
+#[cfg(target_os = "linux")]
+mod dbus;
+mod doctor;
+mod encoding;
+mod error;
+mod export;
+mod failed;
+mod git;
+mod gitignore;
+mod graft;
+mod headless;
+mod health;
+mod http;
+mod init;
+mod log_dedup;
+mod notebook;
+mod notify;
+mod observe;
+mod outbox;
+mod pause;
+mod plan;
+mod policy;
+mod provenance;
+mod push;
+mod rate_limit;
+mod repeat;
+mod repo_access;
+mod report;
+mod retract;
+mod scheduler;
+mod session;
+mod session_merge;
+mod skipped;
+mod snapshot;
+mod summary;
+mod syntax_check;
+mod team_policy;
+mod template;
+#[cfg(test)]
+mod test_repo;
+mod time_tracking;
+mod title;
+#[cfg(target_os = "linux")]
+mod tray;
+mod types;
+mod vault;
+mod version;
+mod watch;
+
+pub use config::Config;
+pub use git::status::GitChange;
+pub use plan::ChangeGroup;
+pub use session::ChaiSession;
+
+/// The `git-chai` command itself, which the binary runs; not part of the
+/// library's API.
+#[doc(hidden)]
+pub use cli::run as run_cli;
@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+
+/// How often a message that keeps repeating is summarized.
+const SUMMARY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Collapses a message that recurs on every headless cycle (such as a push that
+/// keeps failing authentication) into periodic summaries. The message is logged
+/// in full the first time and whenever it changes.
+#[derive(Debug, Default)]
+pub struct RepeatedMessage {
+    last: Option<String>,
+    /// Repeats of `last` not yet reported
+    repeats: u32,
+    /// When `last` was last logged or summarized
+    reported_at: Option<Instant>,
+}
+
+impl RepeatedMessage {
+    /// Logs `message` at `level` unless it repeats the previous one. Returns true
+    /// if the message was logged in full, so callers can add follow-up hints.
+    pub fn report(&mut self, level: log::Level, message: String) -> bool {
+        let lines = self.next(message, Instant::now());
+        let full = lines
+            .last()
+            .is_some_and(|line| Some(line) == self.last.as_ref());
+        for line in lines {
+            log::log!(level, "{}", line);
+        }
+        full
+    }
+
+    /// Forgets the message once its cause has gone away, reporting any repeats
+    /// that were not summarized yet.
+    pub fn resolve(&mut self) {
+        if let Some(last) = self.last.take()
+            && self.repeats > 0
+        {
+            log::info!(
+                "Resolved after {} more occurrence(s): {}",
+                self.repeats,
+                first_line(&last)
+            );
+        }
+        self.repeats = 0;
+        self.reported_at = None;
+    }
+
+    /// Returns the lines to log for this occurrence of `message`.
+    fn next(&mut self, message: String, now: Instant) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        if self.last.as_ref() == Some(&message) {
+            self.repeats += 1;
+            let due = self
+                .reported_at
+                .is_none_or(|at| now.duration_since(at) >= SUMMARY_INTERVAL);
+            if due {
+                lines.push(summary(self.repeats, &message));
+                self.repeats = 0;
+                self.reported_at = Some(now);
+            }
+            return lines;
+        }
+
+        if let Some(last) = &self.last
+            && self.repeats > 0
+        {
+            lines.push(summary(self.repeats, last));
+        }
+        lines.push(message.clone());
+        self.last = Some(message);
+        self.repeats = 0;
+        self.reported_at = Some(now);
+        lines
+    }
+}
+
+fn summary(repeats: u32, message: &str) -> String {
+    format!("Repeated {} more time(s): {}", repeats, first_line(message))
+}
+
+fn first_line(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_collapses_repeats_until_changed() {
+        let mut repeated = RepeatedMessage::default();
+        let start = Instant::now();
+        let auth = "Failed to push: authentication failed\nremote: denied".to_string();
+
+        assert_eq!(repeated.next(auth.clone(), start), vec![auth.clone()]);
+        assert!(repeated.next(auth.clone(), start).is_empty());
+        assert!(repeated.next(auth.clone(), start).is_empty());
+        assert_eq!(
+            repeated.next(auth.clone(), start + SUMMARY_INTERVAL),
+            vec!["Repeated 3 more time(s): Failed to push: authentication failed"]
+        );
+
+        assert!(
+            repeated
+                .next(auth.clone(), start + SUMMARY_INTERVAL)
+                .is_empty()
+        );
+        let offline = "Failed to push: could not resolve host".to_string();
+        assert_eq!(
+            repeated.next(offline.clone(), start + SUMMARY_INTERVAL),
+            vec![
+                "Repeated 1 more time(s): Failed to push: authentication failed".to_string(),
+                offline,
+            ]
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use serde::Serialize;
+use serde_json::Value;
+use serde_json::ser::{PrettyFormatter, Serializer};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::git::isolated::{TempIndex, run_git, stdout_of};
+
+static TEMP_BLOB_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns true if `path` is a Jupyter notebook.
+pub fn is_notebook(path: &str) -> bool {
+    path.to_lowercase().ends_with(".ipynb")
+}
+
+/// Returns a Jupyter notebook with the outputs and execution counts of its code
+/// cells cleared, written the way Jupyter writes notebooks (sorted keys, one
+/// space of indentation), or `None` if `content` isn't a notebook.
+pub fn strip_outputs(content: &[u8]) -> Option<Vec<u8>> {
+    let mut notebook: Value = serde_json::from_slice(content).ok()?;
+    for cell in notebook.get_mut("cells")?.as_array_mut()? {
+        if cell.get("cell_type").and_then(Value::as_str) != Some("code") {
+            continue;
+        }
+        if let Some(cell) = cell.as_object_mut() {
+            cell.insert("outputs".to_string(), Value::Array(Vec::new()));
+            cell.insert("execution_count".to_string(), Value::Null);
+        }
+    }
+
+    let mut stripped = Vec::new();
+    let mut serializer =
+        Serializer::with_formatter(&mut stripped, PrettyFormatter::with_indent(b" "));
+    notebook.serialize(&mut serializer).ok()?;
+    stripped.push(b'\n');
+    Some(stripped)
+}
+
+/// The cells of a notebook, by id (or position, before nbformat 4.5 gave cells
+/// ids), with their type and source.
+fn cells(content: &[u8]) -> Option<HashMap<String, (String, String)>> {
+    let notebook: Value = serde_json::from_slice(content).ok()?;
+    let cells = notebook.get("cells")?.as_array()?;
+    Some(
+        cells
+            .iter()
+            .enumerate()
+            .map(|(position, cell)| {
+                let key = match cell.get("id").and_then(Value::as_str) {
+                    Some(id) => id.to_string(),
+                    None => format!("#{}", position),
+                };
+                let kind = cell
+                    .get("cell_type")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                let source = match cell.get("source") {
+                    Some(Value::String(source)) => source.clone(),
+                    Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+                    _ => String::new(),
+                };
+                (key, (kind, source))
+            })
+            .collect(),
+    )
+}
+
+/// Summarizes how the cells of a notebook changed from `before` (`None` for a
+/// new notebook) to `after`, e.g. `cells: 2 edited, 1 added`. Returns `None`
+/// if no cell was added, removed or edited.
+pub fn cell_summary(before: Option<&[u8]>, after: &[u8]) -> Option<String> {
+    let before = match before {
+        Some(before) => cells(before)?,
+        None => HashMap::new(),
+    };
+    let after = cells(after)?;
+
+    let added = after
+        .keys()
+        .filter(|key| !before.contains_key(*key))
+        .count();
+    let removed = before
+        .keys()
+        .filter(|key| !after.contains_key(*key))
+        .count();
+    let edited = after
+        .iter()
+        .filter(|(key, cell)| before.get(*key).is_some_and(|old| old != *cell))
+        .count();
+
+    let parts: Vec<String> = [(edited, "edited"), (added, "added"), (removed, "removed")]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, what)| format!("{} {}", count, what))
+        .collect();
+    (!parts.is_empty()).then(|| format!("cells: {}", parts.join(", ")))
+}
+
+/// Reads a blob (`HEAD:path`, `:path` or a SHA) of the repository, or `None`
+/// if it doesn't exist.
+fn read_blob(repo_path: &Path, object: &str) -> Option<Vec<u8>> {
+    let output = git_command(repo_path)
+        .arg("cat-file")
+        .arg("blob")
+        .arg(object)
+        .output()
+        .ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+/// The cell summary of the notebook at `path` as it is now against HEAD.
+pub fn notebook_cell_summary(repo_path: &Path, path: &str) -> Option<String> {
+    let after = std::fs::read(repo_path.join(path)).ok()?;
+    cell_summary(
+        read_blob(repo_path, &format!("HEAD:{}", path)).as_deref(),
+        &after,
+    )
+}
+
+/// Returns true if the notebook at `path` differs from HEAD only in its
+/// outputs and execution counts, which `strip_notebook_outputs` leaves out.
+pub fn only_outputs_changed(repo_path: &Path, path: &str) -> bool {
+    let Some(head) = read_blob(repo_path, &format!("HEAD:{}", path)) else {
+        return false;
+    };
+    std::fs::read(repo_path.join(path))
+        .ok()
+        .and_then(|content| strip_outputs(&content))
+        .is_some_and(|stripped| strip_outputs(&head) == Some(stripped))
+}
+
+/// Replaces the notebooks staged in the index (or in `index`) with their
+/// outputs stripped. The working copies keep their outputs.
+pub(crate) fn strip_staged_notebooks(
+    repo_path: &Path,
+    index: Option<&TempIndex>,
+) -> Result<(), GitChaiError> {
+    let staged = stdout_of(run_git(
+        repo_path,
+        index,
+        &[
+            "diff",
+            "--cached",
+            "--name-only",
+            "--diff-filter=AM",
+            "--no-renames",
+            "--",
+            "*.ipynb",
+        ],
+    )?);
+
+    for path in staged.lines() {
+        let entry = stdout_of(run_git(
+            repo_path,
+            index,
+            &["ls-files", "--stage", "--", path],
+        )?);
+        let mut fields = entry.split_whitespace();
+        let (Some(mode), Some(sha)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Some(blob) = read_blob(repo_path, sha) else {
+            continue;
+        };
+        let Some(stripped) = strip_outputs(&blob).filter(|stripped| *stripped != blob) else {
+            continue;
+        };
+
+        let temp = std::env::temp_dir().join(format!(
+            "git-chai-notebook-{}-{}",
+            std::process::id(),
+            TEMP_BLOB_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::write(&temp, &stripped)?;
+        let written = run_git(
+            repo_path,
+            None,
+            &["hash-object", "-w", "--no-filters", &temp.to_string_lossy()],
+        );
+        let _ = std::fs::remove_file(&temp);
+        let stripped_sha = stdout_of(written?);
+
+        run_git(
+            repo_path,
+            index,
+            &[
+                "update-index",
+                "--cacheinfo",
+                &format!("{},{},{}", mode, stripped_sha, path),
+            ],
+        )?;
+        log::debug!("Stripped the outputs of {} before committing", path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_outputs_and_summarize_cells() {
+        let before = br##"{"cells": [
+            {"cell_type": "markdown", "id": "a1", "metadata": {}, "source": ["# Analysis"]},
+            {"cell_type": "code", "id": "b2", "execution_count": 3, "metadata": {},
+             "outputs": [{"output_type": "stream", "name": "stdout", "text": ["42\n"]}],
+             "source": ["print(6 * 7)"]},
+            {"cell_type": "code", "id": "c3", "execution_count": 4, "metadata": {}, "outputs": [], "source": "x = 1"}
+        ], "metadata": {}, "nbformat": 4, "nbformat_minor": 5}"##;
+        let stripped = String::from_utf8(strip_outputs(before).unwrap()).unwrap();
+        assert!(stripped.starts_with("{\n \"cells\": [\n  {\n   \"cell_type\": \"markdown\""));
+        assert!(stripped.contains("\"execution_count\": null"));
+        assert!(!stripped.contains("stdout"));
+        assert!(stripped.ends_with("}\n"));
+        assert_eq!(
+            strip_outputs(stripped.as_bytes()).unwrap(),
+            stripped.as_bytes()
+        );
+        assert_eq!(strip_outputs(b"not json"), None);
+
+        let after = br##"{"cells": [
+            {"cell_type": "markdown", "id": "a1", "metadata": {}, "source": ["# Analysis"]},
+            {"cell_type": "code", "id": "b2", "execution_count": 9, "metadata": {}, "outputs": [],
+             "source": ["print(6 * 9)"]},
+            {"cell_type": "code", "id": "d4", "execution_count": null, "metadata": {}, "outputs": [], "source": []},
+            {"cell_type": "code", "id": "e5", "execution_count": null, "metadata": {}, "outputs": [], "source": []}
+        ], "metadata": {}, "nbformat": 4, "nbformat_minor": 5}"##;
+        assert_eq!(
+            cell_summary(Some(before), after).as_deref(),
+            Some("cells: 1 edited, 2 added, 1 removed")
+        );
+        assert_eq!(
+            cell_summary(None, before).as_deref(),
+            Some("cells: 3 added")
+        );
+        assert_eq!(cell_summary(Some(before), stripped.as_bytes()), None);
+    }
+}
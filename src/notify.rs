@@ -0,0 +1,418 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::config::{Config, NotifySeverity};
+use crate::http::{HttpClient, escape};
+use crate::outbox::{Outbox, QueuedNotification};
+use crate::template::{environment_value, format_utc};
+
+/// Keeps message file names and Matrix transaction IDs unique within the process.
+static UNIQUE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Something that happened in a repository and may need attention.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub severity: NotifySeverity,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Where a notification is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Email,
+    Matrix,
+    Telegram,
+}
+
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Channel::Email => write!(f, "email"),
+            Channel::Matrix => write!(f, "Matrix"),
+            Channel::Telegram => write!(f, "Telegram"),
+        }
+    }
+}
+
+/// Sends notifications to the channels configured for a repository (email,
+/// Matrix, Telegram), holding emails for a digest when
+/// `notify_email_digest_minutes` is set. Notifications that can't be sent are
+/// queued in the repository's outbox and retried on later flushes.
+#[derive(Debug, Default)]
+pub struct Notifier {
+    digest: Vec<Notification>,
+    /// When the first notification of the pending digest arrived
+    digest_started: Option<Instant>,
+    /// Notifications waiting to be retried, loaded on first use
+    outbox: Option<Outbox>,
+}
+
+impl Notifier {
+    pub fn notify(
+        &mut self,
+        config: &Config,
+        severity: NotifySeverity,
+        subject: impl Into<String>,
+        body: impl Into<String>,
+    ) {
+        let notification = Notification {
+            severity,
+            subject: subject.into(),
+            body: body.into(),
+        };
+
+        let mut channels = Vec::new();
+        if config.notify_matrix_homeserver.is_some()
+            && config.notify_matrix_room.is_some()
+            && severity >= config.notify_matrix_min_severity
+        {
+            channels.push(Channel::Matrix);
+        }
+        if config.notify_telegram_chat_id.is_some()
+            && severity >= config.notify_telegram_min_severity
+        {
+            channels.push(Channel::Telegram);
+        }
+        if email_enabled(config) && severity >= config.notify_email_min_severity {
+            if config.notify_email_digest_minutes.is_some() {
+                log::debug!(
+                    "Holding notification for the digest: {}",
+                    notification.subject
+                );
+                self.digest_started.get_or_insert_with(Instant::now);
+                self.digest.push(notification.clone());
+            } else {
+                channels.push(Channel::Email);
+            }
+        }
+
+        for channel in channels {
+            if let Err(e) = deliver(config, channel, &notification.subject, &notification.body) {
+                log::warn!("Failed to send {} notification: {:#}", channel, e);
+                self.queue(config, channel, &notification.subject, &notification.body);
+            }
+        }
+    }
+
+    /// Retries queued notifications, then sends the pending digest once
+    /// `notify_email_digest_minutes` have passed since its first notification,
+    /// or right away with `force` (on shutdown).
+    pub fn flush(&mut self, config: &Config, force: bool) {
+        self.retry_queued(config);
+
+        let Some(started) = self.digest_started else {
+            return;
+        };
+        let interval = Duration::from_secs(config.notify_email_digest_minutes.unwrap_or(0) * 60);
+        if !force && started.elapsed() < interval {
+            return;
+        }
+
+        let (subject, body) = digest_message(&config.repo_path.display().to_string(), &self.digest);
+        match deliver(config, Channel::Email, &subject, &body) {
+            Ok(()) => {}
+            Err(e) if config.notify_queue_max_age_minutes > 0 => {
+                log::warn!("Failed to send notification digest: {:#}", e);
+                self.queue(config, Channel::Email, &subject, &body);
+            }
+            Err(e) => {
+                // Try again with whatever has been added by then
+                log::warn!("Failed to send notification digest: {:#}", e);
+                return;
+            }
+        }
+        self.digest.clear();
+        self.digest_started = None;
+    }
+
+    fn outbox(&mut self, config: &Config) -> &mut Outbox {
+        self.outbox
+            .get_or_insert_with(|| Outbox::load(&config.repo_path))
+    }
+
+    /// Keeps a notification that couldn't be sent for a later flush, unless
+    /// `notify_queue_max_age_minutes` is 0.
+    fn queue(&mut self, config: &Config, channel: Channel, subject: &str, body: &str) {
+        if config.notify_queue_max_age_minutes == 0 {
+            return;
+        }
+        let outbox = self.outbox(config);
+        outbox.push(QueuedNotification {
+            channel,
+            subject: subject.to_string(),
+            body: body.to_string(),
+            queued_at: now(),
+        });
+        if let Err(e) = outbox.save() {
+            log::warn!("Failed to queue {} notification: {:#}", channel, e);
+        }
+    }
+
+    /// Sends the queued notifications that haven't expired, noting in each
+    /// when it was first meant to go out.
+    fn retry_queued(&mut self, config: &Config) {
+        let outbox = self.outbox(config);
+        if outbox.is_empty() {
+            return;
+        }
+        let delivered = outbox.deliver(now(), config.notify_queue_max_age_minutes * 60, |queued| {
+            let body = format!(
+                "{}\n\nQueued at {} while notifications could not be sent.",
+                queued.body.trim_end(),
+                format_utc(queued.queued_at as i64)
+            );
+            deliver(config, queued.channel, &queued.subject, &body)
+        });
+        if delivered > 0 {
+            log::info!("Sent {} queued notification(s)", delivered);
+        }
+        if let Err(e) = outbox.save() {
+            log::warn!("Failed to save queued notifications: {:#}", e);
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn email_enabled(config: &Config) -> bool {
+    !config.notify_email_to.is_empty() && config.notify_smtp_url.is_some()
+}
+
+/// Sends one notification to `channel`. Tokens and passwords are read from the
+/// environment each time, so a queued notification uses the current ones.
+fn deliver(config: &Config, channel: Channel, subject: &str, body: &str) -> Result<()> {
+    let subject = format!("[git-chai] {}", subject);
+    let text = format!("{}\n\n{}", subject, body);
+    match channel {
+        Channel::Email => send_email(config, &subject, body),
+        Channel::Matrix => {
+            let (Some(homeserver), Some(room)) =
+                (&config.notify_matrix_homeserver, &config.notify_matrix_room)
+            else {
+                bail!("Matrix notifications are not configured");
+            };
+            let token = std::env::var(&config.notify_matrix_token_env).unwrap_or_default();
+            send_matrix(homeserver, room, &token, &text)
+        }
+        Channel::Telegram => {
+            let Some(chat_id) = &config.notify_telegram_chat_id else {
+                bail!("Telegram notifications are not configured");
+            };
+            let token = std::env::var(&config.notify_telegram_token_env).unwrap_or_default();
+            send_telegram(&token, chat_id, &text)
+        }
+    }
+}
+
+fn digest_message(repo: &str, notifications: &[Notification]) -> (String, String) {
+    let subject = format!("{} notification(s) for {}", notifications.len(), repo);
+    let body = notifications
+        .iter()
+        .map(|n| format!("[{}] {}\n{}\n", n.severity, n.subject, n.body))
+        .collect::<Vec<_>>()
+        .join("\n");
+    (subject, body)
+}
+
+fn email_message(from: &str, to: &[String], subject: &str, body: &str) -> String {
+    format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+        from,
+        to.join(", "),
+        subject,
+        body.replace('\n', "\r\n")
+    )
+}
+
+/// Sends an email through `notify_smtp_url` with curl. The message goes through
+/// a temporary file and the credentials through curl's stdin, so neither shows
+/// up in the process list.
+fn send_email(config: &Config, subject: &str, body: &str) -> Result<()> {
+    let Some(url) = &config.notify_smtp_url else {
+        bail!("notify_smtp_url is not set");
+    };
+    let Some(from) = config
+        .notify_email_from
+        .as_ref()
+        .or(config.notify_smtp_user.as_ref())
+    else {
+        bail!("set notify_email_from (or notify_smtp_user) to send notification emails");
+    };
+
+    let message_file = std::env::temp_dir().join(format!(
+        "git-chai-email-{}-{}",
+        std::process::id(),
+        UNIQUE_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    std::fs::write(
+        &message_file,
+        email_message(from, &config.notify_email_to, subject, body),
+    )?;
+
+    let mut command = Command::new("curl");
+    command
+        .arg("--silent")
+        .arg("--show-error")
+        .arg("--url")
+        .arg(smtp_url(url))
+        .arg("--mail-from")
+        .arg(mail_address(from))
+        .arg("--upload-file")
+        .arg(&message_file)
+        .arg("--config")
+        .arg("-");
+    for to in &config.notify_email_to {
+        command.arg("--mail-rcpt").arg(mail_address(to));
+    }
+
+    let mut credentials = String::new();
+    if let Some(user) = &config.notify_smtp_user {
+        let password = std::env::var(&config.notify_smtp_password_env).unwrap_or_default();
+        credentials = format!("user = \"{}:{}\"\n", escape(user), escape(&password));
+        // Never send the password in the clear
+        command.arg("--ssl-reqd");
+    }
+
+    let result = run_curl(&mut command, credentials.as_bytes());
+    let _ = std::fs::remove_file(&message_file);
+    result
+}
+
+fn run_curl(command: &mut Command, stdin: &[u8]) -> Result<()> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut pipe) = child.stdin.take() {
+        pipe.write_all(stdin)?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "curl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Posts a text message to a Matrix room with the client-server API.
+fn send_matrix(homeserver: &str, room: &str, token: &str, text: &str) -> Result<()> {
+    let transaction = format!(
+        "git-chai-{}-{}",
+        std::process::id(),
+        UNIQUE_COUNTER.fetch_add(1, Ordering::SeqCst)
+    );
+    let url = matrix_send_url(homeserver, room, &transaction);
+    let body = serde_json::json!({ "msgtype": "m.text", "body": text });
+    HttpClient::default().send_json(
+        "PUT",
+        &url,
+        &[format!("Authorization: Bearer {}", token)],
+        &body,
+    )?;
+    Ok(())
+}
+
+fn matrix_send_url(homeserver: &str, room: &str, transaction: &str) -> String {
+    let room: String = room
+        .chars()
+        .map(|c| match c {
+            '!' => "%21".to_string(),
+            ':' => "%3A".to_string(),
+            '#' => "%23".to_string(),
+            c => c.to_string(),
+        })
+        .collect();
+    format!(
+        "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+        homeserver.trim_end_matches('/'),
+        room,
+        transaction
+    )
+}
+
+/// Sends a message from a Telegram bot to a chat.
+fn send_telegram(token: &str, chat_id: &str, text: &str) -> Result<()> {
+    let url = format!("https://api.telegram.org/bot{}/sendMessage", token);
+    let body = serde_json::json!({ "chat_id": chat_id, "text": text });
+    HttpClient::default().send_json("POST", &url, &[], &body)?;
+    Ok(())
+}
+
+/// curl takes the path of an SMTP URL as the EHLO domain, and without one it
+/// would use the name of the uploaded message file, so the hostname is filled in.
+fn smtp_url(url: &str) -> String {
+    let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    if after_scheme.trim_end_matches('/').contains('/') {
+        return url.to_string();
+    }
+    let hostname = environment_value("hostname").unwrap_or_default();
+    format!("{}/{}", url.trim_end_matches('/'), hostname)
+}
+
+/// The bare address of `Name <address>`, as SMTP envelopes expect.
+fn mail_address(mailbox: &str) -> &str {
+    match (mailbox.rfind('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_email_and_digest_messages() {
+        assert_eq!(
+            mail_address("git-chai <chai@example.com>"),
+            "chai@example.com"
+        );
+        assert_eq!(mail_address("me@example.com"), "me@example.com");
+        assert_eq!(
+            smtp_url("smtps://smtp.example.com:465/client.example.com"),
+            "smtps://smtp.example.com:465/client.example.com"
+        );
+        assert!(
+            smtp_url("smtps://smtp.example.com:465/").starts_with("smtps://smtp.example.com:465/")
+        );
+        assert!(!smtp_url("smtp://localhost").ends_with("localhost/"));
+        assert_eq!(
+            matrix_send_url("https://matrix.example.org/", "!abc:example.org", "t1"),
+            "https://matrix.example.org/_matrix/client/v3/rooms/%21abc%3Aexample.org/send/m.room.message/t1"
+        );
+
+        let message = email_message(
+            "git-chai <chai@example.com>",
+            &["a@example.com".to_string(), "b@example.com".to_string()],
+            "[git-chai] Push failing",
+            "line one\nline two",
+        );
+        assert!(message.starts_with("From: git-chai <chai@example.com>\r\n"));
+        assert!(message.contains("To: a@example.com, b@example.com\r\n"));
+        assert!(message.ends_with("\r\n\r\nline one\r\nline two\r\n"));
+
+        let notifications = vec![Notification {
+            severity: NotifySeverity::Critical,
+            subject: "Push failing".to_string(),
+            body: "auth failed".to_string(),
+        }];
+        let (subject, body) = digest_message("/srv/notes", &notifications);
+        assert_eq!(subject, "1 notification(s) for /srv/notes");
+        assert_eq!(body, "[critical] Push failing\nauth failed\n");
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::git::commit::{CommitSettings, with_suffix};
+use crate::git::get_git_dir;
+use crate::skipped::SkippedPaths;
+
+/// File in the git directory that `--observe` appends one JSON line per scan to.
+pub const OBSERVE_FILE: &str = "chai-observe.jsonl";
+
+/// A commit git-chai would have made.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedCommit {
+    /// The full message, as it would be written
+    pub message: String,
+    pub paths: Vec<String>,
+    /// Set if the commit would go to a branch other than the checked-out one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+/// What one scan would have done, collected by `--observe` instead of doing it.
+#[derive(Debug, Default)]
+pub struct Observation {
+    commits: Vec<PlannedCommit>,
+}
+
+#[derive(Serialize)]
+struct ObservationRecord<'a> {
+    time: u64,
+    repo: &'a Path,
+    commits: &'a [PlannedCommit],
+    /// Paths left uncommitted, with the reason
+    skipped: BTreeMap<&'a str, &'a str>,
+    push: bool,
+}
+
+impl Observation {
+    /// Records a commit of `paths` with the generated `message`, as `settings`
+    /// would write it (to `branch`, if not the checked-out one).
+    pub fn plan(
+        &mut self,
+        settings: &CommitSettings,
+        message: &str,
+        paths: Vec<String>,
+        branch: Option<&str>,
+    ) {
+        self.commits.push(PlannedCommit {
+            message: with_suffix(settings, message),
+            paths,
+            branch: branch.map(str::to_string),
+        });
+    }
+
+    /// Appends the scan to `.git/chai-observe.jsonl`. Scans that would neither
+    /// commit nor skip anything are not recorded.
+    pub fn write(&self, repo_path: &Path, skipped: &SkippedPaths, push: bool) -> Result<()> {
+        let Some(line) = self.record(repo_path, skipped, push, SystemTime::now()) else {
+            return Ok(());
+        };
+        let Some(file) = get_git_dir(repo_path).map(|dir| dir.join(OBSERVE_FILE)) else {
+            return Ok(());
+        };
+
+        let mut log = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file)
+            .with_context(|| format!("Failed to open {}", file.display()))?;
+        writeln!(log, "{}", line).with_context(|| format!("Failed to write {}", file.display()))?;
+        log::info!(
+            "Observed: {} commit(s) would be made; recorded in {}",
+            self.commits.len(),
+            file.display()
+        );
+        Ok(())
+    }
+
+    fn record(
+        &self,
+        repo_path: &Path,
+        skipped: &SkippedPaths,
+        push: bool,
+        now: SystemTime,
+    ) -> Option<String> {
+        if self.commits.is_empty() && skipped.reasons().is_empty() {
+            return None;
+        }
+        let record = ObservationRecord {
+            time: now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            repo: repo_path,
+            commits: &self.commits,
+            skipped: skipped
+                .reasons()
+                .iter()
+                .map(|(path, reason)| (path.as_str(), reason.as_str()))
+                .collect(),
+            push: push && !self.commits.is_empty(),
+        };
+        serde_json::to_string(&record).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_record_is_one_json_line() {
+        let repo = Path::new("/nonexistent/observe-test");
+        let mut observation = Observation::default();
+        let mut skipped = SkippedPaths::default();
+        assert_eq!(observation.record(repo, &skipped, true, UNIX_EPOCH), None);
+
+        let settings = CommitSettings::default();
+        observation.plan(&settings, "mod: a.txt", vec!["a.txt".to_string()], None);
+        observation.plan(
+            &settings,
+            "add: api/",
+            vec!["api/".to_string()],
+            Some("pr/api"),
+        );
+        skipped.skip("dist/", "looks like build output");
+        assert_eq!(
+            observation
+                .record(repo, &skipped, true, UNIX_EPOCH + Duration::from_secs(60))
+                .unwrap(),
+            r#"{"time":60,"repo":"/nonexistent/observe-test","commits":[{"message":"mod: a.txt","paths":["a.txt"]},{"message":"add: api/","paths":["api/"],"branch":"pr/api"}],"skipped":{"dist/":"looks like build output"},"push":true}"#
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::git::get_git_dir;
+use crate::notify::Channel;
+
+/// File in the git directory holding notifications that couldn't be sent yet.
+pub const OUTBOX_FILE: &str = "chai-outbox.toml";
+
+/// A notification that failed to go out, kept for a later attempt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedNotification {
+    pub channel: Channel,
+    pub subject: String,
+    pub body: String,
+    /// When it was first meant to be sent, in seconds since the epoch
+    pub queued_at: u64,
+}
+
+/// Notifications waiting for the network to come back, persisted across runs
+/// so that alerts raised while offline aren't lost.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Outbox {
+    #[serde(default)]
+    notifications: Vec<QueuedNotification>,
+    #[serde(skip)]
+    file: Option<PathBuf>,
+}
+
+impl Outbox {
+    /// Loads the queue of a repository. A missing or unreadable file gives an
+    /// empty queue.
+    pub fn load(repo_path: &Path) -> Outbox {
+        let Some(file) = get_git_dir(repo_path).map(|dir| dir.join(OUTBOX_FILE)) else {
+            return Outbox::default();
+        };
+
+        let mut outbox = match std::fs::read_to_string(&file) {
+            Ok(content) => toml::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Ignoring unreadable {}: {}", file.display(), e);
+                Outbox::default()
+            }),
+            Err(_) => Outbox::default(),
+        };
+        outbox.file = Some(file);
+        outbox
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if self.notifications.is_empty() {
+            if file.exists() {
+                std::fs::remove_file(file)
+                    .with_context(|| format!("Failed to remove {}", file.display()))?;
+            }
+            return Ok(());
+        }
+
+        let content = toml::to_string(self).context("Failed to serialize queued notifications")?;
+        std::fs::write(file, content).with_context(|| format!("Failed to write {}", file.display()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notifications.is_empty()
+    }
+
+    pub fn push(&mut self, notification: QueuedNotification) {
+        self.notifications.push(notification);
+    }
+
+    /// Tries to deliver the queued notifications, oldest first, and returns how
+    /// many went out. Those older than `max_age_secs` are dropped, and once a
+    /// channel fails again its remaining notifications wait for the next call,
+    /// so they still arrive in order.
+    pub fn deliver(
+        &mut self,
+        now: u64,
+        max_age_secs: u64,
+        mut send: impl FnMut(&QueuedNotification) -> Result<()>,
+    ) -> usize {
+        let mut offline = HashSet::new();
+        let mut delivered = 0;
+        self.notifications.retain(|notification| {
+            if now.saturating_sub(notification.queued_at) > max_age_secs {
+                log::warn!(
+                    "Dropping {} notification that could not be sent in time: {}",
+                    notification.channel,
+                    notification.subject
+                );
+                return false;
+            }
+            if offline.contains(&notification.channel) {
+                return true;
+            }
+            match send(notification) {
+                Ok(()) => {
+                    delivered += 1;
+                    false
+                }
+                Err(e) => {
+                    log::debug!(
+                        "{} notifications still can't be sent: {:#}",
+                        notification.channel,
+                        e
+                    );
+                    offline.insert(notification.channel);
+                    true
+                }
+            }
+        });
+        delivered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(channel: Channel, subject: &str, queued_at: u64) -> QueuedNotification {
+        QueuedNotification {
+            channel,
+            subject: subject.to_string(),
+            body: String::new(),
+            queued_at,
+        }
+    }
+
+    #[test]
+    fn test_delivers_in_order_and_drops_stale_notifications() {
+        let mut outbox = Outbox::default();
+        outbox.push(queued(Channel::Matrix, "stale", 0));
+        outbox.push(queued(Channel::Matrix, "push failing", 900));
+        outbox.push(queued(Channel::Telegram, "push failing", 900));
+        outbox.push(queued(Channel::Matrix, "still failing", 950));
+
+        let mut sent = Vec::new();
+        let delivered = outbox.deliver(1_000, 500, |notification| {
+            if notification.channel == Channel::Matrix {
+                anyhow::bail!("offline");
+            }
+            sent.push(notification.subject.clone());
+            Ok(())
+        });
+        assert_eq!(delivered, 1);
+        assert_eq!(sent, ["push failing"]);
+        assert_eq!(
+            outbox.notifications,
+            [
+                queued(Channel::Matrix, "push failing", 900),
+                queued(Channel::Matrix, "still failing", 950)
+            ]
+        );
+
+        assert_eq!(outbox.deliver(1_000, 500, |_| Ok(())), 2);
+        assert!(outbox.is_empty());
+    }
+}
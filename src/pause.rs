@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use crate::config::Config;
+use crate::git::{get_current_branch, get_git_dir, in_progress_operation};
+use crate::team_policy::TEAM_POLICY_FILE;
+
+/// Modification time of each repository's index when git-chai's last cycle
+/// there ended, so its own writes aren't mistaken for another program's.
+static OWN_INDEX_MTIMES: OnceLock<Mutex<HashMap<PathBuf, SystemTime>>> = OnceLock::new();
+
+/// Returns why committing should be paused right now, or `None` if it can go
+/// ahead. Paused cycles commit nothing and resume on their own once the
+/// operation or process has finished.
+pub fn pause_reason(config: &Config) -> Option<String> {
+    if config.pause_on_in_progress
+        && let Some(operation) = in_progress_operation(&config.repo_path)
+    {
+        return Some(format!("a {} is in progress", operation));
+    }
+
+    if !config.blocking_processes.is_empty()
+        && let Some(process) = find_running_process(&config.blocking_processes)
+    {
+        return Some(format!("'{}' is running", process));
+    }
+
+    if let Some(policy) = &config.team_policy
+        && let Ok(Some(branch)) = get_current_branch(&config.repo_path)
+        && policy.protects(&branch)
+    {
+        return Some(format!(
+            "branch {} is protected by {}",
+            branch, TEAM_POLICY_FILE
+        ));
+    }
+
+    if let Some(window) = config.external_git_quiet_secs
+        && let Some(reason) = external_git_activity(&config.repo_path, Duration::from_secs(window))
+    {
+        return Some(reason);
+    }
+
+    None
+}
+
+/// Records the index's modification time when dropped, at the end of a cycle
+/// that may have staged or committed, for `external_git_quiet_secs`.
+pub struct OwnIndexWrites {
+    index: Option<PathBuf>,
+}
+
+impl OwnIndexWrites {
+    pub fn track(config: &Config) -> Self {
+        OwnIndexWrites {
+            index: config
+                .external_git_quiet_secs
+                .and_then(|_| get_git_dir(&config.repo_path))
+                .map(|dir| dir.join("index")),
+        }
+    }
+}
+
+impl Drop for OwnIndexWrites {
+    fn drop(&mut self) {
+        if let Some(index) = &self.index
+            && let Ok(mtime) = std::fs::metadata(index).and_then(|metadata| metadata.modified())
+        {
+            OWN_INDEX_MTIMES
+                .get_or_init(Default::default)
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(index.clone(), mtime);
+        }
+    }
+}
+
+/// Returns why another git client (an IDE refreshing its status, a terminal)
+/// looks active in the repository: it holds `index.lock`, or wrote the index
+/// less than `window` ago.
+fn external_git_activity(repo_path: &Path, window: Duration) -> Option<String> {
+    let git_dir = get_git_dir(repo_path)?;
+    if git_dir.join("index.lock").exists() {
+        return Some("another git process holds index.lock".to_string());
+    }
+
+    let index = git_dir.join("index");
+    let mtime = std::fs::metadata(&index)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    let own = OWN_INDEX_MTIMES.get().and_then(|mtimes| {
+        mtimes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&index)
+            .copied()
+    });
+    recent_external_write(mtime, own, SystemTime::now(), window).map(|age| {
+        format!(
+            "another program used git {}s ago (external_git_quiet_secs)",
+            age.as_secs()
+        )
+    })
+}
+
+/// Returns how long ago the index was written by someone else, if that was
+/// within `window`. A write matching git-chai's own last one doesn't count.
+fn recent_external_write(
+    mtime: SystemTime,
+    own: Option<SystemTime>,
+    now: SystemTime,
+    window: Duration,
+) -> Option<Duration> {
+    if own == Some(mtime) {
+        return None;
+    }
+    let age = now.duration_since(mtime).unwrap_or_default();
+    (age < window).then_some(age)
+}
+
+/// Returns the first of `names` that matches a running process.
+fn find_running_process(names: &[String]) -> Option<String> {
+    let output = Command::new("ps")
+        .arg("-A")
+        .arg("-o")
+        .arg("comm=")
+        .output()
+        .map_err(|e| log::warn!("Failed to list running processes: {}", e))
+        .ok()?;
+
+    let running = String::from_utf8_lossy(&output.stdout);
+    find_in_process_list(&running, names)
+}
+
+fn find_in_process_list(running: &str, names: &[String]) -> Option<String> {
+    running
+        .lines()
+        .filter_map(|line| Path::new(line.trim()).file_name())
+        .map(|name| name.to_string_lossy())
+        .find(|name| names.iter().any(|blocking| blocking == name))
+        .map(|name| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_in_process_list_matches_basename() {
+        let running = "systemd\n/usr/bin/meld\nbash\n";
+        let names = vec!["kdiff3".to_string(), "meld".to_string()];
+
+        assert_eq!(
+            find_in_process_list(running, &names),
+            Some("meld".to_string())
+        );
+        assert_eq!(find_in_process_list("bash\n", &names), None);
+    }
+
+    #[test]
+    fn test_recent_external_write() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let window = Duration::from_secs(10);
+        let written = now - Duration::from_secs(3);
+
+        assert_eq!(
+            recent_external_write(written, None, now, window),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            recent_external_write(written, Some(written), now, window),
+            None
+        );
+        assert_eq!(
+            recent_external_write(now - Duration::from_secs(30), None, now, window),
+            None
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::types::ChangeType;
+
+/// How a group of changes is committed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupKind {
+    /// One commit staging the whole directory, described by its change type
+    Directory(ChangeType),
+    /// One commit per file
+    Individual,
+}
+
+impl fmt::Display for GroupKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupKind::Directory(change_type) => write!(f, "{}", change_type),
+            GroupKind::Individual => write!(f, "individual"),
+        }
+    }
+}
+
+/// A changed path in the plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedFile {
+    /// Repository-relative path; untracked directories end in `/`
+    pub path: String,
+    pub change_type: ChangeType,
+}
+
+/// A unit of the commit plan: a directory committed at once, or files committed
+/// one by one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeGroup {
+    pub path: PathBuf,
+    pub kind: GroupKind,
+    pub files: Vec<PlannedFile>,
+}
+
+impl ChangeGroup {
+    /// A group of files committed one by one.
+    pub fn individual(files: Vec<PlannedFile>) -> Self {
+        ChangeGroup {
+            path: PathBuf::from("."),
+            kind: GroupKind::Individual,
+            files,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Result;
+use std::io::{BufRead, Write};
+
+use crate::config::{BranchMapRule, PathPolicyRule};
+
+/// Returns the first rule that applies to `path`. An untracked directory
+/// (ending in `/`) is covered by a rule that matches the directory itself or
+/// names paths inside it, since its files are committed together.
+pub fn policy_for<'a>(rules: &'a [PathPolicyRule], path: &str) -> Option<&'a PathPolicyRule> {
+    rules
+        .iter()
+        .find(|rule| covers("path_policies", &rule.pattern, path))
+}
+
+/// Returns the `branch_map` branch for `path`, matched like [`policy_for`].
+pub fn branch_for<'a>(rules: &'a [BranchMapRule], path: &str) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| covers("branch_map", &rule.pattern, path))
+        .map(|rule| rule.branch.as_str())
+}
+
+fn covers(key: &str, pattern: &str, path: &str) -> bool {
+    match glob::Pattern::new(pattern) {
+        Ok(glob) => {
+            glob.matches(path)
+                || path
+                    .strip_suffix('/')
+                    .is_some_and(|dir| glob.matches(dir) || pattern.starts_with(path))
+        }
+        Err(e) => {
+            log::warn!("Invalid {} pattern '{}': {}", key, pattern, e);
+            false
+        }
+    }
+}
+
+/// Asks on the terminal whether a change to `path` may be committed.
+pub fn ask_approval(change_type: &str, path: &str) -> Result<bool> {
+    print!(
+        "Commit {}: {}? (required by path_policies) [y/N] ",
+        change_type, path
+    );
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PathPolicy;
+
+    fn rule(pattern: &str, policy: PathPolicy) -> PathPolicyRule {
+        PathPolicyRule {
+            pattern: pattern.to_string(),
+            policy,
+            branch: None,
+        }
+    }
+
+    #[test]
+    fn test_policy_for_files_and_untracked_directories() {
+        let rules = vec![
+            rule("migrations/**", PathPolicy::RequireApproval),
+            rule("*.key", PathPolicy::NeverCommit),
+            rule("docs/**", PathPolicy::AutoCommit),
+        ];
+        let policy = |path: &str| policy_for(&rules, path).map(|rule| rule.policy);
+
+        assert_eq!(
+            policy("migrations/0001_init.sql"),
+            Some(PathPolicy::RequireApproval)
+        );
+        assert_eq!(policy("migrations/"), Some(PathPolicy::RequireApproval));
+        assert_eq!(policy("server.key"), Some(PathPolicy::NeverCommit));
+        assert_eq!(policy("docs/guide.md"), Some(PathPolicy::AutoCommit));
+        assert_eq!(policy("src/main.rs"), None);
+        assert_eq!(policy("src/"), None);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::config::{Config, ProvenanceMode};
+use crate::git::{CommitSettings, add_note, short_sha};
+use crate::version::version_info;
+
+/// Notes ref holding the provenance of each autosave with `provenance = "note"`.
+pub const PROVENANCE_NOTES_REF: &str = "refs/notes/chai-provenance";
+
+/// Trailer holding the provenance of each autosave with `provenance = "trailer"`.
+pub const PROVENANCE_TRAILER: &str = "Chai-Provenance";
+
+/// What produced the commits of one scan: the git-chai build, the settings and
+/// the plan they were made from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    mode: ProvenanceMode,
+    /// `git-chai/<version>+<commit>`
+    tool: String,
+    /// Hash of the effective configuration, wherever the repository is checked out
+    config_hash: String,
+    /// How changes were grouped into commits
+    grouping: String,
+    /// Hash of the scan's planned commit subjects, in order
+    plan_hash: String,
+}
+
+impl Provenance {
+    /// Describes a scan of `config`'s repository that planned commits with the
+    /// generated subjects in `plan`.
+    pub fn new(config: &Config, mode: ProvenanceMode, plan: &[String]) -> Self {
+        let version = version_info();
+        Provenance {
+            mode,
+            tool: format!("git-chai/{}+{}", version.version, version.commit),
+            config_hash: config_hash(config),
+            grouping: grouping(config),
+            plan_hash: stable_hash(&plan.join("\n")),
+        }
+    }
+
+    fn fields(&self) -> [(&str, &str); 4] {
+        [
+            ("tool", &self.tool),
+            ("config", &self.config_hash),
+            ("grouping", &self.grouping),
+            ("plan", &self.plan_hash),
+        ]
+    }
+
+    /// The note attached to each commit, one `key: value` line per field.
+    fn note(&self) -> String {
+        self.fields()
+            .iter()
+            .map(|(key, value)| format!("{}: {}\n", key, value))
+            .collect()
+    }
+
+    /// The trailer added to each commit message.
+    fn trailer(&self) -> String {
+        let fields: Vec<String> = self
+            .fields()
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        format!("{}: {}", PROVENANCE_TRAILER, fields.join("; "))
+    }
+
+    /// In trailer mode, adds the provenance to the messages of the commits made
+    /// with `settings`.
+    pub fn attach(&self, settings: &mut CommitSettings) {
+        if self.mode == ProvenanceMode::Trailer {
+            settings.provenance = Some(self.trailer());
+        }
+    }
+
+    /// In note mode, attaches the provenance to each of `shas`, the commits the
+    /// scan made. Failures are logged; the commits themselves are kept.
+    pub fn record(&self, config: &Config, shas: &[String]) {
+        if self.mode != ProvenanceMode::Note {
+            return;
+        }
+        let note = self.note();
+        for sha in shas {
+            if let Err(e) = add_note(&config.repo_path, PROVENANCE_NOTES_REF, sha, &note) {
+                log::warn!("Failed to record provenance of {}: {}", short_sha(sha), e);
+            }
+        }
+    }
+}
+
+/// Hashes the configuration without `repo_path`, so that the same `.chai.toml`
+/// gives the same hash in every clone.
+fn config_hash(config: &Config) -> String {
+    let mut value = match toml::Value::try_from(config) {
+        Ok(value) => value,
+        Err(e) => {
+            log::warn!(
+                "Failed to serialize the configuration for provenance: {}",
+                e
+            );
+            return "unknown".to_string();
+        }
+    };
+    if let Some(table) = value.as_table_mut() {
+        table.remove("repo_path");
+    }
+    stable_hash(&value.to_string())
+}
+
+/// `directory`, followed by the directories committed as a whole and the
+/// number of `branch_map` rules, if any.
+fn grouping(config: &Config) -> String {
+    let mut grouping = "directory".to_string();
+    if !config.opaque_directories.is_empty() {
+        grouping.push_str(&format!(
+            ", opaque: {}",
+            config.opaque_directories.join(" ")
+        ));
+    }
+    if !config.branch_map.is_empty() {
+        grouping.push_str(&format!(", branch_map: {} rules", config.branch_map.len()));
+    }
+    grouping
+}
+
+/// 64-bit FNV-1a, which unlike std's hasher is the same across Rust releases.
+fn stable_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_provenance_is_stable_across_clones() {
+        let plan = vec!["mod: a.txt".to_string(), "add: api/".to_string()];
+        let config = Config {
+            repo_path: PathBuf::from("/home/me/notes"),
+            opaque_directories: vec!["vendor".to_string()],
+            ..Default::default()
+        };
+        let clone = Config {
+            repo_path: PathBuf::from("/srv/notes"),
+            opaque_directories: vec!["vendor".to_string()],
+            ..Default::default()
+        };
+        let provenance = Provenance::new(&config, ProvenanceMode::Trailer, &plan);
+        assert_eq!(
+            provenance,
+            Provenance::new(&clone, ProvenanceMode::Trailer, &plan)
+        );
+        assert_ne!(
+            provenance.plan_hash,
+            Provenance::new(&config, ProvenanceMode::Trailer, &plan[..1]).plan_hash
+        );
+        assert_ne!(
+            provenance.config_hash,
+            Provenance::new(&Config::default(), ProvenanceMode::Trailer, &plan).config_hash
+        );
+
+        assert_eq!(stable_hash(""), "cbf29ce484222325");
+        assert_eq!(provenance.grouping, "directory, opaque: vendor");
+        let trailer = provenance.trailer();
+        assert!(trailer.starts_with("Chai-Provenance: tool=git-chai/"));
+        assert!(trailer.ends_with(&format!(
+            "; grouping=directory, opaque: vendor; plan={}",
+            provenance.plan_hash
+        )));
+        assert!(
+            provenance
+                .note()
+                .contains(&format!("config: {}\n", provenance.config_hash))
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use crate::config::Config;
+use crate::git::{
+    bridge_kind, get_push_url, get_unpushed_size, get_upstream_divergence, is_shallow,
+    push_changes, unshallow,
+};
+use crate::log_dedup::RepeatedMessage;
+
+/// Consecutive failed pushes after which the failure is reported as persistent.
+const PERSISTENT_FAILURES: u32 = 3;
+
+/// Push state that persists across cycles, so that a failure that would repeat on
+/// every cycle is only reported once.
+#[derive(Debug, Default)]
+pub struct PushState {
+    /// A push from this shallow clone was rejected; further pushes are skipped
+    shallow_rejected: bool,
+    /// The remote is excluded by the push remote patterns; further pushes are skipped
+    remote_forbidden: bool,
+    /// The branch was more than `max_behind_commits` behind its upstream at the last check
+    behind_upstream: bool,
+    /// Failed pushes, so a failure that repeats every cycle is only summarized
+    failures: RepeatedMessage,
+    /// Pushes that failed in a row, and the last error
+    failed_in_a_row: u32,
+    last_error: Option<String>,
+    /// The current run of failures was already returned by `take_persistent_failure`
+    failure_reported: bool,
+}
+
+impl PushState {
+    /// Compares the branch with its upstream and warns when it has fallen more
+    /// than `max_behind_commits` behind, so autosave commits don't pile up on a
+    /// stale base. The warning is repeated only after the branch has caught up
+    /// in between.
+    pub fn check_divergence(&mut self, config: &Config) {
+        let Some(max) = config.max_behind_commits else {
+            return;
+        };
+
+        let divergence = match get_upstream_divergence(&config.repo_path) {
+            Ok(Some(divergence)) => divergence,
+            Ok(None) => return,
+            Err(e) => {
+                log::warn!("Failed to compare with the upstream branch: {}", e);
+                return;
+            }
+        };
+        log::debug!(
+            "{} ahead, {} behind {}",
+            divergence.ahead,
+            divergence.behind,
+            divergence.upstream
+        );
+
+        let behind = divergence.behind > max;
+        if behind && !self.behind_upstream {
+            log::warn!(
+                "The branch is {} commits behind {} (and {} ahead); new commits are piling onto a stale base. Pull or rebase soon.",
+                divergence.behind,
+                divergence.upstream,
+                divergence.ahead
+            );
+            if config.pause_push_when_behind {
+                log::warn!("Pushing is paused until the branch catches up");
+            }
+        } else if !behind && self.behind_upstream {
+            log::info!("The branch has caught up with {}", divergence.upstream);
+        }
+        self.behind_upstream = behind;
+    }
+
+    /// Pushes HEAD to the remote and logs the outcome.
+    ///
+    /// Nothing is pushed to a remote excluded by `push_allowed_remotes` or
+    /// `push_denied_remotes`, or from a git-svn or hg-bridged repository,
+    /// whatever `--push` says. The push is deferred while
+    /// the unpushed commits exceed `max_push_bytes` or `max_push_lines`, and with
+    /// `pause_push_when_behind` while the branch is far behind its upstream.
+    ///
+    /// In a shallow clone the missing history is fetched first when
+    /// `unshallow_before_push` is set. Otherwise a rejected push is reported once
+    /// and not retried for the rest of the session.
+    pub fn push(&mut self, config: &Config) {
+        if self.shallow_rejected {
+            log::debug!("Skipping push from shallow clone after an earlier rejection");
+            return;
+        }
+
+        if self.remote_forbidden {
+            log::debug!("Skipping push to a forbidden remote");
+            return;
+        }
+        if let Some(reason) = forbidden_remote_reason(config) {
+            log::warn!("Not pushing: {}", reason);
+            log::warn!("Pushing is disabled for this session; changes stay committed locally.");
+            self.remote_forbidden = true;
+            return;
+        }
+
+        let shallow = is_shallow(&config.repo_path);
+        if shallow && config.unshallow_before_push {
+            log::info!("Shallow clone: fetching full history before pushing");
+            if let Err(e) = unshallow(&config.repo_path) {
+                log::warn!("Failed to unshallow repository: {}", e);
+                log::warn!("Changes were committed locally but not pushed to remote.");
+                return;
+            }
+        }
+
+        if self.behind_upstream && config.pause_push_when_behind {
+            log::warn!(
+                "Push deferred: the branch is more than max_behind_commits behind its upstream"
+            );
+            return;
+        }
+
+        if let Some(reason) = over_push_budget(config) {
+            log::warn!("Push deferred: {}", reason);
+            log::warn!(
+                "Changes were committed locally; push them manually or raise max_push_bytes/max_push_lines."
+            );
+            return;
+        }
+
+        match push_changes(&config.repo_path, &config.push_options) {
+            Ok(()) => {
+                self.failures.resolve();
+                self.failed_in_a_row = 0;
+                self.failure_reported = false;
+                log::info!("Successfully pushed changes to remote!");
+            }
+            Err(e) if shallow && !config.unshallow_before_push => {
+                log::warn!("Failed to push changes from a shallow clone: {}", e);
+                log::warn!(
+                    "Pushing is disabled for this session; pass --unshallow-before-push to fetch full history first."
+                );
+                self.shallow_rejected = true;
+            }
+            Err(e) => {
+                self.failed_in_a_row += 1;
+                self.last_error = Some(e.to_string());
+                let message = format!("Failed to push changes ({}): {}", e.class(), e);
+                if self.failures.report(log::Level::Warn, message) {
+                    log::warn!("Changes were committed locally but not pushed to remote.");
+                }
+            }
+        }
+    }
+
+    /// Returns the last error once pushing has failed `PERSISTENT_FAILURES` times
+    /// in a row. It is returned only once until a push succeeds again.
+    pub fn take_persistent_failure(&mut self) -> Option<String> {
+        if self.failed_in_a_row < PERSISTENT_FAILURES || self.failure_reported {
+            return None;
+        }
+        self.failure_reported = true;
+        self.last_error.clone()
+    }
+}
+
+/// Returns why the unpushed commits exceed the configured push size budget, if
+/// they do.
+fn over_push_budget(config: &Config) -> Option<String> {
+    if config.max_push_bytes.is_none() && config.max_push_lines.is_none() {
+        return None;
+    }
+
+    let size = match get_unpushed_size(&config.repo_path) {
+        Ok(size) => size,
+        Err(e) => {
+            log::warn!("Failed to measure unpushed commits: {}", e);
+            return None;
+        }
+    };
+    log::debug!(
+        "Unpushed commits: {} bytes, {} lines",
+        size.bytes,
+        size.lines
+    );
+
+    if let Some(max) = config.max_push_bytes
+        && size.bytes > max
+    {
+        return Some(format!(
+            "unpushed commits take up {} bytes (max_push_bytes = {})",
+            size.bytes, max
+        ));
+    }
+    if let Some(max) = config.max_push_lines
+        && size.lines > max
+    {
+        return Some(format!(
+            "unpushed commits change {} lines (max_push_lines = {})",
+            size.lines, max
+        ));
+    }
+
+    None
+}
+
+/// Returns why pushing to `origin` is forbidden, if it is: by a bridge to
+/// Subversion or Mercurial (unless `allow_bridge_push` is set), or by the push
+/// remote patterns. A remote whose URL can't be read is treated as forbidden
+/// when any pattern is configured.
+fn forbidden_remote_reason(config: &Config) -> Option<String> {
+    if !config.allow_bridge_push
+        && let Some(bridge) = bridge_kind(&config.repo_path)
+    {
+        return Some(format!(
+            "this is a {} repository; {}",
+            bridge,
+            bridge.push_hint()
+        ));
+    }
+    if config.push_allowed_remotes.is_empty() && config.push_denied_remotes.is_empty() {
+        return None;
+    }
+
+    let url = match get_push_url(&config.repo_path, "origin") {
+        Ok(url) => url,
+        Err(e) => return Some(format!("could not read the push URL of origin: {}", e)),
+    };
+    remote_refusal(
+        &url,
+        &config.push_allowed_remotes,
+        &config.push_denied_remotes,
+    )
+}
+
+fn remote_refusal(url: &str, allowed: &[String], denied: &[String]) -> Option<String> {
+    let matches = |pattern: &String| glob::Pattern::new(pattern).is_ok_and(|p| p.matches(url));
+
+    if let Some(pattern) = denied.iter().find(|pattern| matches(pattern)) {
+        return Some(format!("{} matches push_denied_remotes '{}'", url, pattern));
+    }
+    if !allowed.is_empty() && !allowed.iter().any(matches) {
+        return Some(format!("{} does not match push_allowed_remotes", url));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_refusal() {
+        let allowed = vec!["git@git.corp.example:*".to_string()];
+        let denied = vec!["*:public/*".to_string()];
+
+        assert_eq!(
+            remote_refusal("git@git.corp.example:team/notes.git", &allowed, &denied),
+            None
+        );
+        assert!(remote_refusal("https://github.com/me/notes.git", &allowed, &denied).is_some());
+        assert!(
+            remote_refusal("git@git.corp.example:public/notes.git", &allowed, &denied).is_some()
+        );
+        assert_eq!(
+            remote_refusal("https://github.com/me/notes.git", &[], &[]),
+            None
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Fixed-window limiter for the number of commits created per hour in headless mode.
+#[derive(Debug)]
+pub struct CommitRateLimiter {
+    max_per_window: usize,
+    window_start: Instant,
+    commits: usize,
+    holding: bool,
+}
+
+impl CommitRateLimiter {
+    pub fn new(max_per_hour: usize, now: Instant) -> Self {
+        Self {
+            max_per_window: max_per_hour,
+            window_start: now,
+            commits: 0,
+            holding: false,
+        }
+    }
+
+    /// Starts a new window if the current one has elapsed. Returns true if changes
+    /// were held back in the previous window and should now be committed as a batch.
+    pub fn roll_window(&mut self, now: Instant) -> bool {
+        if now.duration_since(self.window_start) < WINDOW {
+            return false;
+        }
+
+        log::debug!("Commit rate window reset ({} commits)", self.commits);
+        self.window_start = now;
+        self.commits = 0;
+        std::mem::take(&mut self.holding)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.max_per_window.saturating_sub(self.commits)
+    }
+
+    /// Records commits made in the current window; once the budget is used up any
+    /// further changes are held until the window resets.
+    pub fn record(&mut self, commits: usize) {
+        self.commits += commits;
+        if self.remaining() == 0 && !self.holding {
+            log::warn!(
+                "Reached max_commits_per_hour ({}); holding changes until the window resets",
+                self.max_per_window
+            );
+            self.holding = true;
+        }
+    }
+
+    pub fn is_holding(&self) -> bool {
+        self.holding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limiter_holds_and_releases_batch() {
+        let start = Instant::now();
+        let mut limiter = CommitRateLimiter::new(3, start);
+
+        assert_eq!(limiter.remaining(), 3);
+        limiter.record(2);
+        assert!(!limiter.is_holding());
+        limiter.record(1);
+        assert!(limiter.is_holding());
+        assert_eq!(limiter.remaining(), 0);
+
+        assert!(!limiter.roll_window(start + Duration::from_secs(60)));
+        assert!(limiter.roll_window(start + WINDOW));
+        assert!(!limiter.is_holding());
+        assert_eq!(limiter.remaining(), 3);
+    }
+
+    #[test]
+    fn test_limiter_window_reset_without_holding() {
+        let start = Instant::now();
+        let mut limiter = CommitRateLimiter::new(5, start);
+        limiter.record(1);
+        assert!(!limiter.roll_window(start + WINDOW));
+        assert_eq!(limiter.remaining(), 5);
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::config::{RepeatChangeRule, RepeatPolicy};
+
+/// Returns the first rule whose glob pattern matches `filename`.
+pub fn rule_for<'a>(rules: &'a [RepeatChangeRule], filename: &str) -> Option<&'a RepeatChangeRule> {
+    rules
+        .iter()
+        .find(|rule| match glob::Pattern::new(&rule.pattern) {
+            Ok(pattern) => pattern.matches(filename),
+            Err(e) => {
+                log::warn!(
+                    "Invalid repeat_change_rules pattern '{}': {}",
+                    rule.pattern,
+                    e
+                );
+                false
+            }
+        })
+}
+
+/// Returns true if the file should be left alone this cycle because it has
+/// been modified more recently than its rule's quiet period.
+pub fn should_hold(repo_path: &Path, filename: &str, rule: &RepeatChangeRule) -> bool {
+    if rule.policy != RepeatPolicy::Hold {
+        return false;
+    }
+
+    let Ok(modified) = std::fs::metadata(repo_path.join(filename)).and_then(|m| m.modified())
+    else {
+        return false;
+    };
+
+    let quiet = Duration::from_secs(rule.quiet_minutes * 60);
+    let held = SystemTime::now()
+        .duration_since(modified)
+        .map(|elapsed| elapsed < quiet)
+        .unwrap_or(true);
+
+    if held {
+        log::debug!(
+            "Holding {} until it has been quiet for {:?}",
+            filename,
+            quiet
+        );
+    }
+    held
+}
+
+/// Remembers which commit git-chai last created for each file, so that repeated
+/// changes can amend that commit instead of stacking new ones on top.
+#[derive(Debug, Default)]
+pub struct RepeatChangeTracker {
+    last_commits: HashMap<String, String>,
+}
+
+impl RepeatChangeTracker {
+    /// Returns true if the file's previous git-chai commit is still HEAD and can be amended.
+    pub fn can_amend(&self, filename: &str, head_sha: &str) -> bool {
+        self.last_commits
+            .get(filename)
+            .is_some_and(|sha| sha == head_sha)
+    }
+
+    pub fn record_commit(&mut self, filename: &str, sha: String) {
+        self.last_commits.insert(filename.to_string(), sha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, policy: RepeatPolicy) -> RepeatChangeRule {
+        RepeatChangeRule {
+            pattern: pattern.to_string(),
+            policy,
+            quiet_minutes: 5,
+        }
+    }
+
+    #[test]
+    fn test_rule_for_first_match_wins() {
+        let rules = vec![
+            rule("notes/**", RepeatPolicy::Hold),
+            rule("*.md", RepeatPolicy::Amend),
+        ];
+
+        assert_eq!(
+            rule_for(&rules, "notes/today.md").map(|r| r.policy),
+            Some(RepeatPolicy::Hold)
+        );
+        assert_eq!(
+            rule_for(&rules, "README.md").map(|r| r.policy),
+            Some(RepeatPolicy::Amend)
+        );
+        assert!(rule_for(&rules, "src/main.rs").is_none());
+    }
+
+    #[test]
+    fn test_can_amend_only_own_head() {
+        let mut tracker = RepeatChangeTracker::default();
+        assert!(!tracker.can_amend("a.txt", "abc"));
+
+        tracker.record_commit("a.txt", "abc".to_string());
+        assert!(tracker.can_amend("a.txt", "abc"));
+        assert!(!tracker.can_amend("a.txt", "def"));
+        assert!(!tracker.can_amend("b.txt", "abc"));
+    }
+}
@@ -0,0 +1,127 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::config::global_config_path;
+use crate::error::GitChaiError;
+
+/// Repositories git-chai may commit in, from the global config file:
+///
+/// ```toml
+/// allowed_repos = ["~/notes", "~/work/*"]
+/// denied_repos = ["~/work/payments"]
+/// ```
+///
+/// Entries are paths or glob patterns; a path also covers everything below it.
+/// The denylist wins over the allowlist, and an empty allowlist allows every
+/// repository that is not denied.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RepoAccess {
+    pub allowed_repos: Vec<String>,
+    pub denied_repos: Vec<String>,
+    #[serde(skip)]
+    source: Option<PathBuf>,
+}
+
+impl RepoAccess {
+    /// Reads the allow/denylist from the global config file, if there is one.
+    pub fn load() -> Result<Self, GitChaiError> {
+        match global_config_path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Reads the allow/denylist from the config file at `path`, if it exists.
+    pub fn load_from(path: &Path) -> Result<Self, GitChaiError> {
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)?;
+        let mut access: RepoAccess = toml::from_str(&contents)
+            .map_err(|e| GitChaiError::ConfigError(format!("Failed to parse {:?}: {}", path, e)))?;
+        access.source = Some(path.to_path_buf());
+        Ok(access)
+    }
+
+    /// Returns why git-chai must not operate in `repo_root`, or `None` if it may.
+    pub fn refusal_reason(&self, repo_root: &Path) -> Option<String> {
+        let source = self.source.as_ref().map_or_else(
+            || "the global config".to_string(),
+            |p| p.display().to_string(),
+        );
+
+        if let Some(pattern) = self
+            .denied_repos
+            .iter()
+            .find(|pattern| matches_repo(pattern, repo_root))
+        {
+            return Some(format!(
+                "it matches '{}' in denied_repos of {}",
+                pattern, source
+            ));
+        }
+
+        if !self.allowed_repos.is_empty()
+            && !self
+                .allowed_repos
+                .iter()
+                .any(|pattern| matches_repo(pattern, repo_root))
+        {
+            return Some(format!("it is not listed in allowed_repos of {}", source));
+        }
+
+        None
+    }
+}
+
+fn matches_repo(pattern: &str, repo_root: &Path) -> bool {
+    let pattern = expand_home(pattern);
+    if repo_root.starts_with(&pattern) {
+        return true;
+    }
+
+    glob::Pattern::new(&pattern.to_string_lossy()).is_ok_and(|glob| glob.matches_path(repo_root))
+}
+
+fn expand_home(pattern: &str) -> PathBuf {
+    match (pattern.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(pattern),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refusal_reason() {
+        let access = RepoAccess {
+            allowed_repos: vec!["/home/me/notes".to_string(), "/home/me/work/*".to_string()],
+            denied_repos: vec!["/home/me/work/payments".to_string()],
+            source: None,
+        };
+
+        assert_eq!(access.refusal_reason(Path::new("/home/me/notes")), None);
+        assert_eq!(access.refusal_reason(Path::new("/home/me/notes/sub")), None);
+        assert_eq!(access.refusal_reason(Path::new("/home/me/work/blog")), None);
+        assert!(
+            access
+                .refusal_reason(Path::new("/home/me/work/payments"))
+                .unwrap()
+                .contains("denied_repos")
+        );
+        assert!(
+            access
+                .refusal_reason(Path::new("/home/me/notes-old"))
+                .unwrap()
+                .contains("allowed_repos")
+        );
+        assert_eq!(
+            RepoAccess::default().refusal_reason(Path::new("/anywhere")),
+            None
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::git::audit::{AuditRecord, read_log};
+
+/// Number of entries shown per section in the table output.
+const TABLE_ROWS: usize = 20;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ReportFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct Churn {
+    pub path: String,
+    pub count: usize,
+}
+
+/// How often git-chai staged each file and directory, most active first.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct Heatmap {
+    pub directories: Vec<Churn>,
+    pub files: Vec<Churn>,
+}
+
+/// Runs `git-chai report --heatmap` for one repository.
+pub fn run_heatmap(repo_path: &Path, audit_log: &Path, format: ReportFormat) -> Result<()> {
+    let records = read_log(audit_log)
+        .with_context(|| format!("Failed to read audit log {}", audit_log.display()))?;
+    let heatmap = build_heatmap(repo_path, &records);
+
+    match format {
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&heatmap)?),
+        ReportFormat::Csv => {
+            println!("kind,path,count");
+            for (kind, rows) in [
+                ("directory", &heatmap.directories),
+                ("file", &heatmap.files),
+            ] {
+                for row in rows {
+                    println!("{},{},{}", kind, csv_field(&row.path), row.count);
+                }
+            }
+        }
+        ReportFormat::Table => {
+            println!("git-chai activity: {}", repo_path.display());
+            if heatmap.files.is_empty() {
+                println!("No files staged by git-chai in {}", audit_log.display());
+                return Ok(());
+            }
+            for (title, rows) in [
+                ("Directories", &heatmap.directories),
+                ("Files", &heatmap.files),
+            ] {
+                println!("{}:", title);
+                for row in rows.iter().take(TABLE_ROWS) {
+                    println!("  {:>6}  {}", row.count, row.path);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Counts the paths of successful `git add` commands run in `repo_path`. Staging
+/// for rename detection (`--intent-to-add`) doesn't count, and staged directories
+/// (checked against the working tree) only count towards the directory.
+fn build_heatmap(repo_path: &Path, records: &[AuditRecord]) -> Heatmap {
+    let mut files: HashMap<String, usize> = HashMap::new();
+    let mut directories: HashMap<String, usize> = HashMap::new();
+
+    let staged = records
+        .iter()
+        .filter(|record| record.cwd == repo_path && record.exit_code == Some(0))
+        .filter(|record| record.argv.get(1).is_some_and(|arg| arg == "add"))
+        .filter(|record| !record.argv.iter().any(|arg| arg == "--intent-to-add"));
+
+    for record in staged {
+        for path in pathspecs(&record.argv[2..]) {
+            let path = path.trim_start_matches("./").trim_end_matches('/');
+            // Directory commits stage the directory itself
+            if path.is_empty() || path == "." || repo_path.join(path).is_dir() {
+                let directory = if path.is_empty() || path == "." {
+                    "./".to_string()
+                } else {
+                    format!("{}/", path)
+                };
+                *directories.entry(directory).or_default() += 1;
+                continue;
+            }
+
+            let directory = path
+                .rsplit_once('/')
+                .map_or_else(|| "./".to_string(), |(parent, _)| format!("{}/", parent));
+            *directories.entry(directory).or_default() += 1;
+            *files.entry(path.to_string()).or_default() += 1;
+        }
+    }
+
+    Heatmap {
+        directories: sorted(directories),
+        files: sorted(files),
+    }
+}
+
+/// Non-option arguments, plus everything after `--`.
+fn pathspecs(args: &[String]) -> Vec<&str> {
+    match args.iter().position(|arg| arg == "--") {
+        Some(separator) => args[separator + 1..].iter().map(String::as_str).collect(),
+        None => args
+            .iter()
+            .filter(|arg| !arg.starts_with('-'))
+            .map(String::as_str)
+            .collect(),
+    }
+}
+
+fn sorted(counts: HashMap<String, usize>) -> Vec<Churn> {
+    let mut rows: Vec<Churn> = counts
+        .into_iter()
+        .map(|(path, count)| Churn { path, count })
+        .collect();
+    rows.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.path.cmp(&b.path)));
+    rows
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn record(cwd: &str, exit_code: i32, argv: &[&str]) -> AuditRecord {
+        AuditRecord {
+            cwd: PathBuf::from(cwd),
+            exit_code: Some(exit_code),
+            argv: argv.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_build_heatmap() {
+        let records = vec![
+            record("/repo", 0, &["git", "add", "notes/today.md"]),
+            record("/repo", 0, &["git", "add", "notes/today.md"]),
+            record(
+                "/repo",
+                0,
+                &["git", "add", "--all", "--", "src/a.rs", "README.md"],
+            ),
+            record(
+                "/repo",
+                0,
+                &["git", "add", "--intent-to-add", "--", "x.txt"],
+            ),
+            record("/repo", 1, &["git", "add", "failed.txt"]),
+            record("/other", 0, &["git", "add", "notes/today.md"]),
+            record("/repo", 0, &["git", "commit", "-m", "mod: today.md"]),
+        ];
+
+        let heatmap = build_heatmap(Path::new("/repo"), &records);
+        assert_eq!(
+            heatmap.files[0],
+            Churn {
+                path: "notes/today.md".to_string(),
+                count: 2
+            }
+        );
+        assert_eq!(heatmap.files.len(), 3);
+        assert_eq!(
+            heatmap
+                .directories
+                .iter()
+                .map(|row| (row.path.as_str(), row.count))
+                .collect::<Vec<_>>(),
+            vec![("notes/", 2), ("./", 1), ("src/", 1)]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::{Result, bail};
+
+use crate::config::{Config, PathPolicy, PathPolicyRule};
+use crate::git::{
+    get_current_branch, get_last_commit_touching, get_unpublished_commits, in_progress_operation,
+    retract_path, short_sha,
+};
+use crate::policy::policy_for;
+use crate::summary::is_chai_commit;
+
+/// Runs `git-chai retract`: takes `path` out of the unpublished git-chai
+/// commits that added or changed it, leaving the file uncommitted in the
+/// working tree, and adds a `never-commit` path policy so it isn't picked up
+/// again. Commits behind the rewrite horizon (on any remote), or that the user
+/// made by hand, are never rewritten.
+pub fn run_retract(config: &Config, path: &str, dry_run: bool) -> Result<()> {
+    let repo = &config.repo_path;
+    let path = path.trim_start_matches("./");
+    let Some(branch) = get_current_branch(repo)? else {
+        bail!("HEAD is detached; check out the branch the commits were made on first");
+    };
+    if let Some(operation) = in_progress_operation(repo) {
+        bail!(
+            "A {} is in progress; finish it before retracting",
+            operation
+        );
+    }
+
+    let touching = get_unpublished_commits(repo, Some(path))?;
+    if touching.is_empty() {
+        match get_last_commit_touching(repo, path)? {
+            Some(commit) => bail!(
+                "{} was last committed in {} ({}), which is already on a remote; retract only rewrites unpublished commits",
+                path,
+                short_sha(&commit.sha),
+                commit.subject
+            ),
+            None => bail!("{} is not in any commit on {}", path, branch),
+        }
+    }
+    if let Some(commit) = touching
+        .iter()
+        .find(|commit| !is_chai_commit(commit.generated_subject()))
+    {
+        bail!(
+            "{} was also changed by {} ({}), which git-chai didn't make; remove it from that commit by hand",
+            path,
+            short_sha(&commit.sha),
+            commit.subject
+        );
+    }
+
+    // Everything from the oldest commit touching the path up to HEAD is
+    // recreated; the unpushed commits are listed newest first
+    let unpushed = get_unpublished_commits(repo, None)?;
+    let oldest = &touching[touching.len() - 1].sha;
+    let Some(position) = unpushed.iter().position(|commit| &commit.sha == oldest) else {
+        bail!(
+            "{} is not among the unpushed commits of {}",
+            short_sha(oldest),
+            branch
+        );
+    };
+    let mut commits: Vec<String> = unpushed[..=position]
+        .iter()
+        .map(|commit| commit.sha.clone())
+        .collect();
+    commits.reverse();
+
+    log::info!(
+        "{} {} from {} commit(s) on {}:",
+        if dry_run {
+            "DRY RUN: Would remove"
+        } else {
+            "Removing"
+        },
+        path,
+        touching.len(),
+        branch
+    );
+    for commit in &touching {
+        log::info!("  {} {}", short_sha(&commit.sha), commit.subject);
+    }
+    if commits.len() > touching.len() {
+        log::info!(
+            "  ({} other commit(s) after the first are recreated too)",
+            commits.len() - touching.len()
+        );
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    let retraction = retract_path(repo, &branch, path, &commits)?;
+    log::info!(
+        "Rewrote {} commit(s) and dropped {} that only had {}; {} is now at {} and {} is uncommitted",
+        retraction.rewritten,
+        retraction.dropped,
+        path,
+        branch,
+        short_sha(&retraction.new_tip),
+        path
+    );
+
+    let pattern = if repo.join(path).is_dir() || path.ends_with('/') {
+        format!("{}/**", path.trim_end_matches('/'))
+    } else {
+        path.to_string()
+    };
+    if policy_for(&config.path_policies, path)
+        .is_some_and(|rule| rule.policy == PathPolicy::NeverCommit)
+    {
+        return Ok(());
+    }
+    let rule = PathPolicyRule {
+        pattern,
+        policy: PathPolicy::NeverCommit,
+        branch: None,
+    };
+    let config_path = Config::append_path_policy(repo, &rule)?;
+    log::info!(
+        "Added a never-commit path policy for {} to {}",
+        rule.pattern,
+        config_path.display()
+    );
+    Ok(())
+}
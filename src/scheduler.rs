@@ -0,0 +1,168 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Notify, Semaphore, watch};
+
+/// Scans in a row that found nothing new before `backoff` starts slowing down.
+const BACKOFF_AFTER_IDLE_SCANS: u32 = 3;
+
+/// Multiplexes periodic jobs (one per watched repository) onto the tokio runtime.
+///
+/// Each job runs on the blocking pool, since it shells out to git, and at most
+/// `workers` jobs run at once. Jobs waiting for a worker are served in the order
+/// they became due, so a slow or busy repository cannot starve the others. A job
+/// never runs twice concurrently.
+pub struct Scheduler<T> {
+    jobs: Vec<(T, Arc<Notify>)>,
+}
+
+impl<T: Send + 'static> Scheduler<T> {
+    /// Creates a scheduler where every job is due immediately and then repeats
+    /// after the delay its cycle returns, or sooner when its trigger is notified.
+    pub fn new(jobs: Vec<(T, Arc<Notify>)>) -> Self {
+        Self { jobs }
+    }
+
+    /// Runs jobs until `shutdown` becomes true. Jobs already running are allowed to
+    /// finish; no new job starts afterwards. Returns the jobs so their final state
+    /// can be flushed. Notifying `wake` makes every waiting job due immediately.
+    pub async fn run<F>(
+        self,
+        workers: usize,
+        shutdown: watch::Receiver<bool>,
+        wake: Arc<Notify>,
+        cycle: F,
+    ) -> Vec<T>
+    where
+        F: Fn(&mut T) -> Duration + Send + Sync + 'static,
+    {
+        let permits = Arc::new(Semaphore::new(workers.max(1)));
+        let cycle = Arc::new(cycle);
+
+        let tasks: Vec<_> = self
+            .jobs
+            .into_iter()
+            .map(|(job, trigger)| {
+                tokio::spawn(run_job(
+                    job,
+                    trigger,
+                    permits.clone(),
+                    shutdown.clone(),
+                    wake.clone(),
+                    cycle.clone(),
+                ))
+            })
+            .collect();
+
+        let mut jobs = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            match task.await {
+                Ok(Some(job)) => jobs.push(job),
+                Ok(None) => {}
+                Err(e) => log::error!("Scheduled job failed: {}", e),
+            }
+        }
+        jobs
+    }
+}
+
+async fn run_job<T, F>(
+    mut job: T,
+    trigger: Arc<Notify>,
+    permits: Arc<Semaphore>,
+    mut shutdown: watch::Receiver<bool>,
+    wake: Arc<Notify>,
+    cycle: Arc<F>,
+) -> Option<T>
+where
+    T: Send + 'static,
+    F: Fn(&mut T) -> Duration + Send + Sync + 'static,
+{
+    loop {
+        let Ok(permit) = permits.acquire().await else {
+            return Some(job);
+        };
+        if *shutdown.borrow() {
+            return Some(job);
+        }
+
+        let cycle = cycle.clone();
+        let delay;
+        (job, delay) = match tokio::task::spawn_blocking(move || {
+            let delay = cycle(&mut job);
+            (job, delay)
+        })
+        .await
+        {
+            Ok(done) => done,
+            Err(e) => {
+                log::error!("Scheduled job panicked: {}", e);
+                return None;
+            }
+        };
+        drop(permit);
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = wake.notified() => {}
+            _ = trigger.notified() => {}
+            _ = shutdown.wait_for(|stop| *stop) => return Some(job),
+        }
+    }
+}
+
+/// How long to wait before the next scan after `idle_scans` scans in a row
+/// found nothing new: `interval`, doubled for each idle scan past the first
+/// few, up to `max`.
+pub fn backoff(interval: Duration, max: Option<Duration>, idle_scans: u32) -> Duration {
+    let Some(max) = max.filter(|max| *max > interval) else {
+        return interval;
+    };
+    let doublings = idle_scans.saturating_sub(BACKOFF_AFTER_IDLE_SCANS);
+    interval
+        .saturating_mul(2u32.saturating_pow(doublings))
+        .min(max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_jobs_share_a_single_worker_fairly() {
+        let scheduler = Scheduler::new(vec![
+            (0usize, Arc::new(Notify::new())),
+            (1usize, Arc::new(Notify::new())),
+        ]);
+        let (stop, shutdown) = watch::channel(false);
+        let runs = Arc::new(Mutex::new(vec![0, 0]));
+
+        let counted = runs.clone();
+        scheduler
+            .run(1, shutdown, Arc::new(Notify::new()), move |job| {
+                let mut runs = counted.lock().unwrap();
+                runs[*job] += 1;
+                if runs.iter().sum::<usize>() >= 10 {
+                    stop.send_replace(true);
+                }
+                Duration::ZERO
+            })
+            .await;
+
+        assert_eq!(*runs.lock().unwrap(), vec![5, 5]);
+    }
+
+    #[test]
+    fn test_backoff_doubles_after_idle_scans_up_to_the_maximum() {
+        let interval = Duration::from_millis(500);
+        let max = Some(Duration::from_secs(3));
+        assert_eq!(backoff(interval, max, 0), interval);
+        assert_eq!(backoff(interval, max, 3), interval);
+        assert_eq!(backoff(interval, max, 4), Duration::from_secs(1));
+        assert_eq!(backoff(interval, max, 5), Duration::from_secs(2));
+        assert_eq!(backoff(interval, max, 6), Duration::from_secs(3));
+        assert_eq!(backoff(interval, max, u32::MAX), Duration::from_secs(3));
+        assert_eq!(backoff(interval, None, 100), interval);
+    }
+}
@@ -0,0 +1,2683 @@
+use crate::ai::AiSettings;
+use crate::approval::{ApprovalQueue, Decision};
+use crate::artifacts::is_unwanted_artifact;
+use crate::check::{CheckStatus, check_head};
+use crate::codeowners::CodeOwners;
+use crate::config::{
+    CommitDateStrategy, Config, JjMode, NotifySeverity, PathPolicy, RepeatPolicy, parse_author,
+};
+use crate::conflict_markers::find_conflict_marker;
+use crate::control::{Activity, Control};
+use crate::crypt::locked_paths;
+use crate::encoding::encoding_change;
+use crate::error::{GitChaiError, error_class};
+use crate::failed::{FAILURE_LIMIT, FailedPaths};
+use crate::git::status::GitChange;
+use crate::git::{
+    CommitDate, CommitSettings, RENORMALIZE_COMMIT_MESSAGE, StagingSimulation, SubjectRules,
+    amend_commit, amend_commit_for_file, batch_commit_message, branch_commit_message,
+    commit_in_worktree, commit_isolated, commit_to_branch, count_unpushed_commits,
+    create_batch_commit, create_commit, create_commit_for_directory, create_commit_for_file,
+    create_commit_for_link, create_commit_for_rename, dedup_plan, detect_case_renames,
+    detect_moved_files, detect_symlinks, directory_commit_message, drop_index_only_changes,
+    fetch_prune, file_commit_message, find_opaque_directory, get_changed_files, get_current_branch,
+    get_flagged_files, get_head_sha, get_staged_files, group_changes_by_directory,
+    individual_groups, is_attributes_file, is_case_only_rename, is_jj_colocated, is_partial_clone,
+    learned_style, link_commit_message, load_wordlist, mirror_branch, published_on,
+    rename_commit_message, renormalized_files, scan_fingerprint, short_sha, stage_case_rename,
+    stage_deletion, stage_deletions, stage_file, stage_files, stage_ignored_file, stage_rename,
+    stage_renormalized, unstage_planned, verify_staged_files,
+};
+use crate::gitignore::{IGNORE_COMMIT_MESSAGE, NoiseTracker, append_to_gitignore, ignore_entry};
+use crate::headless::{HeadlessOptions, run_sessions};
+use crate::health::record_cycle;
+use crate::log_dedup::RepeatedMessage;
+use crate::notebook::{is_notebook, only_outputs_changed};
+use crate::notify::Notifier;
+use crate::observe::Observation;
+use crate::pause::{OwnIndexWrites, pause_reason};
+use crate::plan::{ChangeGroup, GroupKind};
+use crate::policy::{ask_approval, branch_for, policy_for};
+use crate::provenance::Provenance;
+use crate::push::PushState;
+use crate::rate_limit::CommitRateLimiter;
+use crate::repeat::{RepeatChangeTracker, rule_for, should_hold};
+use crate::repo_access::RepoAccess;
+use crate::scheduler::backoff;
+use crate::session_merge::merge_session;
+use crate::skipped::SkippedPaths;
+use crate::snapshot::take_snapshot;
+use crate::syntax_check::SyntaxChecker;
+use crate::team_policy::{TEAM_POLICY_FILE, enforce_team_policy};
+use crate::template::{environment_value, expand};
+use crate::types::{ChangeType, GitStatus};
+use crate::vault::{is_editor_temp, plan_notes_commit};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// What a dry run does instead of committing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DryRunMode {
+    /// Only print the commit plan
+    Plan,
+    /// Also stage the plan into a temporary index and run the commit hooks
+    Deep,
+}
+
+/// Commits pending changes and returns the number of commits created. When
+/// `commit_budget` is set, processing stops once that many commits have been made.
+fn process_changes(
+    session: &mut ChaiSession,
+    push: bool,
+    commit_budget: Option<usize>,
+) -> Result<usize> {
+    let config = &session.config;
+    let dry_run = session.dry_run;
+    let verbose = session.verbose;
+    let state = &mut session.state;
+    if let Some(reason) = pause_reason(config) {
+        log::info!("Paused: {}; will resume automatically", reason);
+        return Ok(0);
+    }
+    let _own_writes = OwnIndexWrites::track(config);
+
+    let deep = dry_run == Some(DryRunMode::Deep);
+    let dry_run = dry_run.is_some();
+    let mut observation = session.observe.then(Observation::default);
+
+    state.push_state.check_divergence(config);
+    let mut settings = commit_settings(config);
+
+    log::info!("Scanning for changes in {:?}...", config.repo_path);
+
+    let changes = match scan_changes(config, config.include_ignored) {
+        Ok(changes) => {
+            if changes.is_empty() {
+                log::info!("No changes detected");
+                state.idle_scans += 1;
+                return Ok(0);
+            }
+            changes
+        }
+        Err(e) => {
+            log::error!("Failed to scan for changes: {}", e);
+            return Err(e.into());
+        }
+    };
+
+    let mut failed = FailedPaths::load(&config.repo_path);
+    let mut approvals = ApprovalQueue::load(&config.repo_path);
+
+    // The same scan is planned the same way, unless a queued change was approved
+    // or denied, or a failing path cleared for retry, in the meantime
+    let fingerprint = scan_fingerprint(&config.repo_path, &changes)
+        ^ approvals.fingerprint()
+        ^ failed.fingerprint();
+    if state.last_scan == Some(fingerprint) {
+        log::debug!("Nothing changed since the last scan");
+        state.idle_scans += 1;
+        return Ok(0);
+    }
+    state.last_scan = None;
+    state.idle_scans = 0;
+
+    let gitignore_changed = changes.iter().any(|change| change.filename == ".gitignore");
+    let (filtered, mut skipped) = filter_changes(
+        config,
+        changes,
+        FilterOptions {
+            dry_run,
+            interactive: session.interactive,
+            approvals: &mut approvals,
+            failed: &failed,
+        },
+    )?;
+    let FilteredChanges {
+        changes,
+        branch_changes,
+        artifact_dirs,
+        any_held,
+    } = filtered;
+    if !dry_run && let Err(e) = approvals.save() {
+        log::warn!("Failed to save the approval queue: {:#}", e);
+    }
+
+    let noise = state.noise.observe(&artifact_dirs);
+    for dir in &noise.new {
+        log::warn!(
+            "{} looks like build output but is not ignored; add it to .gitignore, or list it in commit_artifact_dirs to commit it",
+            dir
+        );
+    }
+    let ignore_commits = ignore_recurring_artifacts(
+        config,
+        &settings,
+        dry_run,
+        &noise.recurring,
+        gitignore_changed,
+    );
+    if changes.is_empty() && branch_changes.is_empty() {
+        log::info!("No committable changes this cycle (all are held or skipped)");
+        if !any_held && state.noise.is_settled() {
+            state.last_scan = Some(fingerprint);
+        }
+        return Ok(ignore_commits);
+    }
+    let (attribute_commits, changes) =
+        commit_attributes_first(config, &settings, dry_run, changes, &mut skipped);
+    let ignore_commits = ignore_commits + attribute_commits;
+
+    // Ignored files are committed one at a time and skip rename/symlink detection
+    let (ignored_changes, changes): (Vec<_>, Vec<_>) = changes
+        .into_iter()
+        .partition(|change| change.status == GitStatus::Ignored);
+
+    // Changes inside opaque directories skip rename/symlink detection
+    let (opaque_changes, changes): (Vec<_>, Vec<_>) = changes.into_iter().partition(|change| {
+        find_opaque_directory(&change.filename, &config.opaque_directories).is_some()
+    });
+
+    let changes = match detect_case_renames(&config.repo_path, &changes) {
+        Ok(detected) => detected,
+        Err(e) => {
+            log::warn!("Failed to detect case-only renames: {}", e);
+            changes
+        }
+    };
+    // Rename detection compares blob contents, which would lazily fetch missing
+    // objects in a partial clone (or fail when offline)
+    let changes = if is_partial_clone(&config.repo_path) {
+        log::debug!("Partial clone: skipping move detection");
+        changes
+    } else {
+        match detect_moved_files(&config.repo_path, &changes) {
+            Ok(detected) => detected,
+            Err(e) => {
+                log::warn!("Failed to detect moved files: {}", e);
+                changes
+            }
+        }
+    };
+    let changes = match detect_symlinks(&config.repo_path, &changes) {
+        Ok(detected) => detected,
+        Err(e) => {
+            log::warn!("Failed to detect symlinks: {}", e);
+            changes
+        }
+    };
+    let changes: Vec<_> = if config.skip_symlinks {
+        changes
+            .into_iter()
+            .filter(|change| {
+                if change.symlink_target.is_some() {
+                    skipped.skip(&change.filename, "skip_symlinks is set");
+                }
+                change.symlink_target.is_none()
+            })
+            .collect()
+    } else {
+        changes
+    };
+    let (mut renames, changes): (Vec<_>, Vec<_>) = changes
+        .into_iter()
+        .partition(|change| change.renamed_from.is_some());
+    let (mut links, mut changes): (Vec<_>, Vec<_>) = changes
+        .into_iter()
+        .partition(|change| change.symlink_target.is_some());
+    changes.extend(opaque_changes);
+
+    // Notes mode folds everything into the day's single notes commit
+    let notes: Vec<GitChange> = if config.notes_mode {
+        renames
+            .drain(..)
+            .chain(links.drain(..))
+            .chain(changes.drain(..))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let notes_unit = (!notes.is_empty()).then(|| notes_unit(config, &notes));
+
+    let mut change_groups =
+        group_changes_by_directory(&config.repo_path, &changes, &config.opaque_directories);
+    change_groups.extend(individual_groups(&ignored_changes));
+    let change_groups = dedup_plan(change_groups);
+    let is_ignored = |filename: &str| {
+        ignored_changes
+            .iter()
+            .any(|change| change.filename == filename)
+    };
+
+    let provenance = config.provenance.map(|mode| {
+        let plan: Vec<String> = renames
+            .iter()
+            .filter_map(|change| {
+                let from = change.renamed_from.as_deref()?;
+                Some(rename_commit_message(from, &change.filename))
+            })
+            .chain(links.iter().filter_map(|change| {
+                let target = change.symlink_target.as_deref()?;
+                Some(link_commit_message(&change.filename, target))
+            }))
+            .chain(branch_changes.iter().map(|branch_change| {
+                let change = &branch_change.change;
+                format!(
+                    "{} (to {})",
+                    branch_commit_message(
+                        &config.repo_path,
+                        &settings,
+                        Some(&branch_change.branch),
+                        &change.change_type.to_string(),
+                        &change.filename
+                    ),
+                    branch_change.branch
+                )
+            }))
+            .chain(
+                change_groups
+                    .iter()
+                    .flat_map(|group| group_units(&config.repo_path, &settings, group, is_ignored))
+                    .map(|unit| unit.message),
+            )
+            .chain(notes_unit.iter().map(|unit| unit.message.clone()))
+            .collect();
+        Provenance::new(config, mode, &plan)
+    });
+    if let Some(provenance) = &provenance {
+        provenance.attach(&mut settings);
+    }
+
+    let mut commits_made = ignore_commits;
+    let mut new_commits = Vec::new();
+    let mut replan = false;
+
+    if let Some(unit) = &notes_unit {
+        if dry_run {
+            log::info!(
+                "DRY RUN: Would {} notes commit: {}",
+                if unit.amend { "amend the" } else { "create a" },
+                unit.message
+            );
+            if let Some(observation) = observation.as_mut() {
+                observation.plan(
+                    &settings,
+                    &unit.message,
+                    unit.paths.iter().chain(&unit.removals).cloned().collect(),
+                    None,
+                );
+            }
+        } else if !unit.amend && commit_budget.is_some_and(|budget| commits_made >= budget) {
+            log::info!("Commit limit reached, holding remaining changes");
+            skipped.stop("commit limit reached");
+        } else {
+            log::info!("Processing: {} changed notes", notes.len());
+            let result = stage_and_commit(
+                config,
+                &settings,
+                unit,
+                || {
+                    for path in &unit.removals {
+                        stage_deletion(&config.repo_path, path)?;
+                    }
+                    for path in &unit.paths {
+                        stage_file(&config.repo_path, path)?;
+                    }
+                    Ok(())
+                },
+                || {
+                    if unit.amend {
+                        amend_commit(&config.repo_path, &settings, &unit.message)
+                    } else {
+                        create_commit(&config.repo_path, &settings, &unit.message)
+                    }
+                },
+            );
+            match result {
+                Ok(sha) => {
+                    if !unit.amend {
+                        commits_made += 1;
+                    }
+                    log::info!("Committed: {} ({})", unit.message, short_sha(&sha));
+                    new_commits.push(sha);
+                }
+                Err(CommitStepError::IndexChanged) => replan = true,
+                Err(CommitStepError::Stage(e)) | Err(CommitStepError::Commit(e)) => {
+                    log::error!("Failed to commit notes: {}", e);
+                    for change in &notes {
+                        skipped.fail(&change.filename, format!("failed to commit: {}", e));
+                    }
+                }
+            }
+        }
+    }
+
+    for change in &renames {
+        let Some(from) = change.renamed_from.as_deref() else {
+            continue;
+        };
+
+        if commit_budget.is_some_and(|budget| commits_made >= budget) {
+            skipped.stop("commit limit reached");
+            break;
+        }
+
+        if dry_run {
+            log::info!(
+                "DRY RUN: Would commit rename: {} -> {}",
+                from,
+                change.filename
+            );
+            if let Some(observation) = observation.as_mut() {
+                observation.plan(
+                    &settings,
+                    &rename_commit_message(from, &change.filename),
+                    vec![from.to_string(), change.filename.clone()],
+                    None,
+                );
+            }
+            continue;
+        }
+
+        log::info!("Processing: rename: {} -> {}", from, change.filename);
+
+        let case_only = is_case_only_rename(from, &change.filename);
+        let unit = CommitUnit {
+            paths: if case_only {
+                vec![change.filename.clone()]
+            } else {
+                vec![from.to_string(), change.filename.clone()]
+            },
+            removals: if case_only {
+                vec![from.to_string()]
+            } else {
+                Vec::new()
+            },
+            message: rename_commit_message(from, &change.filename),
+            amend: false,
+            force: false,
+        };
+        let result = stage_and_commit(
+            config,
+            &settings,
+            &unit,
+            || {
+                if case_only {
+                    stage_case_rename(&config.repo_path, from, &change.filename)
+                } else {
+                    stage_rename(&config.repo_path, from, &change.filename)
+                }
+            },
+            || create_commit_for_rename(&config.repo_path, &settings, from, &change.filename),
+        );
+        let sha = match result {
+            Ok(sha) => sha,
+            Err(CommitStepError::Stage(e)) => {
+                log::error!(
+                    "Failed to stage rename {} -> {}: {}",
+                    from,
+                    change.filename,
+                    e
+                );
+                skipped.fail(from, format!("failed to stage: {}", e));
+                skipped.fail(&change.filename, format!("failed to stage: {}", e));
+                continue;
+            }
+            Err(CommitStepError::IndexChanged) => {
+                replan = true;
+                break;
+            }
+            Err(CommitStepError::Commit(e)) => {
+                log::error!(
+                    "Failed to create commit for rename {} -> {}: {}",
+                    from,
+                    change.filename,
+                    e
+                );
+                skipped.fail(from, format!("failed to commit: {}", e));
+                skipped.fail(&change.filename, format!("failed to commit: {}", e));
+                continue;
+            }
+        };
+        commits_made += 1;
+
+        log::info!(
+            "Committed: rename: {} -> {} ({})",
+            from,
+            change.filename,
+            short_sha(&sha)
+        );
+        new_commits.push(sha);
+    }
+
+    for change in &links {
+        let Some(target) = change.symlink_target.as_deref() else {
+            continue;
+        };
+
+        if replan {
+            break;
+        }
+
+        if commit_budget.is_some_and(|budget| commits_made >= budget) {
+            skipped.stop("commit limit reached");
+            break;
+        }
+
+        if dry_run {
+            log::info!(
+                "DRY RUN: Would commit link: {} -> {}",
+                change.filename,
+                target
+            );
+            if let Some(observation) = observation.as_mut() {
+                observation.plan(
+                    &settings,
+                    &link_commit_message(&change.filename, target),
+                    vec![change.filename.clone()],
+                    None,
+                );
+            }
+            continue;
+        }
+
+        log::info!("Processing: link: {} -> {}", change.filename, target);
+
+        let unit = CommitUnit {
+            paths: vec![change.filename.clone()],
+            removals: Vec::new(),
+            message: link_commit_message(&change.filename, target),
+            amend: false,
+            force: false,
+        };
+        let result = stage_and_commit(
+            config,
+            &settings,
+            &unit,
+            || stage_file(&config.repo_path, &change.filename),
+            || create_commit_for_link(&config.repo_path, &settings, &change.filename, target),
+        );
+        let sha = match result {
+            Ok(sha) => sha,
+            Err(CommitStepError::Stage(e)) => {
+                log::error!("Failed to stage symlink {}: {}", change.filename, e);
+                skipped.fail(&change.filename, format!("failed to stage: {}", e));
+                continue;
+            }
+            Err(CommitStepError::IndexChanged) => {
+                replan = true;
+                break;
+            }
+            Err(CommitStepError::Commit(e)) => {
+                log::error!(
+                    "Failed to create commit for symlink {}: {}",
+                    change.filename,
+                    e
+                );
+                skipped.fail(&change.filename, format!("failed to commit: {}", e));
+                continue;
+            }
+        };
+        commits_made += 1;
+
+        log::info!(
+            "Committed: link: {} -> {} ({})",
+            change.filename,
+            target,
+            short_sha(&sha)
+        );
+        new_commits.push(sha);
+    }
+
+    for BranchChange {
+        change,
+        branch,
+        in_worktree,
+    } in &branch_changes
+    {
+        if replan {
+            break;
+        }
+
+        if commit_budget.is_some_and(|budget| commits_made >= budget) {
+            skipped.stop("commit limit reached");
+            break;
+        }
+
+        if let Some(policy) = &config.team_policy
+            && policy.protects(branch)
+        {
+            log::warn!(
+                "Not committing {} to {}: the branch is protected by {}",
+                change.filename,
+                branch,
+                TEAM_POLICY_FILE
+            );
+            skipped.skip(
+                &change.filename,
+                format!("branch {} is protected by {}", branch, TEAM_POLICY_FILE),
+            );
+            continue;
+        }
+
+        let change_type = change.change_type.to_string();
+        if dry_run {
+            log::info!(
+                "DRY RUN: Would commit {}: {} to branch {}",
+                change_type,
+                change.filename,
+                branch
+            );
+            if let Some(observation) = observation.as_mut() {
+                observation.plan(
+                    &settings,
+                    &branch_commit_message(
+                        &config.repo_path,
+                        &settings,
+                        Some(branch),
+                        &change_type,
+                        &change.filename,
+                    ),
+                    vec![change.filename.clone()],
+                    Some(branch),
+                );
+            }
+            continue;
+        }
+
+        let (paths, removals) = if change.change_type == ChangeType::Delete {
+            (Vec::new(), vec![change.filename.clone()])
+        } else {
+            (vec![change.filename.clone()], Vec::new())
+        };
+        let message = branch_commit_message(
+            &config.repo_path,
+            &settings,
+            Some(branch),
+            &change_type,
+            &change.filename,
+        );
+        let result = if *in_worktree {
+            commit_in_worktree(
+                &config.repo_path,
+                &settings,
+                branch,
+                &paths,
+                &removals,
+                &message,
+            )
+        } else {
+            commit_to_branch(
+                &config.repo_path,
+                &settings,
+                branch,
+                &paths,
+                &removals,
+                &message,
+            )
+        };
+        match result {
+            Ok(Some(sha)) => {
+                commits_made += 1;
+                log::info!(
+                    "Committed to {}: {}: {} ({})",
+                    branch,
+                    change_type,
+                    change.filename,
+                    short_sha(&sha)
+                );
+                new_commits.push(sha);
+                skipped.skip(&change.filename, format!("committed to branch {}", branch));
+            }
+            Ok(None) => {
+                log::debug!("{} is already up to date on {}", change.filename, branch);
+                skipped.skip(&change.filename, format!("committed to branch {}", branch));
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to commit {} to branch {}: {}",
+                    change.filename,
+                    branch,
+                    e
+                );
+                skipped.fail(
+                    &change.filename,
+                    format!("failed to commit to {}: {}", branch, e),
+                );
+            }
+        }
+    }
+
+    let mut simulation = deep
+        .then(|| StagingSimulation::new(&config.repo_path, &settings))
+        .and_then(|simulation| {
+            simulation
+                .inspect_err(|e| log::warn!("Cannot simulate staging: {}", e))
+                .ok()
+        });
+    let mut simulated_failures = 0;
+
+    'groups: for group in change_groups {
+        if replan {
+            break;
+        }
+
+        if commit_budget.is_some_and(|budget| commits_made >= budget) {
+            log::info!("Commit limit reached, holding remaining changes");
+            skipped.stop("commit limit reached");
+            break;
+        }
+
+        if dry_run {
+            if verbose {
+                let files: Vec<String> = group
+                    .files
+                    .iter()
+                    .map(|file| format!("{}: {}", file.change_type, file.path))
+                    .collect();
+                log::info!(
+                    "DRY RUN: Would process group - Type: {}, Path: {}, Files: {:?}",
+                    group.kind,
+                    group.path.display(),
+                    files
+                );
+            } else {
+                log::info!(
+                    "DRY RUN: Would process {} files in {}: {}",
+                    group.files.len(),
+                    group.kind,
+                    group.path.display()
+                );
+            }
+            if let Some(simulation) = simulation.as_mut() {
+                simulated_failures +=
+                    simulate_group(&config.repo_path, &settings, simulation, &group, is_ignored);
+            }
+            if let Some(observation) = observation.as_mut() {
+                for unit in group_units(&config.repo_path, &settings, &group, is_ignored) {
+                    observation.plan(&settings, &unit.message, vec![unit.path], None);
+                }
+            }
+            continue;
+        }
+
+        if let GroupKind::Directory(change_type) = group.kind {
+            let deletion = change_type == ChangeType::Delete;
+            let change_type = change_type.to_string();
+            if verbose {
+                log::info!(
+                    "Processing directory: {}: {} (would stage all files and commit)",
+                    change_type,
+                    group.path.display()
+                );
+            } else {
+                log::info!(
+                    "Processing directory: {}: {}",
+                    change_type,
+                    group.path.display()
+                );
+            }
+
+            let directory = format!(
+                "{}/",
+                group.path.display().to_string().trim_end_matches('/')
+            );
+            // The group's own files are staged rather than the directory, so a
+            // file that appeared since planning is left out of the commit
+            let files: Vec<String> = group.files.iter().map(|file| file.path.clone()).collect();
+            let (paths, removals) = if deletion {
+                (Vec::new(), files)
+            } else {
+                (files, Vec::new())
+            };
+            let unit = CommitUnit {
+                paths,
+                removals,
+                message: directory_commit_message(
+                    &settings,
+                    &change_type,
+                    &group.path,
+                    group.files.len(),
+                ),
+                amend: false,
+                force: false,
+            };
+            let result = stage_and_commit(
+                config,
+                &settings,
+                &unit,
+                || {
+                    if deletion {
+                        stage_deletions(&config.repo_path, &unit.removals)
+                    } else {
+                        stage_files(&config.repo_path, &unit.paths)
+                    }
+                },
+                || {
+                    create_commit_for_directory(
+                        &config.repo_path,
+                        &settings,
+                        &group.path,
+                        &change_type,
+                        group.files.len(),
+                    )
+                },
+            );
+            let sha = match result {
+                Ok(sha) => sha,
+                Err(CommitStepError::Stage(e)) => {
+                    log::error!("Failed to stage directory {}: {}", group.path.display(), e);
+                    skipped.fail(&directory, format!("failed to stage: {}", e));
+                    continue;
+                }
+                Err(CommitStepError::IndexChanged) => {
+                    replan = true;
+                    break;
+                }
+                Err(CommitStepError::Commit(e)) => {
+                    log::error!(
+                        "Failed to create commit for directory {}: {}",
+                        group.path.display(),
+                        e
+                    );
+                    skipped.fail(&directory, format!("failed to commit: {}", e));
+                    continue;
+                }
+            };
+            commits_made += 1;
+
+            if verbose {
+                log::info!(
+                    "Committed directory: {}: {} (commit {}, message: '{}')",
+                    change_type,
+                    group.path.display(),
+                    sha,
+                    unit.message
+                );
+            } else {
+                log::info!(
+                    "Committed directory: {}: {} ({})",
+                    change_type,
+                    group.path.display(),
+                    short_sha(&sha)
+                );
+            }
+            new_commits.push(sha);
+        } else {
+            for file in &group.files {
+                if commit_budget.is_some_and(|budget| commits_made >= budget) {
+                    log::info!("Commit limit reached, holding remaining changes");
+                    skipped.stop("commit limit reached");
+                    break 'groups;
+                }
+
+                let clean_filename = &file.path;
+                let deletion = file.change_type == ChangeType::Delete;
+                let change_type = file.change_type.to_string();
+                let change_type = change_type.as_str();
+
+                if verbose {
+                    log::info!(
+                        "Processing: {}: {} (would stage and commit)",
+                        change_type,
+                        clean_filename
+                    );
+                } else {
+                    log::info!("Processing: {}: {}", change_type, clean_filename);
+                }
+
+                // Amending is skipped when pushing, since the previous commit is already on the remote,
+                // and for a commit that reached a remote some other way (the rewrite horizon)
+                let amend_rule = !push
+                    && rule_for(&config.repeat_change_rules, clean_filename)
+                        .is_some_and(|rule| rule.policy == RepeatPolicy::Amend);
+                let amend = amend_rule
+                    && get_head_sha(&config.repo_path).is_ok_and(|sha| {
+                        state.tracker.can_amend(clean_filename, &sha)
+                            && published_on(&config.repo_path, &sha).is_ok_and(|on| on.is_none())
+                    });
+
+                let force = is_ignored(clean_filename);
+                let (paths, removals) = if deletion {
+                    (Vec::new(), vec![clean_filename.clone()])
+                } else {
+                    (vec![clean_filename.clone()], Vec::new())
+                };
+                let unit = CommitUnit {
+                    paths,
+                    removals,
+                    message: file_commit_message(
+                        &config.repo_path,
+                        &settings,
+                        change_type,
+                        clean_filename,
+                    ),
+                    amend,
+                    force,
+                };
+                let result = stage_and_commit(
+                    config,
+                    &settings,
+                    &unit,
+                    || {
+                        if deletion {
+                            stage_deletion(&config.repo_path, clean_filename)
+                        } else if force {
+                            stage_ignored_file(&config.repo_path, clean_filename)
+                        } else {
+                            stage_file(&config.repo_path, clean_filename)
+                        }
+                    },
+                    || {
+                        if amend {
+                            amend_commit_for_file(
+                                &config.repo_path,
+                                &settings,
+                                clean_filename,
+                                change_type,
+                            )
+                        } else {
+                            create_commit_for_file(
+                                &config.repo_path,
+                                &settings,
+                                clean_filename,
+                                change_type,
+                            )
+                        }
+                    },
+                );
+                let sha = match result {
+                    Ok(sha) => sha,
+                    Err(CommitStepError::Stage(e)) => {
+                        log::error!("Failed to stage file {}: {}", clean_filename, e);
+                        skipped.fail(clean_filename, format!("failed to stage: {}", e));
+                        continue;
+                    }
+                    Err(CommitStepError::IndexChanged) => {
+                        replan = true;
+                        break 'groups;
+                    }
+                    Err(CommitStepError::Commit(e)) => {
+                        log::error!("Failed to create commit for {}: {}", clean_filename, e);
+                        skipped.fail(clean_filename, format!("failed to commit: {}", e));
+                        continue;
+                    }
+                };
+                if !amend {
+                    commits_made += 1;
+                }
+
+                if verbose {
+                    log::info!(
+                        "Committed: {}: {} (commit {}, message: '{}')",
+                        change_type,
+                        clean_filename,
+                        sha,
+                        unit.message
+                    );
+                } else {
+                    log::info!(
+                        "Committed: {}: {} ({})",
+                        change_type,
+                        clean_filename,
+                        short_sha(&sha)
+                    );
+                }
+
+                new_commits.push(sha.clone());
+                if amend_rule {
+                    state.tracker.record_commit(clean_filename, sha);
+                }
+            }
+        }
+    }
+
+    if simulation.is_some() {
+        if simulated_failures == 0 {
+            log::info!("DRY RUN: Every commit would succeed");
+        } else {
+            log::warn!("DRY RUN: {} commit(s) would fail", simulated_failures);
+        }
+    }
+
+    if replan {
+        log::warn!("Stopped early because the index changed; remaining changes will be replanned");
+        skipped.stop("index changed during the run; will be replanned");
+    }
+
+    if !dry_run && let Some(remaining) = report_remaining_changes(config, &skipped) {
+        approvals.retain_dirty(&remaining);
+        if let Err(e) = approvals.save() {
+            log::warn!("Failed to save the approval queue: {:#}", e);
+        }
+        for path in failed.record_run(skipped.failures(), &remaining) {
+            let error = skipped.failures().get(&path).cloned().unwrap_or_default();
+            state.notifier.notify(
+                config,
+                NotifySeverity::Warning,
+                format!("{} keeps failing to commit", path),
+                format!(
+                    "{} failed to commit {} times in a row in {} and is no longer retried; run `git-chai status --retry-failed` to retry.\n\nLast error: {}",
+                    path,
+                    FAILURE_LIMIT,
+                    config.repo_path.display(),
+                    error
+                ),
+            );
+        }
+        if let Err(e) = failed.save() {
+            log::warn!("Failed to save the list of failing paths: {:#}", e);
+        }
+    }
+
+    if !any_held
+        && skipped.failures().is_empty()
+        && !skipped.is_stopped()
+        && state.noise.is_settled()
+    {
+        state.last_scan = Some(fingerprint);
+    }
+
+    if let Some(provenance) = &provenance {
+        provenance.record(config, &new_commits);
+    }
+
+    if commits_made > 0 && !dry_run {
+        mirror_commits(config);
+        snapshot_commits(config);
+        check_commits(config);
+    }
+
+    if let Some(observation) = &observation
+        && let Err(e) = observation.write(&config.repo_path, &skipped, push)
+    {
+        log::warn!("Failed to record the observed plan: {:#}", e);
+    }
+
+    if push && !dry_run {
+        state.push_state.push(config);
+    } else if push && dry_run {
+        log::info!("DRY RUN: Would push changes to remote");
+    } else {
+        log::info!("Skipping push (--no-push specified)");
+    }
+
+    Ok(commits_made)
+}
+
+/// A change committed to a branch other than the checked-out one.
+struct BranchChange {
+    change: GitChange,
+    branch: String,
+    /// Committed from a temporary worktree (`branch_map`) rather than through a
+    /// temporary index (the `separate-branch` path policy)
+    in_worktree: bool,
+}
+
+/// What a cycle may commit once [`filter_changes`] has left out everything that
+/// must not be committed.
+struct FilteredChanges {
+    /// Changes to commit on the checked-out branch
+    changes: Vec<GitChange>,
+    /// Changes `path_policies` or `branch_map` send to another branch
+    branch_changes: Vec<BranchChange>,
+    /// Directories left out as build output
+    artifact_dirs: Vec<String>,
+    /// Whether `repeat_change_rules` held back any change
+    any_held: bool,
+}
+
+/// How [`filter_changes`] treats paths that need approval, and the state it
+/// reads.
+struct FilterOptions<'a> {
+    dry_run: bool,
+    /// Ask about paths that need approval instead of queueing them
+    interactive: bool,
+    approvals: &'a mut ApprovalQueue,
+    failed: &'a FailedPaths,
+}
+
+/// Applies every rule that keeps a scanned change out of commits: skip-worktree
+/// flags, paths that keep failing, build output, `.chai-policy.toml`,
+/// `path_policies`, locked encrypted files, conflict markers, the encoding and
+/// syntax guards and more. Returns what may be committed, and the paths left out
+/// with the reason for each.
+fn filter_changes(
+    config: &Config,
+    changes: Vec<GitChange>,
+    options: FilterOptions,
+) -> Result<(FilteredChanges, SkippedPaths)> {
+    let mut skipped = SkippedPaths::default();
+    let FilterOptions {
+        dry_run,
+        interactive,
+        approvals,
+        failed,
+    } = options;
+
+    let flagged = get_flagged_files(&config.repo_path).unwrap_or_else(|e| {
+        log::warn!("Failed to read skip-worktree/assume-unchanged flags: {}", e);
+        Vec::new()
+    });
+    let changes: Vec<_> = changes
+        .into_iter()
+        .filter(|change| {
+            let Some((_, flag)) = flagged.iter().find(|(name, _)| *name == change.filename) else {
+                return true;
+            };
+            log::debug!("Not staging {} ({})", change.filename, flag);
+            skipped.skip(&change.filename, format!("marked {}", flag));
+            false
+        })
+        .collect();
+
+    let changes: Vec<_> = changes
+        .into_iter()
+        .filter(|change| {
+            let Some(entry) = failed.parked_entry(&change.filename) else {
+                return true;
+            };
+            log::debug!("Not retrying {}: {}", change.filename, entry.last_error);
+            skipped.skip(
+                &change.filename,
+                format!(
+                    "failed {} times, last: {}; run `git-chai status --retry-failed` to retry",
+                    entry.failures, entry.last_error
+                ),
+            );
+            false
+        })
+        .collect();
+
+    let (artifact_changes, changes): (Vec<_>, Vec<_>) = changes
+        .into_iter()
+        .partition(|change| is_unwanted_artifact(change, &config.commit_artifact_dirs));
+    let artifact_dirs: Vec<String> = artifact_changes
+        .into_iter()
+        .map(|change| change.filename)
+        .collect();
+    for dir in &artifact_dirs {
+        skipped.skip(dir, "looks like build output");
+    }
+    let changes: Vec<_> = if config.notes_mode {
+        changes
+            .into_iter()
+            .filter(|change| {
+                let temp = is_editor_temp(&change.filename);
+                if temp {
+                    skipped.skip(&change.filename, "editor temporary file");
+                }
+                !temp
+            })
+            .collect()
+    } else {
+        changes
+    };
+    let changes: Vec<_> = if config.strip_notebook_outputs {
+        changes
+            .into_iter()
+            .filter(|change| {
+                let outputs_only = only_outputs_saved(config, change);
+                if outputs_only {
+                    skipped.skip(&change.filename, "only notebook outputs changed");
+                }
+                !outputs_only
+            })
+            .collect()
+    } else {
+        changes
+    };
+    // Staged without their clean filter, encrypted paths would be committed in
+    // plaintext
+    let locked = locked_paths(&config.repo_path, &changes).unwrap_or_else(|e| {
+        log::warn!("Failed to check .gitattributes filters: {}", e);
+        HashMap::new()
+    });
+    let changes: Vec<_> = changes
+        .into_iter()
+        .filter(|change| {
+            let Some(reason) = locked.get(&change.filename) else {
+                return true;
+            };
+            log::warn!("Not committing {}: {}", change.filename, reason);
+            skipped.skip(&change.filename, reason.clone());
+            false
+        })
+        .collect();
+
+    // Held changes are released by time passing, not by the scan changing
+    let mut any_held = false;
+    let changes: Vec<_> = changes
+        .into_iter()
+        .filter(|change| {
+            let held = rule_for(&config.repeat_change_rules, &change.filename)
+                .is_some_and(|rule| should_hold(&config.repo_path, &change.filename, rule));
+            if held {
+                skipped.skip(&change.filename, "held by repeat_change_rules");
+                any_held = true;
+            }
+            !held
+        })
+        .collect();
+    let changes: Vec<_> = if config.allow_conflict_markers {
+        changes
+    } else {
+        changes
+            .into_iter()
+            .filter(
+                |change| match find_conflict_marker(&config.repo_path, change) {
+                    Ok(None) => true,
+                    Ok(Some(location)) => {
+                        log::warn!(
+                            "Not committing {}: unresolved merge conflict marker at {}",
+                            change.filename,
+                            location
+                        );
+                        skipped.skip(
+                            &change.filename,
+                            format!("has a merge conflict marker at {}", location),
+                        );
+                        false
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "Failed to check {} for conflict markers: {}",
+                            change.filename,
+                            e
+                        );
+                        true
+                    }
+                },
+            )
+            .collect()
+    };
+    let changes: Vec<_> = if config.encoding_guard {
+        changes
+            .into_iter()
+            .filter(|change| match encoding_change(&config.repo_path, change) {
+                Ok(None) => true,
+                Ok(Some(description)) => {
+                    log::warn!(
+                        "Not committing {}: its encoding {}; commit it by hand if that was intended",
+                        change.filename,
+                        description
+                    );
+                    skipped.skip(
+                        &change.filename,
+                        format!("encoding {}; review and commit it by hand", description),
+                    );
+                    false
+                }
+                Err(e) => {
+                    log::warn!("Failed to check the encoding of {}: {}", change.filename, e);
+                    true
+                }
+            })
+            .collect()
+    } else {
+        changes
+    };
+    let mut syntax = SyntaxChecker::new(&config.repo_path, &config.syntax_check);
+    let changes: Vec<_> = changes
+        .into_iter()
+        .filter(|change| {
+            let Some(error) = syntax.check(change) else {
+                return true;
+            };
+            log::warn!("Not committing {}: {}", change.filename, error);
+            skipped.skip(&change.filename, error);
+            false
+        })
+        .collect();
+    let changes: Vec<_> = match &config.team_policy {
+        Some(policy) => changes
+            .into_iter()
+            .filter(|change| {
+                let forbidden = policy.forbids(&change.filename);
+                if forbidden {
+                    skipped.skip(
+                        &change.filename,
+                        format!("forbidden by {}", TEAM_POLICY_FILE),
+                    );
+                }
+                !forbidden
+            })
+            .collect(),
+        None => changes,
+    };
+    let changes: Vec<_> = if config.owned_by.is_empty() {
+        changes
+    } else {
+        let owners = CodeOwners::load(&config.repo_path).unwrap_or_default();
+        changes
+            .into_iter()
+            .filter(|change| {
+                if owners.is_owned_by(&change.filename, &config.owned_by) {
+                    return true;
+                }
+                let others = owners.owners_of(&change.filename);
+                if others.is_empty() {
+                    skipped.skip(&change.filename, "has no owner in CODEOWNERS");
+                } else {
+                    skipped.skip(
+                        &change.filename,
+                        format!("owned by {} in CODEOWNERS", others.join(" ")),
+                    );
+                }
+                false
+            })
+            .collect()
+    };
+    let mut branch_changes = Vec::new();
+    let changes = apply_path_policies(
+        config,
+        changes,
+        dry_run,
+        interactive,
+        approvals,
+        &mut skipped,
+        &mut branch_changes,
+    )?;
+    let changes = apply_branch_map(config, changes, &mut branch_changes);
+
+    let filtered = FilteredChanges {
+        changes,
+        branch_changes,
+        artifact_dirs,
+        any_held,
+    };
+    Ok((filtered, skipped))
+}
+
+/// Applies `path_policies` to the scanned changes. Returns the changes to commit
+/// as usual; those to commit to another branch go to `branch_changes` and the
+/// rest are recorded in `skipped`. Paths that need approval are committed once
+/// approved in `git-chai review`, or asked about when `interactive`.
+fn apply_path_policies(
+    config: &Config,
+    changes: Vec<GitChange>,
+    dry_run: bool,
+    interactive: bool,
+    approvals: &mut ApprovalQueue,
+    skipped: &mut SkippedPaths,
+    branch_changes: &mut Vec<BranchChange>,
+) -> Result<Vec<GitChange>> {
+    if config.path_policies.is_empty() {
+        return Ok(changes);
+    }
+
+    let current_branch = get_current_branch(&config.repo_path).unwrap_or_default();
+    let mut kept = Vec::new();
+
+    for change in changes {
+        let Some(rule) = policy_for(&config.path_policies, &change.filename) else {
+            kept.push(change);
+            continue;
+        };
+
+        match rule.policy {
+            PathPolicy::AutoCommit => kept.push(change),
+            PathPolicy::NeverCommit => {
+                log::debug!("Not committing {} (never-commit)", change.filename);
+                skipped.skip(&change.filename, "never committed by path_policies");
+            }
+            PathPolicy::RequireApproval if dry_run => {
+                log::info!("DRY RUN: Would ask before committing {}", change.filename);
+                skipped.skip(&change.filename, "needs approval (path_policies)");
+            }
+            PathPolicy::RequireApproval => {
+                let change_type = change.change_type.to_string();
+                match approvals.check(&config.repo_path, &change.filename, &change_type) {
+                    Decision::Approved => kept.push(change),
+                    Decision::Denied => {
+                        skipped.skip(&change.filename, "denied in `git-chai review`");
+                    }
+                    Decision::Pending if interactive => {
+                        if ask_approval(&change_type, &change.filename)? {
+                            approvals.forget(&change.filename);
+                            kept.push(change);
+                        } else {
+                            approvals.decide(&config.repo_path, &change.filename, Decision::Denied);
+                            skipped.skip(&change.filename, "not approved");
+                        }
+                    }
+                    Decision::Pending => {
+                        log::info!(
+                            "{} needs approval; run `git-chai review` to approve it",
+                            change.filename
+                        );
+                        skipped.skip(&change.filename, "waiting for `git-chai review`");
+                    }
+                }
+            }
+            PathPolicy::SeparateBranch => match &rule.branch {
+                Some(branch) if current_branch.as_ref() == Some(branch) => kept.push(change),
+                Some(branch) => branch_changes.push(BranchChange {
+                    change,
+                    branch: branch.clone(),
+                    in_worktree: false,
+                }),
+                None => {
+                    log::warn!(
+                        "path_policies pattern '{}' uses separate-branch without a branch; not committing {}",
+                        rule.pattern,
+                        change.filename
+                    );
+                    skipped.skip(&change.filename, "separate-branch policy without a branch");
+                }
+            },
+        }
+    }
+
+    Ok(kept)
+}
+
+/// Moves changes that `branch_map` assigns to a branch other than the
+/// checked-out one into `branch_changes`, returning the rest.
+fn apply_branch_map(
+    config: &Config,
+    changes: Vec<GitChange>,
+    branch_changes: &mut Vec<BranchChange>,
+) -> Vec<GitChange> {
+    if config.branch_map.is_empty() {
+        return changes;
+    }
+
+    let current_branch = get_current_branch(&config.repo_path).unwrap_or_default();
+    changes
+        .into_iter()
+        .filter_map(
+            |change| match branch_for(&config.branch_map, &change.filename) {
+                Some(branch) if current_branch.as_deref() != Some(branch) => {
+                    branch_changes.push(BranchChange {
+                        branch: branch.to_string(),
+                        change,
+                        in_worktree: true,
+                    });
+                    None
+                }
+                _ => Some(change),
+            },
+        )
+        .collect()
+}
+
+/// Copies the current branch into `mirror_repo`, if one is configured. Failures
+/// are logged and never stop committing.
+fn mirror_commits(config: &Config) {
+    let Some(mirror) = &config.mirror_repo else {
+        return;
+    };
+    let mirror = config.repo_path.join(mirror);
+
+    match get_current_branch(&config.repo_path) {
+        Ok(Some(branch)) => match mirror_branch(&config.repo_path, &mirror, &branch) {
+            Ok(()) => log::info!("Mirrored {} to {}", branch, mirror.display()),
+            Err(e) => log::warn!("Failed to mirror commits to {}: {}", mirror.display(), e),
+        },
+        Ok(None) => log::debug!("Detached HEAD; not mirroring"),
+        Err(e) => log::warn!("Failed to read the current branch for mirroring: {}", e),
+    }
+}
+
+/// Writes an encrypted snapshot of the new commits, if snapshots are configured.
+fn snapshot_commits(config: &Config) {
+    match take_snapshot(config) {
+        Ok(Some(file)) => log::info!("Wrote encrypted snapshot {}", file.display()),
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to write encrypted snapshot: {:#}", e),
+    }
+}
+
+/// Runs `check_command` on the new HEAD and records the outcome, if a check is
+/// configured.
+fn check_commits(config: &Config) {
+    match check_head(config) {
+        Ok(Some(CheckStatus::Pass)) => log::info!("Check passed"),
+        Ok(Some(CheckStatus::Fail)) => log::warn!("Check failed on the new commit"),
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to run check: {:#}", e),
+    }
+}
+
+/// Returns true if `change` is a notebook whose outputs are all that changed,
+/// with `strip_notebook_outputs`.
+fn only_outputs_saved(config: &Config, change: &GitChange) -> bool {
+    config.strip_notebook_outputs
+        && change.change_type == ChangeType::Modify
+        && is_notebook(&change.filename)
+        && only_outputs_changed(&config.repo_path, &change.filename)
+}
+
+/// Scans the repository for changes. With `isolated_index` the user's index
+/// isn't updated by commits, so paths that only differ from HEAD there are left
+/// out.
+fn scan_changes(config: &Config, include_ignored: bool) -> Result<Vec<GitChange>, GitChaiError> {
+    let changes = get_changed_files(&config.repo_path, include_ignored)?;
+    if config.isolated_index {
+        drop_index_only_changes(&config.repo_path, changes)
+    } else {
+        Ok(changes)
+    }
+}
+
+/// Re-scans the repository after a run and reports any paths that are still
+/// dirty, along with why they were left behind. Returns the dirty paths, or
+/// `None` if the re-scan failed.
+fn report_remaining_changes(config: &Config, skipped: &SkippedPaths) -> Option<Vec<String>> {
+    let remaining = match scan_changes(config, config.include_ignored) {
+        // Committed notebooks keep their outputs in the working tree
+        Ok(remaining) => remaining
+            .into_iter()
+            .filter(|change| !only_outputs_saved(config, change))
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            log::warn!("Failed to verify the working tree after committing: {}", e);
+            return None;
+        }
+    };
+
+    if remaining.is_empty() {
+        log::info!("Successfully committed all changes!");
+        return Some(Vec::new());
+    }
+
+    log::warn!(
+        "{} path(s) still have uncommitted changes:",
+        remaining.len()
+    );
+    for change in &remaining {
+        log::warn!(
+            "  {}: {} ({})",
+            change.change_type,
+            change.filename,
+            skipped.reason_for(&change.filename)
+        );
+    }
+    Some(
+        remaining
+            .into_iter()
+            .map(|change| change.filename)
+            .collect(),
+    )
+}
+
+/// Deals with build output directories that have shown up in several consecutive
+/// scans: with `auto_gitignore` their `.gitignore` entries are appended and
+/// committed on their own, otherwise the entries are suggested. Returns the number
+/// of commits created.
+fn ignore_recurring_artifacts(
+    config: &Config,
+    settings: &CommitSettings,
+    dry_run: bool,
+    recurring: &[String],
+    gitignore_changed: bool,
+) -> usize {
+    if recurring.is_empty() {
+        return 0;
+    }
+
+    let entries: Vec<String> = recurring.iter().map(|dir| ignore_entry(dir)).collect();
+    if !config.auto_gitignore || dry_run || gitignore_changed {
+        if config.auto_gitignore && gitignore_changed {
+            log::warn!(".gitignore has uncommitted changes; not adding build output to it");
+        }
+        log::warn!(
+            "Build output keeps appearing; consider adding to .gitignore: {} (or set auto_gitignore = true)",
+            entries.join(" ")
+        );
+        return 0;
+    }
+
+    if let Err(e) = append_to_gitignore(&config.repo_path, recurring) {
+        log::error!("Failed to update .gitignore: {}", e);
+        return 0;
+    }
+
+    let unit = CommitUnit {
+        paths: vec![".gitignore".to_string()],
+        removals: Vec::new(),
+        message: IGNORE_COMMIT_MESSAGE.to_string(),
+        amend: false,
+        force: false,
+    };
+    let result = stage_and_commit(
+        config,
+        settings,
+        &unit,
+        || stage_file(&config.repo_path, ".gitignore"),
+        || create_commit(&config.repo_path, settings, IGNORE_COMMIT_MESSAGE),
+    );
+    match result {
+        Ok(sha) => {
+            log::info!(
+                "Committed: {} ({}): {}",
+                IGNORE_COMMIT_MESSAGE,
+                short_sha(&sha),
+                entries.join(" ")
+            );
+            1
+        }
+        Err(CommitStepError::Stage(e)) | Err(CommitStepError::Commit(e)) => {
+            log::error!("Failed to commit .gitignore: {}", e);
+            0
+        }
+        Err(CommitStepError::IndexChanged) => 0,
+    }
+}
+
+/// Commits changed `.gitattributes` files ahead of the rest of the cycle, then
+/// restages the tracked files the new attributes normalize differently (line
+/// endings, filters) as a commit of their own, so that the cycle's other
+/// commits are all normalized the same way. Only unmodified files are
+/// renormalized; modified ones, including those filtered out of the cycle, are
+/// left to their own commits. Returns the number of commits made and the
+/// changes still to commit.
+fn commit_attributes_first(
+    config: &Config,
+    settings: &CommitSettings,
+    dry_run: bool,
+    changes: Vec<GitChange>,
+    skipped: &mut SkippedPaths,
+) -> (usize, Vec<GitChange>) {
+    let (attributes, changes): (Vec<_>, Vec<_>) = changes
+        .into_iter()
+        .partition(|change| is_attributes_file(&change.filename) && change.renamed_from.is_none());
+    if attributes.is_empty() {
+        return (0, changes);
+    }
+    if dry_run {
+        log::info!(
+            "DRY RUN: Would commit {} first and renormalize the files it affects",
+            attributes
+                .iter()
+                .map(|change| change.filename.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        return (0, attributes.into_iter().chain(changes).collect());
+    }
+
+    let mut commits = 0;
+    for change in &attributes {
+        let change_type = change.change_type.to_string();
+        let deletion = change.change_type == ChangeType::Delete;
+        let unit = CommitUnit {
+            paths: if deletion {
+                Vec::new()
+            } else {
+                vec![change.filename.clone()]
+            },
+            removals: if deletion {
+                vec![change.filename.clone()]
+            } else {
+                Vec::new()
+            },
+            message: file_commit_message(
+                &config.repo_path,
+                settings,
+                &change_type,
+                &change.filename,
+            ),
+            amend: false,
+            force: false,
+        };
+        let result = stage_and_commit(
+            config,
+            settings,
+            &unit,
+            || {
+                if deletion {
+                    stage_deletion(&config.repo_path, &change.filename)
+                } else {
+                    stage_file(&config.repo_path, &change.filename)
+                }
+            },
+            || create_commit_for_file(&config.repo_path, settings, &change.filename, &change_type),
+        );
+        match result {
+            Ok(sha) => {
+                log::info!("Committed: {} ({})", unit.message, short_sha(&sha));
+                commits += 1;
+            }
+            Err(CommitStepError::Stage(e)) | Err(CommitStepError::Commit(e)) => {
+                log::error!("Failed to commit {}: {}", change.filename, e);
+                skipped.fail(&change.filename, format!("failed to commit: {}", e));
+                return (commits, changes);
+            }
+            Err(CommitStepError::IndexChanged) => return (commits, changes),
+        }
+    }
+
+    let exclude: Vec<&str> = changes
+        .iter()
+        .flat_map(|change| std::iter::once(&change.filename).chain(&change.renamed_from))
+        .map(String::as_str)
+        .collect();
+    let renormalized = match renormalized_files(&config.repo_path, &exclude) {
+        Ok(renormalized) if renormalized.is_empty() => return (commits, changes),
+        Ok(renormalized) => renormalized,
+        Err(e) => {
+            log::warn!("Failed to renormalize files for .gitattributes: {}", e);
+            return (commits, changes);
+        }
+    };
+    let unit = CommitUnit {
+        paths: renormalized,
+        removals: Vec::new(),
+        message: RENORMALIZE_COMMIT_MESSAGE.to_string(),
+        amend: false,
+        force: false,
+    };
+    let result = stage_and_commit(
+        config,
+        settings,
+        &unit,
+        || stage_renormalized(&config.repo_path, &unit.paths),
+        || create_commit(&config.repo_path, settings, RENORMALIZE_COMMIT_MESSAGE),
+    );
+    match result {
+        Ok(sha) => {
+            log::info!(
+                "Committed: {} ({}): {} file(s)",
+                RENORMALIZE_COMMIT_MESSAGE,
+                short_sha(&sha),
+                unit.paths.len()
+            );
+            commits += 1;
+        }
+        Err(CommitStepError::Stage(e)) | Err(CommitStepError::Commit(e)) => {
+            log::error!("Failed to commit renormalized files: {}", e);
+        }
+        Err(CommitStepError::IndexChanged) => {}
+    }
+    (commits, changes)
+}
+
+/// One commit's worth of planned work.
+struct CommitUnit {
+    /// Paths to stage; entries ending in `/` are directories
+    paths: Vec<String>,
+    /// Paths to drop from the index before staging
+    removals: Vec<String>,
+    message: String,
+    amend: bool,
+    /// Stage the paths even if they are matched by `.gitignore`
+    force: bool,
+}
+
+enum CommitStepError {
+    Stage(GitChaiError),
+    /// The index changed between staging and committing; the cycle should be replanned
+    IndexChanged,
+    Commit(GitChaiError),
+}
+
+/// One commit a planned group would be committed as, for dry runs.
+struct GroupUnit {
+    /// A file, or a directory ending in `/`
+    path: String,
+    deletion: bool,
+    message: String,
+    /// The path is matched by `.gitignore`
+    force: bool,
+}
+
+/// The commits the real run would make for a planned group.
+fn group_units(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    group: &ChangeGroup,
+    is_ignored: impl Fn(&str) -> bool,
+) -> Vec<GroupUnit> {
+    match group.kind {
+        GroupKind::Directory(change_type) => vec![GroupUnit {
+            path: format!(
+                "{}/",
+                group.path.display().to_string().trim_end_matches('/')
+            ),
+            deletion: change_type == ChangeType::Delete,
+            message: directory_commit_message(
+                settings,
+                &change_type.to_string(),
+                &group.path,
+                group.files.len(),
+            ),
+            force: false,
+        }],
+        GroupKind::Individual => group
+            .files
+            .iter()
+            .map(|file| GroupUnit {
+                path: file.path.clone(),
+                deletion: file.change_type == ChangeType::Delete,
+                message: file_commit_message(
+                    repo_path,
+                    settings,
+                    &file.change_type.to_string(),
+                    &file.path,
+                ),
+                force: is_ignored(&file.path),
+            })
+            .collect(),
+    }
+}
+
+/// Stages a planned group into the simulation the way the real run would commit
+/// it, and returns how many of its commits would fail.
+fn simulate_group(
+    repo_path: &Path,
+    settings: &CommitSettings,
+    simulation: &mut StagingSimulation,
+    group: &ChangeGroup,
+    is_ignored: impl Fn(&str) -> bool,
+) -> usize {
+    let mut failures = 0;
+    for unit in group_units(repo_path, settings, group, is_ignored) {
+        let (paths, removals) = if unit.deletion {
+            (Vec::new(), vec![unit.path.clone()])
+        } else {
+            (vec![unit.path.clone()], Vec::new())
+        };
+        if let Err(e) = simulation.check(&paths, &removals, &unit.message, unit.force) {
+            log::warn!("DRY RUN: Committing {} would fail: {}", unit.path, e);
+            failures += 1;
+        }
+    }
+    failures
+}
+
+/// Stages and commits one unit, returning the new commit SHA. With
+/// `isolated_index` the unit is committed through a temporary index and the
+/// real index is left alone; otherwise it is staged with `stage`, verified
+/// against the plan, and committed with `commit`. A unit is skipped if the
+/// index already holds changes outside it.
+fn stage_and_commit(
+    config: &Config,
+    settings: &CommitSettings,
+    unit: &CommitUnit,
+    stage: impl FnOnce() -> Result<(), GitChaiError>,
+    commit: impl FnOnce() -> Result<String, GitChaiError>,
+) -> Result<String, CommitStepError> {
+    if config.isolated_index {
+        return commit_isolated(
+            &config.repo_path,
+            settings,
+            &unit.paths,
+            &unit.removals,
+            &unit.message,
+            unit.amend,
+            unit.force,
+        )
+        .map_err(CommitStepError::Commit);
+    }
+
+    // Changes staged by someone else would be swept into the commit, and
+    // aren't ours to unstage
+    let planned: Vec<String> = unit.paths.iter().chain(&unit.removals).cloned().collect();
+    let before = get_staged_files(&config.repo_path).map_err(CommitStepError::Stage)?;
+    if let Err(e) = verify_staged_files(&config.repo_path, &planned) {
+        log::warn!(
+            "Skipping commit, the index already has changes staged: {}",
+            e
+        );
+        return Err(CommitStepError::IndexChanged);
+    }
+
+    stage().map_err(CommitStepError::Stage)?;
+
+    if let Err(e) = verify_staged_files(&config.repo_path, &planned) {
+        log::warn!("Skipping commit: {}", e);
+        // Undo only our own staging, so the next cycle replans from the
+        // working tree
+        if let Err(e) = unstage_planned(&config.repo_path, &planned, &before) {
+            log::error!("Failed to unstage after an index change: {}", e);
+        }
+        return Err(CommitStepError::IndexChanged);
+    }
+
+    commit().map_err(CommitStepError::Commit)
+}
+
+/// The commit `notes_mode` makes of a cycle's changes, amending today's notes
+/// commit if HEAD is one that hasn't been pushed.
+fn notes_unit(config: &Config, notes: &[GitChange]) -> CommitUnit {
+    let (message, amend) = plan_notes_commit(&config.repo_path, notes);
+    let mut paths = Vec::new();
+    let mut removals = Vec::new();
+    for change in notes {
+        if let Some(from) = &change.renamed_from {
+            removals.push(from.clone());
+        }
+        if change.change_type == ChangeType::Delete {
+            removals.push(change.filename.clone());
+        } else {
+            paths.push(change.filename.clone());
+        }
+    }
+    CommitUnit {
+        paths,
+        removals,
+        message,
+        amend,
+        force: false,
+    }
+}
+
+/// Commits everything that was held back by the rate limiter as a single commit.
+fn process_held_batch(config: &Config, push: bool, push_state: &mut PushState) -> Result<usize> {
+    if let Some(reason) = pause_reason(config) {
+        log::info!("Paused: {}; will resume automatically", reason);
+        return Ok(0);
+    }
+    let _own_writes = OwnIndexWrites::track(config);
+
+    let changes = scan_changes(config, false)?;
+    if changes.is_empty() {
+        return Ok(0);
+    }
+
+    // The batch leaves out everything a regular cycle would; changes for other
+    // branches wait for the next regular cycle
+    let failed = FailedPaths::load(&config.repo_path);
+    let mut approvals = ApprovalQueue::load(&config.repo_path);
+    let (filtered, _) = filter_changes(
+        config,
+        changes,
+        FilterOptions {
+            dry_run: false,
+            interactive: false,
+            approvals: &mut approvals,
+            failed: &failed,
+        },
+    )?;
+    if let Err(e) = approvals.save() {
+        log::warn!("Failed to save the approval queue: {:#}", e);
+    }
+    let changes = filtered.changes;
+    if changes.is_empty() {
+        log::info!("No held-back changes can be committed");
+        return Ok(0);
+    }
+    let mut settings = commit_settings(config);
+
+    log::info!(
+        "Committing {} held-back files as a single batch",
+        changes.len()
+    );
+    let provenance = config.provenance.map(|mode| {
+        let plan: Vec<String> = changes
+            .iter()
+            .map(|change| change.filename.clone())
+            .collect();
+        Provenance::new(config, mode, &plan)
+    });
+    if let Some(provenance) = &provenance {
+        provenance.attach(&mut settings);
+    }
+
+    let unit = batch_unit(&changes);
+    let result = stage_and_commit(
+        config,
+        &settings,
+        &unit,
+        || {
+            for change in &changes {
+                if let Some(from) = &change.renamed_from {
+                    stage_rename(&config.repo_path, from, &change.filename)?;
+                }
+            }
+            let deletions: Vec<String> = changes
+                .iter()
+                .filter(|change| change.change_type == ChangeType::Delete)
+                .map(|change| change.filename.clone())
+                .collect();
+            if !deletions.is_empty() {
+                stage_deletions(&config.repo_path, &deletions)?;
+            }
+            if !unit.paths.is_empty() {
+                stage_files(&config.repo_path, &unit.paths)?;
+            }
+            Ok(())
+        },
+        || create_batch_commit(&config.repo_path, &settings, changes.len()),
+    );
+    let sha = match result {
+        Ok(sha) => sha,
+        Err(CommitStepError::IndexChanged) => {
+            log::info!("Index changed while committing the batch; will be replanned");
+            return Ok(0);
+        }
+        Err(CommitStepError::Stage(e)) | Err(CommitStepError::Commit(e)) => return Err(e.into()),
+    };
+    log::info!(
+        "Committed batch of {} files ({})",
+        changes.len(),
+        short_sha(&sha)
+    );
+    if let Some(provenance) = &provenance {
+        provenance.record(config, &[sha]);
+    }
+
+    mirror_commits(config);
+    snapshot_commits(config);
+    check_commits(config);
+
+    if push {
+        push_state.push(config);
+    }
+
+    Ok(1)
+}
+
+/// The batch commit of the changes held back by the commit rate limit.
+fn batch_unit(changes: &[GitChange]) -> CommitUnit {
+    let mut paths = Vec::new();
+    let mut removals = Vec::new();
+    for change in changes {
+        if let Some(from) = &change.renamed_from {
+            removals.push(from.clone());
+        }
+        if change.change_type == ChangeType::Delete {
+            removals.push(change.filename.clone());
+        } else {
+            paths.push(change.filename.clone());
+        }
+    }
+    CommitUnit {
+        paths,
+        removals,
+        message: batch_commit_message(changes.len()),
+        amend: false,
+        force: false,
+    }
+}
+
+/// Push-only mode: never commits, but pushes commits made by hand that `origin`
+/// doesn't have yet. Failed pushes are retried on the next cycle.
+fn push_only_cycle(config: &Config, dry_run: bool, push_state: &mut PushState) -> Result<()> {
+    if let Some(reason) = pause_reason(config) {
+        log::info!("Paused: {}; will resume automatically", reason);
+        return Ok(());
+    }
+
+    push_state.check_divergence(config);
+
+    let unpushed = count_unpushed_commits(&config.repo_path)?;
+    if unpushed == 0 {
+        log::info!("Nothing to push in {}", config.repo_path.display());
+        return Ok(());
+    }
+
+    if dry_run {
+        log::info!("Would push {} unpushed commit(s)", unpushed);
+    } else {
+        log::info!("Pushing {} unpushed commit(s)", unpushed);
+        push_state.push(config);
+    }
+    Ok(())
+}
+
+/// How this cycle's commits are written: whether their messages get
+/// `skip_ci_token` (which depends on the branch checked out), how they are
+/// dated, and how their subjects are templated, restyled and cleaned up.
+fn commit_settings(config: &Config) -> CommitSettings {
+    let branch = get_current_branch(&config.repo_path).ok().flatten();
+    let skip_ci = config.skip_ci
+        && (config.skip_ci_branches.is_empty()
+            || branch.as_ref().is_some_and(|branch| {
+                config
+                    .skip_ci_branches
+                    .iter()
+                    .any(|pattern| glob::Pattern::new(pattern).is_ok_and(|p| p.matches(branch)))
+            }));
+    let date = match config.commit_date {
+        CommitDateStrategy::Real => CommitDate::Now,
+        CommitDateStrategy::Batch => CommitDate::At(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        ),
+        CommitDateStrategy::Offset => CommitDate::Shifted(config.commit_date_offset_secs),
+    };
+
+    CommitSettings {
+        message_suffix: skip_ci.then(|| config.skip_ci_token.clone()),
+        trailer: config
+            .commit_trailer
+            .as_ref()
+            .map(|trailer| expand(trailer, environment_value)),
+        date,
+        author: config
+            .commit_author
+            .as_deref()
+            .and_then(|author| parse_author("commit_author", author).ok()),
+        style: config
+            .learn_message_style
+            .then(|| learned_style(&config.repo_path))
+            .flatten(),
+        subject_rules: SubjectRules {
+            case: config.subject_case,
+            strip_extensions: config.strip_extensions,
+            max_length: config.max_subject_length,
+            spelling: config
+                .spelling_wordlist
+                .as_ref()
+                .map(|path| load_wordlist(&config.repo_path.join(path)))
+                .unwrap_or_default(),
+        },
+        provenance: None,
+        template: Some(config.commit_message_template.clone()),
+        content_titles: config.content_titles,
+        strip_notebook_outputs: config.strip_notebook_outputs,
+        branch,
+        ai: AiSettings::from_config(config),
+    }
+}
+
+/// Runs `git fetch --prune` if `fetch_interval_secs` has passed since the last
+/// fetch. Failures are logged and retried at the next interval.
+fn fetch_if_due(config: &Config, last_fetch: &mut Option<std::time::Instant>) {
+    let Some(interval) = config.fetch_interval_secs else {
+        return;
+    };
+    let now = std::time::Instant::now();
+    if last_fetch.is_some_and(|last| now.duration_since(last).as_secs() < interval) {
+        return;
+    }
+    *last_fetch = Some(now);
+
+    match fetch_prune(&config.repo_path) {
+        Ok(()) => log::debug!("Fetched from the remote"),
+        Err(e) => log::warn!("Failed to fetch ({}): {}", e.class(), e),
+    }
+}
+
+/// Per-repository state that `process_changes` carries from one cycle to the next.
+#[derive(Default)]
+struct RepoState {
+    tracker: RepeatChangeTracker,
+    push_state: PushState,
+    noise: NoiseTracker,
+    /// When `fetch_interval_secs` last triggered a fetch
+    last_fetch: Option<std::time::Instant>,
+    /// Fingerprint of the last scan that left nothing to retry
+    last_scan: Option<u64>,
+    /// Errors that ended a headless cycle
+    errors: RepeatedMessage,
+    notifier: Notifier,
+    /// Scans in a row that found nothing new, for `scan_backoff_max_secs`
+    idle_scans: u32,
+}
+
+/// Why git-chai doesn't commit in a repository colocated with Jujutsu: jj
+/// snapshots the working copy into its own commits and rewrites the git index
+/// when it imports them, so committing through that index leaves half-states in
+/// both.
+pub const JJ_REFUSAL: &str = "it is colocated with jj, which already records every change to the working copy; \
+     use `jj commit`/`jj describe` there, or set jj_mode = \"git-only\" to let git-chai commit \
+     to git through a temporary index";
+
+/// The auto-commit engine for one repository, as the `git-chai` command runs
+/// it: each cycle scans the working tree, commits what changed according to
+/// `config` and pushes if `push_by_default` is set. What a cycle learns (the
+/// last scan, files being amended or held, failing pushes, queued
+/// notifications) carries over to the next one, so a session should be kept
+/// for as long as the repository is watched.
+pub struct ChaiSession {
+    pub(crate) config: Config,
+    /// Only report what would be committed
+    pub(crate) dry_run: Option<DryRunMode>,
+    /// Log each file as it is staged and committed
+    pub verbose: bool,
+    /// Ask on the terminal before committing paths under `require-approval`
+    pub interactive: bool,
+    /// Record each scan's plan in `.git/chai-observe.jsonl` (with `dry_run`)
+    pub observe: bool,
+    /// Commit even where the global allow/denylist excludes the repository
+    /// (`--i-know-what-im-doing`)
+    pub ignore_repo_access: bool,
+    /// Scans are started by filesystem events (`--watch`)
+    pub(crate) watching: bool,
+    /// Why the global allow/denylist excludes the repository
+    denied: Option<String>,
+    /// The repository is colocated with jj and `jj_mode = "refuse"`
+    jj_refused: bool,
+    rate_limiter: Option<CommitRateLimiter>,
+    state: RepoState,
+}
+
+impl ChaiSession {
+    /// A session for the repository `config` describes. The repository's
+    /// committed `.chai-policy.toml` is enforced over `config`, and a repository
+    /// colocated with jj is committed to through a temporary index if `jj_mode =
+    /// "git-only"` allows it. Otherwise cycles refuse to commit there, as they do
+    /// in a repository excluded by the allow/denylist given to
+    /// [`load_repo_access`](Self::load_repo_access).
+    pub fn new(mut config: Config) -> Result<Self> {
+        enforce_team_policy(&mut config)
+            .with_context(|| format!("Failed to load {}", TEAM_POLICY_FILE))?;
+
+        let mut jj_refused = false;
+        if is_jj_colocated(&config.repo_path) {
+            match config.jj_mode {
+                JjMode::GitOnly => {
+                    log::info!(
+                        "{} is colocated with jj: committing through a temporary index (jj_mode = \"git-only\")",
+                        config.repo_path.display()
+                    );
+                    config.isolated_index = true;
+                }
+                JjMode::Refuse => jj_refused = true,
+            }
+        }
+
+        let rate_limiter = config
+            .max_commits_per_hour
+            .map(|max| CommitRateLimiter::new(max, std::time::Instant::now()));
+        Ok(Self {
+            config,
+            dry_run: None,
+            verbose: false,
+            interactive: false,
+            observe: false,
+            ignore_repo_access: false,
+            watching: false,
+            denied: None,
+            jj_refused,
+            rate_limiter,
+            state: RepoState::default(),
+        })
+    }
+
+    /// Refuses to commit if the repository is excluded by the `allowed_repos`
+    /// and `denied_repos` of the config file at `path`, as the command does with
+    /// its global config file. A missing file excludes nothing.
+    pub fn load_repo_access(&mut self, path: &Path) -> Result<()> {
+        self.denied = RepoAccess::load_from(path)?.refusal_reason(&self.config.repo_path);
+        Ok(())
+    }
+
+    /// Only report what would be committed from now on, or commit again.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run.then_some(DryRunMode::Plan);
+    }
+
+    /// The configuration the session commits with, after `.chai-policy.toml`
+    /// and `jj_mode` were applied.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Why the session won't commit in its repository, if it won't outside a
+    /// dry run.
+    pub fn refusal(&self) -> Option<String> {
+        if self.jj_refused {
+            return Some(JJ_REFUSAL.to_string());
+        }
+        self.denied.clone().filter(|_| !self.ignore_repo_access)
+    }
+
+    /// Runs one cycle and returns the number of commits it created.
+    pub fn process_once(&mut self) -> Result<usize> {
+        // Pushing is all a push-only session does
+        self.cycle(self.config.push_by_default || self.config.push_only)
+    }
+
+    /// Runs cycles every `scan_interval_secs` until `shutdown` completes, as
+    /// `git-chai --headless` does until Ctrl+C. Runs on the caller's tokio
+    /// runtime, which needs its time driver; a cycle still running at shutdown
+    /// gets 30 seconds to finish, or an error is returned.
+    pub async fn run(self, shutdown: impl Future<Output = ()> + Send + 'static) -> Result<()> {
+        self.check_allowed()?;
+        run_sessions(vec![self], HeadlessOptions::default(), shutdown).await
+    }
+
+    /// Fails outside a dry run if the session refuses to commit.
+    fn check_allowed(&self) -> Result<()> {
+        if self.dry_run.is_none()
+            && let Some(reason) = self.refusal()
+        {
+            anyhow::bail!(
+                "Refusing to run in {}: {}",
+                self.config.repo_path.display(),
+                reason
+            );
+        }
+        Ok(())
+    }
+
+    /// Runs one cycle; `push` replaces `push_by_default` so the tray can switch
+    /// pushing off and on.
+    fn cycle(&mut self, push: bool) -> Result<usize> {
+        self.check_allowed()?;
+        let dry_run = self.dry_run.is_some();
+        if !dry_run {
+            fetch_if_due(&self.config, &mut self.state.last_fetch);
+        }
+        if self.config.push_only {
+            if !push {
+                log::debug!(
+                    "{}: pushing is switched off",
+                    self.config.repo_path.display()
+                );
+                return Ok(0);
+            }
+            push_only_cycle(&self.config, dry_run, &mut self.state.push_state)?;
+            return Ok(0);
+        }
+
+        let Some(mut limiter) = self.rate_limiter.take().filter(|_| !dry_run) else {
+            return process_changes(self, push, None);
+        };
+
+        let result = if limiter.roll_window(std::time::Instant::now()) {
+            process_held_batch(&self.config, push, &mut self.state.push_state)
+        } else if limiter.is_holding() {
+            log::debug!("Commit rate limit reached, holding changes");
+            Ok(0)
+        } else {
+            process_changes(self, push, Some(limiter.remaining()))
+        };
+        if let Ok(commits) = result {
+            limiter.record(commits);
+        }
+        self.rate_limiter = Some(limiter);
+        result
+    }
+
+    /// Runs one headless cycle under `control`, records it for `healthz` and
+    /// sends the notifications it raised. Returns how long to wait before the
+    /// next one.
+    pub(crate) fn headless_cycle(&mut self, control: &Control) -> Duration {
+        let repo_path = self.config.repo_path.clone();
+        if control.is_paused() {
+            log::debug!("{}: paused over D-Bus", repo_path.display());
+            // Paused on purpose, so the watcher is still alive for healthz
+            let _ = record_cycle(&self.config, None);
+            return self.config.scan_interval();
+        }
+
+        control.set_activity(&repo_path, Activity::Scanning);
+        let push = control.push_enabled(self.config.push_by_default);
+        let result = self.cycle(push);
+        if let Err(e) = record_cycle(&self.config, result.as_ref().err().map(|e| e.to_string())) {
+            log::debug!("Failed to record the cycle for healthz: {:#}", e);
+        }
+        match result {
+            Ok(_) => {
+                self.state.errors.resolve();
+                control.set_activity(&repo_path, Activity::Idle);
+            }
+            Err(e) => {
+                control.record_error(&repo_path, e.to_string());
+                let class = error_class(&e);
+                if !class.is_retryable() {
+                    self.state.notifier.notify(
+                        &self.config,
+                        NotifySeverity::Critical,
+                        "Headless cycle failed",
+                        format!(
+                            "Processing changes in {} failed ({}) and will keep failing until fixed:\n\n{}",
+                            repo_path.display(),
+                            class,
+                            e
+                        ),
+                    );
+                }
+                let (level, message) = if class.is_retryable() {
+                    (
+                        log::Level::Warn,
+                        format!(
+                            "Error processing changes in {} ({}), retrying next cycle: {}",
+                            repo_path.display(),
+                            class,
+                            e
+                        ),
+                    )
+                } else {
+                    (
+                        log::Level::Error,
+                        format!(
+                            "Error processing changes in {} ({}): {}",
+                            repo_path.display(),
+                            class,
+                            e
+                        ),
+                    )
+                };
+                self.state.errors.report(level, message);
+            }
+        }
+
+        if let Some(error) = self.state.push_state.take_persistent_failure() {
+            self.state.notifier.notify(
+                &self.config,
+                NotifySeverity::Critical,
+                "Pushes keep failing",
+                format!(
+                    "Pushing from {} has failed several times in a row; commits are piling up locally.\n\nLast error: {}",
+                    repo_path.display(),
+                    error
+                ),
+            );
+        }
+        self.state.notifier.flush(&self.config, false);
+
+        let delay = backoff(
+            self.config.scan_interval(),
+            self.config.scan_backoff_max(),
+            self.state.idle_scans,
+        );
+        if self.watching {
+            log::info!("Waiting for changes in {}...", repo_path.display());
+        } else {
+            log::info!(
+                "Waiting {} seconds before next scan of {}...",
+                delay.as_secs_f64(),
+                repo_path.display()
+            );
+        }
+        delay
+    }
+
+    /// Wraps up when headless mode stops: logs state that is lost, sends the
+    /// last notifications and merges the session branch if configured.
+    pub(crate) fn finish(&mut self) {
+        if self
+            .rate_limiter
+            .as_ref()
+            .is_some_and(CommitRateLimiter::is_holding)
+        {
+            log::warn!(
+                "{}: changes held back by max_commits_per_hour were left uncommitted",
+                self.config.repo_path.display()
+            );
+        }
+        self.state.notifier.flush(&self.config, true);
+        if let Some(into) = &self.config.session_merge_into
+            && let Err(e) = merge_session(&self.config, into, self.dry_run.is_some())
+        {
+            log::warn!("{:#}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CONFIG_FILE_NAME, PathPolicyRule};
+    use crate::test_repo::TempRepo;
+
+    #[test]
+    fn test_process_once_commits_new_changes() {
+        let repo = TempRepo::new("session");
+        let git = |args: &[&str]| repo.git(args);
+        repo.write("today.md", "# Today\n");
+        repo.write("README.md", "Notes\n");
+        git(&["add", "today.md", "README.md"]);
+        git(&["commit", "--quiet", "-m", "initial"]);
+        repo.write("today.md", "# Today\n\nTea.\n");
+
+        let config = || Config {
+            repo_path: repo.to_path_buf(),
+            ..Config::default()
+        };
+        let mut dry_run = ChaiSession::new(config()).unwrap();
+        dry_run.dry_run = Some(DryRunMode::Plan);
+        assert_eq!(dry_run.process_once().unwrap(), 0);
+        assert_eq!(git(&["log", "-1", "--format=%s"]), "initial");
+
+        let mut session = ChaiSession::new(config()).unwrap();
+        assert_eq!(session.process_once().unwrap(), 1);
+        assert_eq!(git(&["log", "-1", "--format=%s"]), "mod: today.md");
+        assert_eq!(session.process_once().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_config_without_push_by_default_does_not_push() {
+        let origin = TempRepo::dir("session-origin");
+        origin.git(&["init", "--quiet", "--bare", "--initial-branch=main"]);
+        let repo = TempRepo::new("session-no-push");
+        repo.write(CONFIG_FILE_NAME, "min_files_for_directory_commit = 2\n");
+        repo.write("today.md", "# Today\n");
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "--quiet", "-m", "initial"]);
+        repo.git(&["remote", "add", "origin", &origin.to_string_lossy()]);
+        repo.git(&["push", "--quiet", "origin", "main"]);
+        repo.write("today.md", "# Today\n\nTea.\n");
+
+        let config = Config::load(&repo, None).unwrap();
+        let mut session = ChaiSession::new(config).unwrap();
+        assert_eq!(session.process_once().unwrap(), 1);
+        assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "mod: today.md");
+        assert_eq!(origin.git(&["log", "-1", "--format=%s", "main"]), "initial");
+    }
+
+    #[test]
+    fn test_renormalizing_leaves_out_never_commit_paths() {
+        let repo = TempRepo::new("session-renormalize");
+        repo.git(&["config", "core.autocrlf", "false"]);
+        repo.write("secrets/key.txt", "OLDSECRET\r\n");
+        repo.write("windows.txt", "a\r\n");
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "--quiet", "-m", "initial"]);
+        repo.write("secrets/key.txt", "NEWSECRET\r\n");
+        repo.write(".gitattributes", "* text=auto\n");
+
+        let mut session = ChaiSession::new(Config {
+            repo_path: repo.to_path_buf(),
+            path_policies: vec![PathPolicyRule {
+                pattern: "secrets/**".to_string(),
+                policy: PathPolicy::NeverCommit,
+                branch: None,
+            }],
+            ..Config::default()
+        })
+        .unwrap();
+        assert_eq!(session.process_once().unwrap(), 2);
+
+        assert_eq!(
+            repo.git(&["log", "-1", "--format=%s"]),
+            RENORMALIZE_COMMIT_MESSAGE
+        );
+        assert_eq!(
+            repo.git(&["show", "HEAD:windows.txt"]),
+            "a",
+            "the committed CRLF file is renormalized"
+        );
+        assert_eq!(
+            repo.git(&["show", "HEAD:secrets/key.txt"]).trim_end(),
+            "OLDSECRET"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_uses_the_callers_runtime_until_shutdown() {
+        let repo = TempRepo::new("session-run");
+        repo.write("today.md", "# Today\n");
+        repo.write("README.md", "Notes\n");
+        repo.git(&["add", "today.md", "README.md"]);
+        repo.git(&["commit", "--quiet", "-m", "initial"]);
+        repo.write("today.md", "# Today\n\nTea.\n");
+        let session = ChaiSession::new(Config {
+            repo_path: repo.to_path_buf(),
+            push_by_default: false,
+            ..Config::default()
+        })
+        .unwrap();
+
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let run = tokio::spawn(session.run(async {
+            let _ = stopped.await;
+        }));
+        for _ in 0..200 {
+            if repo.git(&["log", "-1", "--format=%s"]) == "mod: today.md" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        stop.send(()).unwrap();
+
+        run.await.unwrap().unwrap();
+        assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "mod: today.md");
+    }
+
+    #[test]
+    fn test_sessions_refuse_repositories_their_access_list_excludes() {
+        let repo = TempRepo::new("session-access");
+        repo.write("today.md", "# Today\n");
+        repo.git(&["add", "today.md"]);
+        repo.git(&["commit", "--quiet", "-m", "initial"]);
+        repo.write("today.md", "# Today\n\nTea.\n");
+        let global = TempRepo::dir("session-access-global");
+        global.write(
+            "config.toml",
+            format!("denied_repos = [{:?}]\n", repo.to_string_lossy()),
+        );
+
+        let config = || Config {
+            repo_path: repo.to_path_buf(),
+            ..Config::default()
+        };
+        let mut session = ChaiSession::new(config()).unwrap();
+        assert!(session.refusal().is_none());
+        session
+            .load_repo_access(&global.join("config.toml"))
+            .unwrap();
+        assert!(session.refusal().is_some());
+        assert!(session.process_once().is_err());
+        assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "initial");
+
+        session
+            .load_repo_access(&global.join("missing.toml"))
+            .unwrap();
+        assert_eq!(session.process_once().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_sessions_apply_jj_mode() {
+        let repo = TempRepo::new("session-jj");
+        repo.write("today.md", "# Today\n");
+        repo.git(&["add", "today.md"]);
+        repo.git(&["commit", "--quiet", "-m", "initial"]);
+        repo.write("today.md", "# Today\n\nTea.\n");
+        std::fs::create_dir(repo.join(".jj")).unwrap();
+        let config = || Config {
+            repo_path: repo.to_path_buf(),
+            ..Config::default()
+        };
+
+        let mut session = ChaiSession::new(config()).unwrap();
+        assert!(session.refusal().is_some());
+        assert!(session.process_once().is_err());
+        session.dry_run = Some(DryRunMode::Plan);
+        assert_eq!(session.process_once().unwrap(), 0);
+        assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "initial");
+
+        let session = ChaiSession::new(Config {
+            jj_mode: JjMode::GitOnly,
+            ..config()
+        })
+        .unwrap();
+        assert!(session.refusal().is_none());
+        assert!(session.config().isolated_index);
+    }
+
+    #[test]
+    fn test_commit_is_skipped_when_something_else_is_staged_meanwhile() {
+        let repo = TempRepo::new("session-race");
+        repo.write("today.md", "# Today\n");
+        repo.git(&["add", "today.md"]);
+        repo.git(&["commit", "--quiet", "-m", "initial"]);
+        repo.write("today.md", "# Today\n\nTea.\n");
+        repo.write("secret.env", "TOKEN=1\n");
+
+        let config = Config {
+            repo_path: repo.to_path_buf(),
+            ..Config::default()
+        };
+        let unit = CommitUnit {
+            paths: vec!["today.md".to_string()],
+            removals: Vec::new(),
+            message: "mod: today.md".to_string(),
+            amend: false,
+            force: false,
+        };
+        let result = stage_and_commit(
+            &config,
+            &CommitSettings::default(),
+            &unit,
+            || {
+                stage_file(&repo, "today.md")?;
+                // Another process stages a file between our add and commit
+                repo.git(&["add", "secret.env"]);
+                Ok(())
+            },
+            || create_commit(&repo, &CommitSettings::default(), &unit.message),
+        );
+
+        assert!(matches!(result, Err(CommitStepError::IndexChanged)));
+        assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "initial");
+        // Only our own staging is undone
+        assert_eq!(get_staged_files(&repo).unwrap(), ["secret.env"]);
+    }
+
+    #[test]
+    fn test_changes_staged_by_the_user_are_left_alone() {
+        let repo = TempRepo::new("session-user-staged");
+        repo.write("d1/a.txt", "a\n");
+        repo.write("README.md", "Notes\n");
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "--quiet", "-m", "initial"]);
+        repo.write("d1/a.txt", "a\nb\n");
+        repo.write("secrets/k", "TOKEN=1\n");
+        repo.git(&["add", "secrets/k"]);
+
+        let mut session = ChaiSession::new(Config {
+            repo_path: repo.to_path_buf(),
+            path_policies: vec![PathPolicyRule {
+                pattern: "secrets/**".to_string(),
+                policy: PathPolicy::NeverCommit,
+                branch: None,
+            }],
+            ..Config::default()
+        })
+        .unwrap();
+        assert_eq!(session.process_once().unwrap(), 0);
+
+        assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "initial");
+        assert_eq!(get_staged_files(&repo).unwrap(), ["secrets/k"]);
+    }
+
+    #[test]
+    fn test_held_batch_leaves_out_never_commit_paths() {
+        let repo = TempRepo::new("session-batch");
+        repo.write("README.md", "Notes\n");
+        repo.git(&["add", "README.md"]);
+        repo.git(&["commit", "--quiet", "-m", "initial"]);
+        repo.write("today.md", "# Today\n");
+        repo.write("README.md", "Notes\n\nMore.\n");
+        repo.write("secret.env", "TOKEN=1\n");
+
+        let config = Config {
+            repo_path: repo.to_path_buf(),
+            path_policies: vec![crate::config::PathPolicyRule {
+                pattern: "*.env".to_string(),
+                policy: PathPolicy::NeverCommit,
+                branch: None,
+            }],
+            ..Config::default()
+        };
+        let commits = process_held_batch(&config, false, &mut PushState::default()).unwrap();
+
+        assert_eq!(commits, 1);
+        assert_eq!(
+            repo.git(&["show", "--name-only", "--format=%s", "HEAD"]),
+            "batch: 2 files\n\nREADME.md\ntoday.md"
+        );
+        assert_eq!(repo.git(&["status", "--porcelain"]), "?? secret.env");
+    }
+}
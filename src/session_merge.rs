@@ -0,0 +1,81 @@
+use anyhow::{Context, Result, bail};
+
+use crate::config::Config;
+use crate::git::{
+    LogCommit, get_commits_in_range, get_current_branch, merge_branch_into, short_sha,
+};
+use crate::summary::describe_by_package;
+
+/// Merges the checked-out autosave branch into `into` as one merge commit whose
+/// message summarizes the session. The fine-grained commits stay on the
+/// autosave branch; `into` only gains the merge. The working tree is left alone.
+pub fn merge_session(config: &Config, into: &str, dry_run: bool) -> Result<()> {
+    let repo = &config.repo_path;
+    let Some(branch) = get_current_branch(repo)? else {
+        bail!("HEAD is detached; check out the autosave branch to merge");
+    };
+    if branch == into {
+        bail!("The autosave branch is {} itself; nothing to merge", into);
+    }
+
+    let commits = get_commits_in_range(repo, &format!("{}..{}", into, branch))?;
+    if commits.is_empty() {
+        log::info!("{} has no commits that {} doesn't have", branch, into);
+        return Ok(());
+    }
+
+    let message = merge_message(&branch, &commits);
+    if dry_run {
+        log::info!(
+            "DRY RUN: Would merge {} into {}:\n{}",
+            branch,
+            into,
+            message
+        );
+        return Ok(());
+    }
+
+    let sha = merge_branch_into(repo, &branch, into, &message)
+        .with_context(|| format!("Failed to merge {} into {}", branch, into))?;
+    log::info!(
+        "Merged {} commit(s) from {} into {} ({})",
+        commits.len(),
+        branch,
+        into,
+        short_sha(&sha)
+    );
+    Ok(())
+}
+
+fn merge_message(branch: &str, commits: &[LogCommit]) -> String {
+    format!(
+        "Merge autosave session '{}' ({} commits)\n{}",
+        branch,
+        commits.len(),
+        describe_by_package(commits)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_message_summarizes_session() {
+        let commit = |subject: &str, file: &str| LogCommit {
+            sha: String::new(),
+            subject: subject.to_string(),
+            chai_subject: None,
+            files: vec![file.to_string()],
+        };
+        let commits = vec![
+            commit("mod: b.rs", "src/b.rs"),
+            commit("add: a.rs", "src/a.rs"),
+        ];
+
+        assert_eq!(
+            merge_message("wip", &commits),
+            "Merge autosave session 'wip' (2 commits)\n\nsrc/ - 2 commit(s)\n  - add: a.rs\n  - mod: b.rs\n"
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+/// Records why paths were left uncommitted during a run, so that anything still
+/// dirty afterwards can be reported with a reason.
+#[derive(Debug, Default)]
+pub struct SkippedPaths {
+    reasons: HashMap<String, String>,
+    /// Reason for everything not reached when processing stopped early
+    stopped: Option<String>,
+    /// Paths that failed to stage or commit, with the error
+    failed: HashMap<String, String>,
+}
+
+impl SkippedPaths {
+    /// Records a reason for a path; paths ending in `/` cover a whole directory.
+    pub fn skip(&mut self, path: &str, reason: impl Into<String>) {
+        self.reasons.insert(path.to_string(), reason.into());
+    }
+
+    /// Records a path that failed to stage or commit.
+    pub fn fail(&mut self, path: &str, reason: impl Into<String>) {
+        let reason = reason.into();
+        self.failed.insert(path.to_string(), reason.clone());
+        self.skip(path, reason);
+    }
+
+    /// Every path recorded with a reason, including failures.
+    pub fn reasons(&self) -> &HashMap<String, String> {
+        &self.reasons
+    }
+
+    pub fn failures(&self) -> &HashMap<String, String> {
+        &self.failed
+    }
+
+    /// Marks the run as stopped early; unrecorded paths are reported with this reason.
+    pub fn stop(&mut self, reason: impl Into<String>) {
+        self.stopped.get_or_insert_with(|| reason.into());
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.is_some()
+    }
+
+    pub fn reason_for(&self, path: &str) -> &str {
+        if let Some(reason) = self.reasons.get(path) {
+            return reason;
+        }
+
+        self.reasons
+            .iter()
+            .filter(|(skipped, _)| skipped.ends_with('/') && path.starts_with(skipped.as_str()))
+            .max_by_key(|(skipped, _)| skipped.len())
+            .map(|(_, reason)| reason.as_str())
+            .or(self.stopped.as_deref())
+            .unwrap_or("not planned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reason_for_prefers_exact_then_directory() {
+        let mut skipped = SkippedPaths::default();
+        skipped.skip("src/", "stage failed");
+        skipped.skip("src/main.rs", "commit failed");
+
+        assert_eq!(skipped.reason_for("src/main.rs"), "commit failed");
+        assert_eq!(skipped.reason_for("src/lib.rs"), "stage failed");
+        assert_eq!(skipped.reason_for("README.md"), "not planned");
+
+        skipped.stop("commit limit reached");
+        assert_eq!(skipped.reason_for("README.md"), "commit limit reached");
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{Config, SnapshotEncryption};
+use crate::git::{get_head_sha, last_snapshot, record_snapshot, short_sha, snapshot_patch};
+
+/// Writes an encrypted patch of everything committed since the previous snapshot
+/// to `snapshot_dir` (and copies it to `snapshot_rclone_remote`), for users who
+/// can't push work in progress anywhere. Returns the snapshot file, or `None` if
+/// snapshots are not configured or nothing changed.
+pub fn take_snapshot(config: &Config) -> Result<Option<PathBuf>> {
+    let (Some(dir), Some(recipient)) = (&config.snapshot_dir, &config.snapshot_recipient) else {
+        return Ok(None);
+    };
+    let dir = config.repo_path.join(dir);
+
+    let head = get_head_sha(&config.repo_path)?;
+    let base = last_snapshot(&config.repo_path);
+    if base.as_deref() == Some(head.as_str()) {
+        return Ok(None);
+    }
+
+    let patch = snapshot_patch(&config.repo_path, base.as_deref(), &head)?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let file = dir.join(format!(
+        "{}-{}-{}.patch.{}",
+        timestamp,
+        base.as_deref().map_or("root", short_sha),
+        short_sha(&head),
+        config.snapshot_encryption.program()
+    ));
+
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create snapshot directory {}", dir.display()))?;
+    encrypt(config.snapshot_encryption, recipient, &patch, &file)?;
+
+    if let Some(remote) = &config.snapshot_rclone_remote {
+        upload(&file, remote)?;
+    }
+
+    record_snapshot(&config.repo_path, &head)?;
+    Ok(Some(file))
+}
+
+fn encrypt(
+    encryption: SnapshotEncryption,
+    recipient: &str,
+    data: &[u8],
+    output: &Path,
+) -> Result<()> {
+    let mut command = match encryption {
+        SnapshotEncryption::Age => {
+            let mut command = Command::new("age");
+            command.arg("--encrypt").arg("--recipient").arg(recipient);
+            command
+        }
+        SnapshotEncryption::Gpg => {
+            let mut command = Command::new("gpg");
+            command
+                .arg("--batch")
+                .arg("--yes")
+                .arg("--encrypt")
+                .arg("--recipient")
+                .arg(recipient);
+            command
+        }
+    };
+    command
+        .arg("--output")
+        .arg(output)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+
+    let program = encryption.program();
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(data)
+            .with_context(|| format!("Failed to write to {}", program))?;
+    }
+
+    let result = child.wait_with_output()?;
+    if !result.status.success() {
+        // Never leave a partial or unencrypted file behind
+        let _ = std::fs::remove_file(output);
+        bail!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn upload(file: &Path, remote: &str) -> Result<()> {
+    let output = Command::new("rclone")
+        .arg("copy")
+        .arg(file)
+        .arg(remote)
+        .output()
+        .context("Failed to run rclone")?;
+
+    if !output.status.success() {
+        bail!(
+            "rclone copy to {} failed: {}",
+            remote,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use anyhow::{Context, Result, bail};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::git::{LogCommit, RENORMALIZE_COMMIT_MESSAGE, get_commits_since};
+use crate::gitignore::IGNORE_COMMIT_MESSAGE;
+use crate::http::HttpClient;
+
+/// Subject prefixes of the commits git-chai creates with the default template.
+const CHAI_PREFIXES: &[&str] = &[
+    "add: ", "mod: ", "del: ", "rename: ", "copy: ", "link: ", "batch: ", "notes: ",
+];
+
+/// Runs `git-chai summary`: groups the commits since `since` by top-level
+/// directory and prints the result, or posts it to `summary_webhook`.
+pub fn run_summary(config: &Config, since: &str, all_commits: bool, post: bool) -> Result<()> {
+    let commits = commits_since(&config.repo_path, since, all_commits)?;
+    let summary = format_summary(since, &commits);
+
+    if !post {
+        print!("{}", summary);
+        return Ok(());
+    }
+
+    let Some(webhook) = &config.summary_webhook else {
+        bail!("--post needs summary_webhook to be set");
+    };
+    post_to_webhook(webhook, &summary)?;
+    log::info!("Posted summary of {} commit(s)", commits.len());
+    Ok(())
+}
+
+/// Summarizes git-chai's commits since `started` (seconds since the epoch),
+/// for the tray icon's session summary.
+pub fn session_summary(repo_path: &Path, started: u64) -> Result<String> {
+    let commits = commits_since(repo_path, &started.to_string(), false)?;
+    Ok(format_summary("the session started", &commits))
+}
+
+fn commits_since(repo_path: &Path, since: &str, all_commits: bool) -> Result<Vec<LogCommit>> {
+    Ok(get_commits_since(repo_path, since)?
+        .into_iter()
+        .filter(|commit| all_commits || is_chai_commit(commit.generated_subject()))
+        .collect())
+}
+
+/// Whether a subject looks like one git-chai wrote with the default template.
+pub fn is_chai_commit(subject: &str) -> bool {
+    subject == IGNORE_COMMIT_MESSAGE
+        || subject == RENORMALIZE_COMMIT_MESSAGE
+        || CHAI_PREFIXES.iter().any(|p| subject.starts_with(p))
+}
+
+/// Top-level directory of a path, or `.` for files in the repository root.
+fn package_of(path: &str) -> &str {
+    path.split_once('/').map_or(".", |(dir, _)| dir)
+}
+
+fn format_summary(since: &str, commits: &[LogCommit]) -> String {
+    if commits.is_empty() {
+        return format!("No commits since {}\n", since);
+    }
+
+    format!(
+        "Work since {} ({} commits)\n{}",
+        since,
+        commits.len(),
+        describe_by_package(commits)
+    )
+}
+
+/// Lists the subjects of `commits` (oldest first) under each top-level
+/// directory they touched.
+pub fn describe_by_package(commits: &[LogCommit]) -> String {
+    // Commits touching several packages are listed under each of them
+    let mut packages: BTreeMap<&str, Vec<&LogCommit>> = BTreeMap::new();
+    for commit in commits {
+        let mut touched: Vec<&str> = commit.files.iter().map(|f| package_of(f)).collect();
+        touched.sort_unstable();
+        touched.dedup();
+        if touched.is_empty() {
+            touched.push(".");
+        }
+        for package in touched {
+            packages.entry(package).or_default().push(commit);
+        }
+    }
+
+    let mut summary = String::new();
+    for (package, commits) in &packages {
+        let name = if *package == "." {
+            "(root)".to_string()
+        } else {
+            format!("{}/", package)
+        };
+        summary.push_str(&format!("\n{} - {} commit(s)\n", name, commits.len()));
+        for commit in commits.iter().rev() {
+            summary.push_str(&format!("  - {}\n", commit.subject));
+        }
+    }
+    summary
+}
+
+/// Posts `text` as `{"text": ...}`, the shape Slack and Mattermost incoming
+/// webhooks expect.
+fn post_to_webhook(url: &str, text: &str) -> Result<()> {
+    HttpClient::default()
+        .send_json("POST", url, &[], &serde_json::json!({ "text": text }))
+        .context("Posting to the webhook failed")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(subject: &str, files: &[&str]) -> LogCommit {
+        LogCommit {
+            sha: String::new(),
+            subject: subject.to_string(),
+            chai_subject: None,
+            files: files.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_format_summary_groups_by_package() {
+        let commits = vec![
+            commit("mod: b.md", &["notes/b.md"]),
+            commit("add: a.md", &["notes/a.md"]),
+            commit("mod: README.md", &["README.md", "notes/index.md"]),
+        ];
+
+        assert_eq!(
+            format_summary("yesterday", &commits),
+            "Work since yesterday (3 commits)\n\
+             \n(root) - 1 commit(s)\n  - mod: README.md\n\
+             \nnotes/ - 3 commit(s)\n  - mod: README.md\n  - add: a.md\n  - mod: b.md\n"
+        );
+        assert!(is_chai_commit("batch: 3 files"));
+        assert!(!is_chai_commit("Fix typo"));
+    }
+}
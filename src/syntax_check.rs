@@ -0,0 +1,214 @@
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config::SyntaxCheck;
+use crate::git::status::GitChange;
+use crate::types::ChangeType;
+
+/// Compiles a Python file without writing `__pycache__` into the working tree.
+const PYTHON_CHECK: &str = "import sys
+try:
+    compile(open(sys.argv[1], 'rb').read(), sys.argv[1], 'exec')
+except SyntaxError as e:
+    sys.exit(f'line {e.lineno}: {e.msg}')";
+
+/// Runs the `syntax_check` languages over the files of each change during one
+/// scan. `cargo check` runs at most once per package per scan, however many of
+/// its files changed.
+pub struct SyntaxChecker<'a> {
+    repo_path: &'a Path,
+    languages: &'a [SyntaxCheck],
+    /// Outcome of `cargo check` per package directory
+    cargo: HashMap<PathBuf, Option<String>>,
+    /// Tools that are not installed, warned about once
+    missing: HashSet<&'static str>,
+}
+
+impl<'a> SyntaxChecker<'a> {
+    pub fn new(repo_path: &'a Path, languages: &'a [SyntaxCheck]) -> Self {
+        SyntaxChecker {
+            repo_path,
+            languages,
+            cargo: HashMap::new(),
+            missing: HashSet::new(),
+        }
+    }
+
+    /// Returns why the change fails its syntax check, or `None` if it passes or
+    /// has no files in a checked language. An untracked directory is checked
+    /// file by file.
+    pub fn check(&mut self, change: &GitChange) -> Option<String> {
+        if self.languages.is_empty() || change.change_type == ChangeType::Delete {
+            return None;
+        }
+
+        let path = self.repo_path.join(&change.filename);
+        let mut files = Vec::new();
+        if change.filename.ends_with('/') {
+            collect_files(&path, &mut files);
+            files.sort();
+        } else {
+            files.push(path);
+        }
+
+        files.iter().find_map(|file| {
+            let language = language_for(file)?;
+            if !self.languages.contains(&language) {
+                return None;
+            }
+            let error = self.check_file(language, file)?;
+            Some(if change.filename.ends_with('/') {
+                let relative = file.strip_prefix(self.repo_path).unwrap_or(file);
+                format!(
+                    "{} syntax check failed on {}: {}",
+                    language,
+                    relative.display(),
+                    error
+                )
+            } else {
+                format!("{} syntax check failed: {}", language, error)
+            })
+        })
+    }
+
+    fn check_file(&mut self, language: SyntaxCheck, file: &Path) -> Option<String> {
+        match language {
+            SyntaxCheck::Python => {
+                let mut command = Command::new("python3");
+                command.arg("-c").arg(PYTHON_CHECK).arg(file);
+                self.run("python3", command, |stderr| {
+                    stderr.lines().last().map(str::to_string)
+                })
+            }
+            SyntaxCheck::JavaScript => {
+                let mut command = Command::new("node");
+                command.arg("--check").arg(file);
+                self.run("node", command, |stderr| {
+                    let line = stderr
+                        .lines()
+                        .next()
+                        .and_then(|location| location.rsplit(':').next())
+                        .unwrap_or("?");
+                    stderr
+                        .lines()
+                        .find(|text| text.contains("Error:"))
+                        .map(|error| format!("line {}: {}", line, error.trim()))
+                })
+            }
+            SyntaxCheck::Rust => {
+                let package = find_package(self.repo_path, file)?;
+                if let Some(outcome) = self.cargo.get(&package) {
+                    return outcome.clone();
+                }
+                let mut command = Command::new("cargo");
+                command
+                    .args(["check", "--quiet", "--message-format", "short"])
+                    .current_dir(&package);
+                let outcome = self.run("cargo", command, |stderr| {
+                    stderr
+                        .lines()
+                        .find(|text| text.contains("error"))
+                        .map(str::to_string)
+                });
+                self.cargo.insert(package, outcome.clone());
+                outcome
+            }
+        }
+    }
+
+    /// Runs a check, returning the error picked out of its stderr if it fails.
+    /// A tool that is not installed passes, with a warning.
+    fn run(
+        &mut self,
+        tool: &'static str,
+        mut command: Command,
+        error: impl Fn(&str) -> Option<String>,
+    ) -> Option<String> {
+        let output = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => None,
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                Some(error(&stderr).unwrap_or_else(|| "syntax error".to_string()))
+            }
+            Err(e) => {
+                if self.missing.insert(tool) {
+                    if e.kind() == ErrorKind::NotFound {
+                        log::warn!("{} is not installed; skipping its syntax_check", tool);
+                    } else {
+                        log::warn!("Failed to run {} for syntax_check: {}", tool, e);
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+fn language_for(file: &Path) -> Option<SyntaxCheck> {
+    match file.extension()?.to_str()? {
+        "py" => Some(SyntaxCheck::Python),
+        "js" | "mjs" | "cjs" => Some(SyntaxCheck::JavaScript),
+        "rs" => Some(SyntaxCheck::Rust),
+        _ => None,
+    }
+}
+
+/// Returns the directory of the nearest `Cargo.toml` above `file`, inside the
+/// repository.
+fn find_package(repo_path: &Path, file: &Path) -> Option<PathBuf> {
+    file.ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(repo_path))
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => collect_files(&path, files),
+            Ok(kind) if kind.is_file() => files.push(path),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_for_and_find_package() {
+        assert_eq!(
+            language_for(Path::new("tools/gen.py")),
+            Some(SyntaxCheck::Python)
+        );
+        assert_eq!(
+            language_for(Path::new("web/app.mjs")),
+            Some(SyntaxCheck::JavaScript)
+        );
+        assert_eq!(language_for(Path::new("README.md")), None);
+        assert_eq!(language_for(Path::new("Makefile")), None);
+
+        let repo = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(
+            find_package(repo, &repo.join("src/main.rs")),
+            Some(repo.to_path_buf())
+        );
+        assert_eq!(
+            find_package(&repo.join("src"), &repo.join("src/main.rs")),
+            None
+        );
+    }
+}
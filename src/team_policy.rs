@@ -0,0 +1,268 @@
+use glob::Pattern;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::config::{Config, SyntaxCheck};
+use crate::error::GitChaiError;
+use crate::git::git_command;
+use crate::git::short_sha;
+
+/// Policy the repository's owners commit to constrain every contributor's
+/// git-chai, whatever their local configuration says.
+pub const TEAM_POLICY_FILE: &str = ".chai-policy.toml";
+
+/// A check the policy makes mandatory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Gate {
+    /// Never commit files with conflict markers
+    #[serde(rename = "conflict-markers")]
+    ConflictMarkers,
+    /// `encoding_guard`
+    Encoding,
+    /// Syntax checks, named like in `syntax_check`
+    Python,
+    JavaScript,
+    Rust,
+}
+
+/// `.chai-policy.toml` as written.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyFile {
+    protected_branches: Vec<String>,
+    forbidden_paths: Vec<String>,
+    required_gates: Vec<Gate>,
+}
+
+/// The committed `.chai-policy.toml`. Uncommitted edits to the file don't
+/// count, and once a version is signed, later unsigned changes are ignored, so
+/// a contributor can't loosen the policy on their own.
+#[derive(Debug, Clone)]
+pub struct TeamPolicy {
+    /// Branches git-chai never commits or pushes on
+    protected_branches: Vec<Pattern>,
+    /// Paths git-chai never commits
+    forbidden_paths: Vec<Pattern>,
+    required_gates: Vec<Gate>,
+    /// Commit the policy was read from
+    pub commit: String,
+    /// Whether that commit has a good signature
+    pub signed: bool,
+}
+
+impl TeamPolicy {
+    /// Reads the policy from the newest signed commit that touched it, or from
+    /// HEAD if it was never signed. `None` if the repository has no committed
+    /// policy.
+    pub fn load(repo_path: &Path) -> Result<Option<Self>, GitChaiError> {
+        let mut command = git_command(repo_path);
+        command
+            .arg("log")
+            .arg("--format=%H %G?")
+            .arg("HEAD")
+            .arg("--")
+            .arg(TEAM_POLICY_FILE);
+        let output = command.output().map_err(GitChaiError::IoError)?;
+        if !output.status.success() {
+            // No commits yet
+            return Ok(None);
+        }
+        let history = String::from_utf8_lossy(&output.stdout);
+        let versions: Vec<(&str, bool)> = history
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(sha, signature)| (sha, signature == "G"))
+            .collect();
+        let Some((commit, signed)) = pick_version(&versions) else {
+            if repo_path.join(TEAM_POLICY_FILE).is_file() {
+                log::warn!(
+                    "{} is not committed; it takes effect once it is",
+                    TEAM_POLICY_FILE
+                );
+            }
+            return Ok(None);
+        };
+        if signed && versions[0].0 != commit {
+            log::warn!(
+                "Ignoring unsigned changes to {} after {}",
+                TEAM_POLICY_FILE,
+                short_sha(commit)
+            );
+        }
+
+        let mut command = git_command(repo_path);
+        command
+            .arg("show")
+            .arg(format!("{}:{}", commit, TEAM_POLICY_FILE));
+        let output = command.output().map_err(GitChaiError::IoError)?;
+        if !output.status.success() {
+            // Deleted in that commit
+            return Ok(None);
+        }
+        Self::parse(&String::from_utf8_lossy(&output.stdout), commit, signed).map(Some)
+    }
+
+    fn parse(text: &str, commit: &str, signed: bool) -> Result<Self, GitChaiError> {
+        let file: PolicyFile = toml::from_str(text).map_err(|e| {
+            GitChaiError::ConfigError(format!(
+                "Invalid {} in {}: {}",
+                TEAM_POLICY_FILE,
+                short_sha(commit),
+                e
+            ))
+        })?;
+        let patterns = |globs: Vec<String>| -> Result<Vec<Pattern>, GitChaiError> {
+            globs
+                .iter()
+                .map(|glob| {
+                    Pattern::new(glob).map_err(|e| {
+                        GitChaiError::ConfigError(format!(
+                            "Invalid pattern '{}' in {}: {}",
+                            glob, TEAM_POLICY_FILE, e
+                        ))
+                    })
+                })
+                .collect()
+        };
+
+        Ok(TeamPolicy {
+            protected_branches: patterns(file.protected_branches)?,
+            forbidden_paths: patterns(file.forbidden_paths)?,
+            required_gates: file.required_gates,
+            commit: commit.to_string(),
+            signed,
+        })
+    }
+
+    /// Turns on the required gates in `config`, overriding the local settings,
+    /// and attaches the policy to it.
+    pub fn enforce(self, config: &mut Config) {
+        for gate in &self.required_gates {
+            let language = match gate {
+                Gate::ConflictMarkers => {
+                    config.allow_conflict_markers = false;
+                    continue;
+                }
+                Gate::Encoding => {
+                    config.encoding_guard = true;
+                    continue;
+                }
+                Gate::Python => SyntaxCheck::Python,
+                Gate::JavaScript => SyntaxCheck::JavaScript,
+                Gate::Rust => SyntaxCheck::Rust,
+            };
+            if !config.syntax_check.contains(&language) {
+                config.syntax_check.push(language);
+            }
+        }
+        log::info!(
+            "Applying {} from {}{}",
+            TEAM_POLICY_FILE,
+            short_sha(&self.commit),
+            if self.signed { " (signed)" } else { "" }
+        );
+        config.team_policy = Some(self);
+    }
+
+    pub fn protects(&self, branch: &str) -> bool {
+        self.protected_branches
+            .iter()
+            .any(|pattern| pattern.matches(branch))
+    }
+
+    /// Returns true if `path`, or the untracked directory it names, is
+    /// forbidden.
+    pub fn forbids(&self, path: &str) -> bool {
+        let trimmed = path.trim_end_matches('/');
+        self.forbidden_paths.iter().any(|pattern| {
+            pattern.matches(path)
+                || pattern.matches(trimmed)
+                || (path.ends_with('/') && pattern.as_str().starts_with(path))
+        })
+    }
+
+    /// One-line description for `git-chai doctor`.
+    pub fn describe(&self) -> String {
+        format!(
+            "{} from {} ({}): {} protected branch pattern(s), {} forbidden path pattern(s), {} required gate(s)",
+            TEAM_POLICY_FILE,
+            short_sha(&self.commit),
+            if self.signed { "signed" } else { "not signed" },
+            self.protected_branches.len(),
+            self.forbidden_paths.len(),
+            self.required_gates.len()
+        )
+    }
+}
+
+/// Loads the repository's committed policy and enforces it over `config`, which
+/// has to happen after every other source of settings so none can loosen it.
+/// Does nothing if `config` already carries the policy.
+pub fn enforce_team_policy(config: &mut Config) -> Result<(), GitChaiError> {
+    if config.team_policy.is_some() {
+        return Ok(());
+    }
+    if let Some(policy) = TeamPolicy::load(&config.repo_path)? {
+        policy.enforce(config);
+    }
+    Ok(())
+}
+
+/// Picks the version of the policy to apply from its history (newest first,
+/// with whether each commit is signed): the newest signed one, or the newest
+/// if none is.
+fn pick_version<'a>(versions: &[(&'a str, bool)]) -> Option<(&'a str, bool)> {
+    versions
+        .iter()
+        .find(|(_, signed)| *signed)
+        .or(versions.first())
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_team_policy() {
+        assert_eq!(
+            pick_version(&[("c3", false), ("c2", true), ("c1", false)]),
+            Some(("c2", true))
+        );
+        assert_eq!(
+            pick_version(&[("c2", false), ("c1", false)]),
+            Some(("c2", false))
+        );
+        assert_eq!(pick_version(&[]), None);
+
+        let policy = TeamPolicy::parse(
+            r#"
+protected_branches = ["main", "release/*"]
+forbidden_paths = ["secrets/**", "*.pem"]
+required_gates = ["conflict-markers", "encoding", "python"]
+"#,
+            "c2",
+            true,
+        )
+        .unwrap();
+        assert!(policy.protects("release/1.2"));
+        assert!(!policy.protects("feature/main"));
+        assert!(policy.forbids("secrets/prod.env"));
+        assert!(policy.forbids("secrets/"));
+        assert!(policy.forbids("certs/server.pem"));
+        assert!(!policy.forbids("src/main.rs"));
+
+        let mut config = Config {
+            allow_conflict_markers: true,
+            ..Default::default()
+        };
+        policy.enforce(&mut config);
+        assert!(!config.allow_conflict_markers);
+        assert!(config.encoding_guard);
+        assert_eq!(config.syntax_check, vec![SyntaxCheck::Python]);
+        assert!(config.team_policy.is_some());
+
+        assert!(TeamPolicy::parse("protect_branches = [\"main\"]", "c1", false).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+/// Expands `{placeholder}` occurrences in `template` using `lookup`. Placeholders
+/// `lookup` doesn't know, and unmatched braces, are kept as written.
+pub fn expand(template: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after
+            .find('}')
+            .and_then(|end| Some((lookup(&after[..end])?, end)))
+        {
+            Some((value, end)) => {
+                expanded.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                expanded.push('{');
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Placeholders describing where git-chai runs: `{hostname}`, `{user}`, `{os}`
+/// and `{env:VAR}` for any environment variable (empty if unset).
+pub fn environment_value(name: &str) -> Option<String> {
+    if let Some(var) = name.strip_prefix("env:") {
+        return Some(std::env::var(var).unwrap_or_default());
+    }
+    match name {
+        "hostname" => Some(hostname()),
+        "user" => Some(
+            std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "unknown".to_string()),
+        ),
+        "os" => Some(std::env::consts::OS.to_string()),
+        _ => None,
+    }
+}
+
+/// Formats a Unix timestamp as `2026-03-01T14:05:00Z`.
+pub fn format_utc(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86_400);
+    let secs = timestamp.rem_euclid(86_400);
+    // Civil date from days since the epoch, after Howard Hinnant's `civil_from_days`
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_environment_placeholders() {
+        let lookup = |name: &str| match name {
+            "os" => Some("linux".to_string()),
+            _ => environment_value(name),
+        };
+        // SAFETY: no other test reads this variable
+        unsafe { std::env::set_var("GIT_CHAI_TEMPLATE_TEST", "laptop") };
+
+        assert_eq!(
+            expand("Autosave-From: {env:GIT_CHAI_TEMPLATE_TEST} ({os})", lookup),
+            "Autosave-From: laptop (linux)"
+        );
+        assert_eq!(expand("{unknown} {os", lookup), "{unknown} {os");
+        assert_eq!(expand("{env:GIT_CHAI_UNSET_VAR}", lookup), "");
+    }
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(951_825_600), "2000-02-29T12:00:00Z");
+        assert_eq!(format_utc(1_772_373_900), "2026-03-01T14:05:00Z");
+    }
+}
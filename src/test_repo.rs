@@ -0,0 +1,69 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::git::isolated::{run_git, stdout_of};
+
+static TEMP_REPO_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A scratch directory for tests, usually a git repository, removed when
+/// dropped, even if the test panics. Dereferences to its path.
+pub struct TempRepo {
+    path: PathBuf,
+}
+
+impl TempRepo {
+    /// An empty directory that is not a repository.
+    pub fn dir(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "git-chai-{}-{}-{}",
+            name,
+            std::process::id(),
+            TEMP_REPO_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempRepo { path }
+    }
+
+    /// A new repository on branch `main` with a committer configured.
+    pub fn new(name: &str) -> Self {
+        let repo = Self::dir(name);
+        repo.git(&["init", "--quiet", "--initial-branch=main"]);
+        repo.git(&["config", "user.email", "chai@example.com"]);
+        repo.git(&["config", "user.name", "chai"]);
+        repo
+    }
+
+    /// Runs git in the repository and returns its trimmed output, panicking if
+    /// it fails.
+    pub fn git(&self, args: &[&str]) -> String {
+        match run_git(&self.path, None, args) {
+            Ok(output) => stdout_of(output),
+            Err(e) => panic!("git {:?} failed: {}", args, e),
+        }
+    }
+
+    /// Writes `content` to `path` in the repository, creating its directories.
+    pub fn write(&self, path: &str, content: impl AsRef<[u8]>) {
+        let path = self.path.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(path, content).unwrap();
+    }
+}
+
+impl Deref for TempRepo {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempRepo {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
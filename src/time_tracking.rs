@@ -0,0 +1,116 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+use crate::config::Config;
+use crate::git::{get_commit_times, get_current_branch, get_local_branches};
+
+/// Work assumed to have happened before the first commit of each session.
+const SESSION_LEAD_IN_SECS: i64 = 5 * 60;
+
+/// A run of commits with no gap longer than the idle threshold.
+#[derive(Debug, PartialEq, Eq)]
+struct Session {
+    day: String,
+    start: i64,
+    end: i64,
+}
+
+impl Session {
+    fn active_secs(&self) -> i64 {
+        self.end - self.start + SESSION_LEAD_IN_SECS
+    }
+}
+
+/// Runs `git-chai time`: estimates active work per day from the gaps between
+/// commits, for the current branch or every local branch.
+pub fn run_time_report(
+    config: &Config,
+    since: &str,
+    idle_minutes: Option<u64>,
+    all_branches: bool,
+) -> Result<()> {
+    let idle_minutes = idle_minutes.unwrap_or(config.idle_threshold_minutes);
+    let branches = if all_branches {
+        get_local_branches(&config.repo_path)?
+    } else {
+        vec![get_current_branch(&config.repo_path)?.unwrap_or_else(|| "HEAD".to_string())]
+    };
+
+    for branch in branches {
+        let times = get_commit_times(&config.repo_path, &branch, since)?;
+        let sessions = sessions(&times, idle_minutes as i64 * 60);
+
+        println!(
+            "Active time on {} since {} (idle threshold {}m):",
+            branch, since, idle_minutes
+        );
+        if sessions.is_empty() {
+            println!("  no commits");
+            continue;
+        }
+
+        let mut days: BTreeMap<&str, (i64, usize)> = BTreeMap::new();
+        for session in &sessions {
+            let day = days.entry(&session.day).or_default();
+            day.0 += session.active_secs();
+            day.1 += 1;
+        }
+        for (day, (secs, count)) in &days {
+            println!(
+                "  {}  {:>7}  ({} session(s))",
+                day,
+                format_duration(*secs),
+                count
+            );
+        }
+        let total: i64 = days.values().map(|(secs, _)| secs).sum();
+        println!("  {:<10}  {:>7}", "total", format_duration(total));
+    }
+    Ok(())
+}
+
+/// Splits commit times (oldest first) into sessions wherever the gap between
+/// two commits exceeds `idle_secs`. A session belongs to the day it started.
+fn sessions(times: &[(i64, String)], idle_secs: i64) -> Vec<Session> {
+    let mut sessions: Vec<Session> = Vec::new();
+    for (time, day) in times {
+        match sessions.last_mut() {
+            Some(session) if time - session.end <= idle_secs => session.end = *time,
+            _ => sessions.push(Session {
+                day: day.clone(),
+                start: *time,
+                end: *time,
+            }),
+        }
+    }
+    sessions
+}
+
+fn format_duration(secs: i64) -> String {
+    let minutes = secs / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_split_on_idle_gaps() {
+        let day = |d: &str| d.to_string();
+        let times = vec![
+            (0, day("2026-10-14")),
+            (600, day("2026-10-14")),
+            (1200, day("2026-10-14")),
+            (10_000, day("2026-10-14")),
+            (90_000, day("2026-10-15")),
+        ];
+
+        let sessions = sessions(&times, 15 * 60);
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(sessions[0].active_secs(), 1200 + SESSION_LEAD_IN_SECS);
+        assert_eq!(sessions[1].active_secs(), SESSION_LEAD_IN_SECS);
+        assert_eq!(sessions[2].day, "2026-10-15");
+        assert_eq!(format_duration(3 * 3600 + 5 * 60), "3h 05m");
+    }
+}
@@ -0,0 +1,168 @@
+use std::io::Read;
+use std::path::Path;
+
+/// How much of a file is read looking for its title.
+const MAX_TITLE_SCAN_BYTES: u64 = 64 * 1024;
+
+/// The first level-one heading of a Markdown note, skipping YAML front matter
+/// and fenced code blocks.
+pub fn markdown_title(content: &str) -> Option<String> {
+    let mut lines = content.lines().peekable();
+    if lines.peek().is_some_and(|line| line.trim_end() == "---") {
+        lines.next();
+        lines
+            .by_ref()
+            .find(|line| matches!(line.trim_end(), "---" | "..."));
+    }
+
+    let mut fence: Option<&str> = None;
+    for line in lines {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+        if let Some(heading) = trimmed.strip_prefix("# ") {
+            let title = heading.trim().trim_end_matches('#').trim_end();
+            if !title.is_empty() {
+                return Some(title.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// The `title` of a file's YAML (`---`) or TOML (`+++`) front matter, as
+/// Jekyll, Hugo and most note apps write it.
+fn frontmatter_title(content: &str) -> Option<String> {
+    let mut lines = content.lines();
+    let (separator, delimiter) = match lines.next()?.trim_end() {
+        "---" => (':', "---"),
+        "+++" => ('=', "+++"),
+        _ => return None,
+    };
+    lines
+        .take_while(|line| !matches!(line.trim_end(), end if end == delimiter || end == "..."))
+        .find_map(|line| {
+            let (key, value) = line.split_once(separator)?;
+            if key.trim() != "title" {
+                return None;
+            }
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .or_else(|| {
+                    value
+                        .strip_prefix('\'')
+                        .and_then(|value| value.strip_suffix('\''))
+                })
+                .unwrap_or(value)
+                .trim();
+            (!value.is_empty()).then(|| value.to_string())
+        })
+}
+
+/// The summary line of a Rust file's `//!` module docs: their first paragraph,
+/// without its closing period. License headers and inner attributes before
+/// the docs are skipped.
+fn rust_doc_summary(content: &str) -> Option<String> {
+    let mut summary: Vec<&str> = Vec::new();
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if let Some(doc) = trimmed.strip_prefix("//!") {
+            let doc = doc.trim();
+            if doc.is_empty() {
+                if summary.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            summary.push(doc);
+        } else if !summary.is_empty() {
+            break;
+        } else if !(trimmed.is_empty() || trimmed.starts_with("//") || trimmed.starts_with("#![")) {
+            return None;
+        }
+    }
+    let summary = summary.join(" ");
+    let summary = summary.trim_end_matches('.').trim_end();
+    (!summary.is_empty()).then(|| summary.to_string())
+}
+
+/// The title `content_titles` gives the file at `path` with `content`: its
+/// first Markdown heading, its `//!` summary if it is Rust, or its front
+/// matter `title`.
+pub fn content_title(path: &str, content: &str) -> Option<String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    let title = match extension.as_deref() {
+        Some("md" | "markdown" | "mdx") => {
+            markdown_title(content).or_else(|| frontmatter_title(content))
+        }
+        Some("rs") => rust_doc_summary(content),
+        _ => frontmatter_title(content),
+    };
+    // A title spanning lines would end the subject early
+    title.map(|title| title.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Reads the title of the file at `path` in `repo_path`, if it has one.
+pub fn file_title(repo_path: &Path, path: &str) -> Option<String> {
+    let file = std::fs::File::open(repo_path.join(path)).ok()?;
+    let mut content = Vec::new();
+    file.take(MAX_TITLE_SCAN_BYTES)
+        .read_to_end(&mut content)
+        .ok()?;
+    content_title(path, &String::from_utf8_lossy(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_titles_by_file_type() {
+        let note = "---\ntitle: Ignored\n# not a heading\n---\n\n```sh\n# comment\n```\n\n## Sub\n# Meeting notes #\n";
+        assert_eq!(markdown_title(note).as_deref(), Some("Meeting notes"));
+        assert_eq!(markdown_title("Just text\n#hashtag\n"), None);
+
+        assert_eq!(
+            content_title(
+                "posts/launch.md",
+                "---\ntitle: \"We launched\"\n---\nNo heading\n"
+            )
+            .as_deref(),
+            Some("We launched")
+        );
+        assert_eq!(
+            content_title(
+                "content/about.html",
+                "+++\ndate = 2024-01-01\ntitle = 'About us'\n+++\n"
+            )
+            .as_deref(),
+            Some("About us")
+        );
+        assert_eq!(
+            content_title(
+                "src/lib.rs",
+                "// SPDX-License-Identifier: MIT\n#![deny(unsafe_code)]\n//! Parses chai\n//! blends.\n//!\n//! More.\nfn main() {}\n"
+            )
+            .as_deref(),
+            Some("Parses chai blends")
+        );
+        assert_eq!(
+            content_title("src/main.rs", "fn main() {}\n//! late\n"),
+            None
+        );
+        assert_eq!(content_title("notes.txt", "# Not markdown\n"), None);
+    }
+}
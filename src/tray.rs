@@ -0,0 +1,257 @@
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use ksni::TrayMethods;
+use ksni::menu::{CheckmarkItem, MenuItem, StandardItem};
+
+use crate::control::Control;
+use crate::summary::session_summary;
+
+/// How often the icon is refreshed from the shared state.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+static SUMMARY_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The tray icon of `--tray`, a StatusNotifierItem shown by KDE, most Linux
+/// panels and GNOME with the AppIndicator extension.
+struct ChaiTray {
+    control: Arc<Control>,
+    /// Shown as the push toggle; starts as whether any repository pushes
+    push: bool,
+    /// Seconds since the epoch when headless mode started
+    started: u64,
+}
+
+impl ksni::Tray for ChaiTray {
+    fn id(&self) -> String {
+        "git-chai".into()
+    }
+
+    fn title(&self) -> String {
+        "git-chai".into()
+    }
+
+    fn icon_name(&self) -> String {
+        match self.control.state().as_str() {
+            "paused" => "media-playback-pause",
+            "error" => "dialog-error",
+            "scanning" => "emblem-synchronizing",
+            _ => "emblem-default",
+        }
+        .into()
+    }
+
+    fn status(&self) -> ksni::Status {
+        if self.control.state() == "error" {
+            ksni::Status::NeedsAttention
+        } else {
+            ksni::Status::Active
+        }
+    }
+
+    fn tool_tip(&self) -> ksni::ToolTip {
+        let mut description = self.control.state();
+        for (path, status) in self.control.repos() {
+            description.push_str(&format!("\n{}: {}", path.display(), status.activity));
+            if let Some(error) = status.last_error {
+                description.push_str(&format!(" ({})", first_line(&error)));
+            }
+        }
+        ksni::ToolTip {
+            title: "git-chai".into(),
+            description,
+            ..Default::default()
+        }
+    }
+
+    fn menu(&self) -> Vec<MenuItem<Self>> {
+        vec![
+            CheckmarkItem {
+                label: "Pause".into(),
+                checked: self.control.is_paused(),
+                activate: Box::new(|tray: &mut Self| {
+                    let paused = !tray.control.is_paused();
+                    log::info!(
+                        "{} from the tray",
+                        if paused { "Paused" } else { "Resumed" }
+                    );
+                    tray.control.set_paused(paused);
+                }),
+                ..Default::default()
+            }
+            .into(),
+            CheckmarkItem {
+                label: "Push after committing".into(),
+                checked: self.push,
+                activate: Box::new(|tray: &mut Self| {
+                    tray.push = !tray.push;
+                    log::info!(
+                        "Pushing {} from the tray",
+                        if tray.push { "enabled" } else { "disabled" }
+                    );
+                    tray.control.set_push(tray.push);
+                }),
+                ..Default::default()
+            }
+            .into(),
+            StandardItem {
+                label: "Scan now".into(),
+                activate: Box::new(|tray: &mut Self| tray.control.request_scan()),
+                ..Default::default()
+            }
+            .into(),
+            MenuItem::Separator,
+            StandardItem {
+                label: "Session summary".into(),
+                icon_name: "text-x-generic".into(),
+                activate: Box::new(|tray: &mut Self| {
+                    let repos: Vec<PathBuf> = tray
+                        .control
+                        .repos()
+                        .into_iter()
+                        .map(|(path, _)| path)
+                        .collect();
+                    let started = tray.started;
+                    // Runs git, so it stays off the thread that answers the panel
+                    std::thread::spawn(move || {
+                        if let Err(e) = open_summary(&repos, started) {
+                            log::warn!("Failed to open the session summary: {:#}", e);
+                        }
+                    });
+                }),
+                ..Default::default()
+            }
+            .into(),
+            StandardItem {
+                label: "Quit".into(),
+                icon_name: "application-exit".into(),
+                activate: Box::new(|tray: &mut Self| tray.control.request_stop()),
+                ..Default::default()
+            }
+            .into(),
+        ]
+    }
+}
+
+/// Shows the tray icon until headless mode stops. `push` is the initial state
+/// of the push toggle.
+pub async fn serve(control: Arc<Control>, push: bool) -> Result<()> {
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let tray = ChaiTray {
+        control: control.clone(),
+        push,
+        started,
+    };
+    // The icon shows up once a panel starts, if there is none yet (such as
+    // when git-chai is started before the desktop at login)
+    let handle = tray
+        .assume_sni_available(true)
+        .spawn()
+        .await
+        .context("Failed to show the tray icon")?;
+    log::info!("Tray icon registered");
+
+    // The icon, tooltip and menu are read from `control`; an update makes the
+    // panel read them again
+    tokio::spawn(async move {
+        let mut shown = String::new();
+        loop {
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+            let current = format!("{:?}{:?}", control.state(), control.repos());
+            if current != shown {
+                shown = current;
+                if handle.update(|_| {}).await.is_none() {
+                    break;
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Writes the summary of every repository's commits this session to a file and
+/// opens it with the desktop's default text viewer.
+fn open_summary(repos: &[PathBuf], started: u64) -> Result<()> {
+    let mut text = String::new();
+    for repo in repos {
+        text.push_str(&format!(
+            "{}\n{}\n",
+            repo.display(),
+            session_summary(repo, started)?
+        ));
+    }
+
+    let file = write_private_file(&text)?;
+    let status = std::process::Command::new("xdg-open")
+        .arg(&file)
+        .status()
+        .context("Failed to run xdg-open")?;
+    if !status.success() {
+        anyhow::bail!("xdg-open failed to open {}", file.display());
+    }
+    Ok(())
+}
+
+/// Writes `text` to a new file only the user can read, in `$XDG_RUNTIME_DIR`
+/// (or the temp directory without one). The file is created fresh, so a path
+/// planted there in advance is never written through.
+fn write_private_file(text: &str) -> Result<PathBuf> {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(std::env::temp_dir);
+    loop {
+        let file = dir.join(format!(
+            "git-chai-session-{}-{}.txt",
+            std::process::id(),
+            SUMMARY_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&file)
+        {
+            Ok(mut out) => {
+                out.write_all(text.as_bytes())
+                    .with_context(|| format!("Failed to write {}", file.display()))?;
+                return Ok(file);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", file.display()));
+            }
+        }
+    }
+}
+
+fn first_line(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_summary_files_are_new_and_private() {
+        let first = write_private_file("summary\n").unwrap();
+        let second = write_private_file("summary\n").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "summary\n");
+        let mode = std::fs::metadata(&first).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let _ = std::fs::remove_file(first);
+        let _ = std::fs::remove_file(second);
+    }
+}
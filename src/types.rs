@@ -13,7 +13,7 @@ pub enum GitStatus {
     Renamed,          // R: renamed
     Copied,           // C: copied
     Unmerged,         // U: unmerged
-    Ignored,          // !: ignored
+    Ignored,          // !!: ignored file
     Unknown(String),  // Unknown status code
 }
 
@@ -29,10 +29,11 @@ impl FromStr for GitStatus {
             " M" => Ok(GitStatus::ModifiedUnstaged),
             " D" => Ok(GitStatus::DeletedUnstaged),
             "??" => Ok(GitStatus::Untracked),
-            "R " => Ok(GitStatus::Renamed),
-            "C " => Ok(GitStatus::Copied),
+            // Renamed or copied in the index, possibly edited since
+            "R " | "RM" => Ok(GitStatus::Renamed),
+            "C " | "CM" => Ok(GitStatus::Copied),
             "U " => Ok(GitStatus::Unmerged),
-            "! " => Ok(GitStatus::Ignored),
+            "!!" => Ok(GitStatus::Ignored),
             _ => Ok(GitStatus::Unknown(s.to_string())),
         }
     }
@@ -51,7 +52,7 @@ impl fmt::Display for GitStatus {
             GitStatus::Renamed => write!(f, "R "),
             GitStatus::Copied => write!(f, "C "),
             GitStatus::Unmerged => write!(f, "U "),
-            GitStatus::Ignored => write!(f, "! "),
+            GitStatus::Ignored => write!(f, "!!"),
             GitStatus::Unknown(s) => write!(f, "{}", s),
         }
     }
@@ -64,19 +65,21 @@ pub enum ChangeType {
     Delete,
     Rename,
     Copy,
+    Link,
 }
 
 impl From<GitStatus> for ChangeType {
     fn from(status: GitStatus) -> Self {
         match status {
-            GitStatus::AddedStaged | GitStatus::AddedUnstaged | GitStatus::Untracked => {
-                ChangeType::Add
-            }
+            GitStatus::AddedStaged
+            | GitStatus::AddedUnstaged
+            | GitStatus::Untracked
+            | GitStatus::Ignored => ChangeType::Add,
             GitStatus::ModifiedStaged | GitStatus::ModifiedUnstaged => ChangeType::Modify,
             GitStatus::DeletedStaged | GitStatus::DeletedUnstaged => ChangeType::Delete,
             GitStatus::Renamed => ChangeType::Rename,
             GitStatus::Copied => ChangeType::Copy,
-            GitStatus::Unknown(_) | GitStatus::Unmerged | GitStatus::Ignored => ChangeType::Modify, // Default fallback
+            GitStatus::Unknown(_) | GitStatus::Unmerged => ChangeType::Modify, // Default fallback
         }
     }
 }
@@ -89,6 +92,7 @@ impl fmt::Display for ChangeType {
             ChangeType::Delete => write!(f, "del"),
             ChangeType::Rename => write!(f, "rename"),
             ChangeType::Copy => write!(f, "copy"),
+            ChangeType::Link => write!(f, "link"),
         }
     }
 }
@@ -116,9 +120,10 @@ mod tests {
         );
         assert_eq!(GitStatus::from_str("??").unwrap(), GitStatus::Untracked);
         assert_eq!(GitStatus::from_str("R ").unwrap(), GitStatus::Renamed);
+        assert_eq!(GitStatus::from_str("RM").unwrap(), GitStatus::Renamed);
         assert_eq!(GitStatus::from_str("C ").unwrap(), GitStatus::Copied);
         assert_eq!(GitStatus::from_str("U ").unwrap(), GitStatus::Unmerged);
-        assert_eq!(GitStatus::from_str("! ").unwrap(), GitStatus::Ignored);
+        assert_eq!(GitStatus::from_str("!!").unwrap(), GitStatus::Ignored);
 
         // Test unknown status
         let unknown = GitStatus::from_str("X ").unwrap();
@@ -140,7 +145,7 @@ mod tests {
         assert_eq!(GitStatus::Renamed.to_string(), "R ");
         assert_eq!(GitStatus::Copied.to_string(), "C ");
         assert_eq!(GitStatus::Unmerged.to_string(), "U ");
-        assert_eq!(GitStatus::Ignored.to_string(), "! ");
+        assert_eq!(GitStatus::Ignored.to_string(), "!!");
         assert_eq!(GitStatus::Unknown("X ".to_string()).to_string(), "X ");
     }
 
@@ -149,6 +154,7 @@ mod tests {
         assert_eq!(ChangeType::from(GitStatus::AddedStaged), ChangeType::Add);
         assert_eq!(ChangeType::from(GitStatus::AddedUnstaged), ChangeType::Add);
         assert_eq!(ChangeType::from(GitStatus::Untracked), ChangeType::Add);
+        assert_eq!(ChangeType::from(GitStatus::Ignored), ChangeType::Add);
         assert_eq!(
             ChangeType::from(GitStatus::ModifiedStaged),
             ChangeType::Modify
@@ -170,7 +176,6 @@ mod tests {
 
         // Test fallbacks
         assert_eq!(ChangeType::from(GitStatus::Unmerged), ChangeType::Modify);
-        assert_eq!(ChangeType::from(GitStatus::Ignored), ChangeType::Modify);
         assert_eq!(
             ChangeType::from(GitStatus::Unknown("".to_string())),
             ChangeType::Modify
@@ -184,5 +189,6 @@ mod tests {
         assert_eq!(ChangeType::Delete.to_string(), "del");
         assert_eq!(ChangeType::Rename.to_string(), "rename");
         assert_eq!(ChangeType::Copy.to_string(), "copy");
+        assert_eq!(ChangeType::Link.to_string(), "link");
     }
 }
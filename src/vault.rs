@@ -0,0 +1,130 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::git::status::GitChange;
+use crate::git::{LogCommit, get_commit_day, get_head_commit, get_local_day, published_on};
+use crate::template::format_utc;
+use crate::title::markdown_title;
+
+/// Subject prefix of the daily commits of `notes_mode`.
+const NOTES_PREFIX: &str = "notes: ";
+
+/// File names that editors and sync clients write next to a note while it is
+/// open: swap, lock and backup files. Matched against the last path component.
+const EDITOR_TEMP_SUFFIXES: &[&str] = &[
+    "~",
+    ".swp",
+    ".swo",
+    ".swx",
+    ".tmp",
+    ".crswap",
+    ".kate-swp",
+    ".bak",
+];
+const EDITOR_TEMP_PREFIXES: &[&str] = &[".#", ".~lock.", "~$"];
+const EDITOR_TEMP_NAMES: &[&str] = &[".DS_Store", "Thumbs.db", "desktop.ini", "4913"];
+
+/// Returns true if `path` looks like an editor's temporary file, or Obsidian's
+/// per-device window layout, which `notes_mode` never commits.
+pub fn is_editor_temp(path: &str) -> bool {
+    if path.ends_with('/') {
+        return false;
+    }
+    if path.starts_with(".obsidian/workspace") && path.ends_with(".json") {
+        return true;
+    }
+    let name = path.rsplit('/').next().unwrap_or(path);
+    // Emacs auto-save files: #note.md#
+    (name.len() > 1 && name.starts_with('#') && name.ends_with('#'))
+        || EDITOR_TEMP_NAMES.contains(&name)
+        || EDITOR_TEMP_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        || EDITOR_TEMP_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+}
+
+/// Message of the day's notes commit covering `files`: the note's title when
+/// it is a single Markdown note, otherwise the day and the number of files.
+fn notes_message(repo_path: &Path, day: &str, files: &BTreeSet<&str>) -> String {
+    if files.len() == 1
+        && let Some(path) = files.first()
+        && path.to_lowercase().ends_with(".md")
+        && let Ok(content) = std::fs::read_to_string(repo_path.join(path))
+        && let Some(title) = markdown_title(&content)
+    {
+        return format!("{}{}", NOTES_PREFIX, title);
+    }
+    format!(
+        "{}{} ({} {})",
+        NOTES_PREFIX,
+        day,
+        files.len(),
+        if files.len() == 1 { "file" } else { "files" }
+    )
+}
+
+/// HEAD, if it is today's notes commit and no remote has it yet, so the
+/// cycle's changes can be folded into it.
+fn todays_notes_commit(repo_path: &Path, today: &str) -> Option<LogCommit> {
+    let head = get_head_commit(repo_path).ok()??;
+    let rewritable = head.generated_subject().starts_with(NOTES_PREFIX)
+        && get_commit_day(repo_path, &head.sha).is_ok_and(|day| day == today)
+        && published_on(repo_path, &head.sha).is_ok_and(|on| on.is_none());
+    rewritable.then_some(head)
+}
+
+/// Plans the commit `notes_mode` makes of a cycle's `changes`: returns its
+/// message and whether it amends today's notes commit at HEAD.
+pub fn plan_notes_commit(repo_path: &Path, changes: &[GitChange]) -> (String, bool) {
+    let today = get_local_day(repo_path).unwrap_or_else(|e| {
+        log::warn!("Failed to read the local date, using UTC: {}", e);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        format_utc(now)[..10].to_string()
+    });
+    let previous = todays_notes_commit(repo_path, &today);
+
+    let mut files: BTreeSet<&str> = changes
+        .iter()
+        .map(|change| change.filename.as_str())
+        .collect();
+    if let Some(previous) = &previous {
+        files.extend(previous.files.iter().map(String::as_str));
+    }
+    (notes_message(repo_path, &today, &files), previous.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_temp_files() {
+        for temp in [
+            ".Daily.md.swp",
+            "notes/draft.md~",
+            "notes/.#draft.md",
+            "notes/#draft.md#",
+            ".~lock.budget.ods#",
+            ".DS_Store",
+            "attachments/.scan.pdf.crswap",
+            ".obsidian/workspace.json",
+            ".obsidian/workspace-mobile.json",
+        ] {
+            assert!(is_editor_temp(temp), "{}", temp);
+        }
+        for note in [
+            "Daily.md",
+            "notes/#tags.md",
+            ".obsidian/app.json",
+            "attachments/",
+        ] {
+            assert!(!is_editor_temp(note), "{}", note);
+        }
+    }
+}
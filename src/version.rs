@@ -0,0 +1,37 @@
+use serde::Serialize;
+
+/// Version and build metadata embedded by `build.rs`.
+#[derive(Debug, Serialize)]
+pub struct VersionInfo {
+    pub version: &'static str,
+    pub commit: &'static str,
+    pub build_date: &'static str,
+    pub features: Vec<&'static str>,
+}
+
+pub fn version_info() -> VersionInfo {
+    VersionInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        commit: env!("GIT_CHAI_COMMIT"),
+        build_date: env!("GIT_CHAI_BUILD_DATE"),
+        features: env!("GIT_CHAI_FEATURES")
+            .split(',')
+            .filter(|feature| !feature.is_empty())
+            .collect(),
+    }
+}
+
+impl VersionInfo {
+    /// Multi-line description printed by `--version`.
+    pub fn long_version(&self) -> String {
+        let features = if self.features.is_empty() {
+            "none".to_string()
+        } else {
+            self.features.join(", ")
+        };
+        format!(
+            "git-chai {} ({} {})\nfeatures: {}",
+            self.version, self.commit, self.build_date, features
+        )
+    }
+}
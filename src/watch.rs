@@ -0,0 +1,114 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Notify, mpsc};
+use tokio::time::Instant;
+
+/// Longest a scan is put off while files keep changing, in debounce periods.
+const MAX_DELAY_DEBOUNCES: u32 = 10;
+
+/// Watches the working tree of `repo_path` for `--watch`, notifying `trigger`
+/// once changes have been quiet for `debounce`. Changes inside `.git`,
+/// including git-chai's own commits, are ignored. The watch lasts as long as
+/// the returned watcher.
+pub fn watch(
+    repo_path: &Path,
+    debounce: Duration,
+    trigger: Arc<Notify>,
+) -> notify::Result<RecommendedWatcher> {
+    let (changes, mut changed) = mpsc::unbounded_channel();
+    let root = repo_path.to_path_buf();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        match event {
+            Ok(event) if !is_relevant(&root, &event) => return,
+            Ok(_) => {}
+            // Events may have been lost (e.g. the kernel queue overflowed), so
+            // scan to be safe
+            Err(e) => log::warn!("Error watching {}: {}", root.display(), e),
+        }
+        let _ = changes.send(());
+    })?;
+    watcher.watch(repo_path, RecursiveMode::Recursive)?;
+
+    tokio::spawn(async move {
+        while changed.recv().await.is_some() {
+            let deadline = Instant::now() + debounce * MAX_DELAY_DEBOUNCES;
+            loop {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                match tokio::time::timeout_at((now + debounce).min(deadline), changed.recv()).await
+                {
+                    Ok(Some(())) => {}
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+            // Stored if the repository is being scanned, so it is scanned
+            // again right after
+            trigger.notify_one();
+        }
+    });
+    Ok(watcher)
+}
+
+/// Returns true if `event` may have changed what a scan finds: anything but
+/// reads, outside the git directory of the repository at `root`.
+fn is_relevant(root: &Path, event: &Event) -> bool {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return false;
+    }
+    event.paths.iter().any(|path| {
+        !path
+            .strip_prefix(root)
+            .unwrap_or(path)
+            .components()
+            .any(|component| component.as_os_str() == ".git")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, ModifyKind};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_git_directory_and_reads_are_ignored() {
+        let root = Path::new("/home/me/notes");
+        let event = |kind: EventKind, path: &str| Event::new(kind).add_path(PathBuf::from(path));
+
+        assert!(is_relevant(
+            root,
+            &event(EventKind::Modify(ModifyKind::Any), "/home/me/notes/a.md")
+        ));
+        assert!(is_relevant(
+            root,
+            &event(
+                EventKind::Create(CreateKind::File),
+                "/home/me/notes/sub/b.md"
+            )
+        ));
+        assert!(!is_relevant(
+            root,
+            &event(
+                EventKind::Modify(ModifyKind::Any),
+                "/home/me/notes/.git/index"
+            )
+        ));
+        assert!(!is_relevant(
+            root,
+            &event(
+                EventKind::Create(CreateKind::File),
+                "/home/me/notes/vendor/lib/.git/HEAD"
+            )
+        ));
+        assert!(!is_relevant(
+            root,
+            &event(EventKind::Access(AccessKind::Any), "/home/me/notes/a.md")
+        ));
+    }
+}